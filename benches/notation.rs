@@ -0,0 +1,56 @@
+//! Benchmarks for reading moves in UCI notation: `cargo bench --bench notation`.
+//!
+//! Each iteration parses 100k tokens, the legal moves of three positions over and
+//! over, promotions included. Baseline on one core of an x86-64 Linux machine,
+//! release build:
+//!
+//! | benchmark                | time      |
+//! |--------------------------|-----------|
+//! | parse_uci/100k/new       |   379 µs  |
+//! | parse_uci/100k/from_uci  |   334 µs  |
+//!
+//! `Move::new` took a `String` and copied each square name into another before
+//! reading it, three allocations a move; that took 5.6 ms for the same tokens.
+
+use chessbot::board::Board;
+use chessbot::chess_move::Move;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const TOKENS: usize = 100_000;
+
+fn uci_tokens() -> Vec<String> {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+    ];
+    let moves: Vec<String> = fens
+        .iter()
+        .flat_map(|fen| Board::fen_to_board(fen).legal_moves().to_vec())
+        .map(|m| m.to_string())
+        .collect();
+    moves.iter().cycle().take(TOKENS).cloned().collect()
+}
+
+fn parse_uci(c: &mut Criterion) {
+    let tokens = uci_tokens();
+    let mut group = c.benchmark_group("parse_uci");
+    group.bench_function("100k/new", |b| {
+        b.iter(|| {
+            for token in &tokens {
+                black_box(Move::new(black_box(token)));
+            }
+        })
+    });
+    group.bench_function("100k/from_uci", |b| {
+        b.iter(|| {
+            for token in &tokens {
+                black_box(Move::from_uci(black_box(token)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_uci);
+criterion_main!(benches);
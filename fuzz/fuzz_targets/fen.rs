@@ -0,0 +1,27 @@
+//! `cargo fuzz run fen`: FEN parsing never panics, and a parsed position written
+//! back out parses to the same position.
+
+#![no_main]
+
+use chessbot::board::Board;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(fen) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(board) = Board::from_fen(fen) else {
+        return;
+    };
+
+    let written = board.board_to_fen();
+    let reparsed = Board::from_fen(&written)
+        .unwrap_or_else(|err| panic!("{:?} was written as {:?}: {}", fen, written, err));
+    assert_eq!(reparsed.bitboards, board.bitboards, "{:?}", fen);
+    assert_eq!(reparsed.active_color, board.active_color);
+    assert_eq!(reparsed.castling_rights, board.castling_rights);
+    assert_eq!(reparsed.en_passant_square(), board.en_passant_square());
+    assert_eq!(reparsed.halfmove_clock, board.halfmove_clock);
+    assert_eq!(reparsed.fullmove_number, board.fullmove_number);
+    reparsed.assert_well_formed();
+});
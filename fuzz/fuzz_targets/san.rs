@@ -0,0 +1,32 @@
+//! `cargo fuzz run san`: SAN parsing never panics, and whatever it accepts is a legal
+//! move that writes back to SAN naming the same move.
+//!
+//! The first byte picks the position, the rest is the SAN text.
+
+#![no_main]
+
+use chessbot::board::Board;
+use chessbot::san::{parse_san, to_san};
+use libfuzzer_sys::fuzz_target;
+
+const POSITIONS: [&str; 4] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    "8/8/8/4k3/3Pp3/8/8/4K3 b - d3 0 1",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&index, san)) = data.split_first() else {
+        return;
+    };
+    let Ok(san) = std::str::from_utf8(san) else {
+        return;
+    };
+    let board = Board::fen_to_board(POSITIONS[index as usize % POSITIONS.len()]);
+
+    if let Ok(m) = parse_san(&board, san) {
+        assert!(board.legal_moves().contains(&m), "{:?} gave {}", san, m);
+        assert_eq!(parse_san(&board, &to_san(&board, &m)), Ok(m), "{:?}", san);
+    }
+});
@@ -0,0 +1,15 @@
+//! `cargo fuzz run uci_move`: UCI move parsing never panics and round-trips.
+
+#![no_main]
+
+use chessbot::chess_move::Move;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(uci) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Some(m) = Move::from_uci(uci) {
+        assert_eq!(m.to_string(), uci);
+    }
+});
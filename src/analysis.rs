@@ -0,0 +1,628 @@
+//! Going over a finished game with the engine: its score before and after every
+//! move, the move it would have preferred, and the moves that threw the most away.
+//! [`with_engine_lines`] writes the lines it preferred into the game as variations,
+//! and [`annotated_game`] adds evaluation comments and NAGs as well.
+
+use crate::board::{Board, Color, PieceType, PIECE_VALUES};
+use crate::chess_move::{Move, MoveKind};
+use crate::engine::{AlphaBetaEngine, Engine, SearchLimits};
+use crate::game::{Game, GameTree, Termination};
+use crate::san::to_san;
+use crate::search::{mate_in, SearchOptions, MATE_SCORE, MATE_THRESHOLD};
+
+use std::fmt;
+
+/// Moves losing at least this many centipawns are blunders.
+pub const BLUNDER_THRESHOLD: i32 = 200;
+
+/// Moves losing at least this many centipawns, but less than a blunder, are
+/// mistakes.
+pub const MISTAKE_THRESHOLD: i32 = 100;
+
+/// The most one move counts for in [`average_loss`], so that a single lost mate does
+/// not drown out the rest of the game.
+pub const AVERAGE_LOSS_CAP: i32 = 1000;
+
+/// What the engine made of one move of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlyAnnotation {
+    /// Index of the move in the game, from 0.
+    pub ply: usize,
+    /// The side that played it.
+    pub color: Color,
+    /// Its move number, as PGN writes it.
+    pub move_number: u32,
+    pub played: Move,
+    pub san: String,
+    /// The engine's score before and after the move, in centipawns from White's
+    /// point of view.
+    pub eval_before: i32,
+    pub eval_after: i32,
+    /// The depth of the search behind `eval_after`, in plies. 0 where the game is
+    /// over or the engine does not search.
+    pub depth: u32,
+    /// The move the engine would have played instead, in SAN, if it prefers another.
+    pub best: Option<String>,
+    /// The line the engine expected after its preferred move, starting with it.
+    /// Empty when it would have played the move played.
+    pub best_line: Vec<Move>,
+}
+
+impl PlyAnnotation {
+    /// Centipawns the move cost the side that played it, by the engine's reckoning.
+    /// Negative if the position got better for it.
+    pub fn loss(&self) -> i32 {
+        match self.color {
+            Color::White => self.eval_before - self.eval_after,
+            Color::Black => self.eval_after - self.eval_before,
+        }
+    }
+
+    /// Whether the move was a mistake or a blunder by `thresholds`.
+    pub fn judgement(&self, thresholds: &Thresholds) -> Option<Judgement> {
+        let loss = self.loss();
+        if loss >= thresholds.blunder {
+            Some(Judgement::Blunder)
+        } else if loss >= thresholds.mistake {
+            Some(Judgement::Mistake)
+        } else {
+            None
+        }
+    }
+}
+
+/// How many centipawns a move must lose to be marked a mistake or a blunder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thresholds {
+    pub mistake: i32,
+    pub blunder: i32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            mistake: MISTAKE_THRESHOLD,
+            blunder: BLUNDER_THRESHOLD,
+        }
+    }
+}
+
+/// A move that lost enough to be marked in PGN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// Its numeric annotation glyph: `$2`, written `?`, or `$4`, written `??`.
+    pub fn nag(self) -> u8 {
+        match self {
+            Judgement::Mistake => 2,
+            Judgement::Blunder => 4,
+        }
+    }
+}
+
+/// Writes the move as a PGN move list would, e.g. `12... Qh5`.
+impl fmt::Display for PlyAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dots = match self.color {
+            Color::White => ".",
+            Color::Black => "...",
+        };
+        write!(f, "{}{} {}", self.move_number, dots, self.san)
+    }
+}
+
+/// Searches every position of `game` within `limits`, one after the other, and
+/// annotates each move with the scores before and after it.
+pub fn annotate(game: &Game, limits: &SearchLimits) -> Vec<PlyAnnotation> {
+    annotate_with(
+        &mut AlphaBetaEngine::new(SearchOptions::default()),
+        game,
+        limits,
+    )
+}
+
+/// [`annotate`] with the scores of `engine`, which must evaluate: an engine without
+/// scores sees every position as 0.00.
+pub fn annotate_with(
+    engine: &mut dyn Engine,
+    game: &Game,
+    limits: &SearchLimits,
+) -> Vec<PlyAnnotation> {
+    let mut position = Game::from_board(*game.start());
+
+    // Scores from White's side, and the engine's line, for each position in turn
+    let mut evaluate = |position: &Game| {
+        let board = position.board();
+        let chosen = engine.choose_move(position, limits);
+        let score = match (engine.last_score(), position.outcome()) {
+            (Some(score), _) if chosen.is_some() => score,
+            (_, Some(result)) if result.termination == Termination::Checkmate => -MATE_SCORE,
+            _ => 0,
+        };
+        let score = match board.active_color {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        let depth = chosen.and(engine.last_depth()).unwrap_or(0);
+        let line = match (chosen, engine.last_pv()) {
+            (Some(chosen), []) => vec![chosen],
+            (Some(_), pv) => pv.to_vec(),
+            (None, _) => Vec::new(),
+        };
+        (score, depth, line)
+    };
+
+    let mut annotations = Vec::with_capacity(game.moves().len());
+    let (mut eval, _, mut line) = evaluate(&position);
+    for (ply, &m) in game.moves().iter().enumerate() {
+        let board = *position.board();
+        position.play(m).expect("a game only holds legal moves");
+        let (eval_after, depth, line_after) = evaluate(&position);
+        let best_line = match line.first() {
+            Some(&best) if best != m => line,
+            _ => Vec::new(),
+        };
+
+        annotations.push(PlyAnnotation {
+            ply,
+            color: board.active_color,
+            move_number: board.fullmove_number,
+            played: m,
+            san: to_san(&board, &m),
+            eval_before: eval,
+            eval_after,
+            depth,
+            best: best_line.first().map(|best| to_san(&board, best)),
+            best_line,
+        });
+        eval = eval_after;
+        line = line_after;
+    }
+    annotations
+}
+
+/// `game` with the engine's line as a variation wherever `annotations`, made by
+/// [`annotate`] for it, say the engine preferred another move.
+pub fn with_engine_lines(game: &Game, annotations: &[PlyAnnotation]) -> GameTree {
+    let mut tree = GameTree::new(game.clone());
+    for annotation in annotations.iter().filter(|a| !a.best_line.is_empty()) {
+        let mut viewed = game.clone();
+        if viewed.goto_ply(annotation.ply).is_err() {
+            continue;
+        }
+        let mut line = viewed.fork();
+        for &m in &annotation.best_line {
+            if line.play(m).is_err() {
+                break;
+            }
+        }
+        // Annotations for another game may not branch off this one
+        let _ = tree.add_variation(line.into());
+    }
+    tree
+}
+
+/// The average centipawns `color`'s moves lost, each counted as at least 0 and at
+/// most [`AVERAGE_LOSS_CAP`]. `None` if it played no move.
+pub fn average_loss(annotations: &[PlyAnnotation], color: Color) -> Option<f64> {
+    let losses: Vec<i32> = annotations
+        .iter()
+        .filter(|annotation| annotation.color == color)
+        .map(|annotation| annotation.loss().clamp(0, AVERAGE_LOSS_CAP))
+        .collect();
+    if losses.is_empty() {
+        return None;
+    }
+    Some(losses.iter().sum::<i32>() as f64 / losses.len() as f64)
+}
+
+/// The score after a move as an annotated PGN writes it, from White's side and with
+/// the depth: `+0.34/12`, or `-M3/12` when Black mates in 3.
+pub fn eval_comment(annotation: &PlyAnnotation) -> String {
+    let score = match mate_in(annotation.eval_after) {
+        Some(moves) if moves < 0 => format!("-M{}", -moves),
+        Some(moves) => format!("+M{}", moves),
+        None => format!("{:+.2}", annotation.eval_after as f64 / 100.0),
+    };
+    format!("{}/{}", score, annotation.depth)
+}
+
+/// `game` as [`with_engine_lines`] writes it, and with what `annotations` found: the
+/// score after every move the engine searched as a comment, a NAG on the mistakes
+/// and blunders by `thresholds`, and each side's average centipawn loss in a
+/// comment before the first move.
+pub fn annotated_game(
+    game: &Game,
+    annotations: &[PlyAnnotation],
+    thresholds: &Thresholds,
+) -> GameTree {
+    let mut tree = with_engine_lines(game, annotations);
+    let white = average_loss(annotations, Color::White);
+    let black = average_loss(annotations, Color::Black);
+    if white.is_some() || black.is_some() {
+        let side = |loss: Option<f64>| loss.map_or("-".to_string(), |loss| format!("{:.0}", loss));
+        tree.set_comment(format!(
+            "Average centipawn loss: White {}, Black {}",
+            side(white),
+            side(black)
+        ));
+    }
+
+    for annotation in annotations {
+        let notes = tree.notes_mut(annotation.ply);
+        if let Some(judgement) = annotation.judgement(thresholds) {
+            notes.nags.push(judgement.nag());
+        }
+        if annotation.depth > 0 {
+            notes.comment = Some(eval_comment(annotation));
+        }
+    }
+    tree
+}
+
+/// Searches each of `candidates` on its own in `game`'s current position and returns
+/// them with their scores, best first. Scores are in centipawns for the side to move.
+pub fn score_candidates(
+    game: &Game,
+    candidates: &[Move],
+    limits: &SearchLimits,
+) -> Vec<(Move, i32)> {
+    let mut engine = AlphaBetaEngine::new(SearchOptions::default());
+    let mut scored: Vec<(Move, i32)> = candidates
+        .iter()
+        .filter_map(|&m| {
+            let limits = SearchLimits {
+                root_moves: Some(vec![m]),
+                ..limits.clone()
+            };
+            engine.choose_move(game, &limits)?;
+            Some((m, engine.last_score()?))
+        })
+        .collect();
+    scored.sort_by_key(|&(_, score)| -score);
+    scored
+}
+
+/// A move the engine thinks throws away at least the threshold given to
+/// [`check_blunder`], and how the opponent punishes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlunderReport {
+    pub played: Move,
+    pub san: String,
+    /// The engine's score before and after the move, in centipawns for the side
+    /// that played it.
+    pub eval_before: i32,
+    pub eval_after: i32,
+    /// The opponent's best reply and the line the engine expects after it.
+    pub refutation: Vec<Move>,
+    /// The reply in SAN.
+    pub refutation_san: String,
+    /// The most valuable piece the mover is down by the end of the refutation, if
+    /// it comes to a piece.
+    pub lost: Option<PieceType>,
+}
+
+impl BlunderReport {
+    /// Centipawns the move costs, by the engine's reckoning.
+    pub fn loss(&self) -> i32 {
+        self.eval_before - self.eval_after
+    }
+}
+
+/// Writes the warning a player sees, e.g. `This loses a rook to Nxd5`.
+impl fmt::Display for BlunderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.eval_after <= -MATE_THRESHOLD {
+            write!(f, "This allows mate, starting with {}", self.refutation_san)
+        } else if let Some(peice) = self.lost {
+            let peice = peice.to_string().to_lowercase();
+            write!(f, "This loses a {} to {}", peice, self.refutation_san)
+        } else {
+            let pawns = self.loss() as f64 / 100.0;
+            write!(
+                f,
+                "This costs about {:.1} pawns after {}",
+                pawns, self.refutation_san
+            )
+        }
+    }
+}
+
+/// Searches `game`'s position before and after the side to move plays `m` and, if
+/// the move costs that side at least `threshold` centipawns, says how the opponent
+/// punishes it. `m` must be legal. Keep `limits` small: this runs before a move is
+/// accepted.
+pub fn check_blunder(
+    game: &Game,
+    m: Move,
+    limits: &SearchLimits,
+    threshold: i32,
+) -> Option<BlunderReport> {
+    let mut engine = AlphaBetaEngine::new(SearchOptions::default());
+    let board = *game.board();
+    engine.choose_move(game, limits)?;
+    let eval_before = engine.last_score()?;
+
+    let mut position = game.clone();
+    position.play(m).ok()?;
+    let reply = engine.choose_move(&position, limits);
+    // The opponent's score, so the mover's is its negation
+    let eval_after = match (reply, engine.last_score()) {
+        (Some(_), Some(score)) => -score,
+        _ => match position.outcome() {
+            Some(result) if result.termination == Termination::Checkmate => MATE_SCORE,
+            _ => 0,
+        },
+    };
+    if eval_before - eval_after < threshold {
+        return None;
+    }
+
+    let refutation = engine.last_pv().to_vec();
+    let first = *refutation.first()?;
+    Some(BlunderReport {
+        played: m,
+        san: to_san(&board, &m),
+        eval_before,
+        eval_after,
+        refutation_san: to_san(position.board(), &first),
+        lost: piece_lost(position.board(), &refutation),
+        refutation,
+    })
+}
+
+/// The most valuable piece the opponent takes along `line`, played from `board`,
+/// that is worth no more than the material the side not to move ends up down.
+fn piece_lost(board: &Board, line: &[Move]) -> Option<PieceType> {
+    let mover = !board.active_color;
+    let balance = |board: &Board| board.material_value(mover) - board.material_value(!mover);
+    let start = balance(board);
+
+    let mut board = *board;
+    let mut taken = Vec::new();
+    for m in line {
+        if board.active_color != mover {
+            match board.classify(m) {
+                MoveKind::Capture(peice) | MoveKind::CapturePromotion(peice, _) => {
+                    taken.push(peice)
+                }
+                MoveKind::EnPassant => taken.push(PieceType::Pawn),
+                _ => {}
+            }
+        }
+        board.make_move_unchecked(m);
+    }
+    let down = start - balance(&board);
+    taken
+        .into_iter()
+        .filter(|&peice| PIECE_VALUES[peice as usize] <= down)
+        .max_by_key(|&peice| PIECE_VALUES[peice as usize])
+}
+
+/// The moves that lost at least `threshold` centipawns, the costliest first.
+pub fn largest_swings(annotations: &[PlyAnnotation], threshold: i32) -> Vec<&PlyAnnotation> {
+    let mut swings: Vec<&PlyAnnotation> = annotations
+        .iter()
+        .filter(|annotation| annotation.loss() >= threshold)
+        .collect();
+    swings.sort_by_key(|annotation| -annotation.loss());
+    swings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_games;
+
+    fn annotate_uci(moves: &str) -> Vec<PlyAnnotation> {
+        let moves: Vec<Move> = moves
+            .split_whitespace()
+            .map(|m| Move::from_uci(m).unwrap())
+            .collect();
+        let game = Game::replay(Board::START_FEN, &moves).unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        annotate(&game, &limits)
+    }
+
+    #[test]
+    fn test_annotate_fools_mate() {
+        let annotations = annotate_uci("f2f3 e7e5 g2g4 d8h4");
+        let sans: Vec<&str> = annotations.iter().map(|a| a.san.as_str()).collect();
+        assert_eq!(sans, ["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(annotations[3].move_number, 2);
+        assert_eq!(annotations[3].to_string(), "2... Qh4#");
+
+        // Each move starts where the last left off, and the game ends mated
+        for pair in annotations.windows(2) {
+            assert_eq!(pair[0].eval_after, pair[1].eval_before);
+        }
+        assert_eq!(annotations[3].eval_after, -MATE_SCORE);
+
+        // g4 allows mate in one, which the engine finds and would have avoided
+        let blunders = largest_swings(&annotations, BLUNDER_THRESHOLD);
+        assert_eq!(blunders[0].san, "g4");
+        assert!(blunders[0].best.is_some());
+        assert!(annotations[3].best.is_none(), "the engine mates too");
+        assert!(annotations[3].best_line.is_empty());
+
+        // Its line replaces g4 in the game written with the engine's lines
+        let moves: Vec<Move> = annotations.iter().map(|a| a.played).collect();
+        let game = Game::replay(Board::START_FEN, &moves).unwrap();
+        let tree = with_engine_lines(&game, &annotations);
+        let at_g4 = tree
+            .variations()
+            .iter()
+            .find(|v| v.game().moves()[..2] == moves[..2] && v.game().moves()[2] != moves[2]);
+        let at_g4 = at_g4.expect("a variation to g4");
+        assert_eq!(at_g4.game().moves()[2..], blunders[0].best_line[..]);
+        assert!(tree
+            .to_pgn()
+            .contains(&format!("2. g4 (2. {}", blunders[0].best.as_ref().unwrap())));
+        assert!(blunders
+            .windows(2)
+            .all(|pair| pair[0].loss() >= pair[1].loss()));
+    }
+
+    /// Plays the first legal move and reports the next of its scripted scores, for
+    /// the side to move, at depth 12.
+    struct ScriptedEngine {
+        scores: Vec<i32>,
+        searched: usize,
+    }
+
+    impl Engine for ScriptedEngine {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn new_game(&mut self) {}
+
+        fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> Option<Move> {
+            self.searched += 1;
+            game.board().legal_moves().first()
+        }
+
+        fn last_score(&self) -> Option<i32> {
+            self.scores.get(self.searched - 1).copied()
+        }
+
+        fn last_depth(&self) -> Option<u32> {
+            Some(12)
+        }
+    }
+
+    #[test]
+    fn test_annotated_pgn_has_evals_nags_and_average_loss() {
+        let moves: Vec<Move> = "e2e4 e7e5 g1f3 b8c6"
+            .split_whitespace()
+            .map(|m| Move::from_uci(m).unwrap())
+            .collect();
+        let game = Game::replay(Board::START_FEN, &moves).unwrap();
+        // From White's side: +0.30, +0.20, +1.50, -1.50, -1.40
+        let mut engine = ScriptedEngine {
+            scores: vec![30, -20, 150, 150, -140],
+            searched: 0,
+        };
+        let annotations = annotate_with(&mut engine, &game, &SearchLimits::default());
+        let losses: Vec<i32> = annotations.iter().map(|a| a.loss()).collect();
+        assert_eq!(losses, [10, 130, 300, 10]);
+
+        let thresholds = Thresholds::default();
+        let judgements: Vec<Option<Judgement>> = annotations
+            .iter()
+            .map(|a| a.judgement(&thresholds))
+            .collect();
+        assert_eq!(
+            judgements,
+            [
+                None,
+                Some(Judgement::Mistake),
+                Some(Judgement::Blunder),
+                None
+            ]
+        );
+        let strict = Thresholds {
+            mistake: 5,
+            blunder: 100,
+        };
+        assert_eq!(annotations[0].judgement(&strict), Some(Judgement::Mistake));
+        assert_eq!(annotations[1].judgement(&strict), Some(Judgement::Blunder));
+
+        assert_eq!(average_loss(&annotations, Color::White), Some(155.0));
+        assert_eq!(average_loss(&annotations, Color::Black), Some(70.0));
+        assert_eq!(average_loss(&[], Color::White), None);
+
+        // Without the engine's lines, which the scripted engine only makes up
+        let without_lines: Vec<PlyAnnotation> = annotations
+            .iter()
+            .map(|a| PlyAnnotation {
+                best_line: Vec::new(),
+                ..a.clone()
+            })
+            .collect();
+        let pgn = annotated_game(&game, &without_lines, &thresholds).to_pgn();
+        let (_, movetext) = pgn.split_once("\n\n").unwrap();
+        assert_eq!(
+            movetext.trim_end().replace('\n', " "),
+            "{Average centipawn loss: White 155, Black 70} 1. e4 {+0.20/12} 1... e5 $2 \
+             {+1.50/12} 2. Nf3 $4 {-1.50/12} 2... Nc6 {-1.40/12} *"
+        );
+
+        // Mates are written in moves, and the written game reads back with the
+        // engine's first legal moves as variations, as none of them was played
+        let mut mated = annotations[3].clone();
+        mated.eval_after = -MATE_SCORE + 5;
+        assert_eq!(eval_comment(&mated), "-M3/12");
+        let tree = annotated_game(&game, &annotations, &thresholds);
+        let back = parse_games(&tree.to_pgn()).remove(0);
+        assert_eq!(back.moves, ["e4", "e5", "Nf3", "Nc6"]);
+        assert_eq!(back.variations.len(), 4);
+    }
+
+    #[test]
+    fn test_candidates_are_scored_best_first() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let candidates: Vec<Move> = ["d2d5", "d2a5", "d2h6"]
+            .iter()
+            .map(|m| Move::from_uci(m).unwrap())
+            .collect();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let scored = score_candidates(&game, &candidates, &limits);
+
+        let moves: Vec<String> = scored.iter().map(|(m, _)| m.to_string()).collect();
+        assert_eq!(moves.len(), 3);
+        assert_eq!(moves[0], "d2d5", "Qxd5 wins the queen");
+        assert!(scored[0].1 > 500, "{}", scored[0].1);
+        assert!(scored.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn test_blunder_check_warns_about_hanging_the_queen() {
+        let game =
+            Game::from_fen("rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2").unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let check = |uci: &str| {
+            check_blunder(
+                &game,
+                Move::from_uci(uci).unwrap(),
+                &limits,
+                BLUNDER_THRESHOLD,
+            )
+        };
+
+        // Nc3 is a quiet developing move
+        assert_eq!(check("b1c3"), None);
+
+        // Qh5 walks into Nxh5
+        let report = check("d1h5").expect("hanging the queen is a blunder");
+        assert_eq!(report.san, "Qh5");
+        assert_eq!(report.refutation_san, "Nxh5");
+        assert_eq!(report.lost, Some(PieceType::Queen));
+        assert!(report.loss() >= 500, "{}", report.loss());
+        assert_eq!(report.to_string(), "This loses a queen to Nxh5");
+    }
+
+    #[test]
+    fn test_hanging_the_queen_is_a_blunder() {
+        // 2. Qh5?? walks into Nxh5
+        let annotations = annotate_uci("e2e4 g8f6 d1h5");
+        let blunders = largest_swings(&annotations, BLUNDER_THRESHOLD);
+
+        assert_eq!(blunders.len(), 1);
+        assert_eq!(blunders[0].san, "Qh5");
+        assert!(blunders[0].loss() >= 500, "{}", blunders[0].loss());
+    }
+}
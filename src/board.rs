@@ -1,14 +1,37 @@
-use crate::chess_move::{find_peice_at_from_location, validate_move, Move};
-use crate::utils::EDGE_DISTANCES;
-
+use crate::castling::{CastlingConfig, CastlingSide};
+use crate::chess_move::{
+    find_peice_at_from_location, find_peice_for_color, generate_all_moves_for_color_into,
+    generate_king_moves, generate_moves_for_peice, generate_sliding_moves_for_color, square_name,
+    validate_move, Move, MoveError, MoveKind, MoveList, PROMOTION_PIECES,
+};
+use crate::eval::IncrementalTerms;
+use crate::game::GameError;
+use crate::utils::{
+    squares_between, EAST, EDGE_DISTANCES, NORTH, NORTH_EAST, NORTH_WEST, SOUTH, SOUTH_EAST,
+    SOUTH_WEST, WEST,
+};
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Not;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Color {
     White,
     Black,
 }
 
+impl Color {
+    /// Both colors, White first.
+    pub const BOTH: [Color; 2] = [Color::White, Color::Black];
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 impl Not for Color {
     type Output = Color;
 
@@ -31,23 +54,80 @@ pub enum PieceType {
 }
 
 impl PieceType {
-    /// Creates a new `PieceType`
-    pub fn from(piece_type_str: String) -> Self {
+    /// Every piece type, in [`Board::bitboards`] order.
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
+    /// Creates a new `PieceType` from a lowercase letter. Panics on anything else;
+    /// use [`PieceType::try_from_char`] for input that may not be a piece.
+    pub fn from(piece_type_str: &str) -> Self {
         let piece_type_str = piece_type_str.chars().next().unwrap();
-        let piece_type = match piece_type_str {
+        match PieceType::try_from_char(piece_type_str) {
+            Ok((Color::Black, piece_type)) => piece_type,
+            _ => panic!("Invalid piece type: {}", piece_type_str),
+        }
+    }
+
+    /// The FEN letter of this piece, uppercase for White.
+    pub fn to_char(self, color: Color) -> char {
+        let c = match self {
+            PieceType::Pawn => 'p',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Rook => 'r',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
+        match color {
+            Color::White => c.to_ascii_uppercase(),
+            Color::Black => c,
+        }
+    }
+
+    /// The piece a FEN letter stands for, uppercase being White.
+    pub fn try_from_char(c: char) -> Result<(Color, PieceType), PieceCharError> {
+        let piece_type = match c.to_ascii_lowercase() {
             'p' => PieceType::Pawn,
             'n' => PieceType::Knight,
             'b' => PieceType::Bishop,
             'r' => PieceType::Rook,
             'q' => PieceType::Queen,
             'k' => PieceType::King,
-            _ => panic!("Invalid piece type: {}", piece_type_str),
+            _ => return Err(PieceCharError(c)),
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
         };
+        Ok((color, piece_type))
+    }
+}
+
+impl fmt::Display for PieceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A character that is not one of the FEN piece letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PieceCharError(pub char);
 
-        piece_type
+impl fmt::Display for PieceCharError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a piece", self.0)
     }
 }
 
+impl std::error::Error for PieceCharError {}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Piece {
     color: Color,
@@ -61,6 +141,344 @@ enum Square {
     Piece(Piece),
 }
 
+/// Why a FEN string could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The FEN does not have six space-separated fields.
+    FieldCount(usize),
+    /// The piece placement field is malformed.
+    Placement(String),
+    ActiveColor(String),
+    Castling(String),
+    EnPassant(String),
+    /// The halfmove clock or fullmove number is not a number.
+    Counter(String),
+    /// The FEN reads, but the rules cannot be applied to the position.
+    Illegal(IllegalPosition),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::FieldCount(count) => write!(f, "expected 6 fields, found {}", count),
+            FenError::Placement(reason) => write!(f, "bad piece placement: {}", reason),
+            FenError::ActiveColor(color) => write!(f, "bad active color: {}", color),
+            FenError::Castling(rights) => write!(f, "bad castling rights: {}", rights),
+            FenError::EnPassant(square) => write!(f, "bad en passant square: {}", square),
+            FenError::Counter(counter) => write!(f, "bad move counter: {}", counter),
+            FenError::Illegal(reason) => write!(f, "illegal position: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// What [`Board::try_from_fen_with_notes`] read from a FEN besides the position:
+/// the parts of variant FENs a board has no place for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FenNotes {
+    /// The Crazyhouse holdings, the pieces in brackets after the placement such as
+    /// `Pn`; empty for `[]`, `None` without brackets.
+    pub holdings: Option<String>,
+    /// What was read but does not count for the rules, for the caller to show.
+    pub warnings: Vec<String>,
+}
+
+/// Whether a position can be played from, from [`Board::validity`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionValidity {
+    Ok,
+    /// Play could never reach the position, but the rules still work in it, as
+    /// with nine pawns a side. What is odd about it, for the caller to show.
+    Warning(Vec<String>),
+    /// The rules cannot be applied to the position.
+    Illegal(IllegalPosition),
+}
+
+/// Why the rules cannot be applied to a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalPosition {
+    /// A side has no king, or more than one.
+    KingCount {
+        color: Color,
+        kings: u32,
+    },
+    KingsAdjacent,
+    /// A pawn stands on the first or last rank, where it can never be.
+    PawnOnBackRank {
+        square: u8,
+    },
+    /// The side that just moved left its king in check.
+    NotToMoveInCheck {
+        color: Color,
+    },
+}
+
+impl fmt::Display for IllegalPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IllegalPosition::KingCount { color, kings } => {
+                write!(f, "{} has {} kings", color, kings)
+            }
+            IllegalPosition::KingsAdjacent => write!(f, "the kings stand next to each other"),
+            IllegalPosition::PawnOnBackRank { square } => {
+                write!(
+                    f,
+                    "pawn on the first or last rank at {}",
+                    square_name(*square)
+                )
+            }
+            IllegalPosition::NotToMoveInCheck { color } => {
+                write!(f, "{} is in check but it is not their move", color)
+            }
+        }
+    }
+}
+
+/// How [`Board::try_from_fen`] treats en passant squares and castling rights that
+/// cannot be right for the position, and positions the rules cannot be applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strictness {
+    /// Rejects them, illegal positions with [`FenError::Illegal`].
+    Strict,
+    /// Drops them: an en passant square nobody can capture on becomes "-", and
+    /// castling rights without the king and rook at home are removed.
+    Lax,
+}
+
+/// The usual piece values in centipawns, in [`PieceType::ALL`] order. The king
+/// counts for nothing as both sides always have one.
+pub const PIECE_VALUES: [i32; 6] = [100, 300, 300, 500, 900, 0];
+
+/// How many pieces of each type one side has, indexed by [`PieceType`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaterialCount {
+    pub counts: [u32; 6],
+}
+
+impl MaterialCount {
+    pub fn count(&self, peice_type: PieceType) -> u32 {
+        self.counts[peice_type as usize]
+    }
+
+    /// The material's worth with `values` in [`PieceType::ALL`] order, e.g.
+    /// [`PIECE_VALUES`].
+    pub fn value(&self, values: &[i32; 6]) -> i32 {
+        self.counts
+            .iter()
+            .zip(values)
+            .map(|(&count, &value)| count as i32 * value)
+            .sum()
+    }
+}
+
+/// Writes the pieces besides the king, most valuable first, e.g. `Q, 2R, B, 5P`, or
+/// `-` for a bare king.
+impl fmt::Display for MaterialCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut written = 0;
+        for peice_type in PieceType::ALL[..5].iter().rev() {
+            let count = self.count(*peice_type);
+            if count == 0 {
+                continue;
+            }
+            if written > 0 {
+                write!(f, ", ")?;
+            }
+            if count > 1 {
+                write!(f, "{}", count)?;
+            }
+            write!(f, "{}", peice_type.to_char(Color::White))?;
+            written += 1;
+        }
+        if written == 0 {
+            write!(f, "-")?;
+        }
+        Ok(())
+    }
+}
+
+/// One side's legal moves counted, as part of [`MoveStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SideMoveStats {
+    /// Legal moves, a promotion counting once for each piece.
+    pub moves: u32,
+    /// Legal moves by the type of the piece moved, indexed by [`PieceType`].
+    pub by_piece: [u32; 6],
+    pub captures: u32,
+    pub checks: u32,
+}
+
+/// Move counts of a position, from [`Board::move_stats`], as features for analysis
+/// tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveStats {
+    pub white: SideMoveStats,
+    pub black: SideMoveStats,
+    /// Whether the side to move is in check.
+    pub in_check: bool,
+}
+
+impl MoveStats {
+    pub fn side(&self, color: Color) -> &SideMoveStats {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    /// The stats as a single-line JSON object, with the fields named as serde names
+    /// them.
+    ///
+    /// ```text
+    /// {"white":{"moves":20,"by_piece":[16,4,0,0,0,0],"captures":0,"checks":0},"black":{...},"in_check":false}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let side = |stats: &SideMoveStats| {
+            let by_piece: Vec<String> = stats.by_piece.iter().map(u32::to_string).collect();
+            format!(
+                "{{\"moves\":{},\"by_piece\":[{}],\"captures\":{},\"checks\":{}}}",
+                stats.moves,
+                by_piece.join(","),
+                stats.captures,
+                stats.checks
+            )
+        };
+        format!(
+            "{{\"white\":{},\"black\":{},\"in_check\":{}}}",
+            side(&self.white),
+            side(&self.black),
+            self.in_check
+        )
+    }
+}
+
+/// How a position stands by the rules, from [`Board::status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    InProgress,
+    /// The side to move is in check but has a way out.
+    Check,
+    /// The side to move is mated.
+    Checkmate {
+        winner: Color,
+    },
+    Stalemate,
+    InsufficientMaterial,
+    /// Fifty moves by each side without a capture or pawn move. A draw a player may
+    /// claim; [`Board::status`], with no one to claim it, reports it as one.
+    FiftyMoveRule,
+    /// The position occurred three times. Like [`GameStatus::FiftyMoveRule`], a draw
+    /// to claim.
+    Repetition,
+    /// Seventy-five moves by each side without a capture or pawn move, which ends
+    /// the game by itself. Only [`crate::game::Game::status`] reports it.
+    SeventyFiveMoveRule,
+    /// The position occurred five times, which ends the game by itself. Only
+    /// [`crate::game::Game::status`] reports it.
+    FivefoldRepetition,
+    /// A side has no king, or more than one, so the rules cannot say. Test positions
+    /// and half-finished setups are like this; play is not.
+    Invalid,
+}
+
+impl GameStatus {
+    /// True once the game has ended.
+    pub fn is_over(self) -> bool {
+        !matches!(
+            self,
+            GameStatus::InProgress | GameStatus::Check | GameStatus::Invalid
+        )
+    }
+}
+
+/// Announces the status, e.g. `Checkmate — White wins` or `Stalemate — draw`.
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameStatus::InProgress => write!(f, "In progress"),
+            GameStatus::Check => write!(f, "Check"),
+            GameStatus::Checkmate { winner } => write!(f, "Checkmate — {} wins", winner),
+            GameStatus::Stalemate => write!(f, "Stalemate — draw"),
+            GameStatus::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            GameStatus::FiftyMoveRule => write!(f, "Draw by the fifty-move rule"),
+            GameStatus::Repetition => write!(f, "Draw by threefold repetition"),
+            GameStatus::SeventyFiveMoveRule => write!(f, "Draw by the seventy-five-move rule"),
+            GameStatus::FivefoldRepetition => write!(f, "Draw by fivefold repetition"),
+            GameStatus::Invalid => write!(f, "Invalid position — each side needs one king"),
+        }
+    }
+}
+
+/// State saved by [`Board::make_move_unchecked`] so the move can be taken back.
+#[derive(Debug, Clone, Copy)]
+pub struct Undo {
+    moved: Option<PieceType>,
+    /// Bitboard index and square of the captured piece.
+    captured: Option<(u8, u8)>,
+    castling_rights: u8,
+    en_passant: Option<u8>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    king_moves: u32,
+    position_count: u8,
+    promoted: u64,
+    /// Oldest position hash, pushed out of a full history by this move.
+    dropped_position: Option<u64>,
+    eval_terms: IncrementalTerms,
+}
+
+/// State saved by [`Board::make_null_move`] so the pass can be taken back.
+#[derive(Debug, Clone, Copy)]
+pub struct NullUndo {
+    en_passant: Option<u8>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+/// Which position a [`Board`] holds, from [`Board::position_key`], for keying maps
+/// and sets by position.
+///
+/// Two boards have the same key when the pieces stand on the same squares, the same
+/// side is to move and the same castling rights and en passant captures are open.
+/// The en passant square only counts when a capture is really possible, as for
+/// repetitions. The clocks and the history are left out: they record how the
+/// position was reached, not what it is, and the same position after another move
+/// order should land on the same entry.
+///
+/// Hashing uses the Polyglot key alone; equality compares every field, so two
+/// positions whose keys collide still stay apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionKey {
+    zobrist: u64,
+    bitboards: [u64; 12],
+    active_color: Color,
+    castling_rights: u8,
+    en_passant: Option<u8>,
+    castling: CastlingConfig,
+}
+
+impl PositionKey {
+    /// The Polyglot key of the position.
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+}
+
+impl Hash for PositionKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.zobrist.hash(state);
+    }
+}
+
+/// A full game position.
+///
+/// `Board` is `Copy` so legality checks can play a move on a scratch copy. It is
+/// 968 bytes, most of which is `hashed_board_states`, so pass it around by reference
+/// and only copy it when a scratch position is really needed. A test pins the size
+/// so it does not grow unnoticed.
 #[derive(Debug, Clone, Copy)]
 pub struct Board {
     pub bitboards: [u64; 12],
@@ -86,11 +504,130 @@ pub struct Board {
     pub halfmove_clock: u32,
     pub fullmove_number: u32,
     pub king_moves: u32,
-    pub position_count: u8,  // Track how many positions we've stored
+    pub position_count: u8, // Track how many positions we've stored
     pub hashed_board_states: [u64; 100], // Store up to 100 previous positions as hashes
+    /// Squares of pieces that arose by promotion, which Crazyhouse FENs mark with `~`.
+    pub promoted: u64,
+    /// Where the kings and rooks start, and so how castling moves them.
+    pub castling: CastlingConfig,
+    /// Piece-square sums and phase for the evaluation, kept up to date by make and
+    /// unmake.
+    eval_terms: IncrementalTerms,
+}
+
+/// Bitboards of the standard starting position, in [`Board::bitboards`] order.
+const START_BITBOARDS: [u64; 12] = [
+    0x0000_0000_0000_ff00, // White pawns
+    0x0000_0000_0000_0042, // White knights
+    0x0000_0000_0000_0024, // White bishops
+    0x0000_0000_0000_0081, // White rooks
+    0x0000_0000_0000_0008, // White queen
+    0x0000_0000_0000_0010, // White king
+    0x00ff_0000_0000_0000, // Black pawns
+    0x4200_0000_0000_0000, // Black knights
+    0x2400_0000_0000_0000, // Black bishops
+    0x8100_0000_0000_0000, // Black rooks
+    0x0800_0000_0000_0000, // Black queen
+    0x1000_0000_0000_0000, // Black king
+];
+
+/// The standard starting position, built without parsing a FEN.
+impl Default for Board {
+    fn default() -> Board {
+        Board {
+            bitboards: START_BITBOARDS,
+            all_white_bitboard: 0x0000_0000_0000_ffff,
+            all_black_bitboard: 0xffff_0000_0000_0000,
+            active_color: Color::White,
+            castling_rights: 0b1111,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            king_moves: 0,
+            position_count: 0,
+            hashed_board_states: [0; 100],
+            promoted: 0,
+            castling: CastlingConfig::STANDARD,
+            eval_terms: IncrementalTerms::from_bitboards(&START_BITBOARDS),
+        }
+    }
 }
 
 impl Board {
+    /// FEN of the standard starting position.
+    pub const START_FEN: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// The standard starting position, the same as [`Board::default`].
+    pub fn new() -> Board {
+        Board::default()
+    }
+
+    /// Squares of `color`'s pieces of type `piece_type`.
+    #[inline]
+    pub fn pieces(&self, color: Color, piece_type: PieceType) -> u64 {
+        self.bitboards[color as usize * 6 + piece_type as usize]
+    }
+
+    /// Every square with a piece on it.
+    #[inline]
+    pub fn occupied(&self) -> u64 {
+        self.all_white_bitboard | self.all_black_bitboard
+    }
+
+    /// Squares of `color`'s pieces.
+    #[inline]
+    pub fn occupied_by(&self, color: Color) -> u64 {
+        match color {
+            Color::White => self.all_white_bitboard,
+            Color::Black => self.all_black_bitboard,
+        }
+    }
+
+    /// The piece-square sums and phase the evaluation reads, as of the last move.
+    #[inline]
+    pub fn eval_terms(&self) -> &IncrementalTerms {
+        &self.eval_terms
+    }
+
+    /// Type of `color`'s piece on `square`, if there is one.
+    fn piece_on(&self, color: Color, square: u8) -> Option<PieceType> {
+        PieceType::ALL
+            .into_iter()
+            .find(|&piece_type| self.pieces(color, piece_type) & (1 << square) != 0)
+    }
+
+    /// What the side to move's move `m` does in this position. `m` is taken to be
+    /// pseudo-legal; a move from an empty square counts as quiet.
+    pub fn classify(&self, m: &Move) -> MoveKind {
+        let moved = self.piece_on(self.active_color, m.from);
+        let captured = self.piece_on(!self.active_color, m.to);
+
+        let castle = self.castling.side_of(self.active_color, m);
+
+        match (moved, captured, m.promotion) {
+            (Some(PieceType::King), _, _) if castle.is_some() => match castle {
+                Some(CastlingSide::Kingside) => MoveKind::CastleKingside,
+                _ => MoveKind::CastleQueenside,
+            },
+            (_, Some(captured), Some(promotion)) => MoveKind::CapturePromotion(captured, promotion),
+            (_, None, Some(promotion)) => MoveKind::Promotion(promotion),
+            (_, Some(captured), None) => MoveKind::Capture(captured),
+            (Some(PieceType::Pawn), None, None) if self.en_passant == Some(m.to) => {
+                MoveKind::EnPassant
+            }
+            (Some(PieceType::Pawn), None, None) if m.from.abs_diff(m.to) == 16 => {
+                MoveKind::DoublePawnPush
+            }
+            _ => MoveKind::Quiet,
+        }
+    }
+
+    /// Square of `color`'s king, or `None` if it has no king (as in some test positions).
+    pub fn king_square(&self, color: Color) -> Option<u8> {
+        let king_bb = self.pieces(color, PieceType::King);
+        (king_bb != 0).then(|| king_bb.trailing_zeros() as u8)
+    }
+
     /// Parse the piece-placement field of a FEN (the first space-delimited part)
     /// into an array of 64 squares, where:
     ///
@@ -100,15 +637,20 @@ impl Board {
     /// - `squares[63]` is h8.
     ///
     /// The FEN ranks are given top-to-bottom: rank 8 first, then rank 7, etc.
-    fn fen_to_positions(fen_board: &str) -> [Square; 64] {
+    ///
+    /// A `~` after a piece, as Crazyhouse FENs write it, marks it as promoted; the
+    /// squares of those pieces are returned too.
+    fn fen_to_positions(fen_board: &str) -> Result<([Square; 64], u64), FenError> {
         let mut squares = [Square::Empty; 64];
+        let mut promoted = 0u64;
 
         let ranks: Vec<&str> = fen_board.split('/').collect();
-        assert_eq!(
-            ranks.len(),
-            8,
-            "FEN board must have 8 ranks separated by '/'"
-        );
+        if ranks.len() != 8 {
+            return Err(FenError::Placement(format!(
+                "expected 8 ranks separated by '/', found {}",
+                ranks.len()
+            )));
+        }
 
         // FEN rank 0 = top row (8th rank), rank 7 = bottom row (1st rank).
         // But in our squares array, rank 0 corresponds to squares[0..8] (bottom).
@@ -118,155 +660,250 @@ impl Board {
             let mut file = 0;
 
             for ch in rank_str.chars() {
-                match ch {
-                    '1'..='8' => {
-                        // A digit means N consecutive empty squares
-                        let empty_count = ch.to_digit(10).unwrap() as usize;
-                        file += empty_count;
-                    }
-                    'p' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::Black,
-                            piece_type: PieceType::Pawn,
-                        });
-                        file += 1;
-                    }
-                    'n' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::Black,
-                            piece_type: PieceType::Knight,
-                        });
-                        file += 1;
-                    }
-                    'b' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::Black,
-                            piece_type: PieceType::Bishop,
-                        });
-                        file += 1;
-                    }
-                    'r' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::Black,
-                            piece_type: PieceType::Rook,
-                        });
-                        file += 1;
+                if ch == '~' {
+                    // Marks the piece just placed
+                    let square = (board_rank * 8 + file).checked_sub(1).filter(|_| file > 0);
+                    match square.map(|square| (square, squares[square])) {
+                        Some((square, Square::Piece(piece)))
+                            if !matches!(piece.piece_type, PieceType::Pawn | PieceType::King)
+                                && promoted & (1 << square) == 0 =>
+                        {
+                            promoted |= 1 << square;
+                        }
+                        _ => {
+                            return Err(FenError::Placement(format!(
+                                "'~' in rank '{}' does not follow a piece that can be promoted",
+                                rank_str
+                            )))
+                        }
                     }
-                    'q' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::Black,
-                            piece_type: PieceType::Queen,
-                        });
-                        file += 1;
-                    }
-                    'k' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::Black,
-                            piece_type: PieceType::King,
-                        });
-                        file += 1;
-                    }
-                    'P' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::White,
-                            piece_type: PieceType::Pawn,
-                        });
-                        file += 1;
-                    }
-                    'N' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::White,
-                            piece_type: PieceType::Knight,
-                        });
-                        file += 1;
-                    }
-                    'B' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::White,
-                            piece_type: PieceType::Bishop,
-                        });
-                        file += 1;
-                    }
-                    'R' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::White,
-                            piece_type: PieceType::Rook,
-                        });
-                        file += 1;
-                    }
-                    'Q' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::White,
-                            piece_type: PieceType::Queen,
-                        });
-                        file += 1;
-                    }
-                    'K' => {
-                        squares[board_rank * 8 + file] = Square::Piece(Piece {
-                            color: Color::White,
-                            piece_type: PieceType::King,
-                        });
-                        file += 1;
-                    }
-                    // Ignore the slash itself — it’s part of the FEN rank separators
-                    _ => panic!("Invalid FEN character: {}", ch),
+                    continue;
                 }
+                if file >= 8 {
+                    return Err(FenError::Placement(format!(
+                        "rank '{}' has more than 8 squares",
+                        rank_str
+                    )));
+                }
+                if let Some(empty_count) = ch.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                    // A digit means N consecutive empty squares
+                    file += empty_count as usize;
+                    continue;
+                }
+                let (color, piece_type) = PieceType::try_from_char(ch).map_err(|_| {
+                    FenError::Placement(format!(
+                        "invalid character '{}' in rank '{}'",
+                        ch, rank_str
+                    ))
+                })?;
+                squares[board_rank * 8 + file] = Square::Piece(Piece { color, piece_type });
+                file += 1;
+            }
+            if file != 8 {
+                return Err(FenError::Placement(format!(
+                    "rank '{}' has {} squares instead of 8",
+                    rank_str, file
+                )));
             }
-            assert!(
-                file <= 8,
-                "FEN rank '{}' has too many squares (exceeds 8)",
-                rank_str
-            );
         }
 
-        squares
+        Ok((squares, promoted))
     }
 
-    /// Parse an entire FEN string into a `Board`.
+    /// Parse an entire FEN string into a `Board`. Panics if the FEN is invalid; use
+    /// [`Board::from_fen`] for input that may be.
     /// Expected format: "<piece-placements> <active_color> <castling> <en_passant> <halfmove> <fullmove>"
     pub fn fen_to_board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap_or_else(|err| panic!("Invalid FEN string: {}", err))
+    }
+
+    /// Parses a FEN string, reporting what is wrong with it instead of panicking.
+    /// `"startpos"`, as UCI writes it, is the standard starting position.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        if fen.trim() == "startpos" {
+            return Ok(Board::default());
+        }
         let parts: Vec<&str> = fen.split_whitespace().collect();
-        assert_eq!(parts.len(), 6, "Invalid FEN string");
+        if parts.len() != 6 {
+            return Err(FenError::FieldCount(parts.len()));
+        }
+        Board::from_fen_fields(&parts).map(|(board, _)| board)
+    }
+
+    /// Parses a FEN as other tools write it: the move counters may be left off (they
+    /// default to 0 and 1), and en passant squares and castling rights the position
+    /// does not allow are rejected or dropped depending on `strictness`.
+    pub fn try_from_fen(fen: &str, strictness: Strictness) -> Result<Board, FenError> {
+        Board::try_from_fen_with_notes(fen, strictness).map(|(board, _)| board)
+    }
+
+    /// [`Board::try_from_fen`], also returning what the FEN held that the board
+    /// does not keep, such as Crazyhouse holdings. Both accept such FENs.
+    pub fn try_from_fen_with_notes(
+        fen: &str,
+        strictness: Strictness,
+    ) -> Result<(Board, FenNotes), FenError> {
+        if fen.trim() == "startpos" {
+            return Ok((Board::default(), FenNotes::default()));
+        }
+        let mut parts: Vec<&str> = fen.split_whitespace().collect();
+        match parts.len() {
+            4 => parts.extend(["0", "1"]),
+            6 => {}
+            count => return Err(FenError::FieldCount(count)),
+        }
+        let (mut board, notes) = Board::from_fen_fields(&parts)?;
+        if strictness == Strictness::Strict {
+            if let PositionValidity::Illegal(reason) = board.validity() {
+                return Err(FenError::Illegal(reason));
+            }
+        }
+
+        let possible_rights = board.possible_castling_rights();
+        if board.castling_rights & !possible_rights != 0 {
+            match strictness {
+                Strictness::Strict => return Err(FenError::Castling(parts[2].to_string())),
+                Strictness::Lax => board.castling_rights &= possible_rights,
+            }
+        }
+        if board.en_passant.is_some() && board.en_passant_square().is_none() {
+            match strictness {
+                Strictness::Strict => return Err(FenError::EnPassant(parts[3].to_string())),
+                Strictness::Lax => board.en_passant = None,
+            }
+        }
+        Ok((board, notes))
+    }
+
+    /// Castling rights the kings and rooks still on their starting squares allow.
+    fn possible_castling_rights(&self) -> u8 {
+        let home =
+            |color, piece_type, square: u8| self.pieces(color, piece_type) & (1 << square) != 0;
+        let mut rights = 0;
+        for color in Color::BOTH {
+            if !home(color, PieceType::King, self.castling.king_start(color)) {
+                continue;
+            }
+            for side in CastlingSide::BOTH {
+                if home(
+                    color,
+                    PieceType::Rook,
+                    self.castling.rook_start(color, side),
+                ) {
+                    rights |= self.castling.right(color, side);
+                }
+            }
+        }
+        rights
+    }
+
+    /// The en passant square if the side to move can legally capture on it, as
+    /// X-FEN writes it. [`Board::en_passant`] keeps the square after every double
+    /// pawn push.
+    pub fn en_passant_square(&self) -> Option<u8> {
+        let square = self.en_passant?;
+        let mut attackers = self.en_passant_attackers();
+        while attackers != 0 {
+            let from = attackers.trailing_zeros() as u8;
+            attackers &= attackers - 1;
+            if self.is_legal(&Move::new_from_indices(from, square, None)) {
+                return Some(square);
+            }
+        }
+        None
+    }
+
+    /// The side to move's pawns that can take en passant, pins and checks aside. They
+    /// take the pawn that just moved two squares without attacking its square, so
+    /// [`Board::attackers_to`] that square leaves them out.
+    pub fn en_passant_attackers(&self) -> u64 {
+        let (square, victim) = match self.en_passant_victim() {
+            Some(squares) => squares,
+            None => return 0,
+        };
+        if self.occupied() & (1 << square) != 0 {
+            return 0;
+        }
+        // They stand beside the pawn that just moved two squares, on its rank
+        let beside = Self::get_king_attack_bitboard(1 << victim) & (0xff << (victim / 8 * 8));
+        beside & self.pieces(self.active_color, PieceType::Pawn)
+    }
+
+    /// The en passant square and the square of the pawn that just moved two squares
+    /// past it, if that pawn is still there.
+    fn en_passant_victim(&self) -> Option<(u8, u8)> {
+        let square = self.en_passant?;
+        let victim = match self.active_color {
+            Color::White if square / 8 == 5 => square - 8,
+            Color::Black if square / 8 == 2 => square + 8,
+            _ => return None,
+        };
+        (self.pieces(!self.active_color, PieceType::Pawn) & (1 << victim) != 0)
+            .then_some((square, victim))
+    }
 
-        // 1) Piece placement
-        let squares = Board::fen_to_positions(parts[0]);
+    /// Builds a board from the six fields of a FEN, with the notes on what it held
+    /// besides.
+    fn from_fen_fields(parts: &[&str]) -> Result<(Board, FenNotes), FenError> {
+        // 1) Piece placement, perhaps followed by Crazyhouse holdings: "...R[Pn]"
+        let mut notes = FenNotes::default();
+        let placement = match parts[0].strip_suffix(']').and_then(|p| p.split_once('[')) {
+            Some((placement, holdings)) => {
+                if holdings.chars().any(
+                    |c| !matches!(PieceType::try_from_char(c), Ok((_, p)) if p != PieceType::King),
+                ) {
+                    return Err(FenError::Placement(format!("bad holdings: [{}]", holdings)));
+                }
+                if !holdings.is_empty() {
+                    notes.warnings.push(format!(
+                        "the pieces in hand, {}, are ignored: drops are not played",
+                        holdings
+                    ));
+                }
+                notes.holdings = Some(holdings.to_string());
+                placement
+            }
+            None => parts[0],
+        };
+        let (squares, promoted) = Board::fen_to_positions(placement)?;
 
         // 2) Active color
         let active_color = match parts[1] {
             "w" => Color::White,
             "b" => Color::Black,
-            _ => panic!("Invalid active color: {}", parts[1]),
+            _ => return Err(FenError::ActiveColor(parts[1].to_string())),
         };
 
         // 3) Castling rights
         let mut castling_rights = 0;
-        if parts[2].contains('K') {
-            castling_rights |= 1; // White kingside
-        }
-        if parts[2].contains('Q') {
-            castling_rights |= 1 << 1; // White queenside
-        }
-        if parts[2].contains('k') {
-            castling_rights |= 1 << 2; // Black kingside
-        }
-        if parts[2].contains('q') {
-            castling_rights |= 1 << 3; // Black queenside
+        if parts[2] != "-" {
+            for c in parts[2].chars() {
+                castling_rights |= match c {
+                    'K' => 1,      // White kingside
+                    'Q' => 1 << 1, // White queenside
+                    'k' => 1 << 2, // Black kingside
+                    'q' => 1 << 3, // Black queenside
+                    _ => return Err(FenError::Castling(parts[2].to_string())),
+                };
+            }
         }
 
-        // 4) En passant
-        let en_passant = if parts[3] != "-" {
-            let file = parts[3].chars().nth(0).unwrap() as u8 - 'a' as u8;
-            let rank = parts[3].chars().nth(1).unwrap() as u8 - '1' as u8;
-            Some(rank * 8 + file)
-        } else {
-            None
+        // 4) En passant, always on the third or sixth rank
+        let en_passant = match parts[3].as_bytes() {
+            b"-" => None,
+            [file @ b'a'..=b'h', rank @ (b'3' | b'6')] => Some((rank - b'1') * 8 + (file - b'a')),
+            _ => return Err(FenError::EnPassant(parts[3].to_string())),
         };
 
         // 5) Halfmove clock
-        let halfmove_clock = parts[4].parse::<u32>().unwrap();
+        let halfmove_clock = parts[4]
+            .parse::<u32>()
+            .map_err(|_| FenError::Counter(parts[4].to_string()))?;
 
         // 6) Fullmove number
-        let fullmove_number = parts[5].parse::<u32>().unwrap();
+        let fullmove_number = parts[5]
+            .parse::<u32>()
+            .map_err(|_| FenError::Counter(parts[5].to_string()))?;
 
         // Build bitboards based on squares
         let mut bitboards = [0u64; 12];
@@ -284,47 +921,28 @@ impl Board {
             }
         }
 
-        Board {
+        let board = Board {
             bitboards,
             active_color,
             castling_rights,
             en_passant,
             halfmove_clock,
             fullmove_number,
-            all_white_bitboard: all_white_bitboard,
-            all_black_bitboard: all_black_bitboard,
+            all_white_bitboard,
+            all_black_bitboard,
             king_moves: 0,
             position_count: 0,
             hashed_board_states: [0; 100],
-        }
+            promoted,
+            castling: CastlingConfig::STANDARD,
+            eval_terms: IncrementalTerms::from_bitboards(&bitboards),
+        };
+        Ok((board, notes))
     }
 
     /// Print a textual representation of the board to stdout.
     pub fn display(&self) {
-        // Use these symbols to show the board (white first, then black)
-        let piece_chars = [
-            '♙', '♘', '♗', '♖', '♕', '♔', // White
-            '♟', '♞', '♝', '♜', '♛', '♚', // Black
-        ];
-
-        // row 7 is top (rank 8) in typical text display, row 0 is bottom (rank 1)
-        println!("  a b c d e f g h");
-        for rank in (0..8).rev() {
-            print!("{} ", rank + 1);
-            for file in 0..8 {
-                let sq_index = rank * 8 + file;
-                let mut ch = '*';
-                for (i, bitboard) in self.bitboards.iter().enumerate() {
-                    if (bitboard & (1 << sq_index)) != 0 {
-                        ch = piece_chars[i];
-                        break;
-                    }
-                }
-                print!("{} ", ch);
-            }
-            println!("{}", rank + 1);
-        }
-        println!("  a b c d e f g h");
+        print!("{}", self.to_labeled_diagram());
         println!("Active Color: {:?}", self.active_color);
         println!(
             "Castling Rights: {}{}{}{}",
@@ -352,7 +970,7 @@ impl Board {
         if let Some(square) = self.en_passant {
             println!(
                 "En Passant Target: {}{}",
-                ('a' as u8 + (square % 8) as u8) as char,
+                (b'a' + (square % 8)) as char,
                 (square / 8) + 1
             );
         } else {
@@ -362,7 +980,7 @@ impl Board {
         println!("Fullmove Number: {}", self.fullmove_number);
     }
 
-    /// Utility to print a 64-bit bitboard in an 8x8 grid to stdout.
+    // Utility to print a 64-bit bitboard in an 8x8 grid to stdout.
     // pub fn display_bitboard(bitboard: u64) {
     //     for i in (0..64).rev() {
     //         if i % 8 == 7 {
@@ -381,6 +999,12 @@ impl Board {
     /// from a1..h1 up to a8..h8, you have to be careful to output ranks
     /// top-to-bottom.
     pub fn board_to_fen(&self) -> String {
+        self.board_to_fen_with(false)
+    }
+
+    /// [`Board::board_to_fen`], with `mark_promoted` writing a `~` after each piece
+    /// that arose by promotion, as Crazyhouse FENs do.
+    pub fn board_to_fen_with(&self, mark_promoted: bool) -> String {
         let mut fen = String::new();
 
         // For each rank from top (7) to bottom (0):
@@ -403,22 +1027,12 @@ impl Board {
                         empty_count = 0;
                     }
 
-                    let piece_char = match piece_index {
-                        0 => 'P',  // White Pawn
-                        1 => 'N',  // White Knight
-                        2 => 'B',  // White Bishop
-                        3 => 'R',  // White Rook
-                        4 => 'Q',  // White Queen
-                        5 => 'K',  // White King
-                        6 => 'p',  // Black Pawn
-                        7 => 'n',  // Black Knight
-                        8 => 'b',  // Black Bishop
-                        9 => 'r',  // Black Rook
-                        10 => 'q', // Black Queen
-                        11 => 'k', // Black King
-                        _ => panic!("Invalid piece index: {piece_index}"),
-                    };
+                    let color = Color::BOTH[piece_index / 6];
+                    let piece_char = PieceType::ALL[piece_index % 6].to_char(color);
                     fen.push(piece_char);
+                    if mark_promoted && self.promoted & (1 << sq_index) != 0 {
+                        fen.push('~');
+                    }
                 } else {
                     empty_count += 1;
                 }
@@ -461,10 +1075,10 @@ impl Board {
             }
         }
 
-        // En passant
+        // En passant, only if it can be taken
         fen.push(' ');
-        if let Some(square) = self.en_passant {
-            let file = (square % 8) as u8 + b'a';
+        if let Some(square) = self.en_passant_square() {
+            let file = (square % 8) + b'a';
             let rank = (square / 8) + 1;
             fen.push(file as char);
             fen.push_str(&rank.to_string());
@@ -483,229 +1097,372 @@ impl Board {
         fen
     }
 
+    /// Plays `m` if it is legal and returns whether it was. The move is made on a copy
+    /// of the board that replaces this one at the end, so a rejected move leaves
+    /// every field as it was.
     pub fn move_peice(&mut self, m: Move) -> bool {
-        let valid = validate_move(self, &m);
-        if !valid {
+        // The king may not be left in check, nor castle out of or through it
+        if !validate_move(self, &m) || !self.is_legal(&m) {
             return false;
         }
 
-        // If it's a king move, increment the counter
-        if let Some(piece_type) = find_peice_at_from_location(self, m.from) {
-            if piece_type == PieceType::King {
-                self.king_moves += 1;
-            }
-        }
-        else {
-            self.king_moves = 0;
-        }
+        // figure out what peice were moving
+        let peice_type = match find_peice_at_from_location(self, m.from) {
+            Some(peice_type) => peice_type,
+            None => return false,
+        };
 
-        let prev_board_state = self.clone();
-        if valid {
-            // Update the board state
+        let mut next = *self;
+        let kind = next.classify(&m);
+        next.place_peices(&m, peice_type);
+        next.eval_terms = IncrementalTerms::from_bitboards(&next.bitboards);
+        next.finish_move(&m, peice_type, kind);
 
-            // 1. Move the piece
+        #[cfg(debug_assertions)]
+        next.assert_consistent();
 
-            // figure out what peice were moving
-            let peice_type = find_peice_at_from_location(self, m.from);
+        *self = next;
+        true
+    }
 
-            let peice_type = match peice_type {
-                Some(peice_type) => peice_type,
-                None => return false,
-            };
+    /// Plays `m` without validating it or printing anything.
+    ///
+    /// The caller must already know the move is legal, e.g. because it came from
+    /// [`Board::legal_moves`]. A move whose from-square holds no piece of the side to
+    /// move is ignored.
+    pub fn apply_move_unchecked(&mut self, m: &Move) {
+        self.make_move_unchecked(m);
+    }
 
-            if peice_type == PieceType::Pawn {
-                self.hashed_board_states = [0; 100];
-                self.position_count = 0;
-            }
+    /// Plays `m` like [`Board::apply_move_unchecked`] and returns what
+    /// [`Board::unmake_move`] needs to take it back, so a search can walk the tree on
+    /// one board instead of copying it at every node.
+    pub fn make_move_unchecked(&mut self, m: &Move) -> Undo {
+        let color = self.active_color;
+        let (offset, enemy_offset) = (color as usize * 6, (!color) as usize * 6);
+
+        let mut undo = Undo {
+            moved: None,
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            king_moves: self.king_moves,
+            position_count: self.position_count,
+            promoted: self.promoted,
+            dropped_position: None,
+            eval_terms: self.eval_terms,
+        };
+        let peice_type = match find_peice_for_color(self, color, m.from) {
+            Some(peice_type) => peice_type,
+            None => return undo,
+        };
+        undo.moved = Some(peice_type);
 
-            // en passant
-            if peice_type == PieceType::Pawn
-                && self.en_passant.is_some()
-                && m.to == self.en_passant.unwrap()
-            {
-                // remove the pawn that is being taken
-                let taken_peice_type = match self.active_color {
-                    Color::White => PieceType::Pawn,
-                    Color::Black => PieceType::Pawn,
-                };
+        // Remove whatever is captured. En passant takes the pawn behind the target.
+        let victim_square = match (peice_type, color) {
+            (PieceType::Pawn, Color::White) if self.en_passant == Some(m.to) => m.to - 8,
+            (PieceType::Pawn, Color::Black) if self.en_passant == Some(m.to) => m.to + 8,
+            _ => m.to,
+        };
+        let victim_bit = 1u64 << victim_square;
+        undo.captured = (enemy_offset..enemy_offset + 6)
+            .find(|&idx| self.bitboards[idx] & victim_bit != 0)
+            .map(|idx| (idx as u8, victim_square));
+        if let Some((idx, square)) = undo.captured {
+            self.bitboards[idx as usize] &= !victim_bit;
+            self.eval_terms.remove(idx as usize, square);
+        }
 
-                let capture_bit = 1 << m.to - 8;
-                let taken_peice_type = taken_peice_type as usize
-                    + match self.active_color {
-                        Color::White => 6,
-                        Color::Black => 0,
-                    };
+        // Move the piece, promoting if needed
+        let placed_peice_type = m.promotion.unwrap_or(peice_type);
+        self.bitboards[offset + peice_type as usize] &= !(1u64 << m.from);
+        self.bitboards[offset + placed_peice_type as usize] |= 1u64 << m.to;
+        self.eval_terms.remove(offset + peice_type as usize, m.from);
+        self.eval_terms
+            .add(offset + placed_peice_type as usize, m.to);
+
+        // Castling also moves the rook
+        if let Some((rook_from, rook_to)) = self.castled_rook_squares(color, m, peice_type) {
+            self.bitboards[offset + PieceType::Rook as usize] ^=
+                (1u64 << rook_from) | (1u64 << rook_to);
+            self.eval_terms
+                .remove(offset + PieceType::Rook as usize, rook_from);
+            self.eval_terms
+                .add(offset + PieceType::Rook as usize, rook_to);
+        }
+        self.update_occupancy();
 
-                // remove the captured peice from the bitboard
-                self.bitboards[taken_peice_type] &= !capture_bit;
-            }
+        self.update_move_state(m, peice_type, undo.captured.is_some());
 
-            // update the bitboards
-            let enemy_bitboards = match self.active_color {
-                Color::White => &self.bitboards[6..12],
-                Color::Black => &self.bitboards[0..6],
-            };
+        undo.dropped_position = self.record_position();
 
-            // figure out if the piece is taking another piece
-            let capture = match enemy_bitboards[peice_type as usize] & (1 << m.to) {
-                0 => false,
-                _ => true,
-            };
+        #[cfg(debug_assertions)]
+        self.assert_consistent();
 
-            if capture {
-                // clear the hash
-                self.hashed_board_states = [0; 100];
-                self.position_count = 0;
-                // find what kind of peice we are taking
-                let to_bit = 1 << m.to;
-                let taken_peice_type = enemy_bitboards.iter().position(|&bb| bb & to_bit != 0);
-
-                // Once we find the type of peice we are taking, remove it from the enemy bitboard
-                match taken_peice_type {
-                    Some(taken_peice_type) => {
-                        self.bitboards[taken_peice_type
-                            + match self.active_color {
-                                Color::White => 6,
-                                Color::Black => 0,
-                            }] &= !to_bit;
-                    }
-                    None => return false,
-                }
+        undo
+    }
 
-                // remove from the all white or all black bitboard
-                match self.active_color {
-                    Color::White => self.all_black_bitboard &= !to_bit,
-                    Color::Black => self.all_white_bitboard &= !to_bit,
-                }
-            }
+    /// Passes: the side to move gives the move to the opponent without moving, to ask
+    /// what the opponent could do next, e.g. which threats it has. The en passant
+    /// square lapses and the halfmove clock counts the pass as a move; the fullmove
+    /// number goes up when Black passes, as after a Black move. The pass is not
+    /// added to the position history. The side to move should not be in check, or
+    /// its king could be taken next.
+    pub fn make_null_move(&mut self) -> NullUndo {
+        let undo = NullUndo {
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        };
+        self.en_passant = None;
+        self.halfmove_clock += 1;
+        if self.active_color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.active_color = !self.active_color;
+        undo
+    }
 
-            // Remove the peice being moved from the 'from' location for its given bitboard
-            self.bitboards[peice_type as usize
-                + match self.active_color {
-                    Color::White => 0,
-                    Color::Black => 6,
-                }] &= !(1 << m.from);
+    /// Takes back the pass made with [`Board::make_null_move`], restoring the board
+    /// exactly.
+    pub fn unmake_null_move(&mut self, undo: NullUndo) {
+        self.active_color = !self.active_color;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+    }
 
-            match self.active_color {
-                Color::White => self.all_white_bitboard &= !(1 << m.from),
-                Color::Black => self.all_black_bitboard &= !(1 << m.from),
-            }
+    /// Takes back `m`, which must be the last move played with
+    /// [`Board::make_move_unchecked`], restoring the board exactly.
+    pub fn unmake_move(&mut self, m: &Move, undo: Undo) {
+        let peice_type = match undo.moved {
+            Some(peice_type) => peice_type,
+            None => return,
+        };
+        let color = !self.active_color;
+        let offset = color as usize * 6;
 
-            // Placing our peice in its new location
-            if m.promotion.is_none() {
-                // Add the peice being moved to the 'to' location for its given bitboard
-                self.bitboards[peice_type as usize
-                    + match self.active_color {
-                        Color::White => 0,
-                        Color::Black => 6,
-                    }] |= 1 << m.to;
-            }
-            // We are promoting the pawn and placing the new peice on the 'to' location
-            else {
-                self.bitboards[m.promotion.unwrap() as usize
-                    + match self.active_color {
-                        Color::White => 0,
-                        Color::Black => 6,
-                    }] |= 1 << m.to;
-            }
+        let placed_peice_type = m.promotion.unwrap_or(peice_type);
+        self.bitboards[offset + placed_peice_type as usize] &= !(1u64 << m.to);
+        self.bitboards[offset + peice_type as usize] |= 1u64 << m.from;
 
-            // no matter what we always update the all_white_bitboard or all_black_bitboard
-            match self.active_color {
-                Color::White => self.all_white_bitboard |= 1 << m.to,
-                Color::Black => self.all_black_bitboard |= 1 << m.to,
+        if let Some((rook_from, rook_to)) = self.castled_rook_squares(color, m, peice_type) {
+            self.bitboards[offset + PieceType::Rook as usize] ^=
+                (1u64 << rook_from) | (1u64 << rook_to);
+        }
+        if let Some((idx, square)) = undo.captured {
+            self.bitboards[idx as usize] |= 1u64 << square;
+        }
+        self.update_occupancy();
+
+        self.active_color = color;
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.king_moves = undo.king_moves;
+        self.position_count = undo.position_count;
+        self.promoted = undo.promoted;
+        self.eval_terms = undo.eval_terms;
+        match undo.dropped_position {
+            Some(dropped) => {
+                self.hashed_board_states.copy_within(0..99, 1);
+                self.hashed_board_states[0] = dropped;
             }
+            // Clear the entry the move appended, so the board is exactly as before
+            None => self.hashed_board_states[self.position_count as usize] = 0,
+        }
+    }
 
-            // check to see if move puts the king in check or king is still in check
-            if self.is_in_check(self.active_color) {
-                *self = prev_board_state;
-                return false;
-            }
-            self.display();
-            // 2. Update castling rights
-            if peice_type == PieceType::King {
-                match m.from {
-                    4 => {
-                        // White King
-                        self.castling_rights &= !(1 | 2);
-                    }
-                    60 => {
-                        // Black King
-                        self.castling_rights &= !(4 | 8);
-                    }
-                    _ => {}
-                }
-            } else if peice_type == PieceType::Rook {
-                match m.from {
-                    0 => {
-                        // White Queenside Rook
-                        self.castling_rights &= !1;
-                    }
-                    7 => {
-                        // White Kingside Rook
-                        self.castling_rights &= !2;
-                    }
-                    56 => {
-                        // Black Queenside Rook
-                        self.castling_rights &= !4;
-                    }
-                    63 => {
-                        // Black Kingside Rook
-                        self.castling_rights &= !8;
-                    }
-                    _ => {}
-                }
+    /// Appends the current position to the history. A full history drops its oldest
+    /// position, which is returned, so the latest 100 positions are always kept.
+    fn record_position(&mut self) -> Option<u64> {
+        let position_hash = self.calculate_position_hash();
+        if self.position_count < 100 {
+            self.hashed_board_states[self.position_count as usize] = position_hash;
+            self.position_count += 1;
+            None
+        } else {
+            let dropped = self.hashed_board_states[0];
+            self.hashed_board_states.copy_within(1..100, 0);
+            self.hashed_board_states[99] = position_hash;
+            Some(dropped)
+        }
+    }
+
+    /// True if the current position already occurred since the last capture or pawn
+    /// move. The search treats this as a draw: whatever the side to move could do
+    /// from here, it could have done the first time round.
+    pub fn is_repetition(&self) -> bool {
+        let history = &self.hashed_board_states[..self.position_count as usize];
+        let current = self.calculate_position_hash();
+
+        // The last entry is the current position itself when a move led here
+        history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize + 1)
+            .filter(|&&hash| hash == current)
+            .count()
+            >= 2
+    }
+
+    /// Rook from- and to-squares if `color`'s `peice_type` on `m.from` castles with
+    /// `m`.
+    fn castled_rook_squares(
+        &self,
+        color: Color,
+        m: &Move,
+        peice_type: PieceType,
+    ) -> Option<(u8, u8)> {
+        if peice_type != PieceType::King {
+            return None;
+        }
+        self.castling.rook_squares(color, m)
+    }
+
+    fn update_occupancy(&mut self) {
+        self.all_white_bitboard = self.bitboards[0..6].iter().fold(0, |acc, bb| acc | bb);
+        self.all_black_bitboard = self.bitboards[6..12].iter().fold(0, |acc, bb| acc | bb);
+    }
+
+    /// Updates the piece bitboards for `m`, including captures, en passant,
+    /// promotions and the rook of a castling move.
+    fn place_peices(&mut self, m: &Move, peice_type: PieceType) {
+        // Take whatever stands on the 'to' square or, en passant, the pawn behind it.
+        // Its type comes from that square alone, and it leaves both its own bitboard
+        // and the occupancy, so that no ghost of it attacks anything afterwards and
+        // a rook or queen behind it is seen when checking for a pin.
+        let enemy = !self.active_color;
+        let victim_square = match self.active_color {
+            Color::White if peice_type == PieceType::Pawn && self.en_passant == Some(m.to) => {
+                m.to - 8
             }
-            // 3. Update en passant
-            if peice_type == PieceType::Pawn && (m.from as i8 - m.to as i8).abs() == 16 {
-                self.en_passant = Some((m.from + m.to) / 2); // set the en passant target square to the square behind the pawn
-            } else {
-                self.en_passant = None;
+            Color::Black if peice_type == PieceType::Pawn && self.en_passant == Some(m.to) => {
+                m.to + 8
             }
-            // 4. Update halfmove clock
-            if peice_type == PieceType::Pawn || capture {
-                self.halfmove_clock = 0;
-            } else {
-                self.halfmove_clock += 1;
+            _ => m.to,
+        };
+        if let Some(taken_peice_type) = self.piece_on(enemy, victim_square) {
+            let victim_bit = 1u64 << victim_square;
+            self.bitboards[enemy as usize * 6 + taken_peice_type as usize] &= !victim_bit;
+            match enemy {
+                Color::White => self.all_white_bitboard &= !victim_bit,
+                Color::Black => self.all_black_bitboard &= !victim_bit,
             }
-            // 5. Update fullmove number
-            if self.active_color == Color::Black {
-                self.fullmove_number += 1;
+        }
+
+        // Remove the peice being moved from the 'from' location for its given bitboard
+        self.bitboards[peice_type as usize
+            + match self.active_color {
+                Color::White => 0,
+                Color::Black => 6,
+            }] &= !(1 << m.from);
+
+        match self.active_color {
+            Color::White => self.all_white_bitboard &= !(1 << m.from),
+            Color::Black => self.all_black_bitboard &= !(1 << m.from),
+        }
+
+        // Placing our peice in its new location. When promoting, the new peice is
+        // placed on the 'to' location instead of the pawn.
+        let placed_peice_type = m.promotion.unwrap_or(peice_type);
+        self.bitboards[placed_peice_type as usize
+            + match self.active_color {
+                Color::White => 0,
+                Color::Black => 6,
+            }] |= 1 << m.to;
+
+        // no matter what we always update the all_white_bitboard or all_black_bitboard
+        match self.active_color {
+            Color::White => self.all_white_bitboard |= 1 << m.to,
+            Color::Black => self.all_black_bitboard |= 1 << m.to,
+        }
+
+        // Castling also moves the rook from its corner to the square the king crossed
+        if let Some((rook_from, rook_to)) =
+            self.castled_rook_squares(self.active_color, m, peice_type)
+        {
+            let rook_bits = (1u64 << rook_from) | (1u64 << rook_to);
+            match self.active_color {
+                Color::White => {
+                    self.bitboards[PieceType::Rook as usize] ^= rook_bits;
+                    self.all_white_bitboard ^= rook_bits;
+                }
+                Color::Black => {
+                    self.bitboards[6 + PieceType::Rook as usize] ^= rook_bits;
+                    self.all_black_bitboard ^= rook_bits;
+                }
             }
-            // 6. Switch active color
-            self.active_color = match self.active_color {
-                Color::White => Color::Black,
-                Color::Black => Color::White,
-            };
         }
+    }
 
-        // Update position history
-        let position_hash = self.calculate_position_hash();
-        if self.position_count < 100 {
-            self.hashed_board_states[self.position_count as usize] = position_hash;
-            self.position_count += 1;
+    /// Updates the position history, then the rest of the state via
+    /// [`Board::update_move_state`], once the pieces for `m` have been placed.
+    fn finish_move(&mut self, m: &Move, peice_type: PieceType, kind: MoveKind) {
+        // Irreversible moves clear the repetition history
+        if peice_type == PieceType::Pawn || kind.is_capture() {
+            self.hashed_board_states = [0; 100];
+            self.position_count = 0;
+        }
+
+        self.update_move_state(m, peice_type, kind.is_capture());
+        self.record_position();
+    }
+
+    /// Updates castling rights, en passant, the clocks, the king move counter and
+    /// the side to move after `m`. Every way of playing a move, castling included,
+    /// goes through here.
+    fn update_move_state(&mut self, m: &Move, peice_type: PieceType, is_capture: bool) {
+        // A king or rook leaving its square, or a rook captured on it, loses rights
+        self.castling_rights &=
+            !(self.castling.rights_lost(m.from) | self.castling.rights_lost(m.to));
+
+        // Only a double pawn push leaves an en passant square
+        self.en_passant = if peice_type == PieceType::Pawn && m.from.abs_diff(m.to) == 16 {
+            Some((m.from + m.to) / 2)
+        } else {
+            None
+        };
+
+        // Castling is neither a pawn move nor a capture, so it counts towards the
+        // fifty-move rule
+        if peice_type == PieceType::Pawn || is_capture {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if peice_type == PieceType::King {
+            self.king_moves += 1;
         }
 
-        valid
+        // A promoted piece stays one wherever it goes, until it is captured
+        let was_promoted = self.promoted & (1 << m.from) != 0;
+        self.promoted &= !((1u64 << m.from) | (1u64 << m.to));
+        if was_promoted || m.promotion.is_some() {
+            self.promoted |= 1 << m.to;
+        }
+        if self.active_color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.active_color = !self.active_color;
     }
 
     // Given a color, return the bitboard squares being attacked by that color
     pub fn get_attack_bitboard_by_color(&self, color: Color) -> u64 {
         let mut attack_bitboard: u64 = 0;
 
-        let offset = match color {
-            Color::White => 0,
-            Color::Black => 6,
-        };
-
-        let pawns_bb = self.bitboards[offset + PieceType::Pawn as usize];
+        let pawns_bb = self.pieces(color, PieceType::Pawn);
 
-        let knights_bb = self.bitboards[offset + PieceType::Knight as usize];
-        let bishops_bb = self.bitboards[offset + PieceType::Bishop as usize];
-        let rooks_bb = self.bitboards[offset + PieceType::Rook as usize];
-        let queens_bb = self.bitboards[offset + PieceType::Queen as usize];
-        let king_bb = self.bitboards[offset + PieceType::King as usize];
+        let knights_bb = self.pieces(color, PieceType::Knight);
+        let bishops_bb = self.pieces(color, PieceType::Bishop);
+        let rooks_bb = self.pieces(color, PieceType::Rook);
+        let queens_bb = self.pieces(color, PieceType::Queen);
+        let king_bb = self.pieces(color, PieceType::King);
 
-        let board_occupancy_bb = self.all_white_bitboard | self.all_black_bitboard;
+        let board_occupancy_bb = self.occupied();
         attack_bitboard |= Self::get_pawn_attack_bitboard(pawns_bb, color);
 
         attack_bitboard |= Self::get_knight_attack_bitboard(knights_bb);
@@ -721,7 +1478,7 @@ impl Board {
         attack_bitboard
     }
 
-    fn get_pawn_attack_bitboard(pawn_bb: u64, color: Color) -> u64 {
+    pub(crate) fn get_pawn_attack_bitboard(pawn_bb: u64, color: Color) -> u64 {
         let mut attack_bitboard: u64 = 0;
 
         match color {
@@ -733,54 +1490,64 @@ impl Board {
                 attack_bitboard |= (pawn_bb & !0x0101010101010101) << 7;
             }
             Color::Black => {
-                // capturing left => i -> i - 9, exclude h-file
-                attack_bitboard |= (pawn_bb & !0x8080808080808080) >> 9;
+                // capturing left => i -> i - 9, exclude a-file
+                attack_bitboard |= (pawn_bb & !0x0101010101010101) >> 9;
 
-                // capturing right => i -> i - 7, exclude a-file
-                attack_bitboard |= (pawn_bb & !0x0101010101010101) >> 7;
+                // capturing right => i -> i - 7, exclude h-file
+                attack_bitboard |= (pawn_bb & !0x8080808080808080) >> 7;
             }
         }
         attack_bitboard
     }
 
-    fn get_knight_attack_bitboard(knight_bb: u64) -> u64 {
+    pub(crate) fn get_knight_attack_bitboard(knight_bb: u64) -> u64 {
         let mut attack_bitboard: u64 = 0;
 
-        attack_bitboard |= (knight_bb << 17) & !0x8080808080808080; // Knight moves
-        attack_bitboard |= (knight_bb << 15) & !0x0101010101010101;
-        attack_bitboard |= (knight_bb << 10) & !0x8080808080808080;
-        attack_bitboard |= (knight_bb << 6) & !0x0101010101010101;
-        attack_bitboard |= (knight_bb >> 17) & !0x0101010101010101;
-        attack_bitboard |= (knight_bb >> 15) & !0x8080808080808080;
-        attack_bitboard |= (knight_bb >> 10) & !0x0101010101010101;
-        attack_bitboard |= (knight_bb >> 6) & !0x8080808080808080;
+        // Mask off the files a jump could wrap around to
+        const FILE_A: u64 = 0x0101010101010101;
+        const FILE_B: u64 = 0x0202020202020202;
+        const FILE_G: u64 = 0x4040404040404040;
+        const FILE_H: u64 = 0x8080808080808080;
+
+        attack_bitboard |= (knight_bb << 17) & !FILE_A; // Two up, one right
+        attack_bitboard |= (knight_bb << 15) & !FILE_H; // Two up, one left
+        attack_bitboard |= (knight_bb << 10) & !(FILE_A | FILE_B); // One up, two right
+        attack_bitboard |= (knight_bb << 6) & !(FILE_G | FILE_H); // One up, two left
+        attack_bitboard |= (knight_bb >> 17) & !FILE_H; // Two down, one left
+        attack_bitboard |= (knight_bb >> 15) & !FILE_A; // Two down, one right
+        attack_bitboard |= (knight_bb >> 10) & !(FILE_G | FILE_H); // One down, two left
+        attack_bitboard |= (knight_bb >> 6) & !(FILE_A | FILE_B); // One down, two right
 
         attack_bitboard
     }
 
-    fn get_bishop_attack_bitboard(bishop_bb: u64, board_occpuancy_bb: u64) -> u64 {
+    pub(crate) fn get_bishop_attack_bitboard(bishop_bb: u64, board_occpuancy_bb: u64) -> u64 {
         let mut attack_bitboard: u64 = 0;
 
-        // Directions: NW (+7), NE (+9), SW (-9), SE (-7)
-        let distance_to_jump: [i8; 4] = [9, 7, -7, -9]; // [NE, NW, SE, SW]
-        let dir: [u8; 4] = [4, 5, 6, 7];
+        // How far one step goes in each direction
+        let directions: [(i8, usize); 4] = [
+            (9, NORTH_EAST),
+            (7, NORTH_WEST),
+            (-7, SOUTH_EAST),
+            (-9, SOUTH_WEST),
+        ];
 
-        for i in 0..4 {
+        for (step, direction) in directions {
             let mut temp_bb = bishop_bb;
             // Loop over every bishop to evaluate which squares they can attack
             while temp_bb != 0 {
                 let square = temp_bb.trailing_zeros() as i8; // get the index of the first set bit aka that one of the bishops is on
                 temp_bb &= temp_bb - 1; // remove the bit we just found
 
-                let max_distance = EDGE_DISTANCES[dir[i] as usize][square as usize];
+                let max_distance = EDGE_DISTANCES[direction][square as usize];
 
                 for hop_distance_multiplier in 1..=max_distance {
-                    let hop_distance = distance_to_jump[i] * hop_distance_multiplier as i8;
+                    let hop_distance = step * hop_distance_multiplier as i8;
 
                     let attacking_square = square + hop_distance;
 
                     // Prevents wrapping around the board or going out of bounds
-                    if attacking_square < 0 || attacking_square > 63 {
+                    if !(0..=63).contains(&attacking_square) {
                         break;
                     }
                     let attacking_square_u8 = attacking_square as u8;
@@ -798,33 +1565,28 @@ impl Board {
         attack_bitboard
     }
 
-    fn get_rook_attack_bitboard(rook_bb: u64, board_occpuancy_bb: u64) -> u64 {
+    pub(crate) fn get_rook_attack_bitboard(rook_bb: u64, board_occpuancy_bb: u64) -> u64 {
         let mut attack_bitboard: u64 = 0;
 
-        // Define the distance offsets for Rook movement:
-        // [North, South, East, West]
-        let distance_to_jump: [i8; 4] = [8, -8, 1, -1];
-
-        // Match these directions to EDGE_DISTANCES indices:
-        // 0 = North, 1 = South, 2 = East, 3 = West
-        let dir: [u8; 4] = [0, 1, 2, 3];
+        // How far one step goes in each direction
+        let directions: [(i8, usize); 4] = [(8, NORTH), (-8, SOUTH), (1, EAST), (-1, WEST)];
 
-        for i in 0..4 {
+        for (step, direction) in directions {
             let mut temp_bb = rook_bb;
             // Loop over every bishop to evaluate which squares they can attack
             while temp_bb != 0 {
                 let square = temp_bb.trailing_zeros() as i8; // get the index of the first set bit aka that one of the bishops is on
                 temp_bb &= temp_bb - 1; // remove the bit we just found
 
-                let max_distance = EDGE_DISTANCES[dir[i] as usize][square as usize];
+                let max_distance = EDGE_DISTANCES[direction][square as usize];
 
                 for hop_distance_multiplier in 1..=max_distance {
-                    let hop_distance = distance_to_jump[i] * hop_distance_multiplier as i8;
+                    let hop_distance = step * hop_distance_multiplier as i8;
 
                     let attacking_square = square + hop_distance;
 
                     // Prevents wrapping around the board or going out of bounds
-                    if attacking_square < 0 || attacking_square > 63 {
+                    if !(0..=63).contains(&attacking_square) {
                         break;
                     }
                     let attacking_square_u8 = attacking_square as u8;
@@ -844,8 +1606,8 @@ impl Board {
 
     // just combine the boards of rook and bishop
     fn get_queen_attack_bitboard(queen_bb: u64, board_occpuancy_bb: u64) -> u64 {
-        return Self::get_bishop_attack_bitboard(queen_bb, board_occpuancy_bb)
-            | Self::get_rook_attack_bitboard(queen_bb, board_occpuancy_bb);
+        Self::get_bishop_attack_bitboard(queen_bb, board_occpuancy_bb)
+            | Self::get_rook_attack_bitboard(queen_bb, board_occpuancy_bb)
     }
 
     /// Given a bitboard of kings, returns a bitboard of squares they are attacking.
@@ -894,312 +1656,2877 @@ impl Board {
         attack_bitboard
     }
 
-    pub fn is_in_check(self, color: Color) -> bool {
-        let king_bb = self.bitboards[color as usize * 6 + PieceType::King as usize];
+    /// Returns true if the side to move's move `m` checks the enemy king, without
+    /// playing it. Covers checks by the moved or promoted piece, discovered checks
+    /// (en passant ones included) and checks by the rook of a castling move. `m` must
+    /// be pseudo-legal.
+    pub fn gives_check(&self, m: &Move) -> bool {
+        let color = self.active_color;
+        let king_bb = match self.king_square(!color) {
+            Some(square) => 1u64 << square,
+            None => return false,
+        };
+        let moved = match self.piece_on(color, m.from) {
+            Some(piece_type) => piece_type,
+            None => return false,
+        };
+        let (from_bb, to_bb) = (1u64 << m.from, 1u64 << m.to);
+
+        // Our pieces and the occupancy as they will be after the move
+        let mut ours = PieceType::ALL.map(|piece_type| self.pieces(color, piece_type));
+        ours[moved as usize] &= !from_bb;
+        ours[m.promotion.unwrap_or(moved) as usize] |= to_bb;
+        let mut occupancy = (self.occupied() & !from_bb) | to_bb;
+        match self.classify(m) {
+            MoveKind::EnPassant => {
+                let victim = match color {
+                    Color::White => m.to - 8,
+                    Color::Black => m.to + 8,
+                };
+                occupancy &= !(1u64 << victim);
+            }
+            MoveKind::CastleKingside | MoveKind::CastleQueenside => {
+                let (rook_from, rook_to) = self
+                    .castling
+                    .rook_squares(color, m)
+                    .expect("classified as castling");
+                let rook_move = (1u64 << rook_from) | (1u64 << rook_to);
+                ours[PieceType::Rook as usize] ^= rook_move;
+                occupancy ^= rook_move;
+            }
+            _ => {}
+        }
 
-        let attack_bb = self.get_attack_bitboard_by_color(match color {
-            Color::White => Color::Black,
-            Color::Black => Color::White,
-        });
+        // Look out from the enemy king for the pieces that would attack it
+        let queens = ours[PieceType::Queen as usize];
+        (Self::get_pawn_attack_bitboard(king_bb, !color) & ours[PieceType::Pawn as usize])
+            | (Self::get_knight_attack_bitboard(king_bb) & ours[PieceType::Knight as usize])
+            | (Self::get_bishop_attack_bitboard(king_bb, occupancy)
+                & (ours[PieceType::Bishop as usize] | queens))
+            | (Self::get_rook_attack_bitboard(king_bb, occupancy)
+                & (ours[PieceType::Rook as usize] | queens))
+            != 0
+    }
+
+    /// Returns true if `color`'s king is attacked by the other side. A side with no
+    /// king is never in check.
+    pub fn is_in_check(&self, color: Color) -> bool {
+        let king_bb = self.pieces(color, PieceType::King);
+
+        let attack_bb = self.get_attack_bitboard_by_color(!color);
 
         (king_bb & attack_bb) != 0
     }
 
-    pub fn is_insufficient_material(&self) -> bool {
-        // Only kings left
-        if self.all_white_bitboard.count_ones() == 1 && self.all_black_bitboard.count_ones() == 1 {
-            return true;
+    /// Bitboard of the squares the piece on `square` attacks, stopping sliders at the
+    /// first piece in the way (which is included). Whether a move there would be
+    /// legal is not considered. 0 for an empty square.
+    pub fn attacks_from(&self, square: u8) -> u64 {
+        let square_bb = 1u64 << square;
+        let color = if self.all_white_bitboard & square_bb != 0 {
+            Color::White
+        } else if self.all_black_bitboard & square_bb != 0 {
+            Color::Black
+        } else {
+            return 0;
+        };
+        let occupancy = self.occupied();
+        match find_peice_for_color(self, color, square) {
+            Some(PieceType::Pawn) => Self::get_pawn_attack_bitboard(square_bb, color),
+            Some(PieceType::Knight) => Self::get_knight_attack_bitboard(square_bb),
+            Some(PieceType::Bishop) => Self::get_bishop_attack_bitboard(square_bb, occupancy),
+            Some(PieceType::Rook) => Self::get_rook_attack_bitboard(square_bb, occupancy),
+            Some(PieceType::Queen) => Self::get_queen_attack_bitboard(square_bb, occupancy),
+            Some(PieceType::King) => Self::get_king_attack_bitboard(square_bb),
+            None => 0,
         }
+    }
 
-        // King and bishop/knight vs king
-        if (self.all_white_bitboard.count_ones() == 2 && self.all_black_bitboard.count_ones() == 1) ||
-           (self.all_white_bitboard.count_ones() == 1 && self.all_black_bitboard.count_ones() == 2) {
-            let minor_pieces = self.bitboards[PieceType::Bishop as usize] | 
-                             self.bitboards[PieceType::Knight as usize] |
-                             self.bitboards[(PieceType::Bishop as usize) + 6] | 
-                             self.bitboards[(PieceType::Knight as usize) + 6];
-            if minor_pieces.count_ones() == 1 {
-                return true;
+    /// Bitboard of `color`'s pieces, the king aside, that the other side attacks and
+    /// `color` does not defend.
+    pub fn hanging_pieces(&self, color: Color) -> u64 {
+        let attacked = self.get_attack_bitboard_by_color(!color)
+            & self.occupied_by(color)
+            & !self.pieces(color, PieceType::King);
+
+        let mut hanging = 0;
+        let mut pieces = attacked;
+        while pieces != 0 {
+            let square = pieces.trailing_zeros() as u8;
+            pieces &= pieces - 1;
+            if self.attackers_to(square, color) == 0 {
+                hanging |= 1u64 << square;
             }
         }
+        hanging
+    }
 
-        false
+    /// Returns true if any of `color`'s pieces attack `square`.
+    pub fn is_square_attacked(&self, square: u8, color: Color) -> bool {
+        self.attackers_to(square, color) != 0
     }
 
-    pub fn is_50_move_rule(&self) -> bool {
-        self.king_moves >= 50
+    /// Bitboard of `color`'s pieces that attack `square`, whatever stands on it, so a
+    /// piece of `color` on the square is defended by the pieces returned.
+    pub fn attackers_to(&self, square: u8, color: Color) -> u64 {
+        self.attackers_through(square, color, self.occupied())
     }
 
-    pub fn is_3_fold_repetition(&self) -> bool {
-        if self.position_count < 3 {
-            return false;
-        }
+    /// [`Board::attackers_to`] with only the pieces in `occupancy` on the board, so
+    /// that sliders behind pieces taken off it join in.
+    fn attackers_through(&self, square: u8, color: Color, occupancy: u64) -> u64 {
+        let square_bb = 1u64 << square;
+
+        let queens = self.pieces(color, PieceType::Queen);
+        let diagonal_sliders = self.pieces(color, PieceType::Bishop) | queens;
+        let straight_sliders = self.pieces(color, PieceType::Rook) | queens;
+
+        // A pawn of `color` attacks the square if a pawn of the other color standing on
+        // the square would attack the pawn.
+        ((Self::get_pawn_attack_bitboard(square_bb, !color) & self.pieces(color, PieceType::Pawn))
+            | (Self::get_knight_attack_bitboard(square_bb) & self.pieces(color, PieceType::Knight))
+            | (Self::get_king_attack_bitboard(square_bb) & self.pieces(color, PieceType::King))
+            | (Self::get_bishop_attack_bitboard(square_bb, occupancy) & diagonal_sliders)
+            | (Self::get_rook_attack_bitboard(square_bb, occupancy) & straight_sliders))
+            & occupancy
+    }
 
-        let current_hash = self.calculate_position_hash();
-        let mut repetition_count = 0;
+    /// Static exchange evaluation of the side to move's capture `m`: the material it
+    /// wins, in centipawns at [`PIECE_VALUES`], if both sides go on taking back on
+    /// `m.to` with their least valuable piece for as long as that pays. Pins and
+    /// checks are ignored, but losing the king costs more than any capture wins.
+    ///
+    /// En passant wins the pawn beside the capturing one, and both leave their rank,
+    /// so a slider behind either can take part.
+    pub fn see(&self, m: &Move) -> i32 {
+        // The king is worth more than anything it could win
+        const VALUES: [i32; 6] = [
+            PIECE_VALUES[0],
+            PIECE_VALUES[1],
+            PIECE_VALUES[2],
+            PIECE_VALUES[3],
+            PIECE_VALUES[4],
+            20_000,
+        ];
+        let color = self.active_color;
+        let moved = match self.piece_on(color, m.from) {
+            Some(piece_type) => piece_type,
+            None => return 0,
+        };
+        let victim = match self.en_passant_victim() {
+            Some((square, victim)) if moved == PieceType::Pawn && square == m.to => victim,
+            _ => m.to,
+        };
 
-        for i in 0..self.position_count {
-            if self.hashed_board_states[i as usize] == current_hash {
-                repetition_count += 1;
-                if repetition_count >= 3 {
-                    return true;
-                }
-            }
+        let mut gains = [0; 33];
+        gains[0] = self
+            .piece_on(!color, victim)
+            .map_or(0, |piece_type| VALUES[piece_type as usize]);
+        if let Some(promotion) = m.promotion {
+            gains[0] += VALUES[promotion as usize] - VALUES[PieceType::Pawn as usize];
         }
-
-        false
-    }
-
-    fn calculate_position_hash(&self) -> u64 {
-        // Simple Zobrist-style hashing
-        let mut hash: u64 = 0;
-        
-        // Hash piece positions
-        for i in 0..12 {
-            hash ^= self.bitboards[i];
-        }
-        
-        // Hash castling rights
-        hash ^= self.castling_rights as u64;
-        
-        // Hash en passant
-        if let Some(ep) = self.en_passant {
-            hash ^= ep as u64;
-        }
-        
-        // Hash active color
-        if self.active_color == Color::Black {
-            hash ^= 1u64 << 63;  // Flip the top bit for black
+        let mut on_square = VALUES[m.promotion.unwrap_or(moved) as usize];
+        let mut occupancy = self.occupied() & !(1u64 << m.from) & !(1u64 << victim);
+        let mut side = !color;
+        let mut depth = 0;
+        while depth + 1 < gains.len() {
+            let attackers = self.attackers_through(m.to, side, occupancy);
+            let least_valuable = PieceType::ALL
+                .into_iter()
+                .map(|piece_type| (piece_type, attackers & self.pieces(side, piece_type)))
+                .find(|&(_, pieces)| pieces != 0);
+            let (piece_type, pieces) = match least_valuable {
+                Some(found) => found,
+                None => break,
+            };
+            depth += 1;
+            gains[depth] = on_square - gains[depth - 1];
+            on_square = VALUES[piece_type as usize];
+            occupancy &= !(1u64 << pieces.trailing_zeros());
+            side = !side;
+        }
+        // Either side stops taking once that loses more than it wins
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
         }
-        
-        hash
+        gains[0]
     }
-}
 
-// -------------------------------
-// Tests
-// -------------------------------
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Bitboard of `color`'s pieces that are pinned to their king by an enemy slider.
+    fn pinned_pieces(&self, color: Color) -> u64 {
+        let king_square = match self.king_square(color) {
+            Some(square) => square,
+            None => return 0,
+        };
 
-    #[test]
-    fn test_fen_to_positions_for_one_pawn() {
-        // White pawn on e2
-        let fen = "8/8/8/8/8/8/4P3/8 w - - 0 1";
-        let board = Board::fen_to_board(fen);
-        // e2 => rank=1, file=4 => index = 1*8 + 4 = 12
-        assert_eq!(board.bitboards[0], 1 << 12); // White Pawns are bitboards[0]
-    }
+        let enemy_queens = self.pieces(!color, PieceType::Queen);
+        let enemy_diagonal = self.pieces(!color, PieceType::Bishop) | enemy_queens;
+        let enemy_straight = self.pieces(!color, PieceType::Rook) | enemy_queens;
 
-    #[test]
-    fn test_fen_to_position_for_two_pawns() {
-        // White pawns on d2, e2 => (rank=1, file=3) and (rank=1, file=4)
-        let fen = "8/8/8/8/8/8/3PP3/8 w - - 0 1";
-        let board = Board::fen_to_board(fen);
+        let friendly = self.occupied_by(color);
+        let occupancy = self.occupied();
 
-        let expected_bitboard: u64 = (1 << 11) | (1 << 12);
-        assert_eq!(board.bitboards[0], expected_bitboard);
-    }
+        // Enemy sliders that would see the king on an empty board
+        let king_bb = 1u64 << king_square;
+        let mut snipers = (Self::get_bishop_attack_bitboard(king_bb, 0) & enemy_diagonal)
+            | (Self::get_rook_attack_bitboard(king_bb, 0) & enemy_straight);
 
-    #[test]
-    fn test_fen_to_position_for_many_random_pawns() {
-        let fen = "P6P/8/3pp3/8/8/8/3PP3/P6P w - - 0 1";
-        let board = Board::fen_to_board(fen);
+        let mut pinned = 0;
+        while snipers != 0 {
+            let sniper = snipers.trailing_zeros() as u8;
+            snipers &= snipers - 1;
 
-        let expected_white_bitboard: u64 =
-            (1 << 11) | (1 << 12) | 1 << 0 | 1 << 7 | 1 << 56 | 1 << 63;
-        let expected_black_bitboard: u64 = (1 << 44) | (1 << 43);
-        assert_eq!(board.bitboards[0], expected_white_bitboard);
-        assert_eq!(board.bitboards[6], expected_black_bitboard);
+            // Exactly one friendly piece in between means that piece is pinned
+            let blockers = squares_between(king_square, sniper) & occupancy;
+            if blockers.count_ones() == 1 && blockers & friendly != 0 {
+                pinned |= blockers;
+            }
+        }
+        pinned
     }
 
-    #[test]
-
-    fn test_pawn_capture() {
-        let fen = "8/8/8/8/8/5p2/4P3/8 w - - 0 1".to_string();
+    /// Returns true if playing the pseudo-legal move `m` does not leave the side to
+    /// move in check. Castling is also rejected out of or through check.
+    pub fn is_legal(&self, m: &Move) -> bool {
+        let color = self.active_color;
+        self.is_legal_with(m, self.pinned_pieces(color), self.is_in_check(color))
+    }
 
-        let mut board = Board::fen_to_board(&fen);
+    /// [`Board::is_legal`] with the pinned pieces and check status precomputed, so the
+    /// common case needs no scratch board.
+    fn is_legal_with(&self, m: &Move, pinned: u64, in_check: bool) -> bool {
+        let color = self.active_color;
+        let peice_type = match find_peice_for_color(self, color, m.from) {
+            Some(peice_type) => peice_type,
+            None => return false,
+        };
+        let is_en_passant = peice_type == PieceType::Pawn && self.en_passant == Some(m.to);
 
-        let m = Move {
-            from: 12,
-            to: 21,
-            promotion: None,
+        let castle = match peice_type {
+            PieceType::King => self.castling.side_of(color, m),
+            _ => None,
         };
+        if let Some(side) = castle {
+            // Castling: the king may not leave, pass through or land on an attacked
+            // square
+            let check_path = self.castling.check_path(color, side);
+            if in_check || self.get_attack_bitboard_by_color(!color) & check_path != 0 {
+                return false;
+            }
+        } else if !in_check
+            && peice_type != PieceType::King
+            && pinned & (1u64 << m.from) == 0
+            && !is_en_passant
+        {
+            // Nothing can expose the king, so the move is legal as generated
+            return true;
+        }
 
-        assert!(board.move_peice(m));
+        !self.pieces_after(m, peice_type).is_in_check(color)
+    }
 
-        let expected_white_bitboard: u64 = 1 << 21;
-        let expected_black_bitboard: u64 = 0;
+    /// Copy of the board with only the pieces for `m` moved, enough to test whether
+    /// the mover's king is left in check. Whatever stands on the destination (or the
+    /// en passant victim) is removed from every enemy bitboard.
+    pub(crate) fn pieces_after(&self, m: &Move, peice_type: PieceType) -> Board {
+        let mut scratch = *self;
+        let color = self.active_color;
+        let (offset, enemy_offset) = (color as usize * 6, (!color) as usize * 6);
+
+        let mut removed = 1u64 << m.to;
+        if peice_type == PieceType::Pawn && self.en_passant == Some(m.to) {
+            removed |= match color {
+                Color::White => 1u64 << (m.to - 8),
+                Color::Black => 1u64 << (m.to + 8),
+            };
+        }
+        for bitboard in &mut scratch.bitboards[enemy_offset..enemy_offset + 6] {
+            *bitboard &= !removed;
+        }
 
-        assert_eq!(board.bitboards[0], expected_white_bitboard);
-        assert_eq!(board.bitboards[6], expected_black_bitboard);
+        let placed_peice_type = m.promotion.unwrap_or(peice_type);
+        scratch.bitboards[offset + peice_type as usize] &= !(1u64 << m.from);
+        scratch.bitboards[offset + placed_peice_type as usize] |= 1u64 << m.to;
+
+        let (friendly, enemy) = match color {
+            Color::White => (
+                &mut scratch.all_white_bitboard,
+                &mut scratch.all_black_bitboard,
+            ),
+            Color::Black => (
+                &mut scratch.all_black_bitboard,
+                &mut scratch.all_white_bitboard,
+            ),
+        };
+        *friendly = (*friendly & !(1u64 << m.from)) | (1u64 << m.to);
+        *enemy &= !removed;
+        scratch
     }
 
-    #[test]
-    fn test_pawn_promotion() {
-        let fen = "8/4P3/8/8/8/8/8/8 w - - 0 1".to_string();
-
-        let mut board = Board::fen_to_board(&fen);
+    /// Every legal move for the side to move, in canonical order: by from-square, a1
+    /// first and h8 last as the squares are numbered, then by to-square, then by
+    /// promotion piece, knight, bishop, rook and queen. The order depends on the
+    /// position alone, never on how the moves were generated, so whatever takes the
+    /// first move, breaks ties by position in the list or records the list can count
+    /// on it.
+    pub fn legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.legal_moves_into(&mut moves);
+        moves
+    }
 
-        let m = Move::new("e7e8q".to_string());
+    /// Replaces the contents of `moves` with every legal move for the side to move,
+    /// in the order of [`Board::legal_moves`], so a caller generating moves in a loop
+    /// can reuse one list.
+    pub fn legal_moves_into(&self, moves: &mut MoveList) {
+        self.generate_legal_moves_into(moves);
+        moves.sort_unstable_by_key(|m| (m.from, m.to, m.promotion.map(|p| p as u8)));
+    }
 
-        assert!(board.move_peice(m));
+    /// Like [`Board::legal_moves_into`], but in whatever order the generator finds the
+    /// moves, for the search, which orders them its own way and need not pay for the
+    /// sort.
+    pub(crate) fn generate_legal_moves_into(&self, moves: &mut MoveList) {
+        let color = self.active_color;
+        let pinned = self.pinned_pieces(color);
+        let in_check = self.is_in_check(color);
+
+        moves.clear();
+        generate_all_moves_for_color_into(self, color, moves);
+        moves.retain(|m| self.is_legal_with(m, pinned, in_check));
+    }
 
-        let expected_white_bitboard: u64 = 1 << 60;
-        assert_eq!(board.bitboards[4], expected_white_bitboard);
-        assert_eq!(board.bitboards[0], 0);
+    /// The legal moves of the side to move's piece on `from`, one for each
+    /// promotion piece, or none if the side to move has no piece there. For a
+    /// front-end that wants the moves of the piece the user picked up.
+    pub fn legal_moves_from(&self, from: u8) -> Vec<Move> {
+        let color = self.active_color;
+        if from >= 64 || self.occupied_by(color) & (1u64 << from) == 0 {
+            return Vec::new();
+        }
+        let peice_type = match find_peice_for_color(self, color, from) {
+            Some(peice_type) => peice_type,
+            None => return Vec::new(),
+        };
+        let pinned = self.pinned_pieces(color);
+        let in_check = self.is_in_check(color);
+
+        let mut moves = MoveList::new();
+        generate_moves_for_peice(self, color, peice_type, from, &mut moves);
+        moves
+            .iter()
+            .filter(|m| self.is_legal_with(m, pinned, in_check))
+            .collect()
     }
 
-    #[test]
-    fn test_pawn_promotion_and_capture() {
-        let fen = "5p2/4P3/8/8/8/8/8/8 w - - 0 1".to_string();
+    /// Bitboard of the squares the side to move's piece on `from` can legally go
+    /// to, a promotion square counting once; 0 if it has no piece there.
+    pub fn legal_destinations(&self, from: u8) -> u64 {
+        self.legal_moves_from(from)
+            .iter()
+            .fold(0, |destinations, m| destinations | (1u64 << m.to))
+    }
 
-        let mut board = Board::fen_to_board(&fen);
+    /// True if the side to move has a legal move. Moves are generated a piece at a
+    /// time and the search stops at the first legal one, so this is much cheaper than
+    /// `!legal_moves().is_empty()` in the usual case where a move exists.
+    pub fn has_legal_moves(&self) -> bool {
+        let color = self.active_color;
+        if self.is_in_check(color) {
+            return !self.evasion_moves().is_empty();
+        }
+        let pinned = self.pinned_pieces(color);
 
-        let m = Move::new("e7f8q".to_string());
+        let mut peice_moves = MoveList::new();
+        let mut pieces = self.occupied_by(color);
+        while pieces != 0 {
+            let from = pieces.trailing_zeros() as u8;
+            pieces &= pieces - 1;
 
-        assert!(board.move_peice(m));
+            let peice_type = match find_peice_for_color(self, color, from) {
+                Some(peice_type) => peice_type,
+                None => continue,
+            };
+            peice_moves.clear();
+            generate_moves_for_peice(self, color, peice_type, from, &mut peice_moves);
+            if peice_moves
+                .iter()
+                .any(|m| self.is_legal_with(&m, pinned, false))
+            {
+                return true;
+            }
+        }
+        false
+    }
 
-        let expected_white_bitboard: u64 = 1 << 61;
-        assert_eq!(board.bitboards[4], expected_white_bitboard);
-        assert_eq!(board.bitboards[0], 0);
-        assert_eq!(board.bitboards[6], 0);
+    /// Checks that `m` can be played, saying why not if it cannot: an empty
+    /// from-square, a piece of the side not to move, or the rule the move breaks.
+    pub fn check_move(&self, m: &Move) -> Result<(), MoveError> {
+        let from_bit = 1u64 << m.from;
+        if self.occupied() & from_bit == 0 {
+            return Err(MoveError::EmptySquare { at: m.from });
+        }
+        if self.occupied_by(!self.active_color) & from_bit != 0 {
+            return Err(MoveError::NotYourPiece { at: m.from });
+        }
+        if !self.legal_moves().contains(m) {
+            return Err(self.diagnose(m));
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_all_bitboard_matches_combination_of_bitboards() {
-        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    /// Why the side to move's piece on `m.from` cannot play the illegal move `m`.
+    fn diagnose(&self, m: &Move) -> MoveError {
+        let color = self.active_color;
+        let peice = match self.piece_on(color, m.from) {
+            Some(peice) => peice,
+            None => return MoveError::Illegal,
+        };
+        if self.occupied_by(color) & (1u64 << m.to) != 0 {
+            return MoveError::OwnPieceOnTarget { at: m.to };
+        }
 
-        let board = Board::fen_to_board(&fen);
+        let file_diff = (m.to % 8) as i8 - (m.from % 8) as i8;
+        let rank_diff = (m.to / 8) as i8 - (m.from / 8) as i8;
+        let forward = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let home_rank = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let first_in = |squares: u64| {
+            let squares = squares & self.occupied();
+            (squares != 0).then(|| squares.trailing_zeros() as u8)
+        };
 
-        let folded_white_bitboard = board.bitboards[0..6].iter().fold(0, |acc, &bb| acc | bb);
-        let folded_black_bitboard = board.bitboards[6..12].iter().fold(0, |acc, &bb| acc | bb);
+        if peice == PieceType::King && rank_diff == 0 && file_diff.abs() == 2 {
+            let side = match self.castling.side_of(color, m) {
+                Some(side) => side,
+                None => return MoveError::WrongPattern { peice },
+            };
+            if self.castling_rights & self.castling.right(color, side) == 0 {
+                return MoveError::NoCastlingRight;
+            }
+            if let Some(by) = first_in(self.castling.path(color, side)) {
+                return MoveError::Blocked { by };
+            }
+            let king_square = m.from;
+            if let Some(by) = self.first_attacker(king_square, !color) {
+                return MoveError::CastlingOutOfCheck { by };
+            }
+            let passed = self.castling.check_path(color, side) & !(1u64 << king_square);
+            for square in (0..64u8).filter(|square| passed & (1u64 << square) != 0) {
+                if let Some(by) = self.first_attacker(square, !color) {
+                    return MoveError::CastlingThroughCheck { square, by };
+                }
+            }
+            return MoveError::Illegal;
+        }
 
-        assert_eq!(board.all_white_bitboard, folded_white_bitboard);
-        assert_eq!(board.all_black_bitboard, folded_black_bitboard);
-    }
+        let (files, ranks) = (file_diff.abs(), rank_diff.abs());
+        let fits_pattern = match peice {
+            PieceType::Pawn => {
+                let start_rank = (home_rank as i8 + forward) as u8;
+                (files == 0 && rank_diff == forward)
+                    || (files == 0 && rank_diff == 2 * forward && m.from / 8 == start_rank)
+                    || (files == 1 && rank_diff == forward)
+            }
+            PieceType::Knight => (files, ranks) == (1, 2) || (files, ranks) == (2, 1),
+            PieceType::Bishop => files == ranks && files > 0,
+            PieceType::Rook => (files == 0) != (ranks == 0),
+            PieceType::Queen => (files == ranks && files > 0) || ((files == 0) != (ranks == 0)),
+            PieceType::King => files.max(ranks) == 1,
+        };
+        if !fits_pattern {
+            return MoveError::WrongPattern { peice };
+        }
+        if matches!(
+            peice,
+            PieceType::Bishop | PieceType::Rook | PieceType::Queen
+        ) || (peice == PieceType::Pawn && files == 0)
+        {
+            let mut path = squares_between(m.from, m.to);
+            if peice == PieceType::Pawn {
+                // Pawns push only onto empty squares
+                path |= 1u64 << m.to;
+            }
+            if let Some(by) = first_in(path) {
+                return MoveError::Blocked { by };
+            }
+        }
+        if peice == PieceType::Pawn
+            && files == 1
+            && self.occupied_by(!color) & (1u64 << m.to) == 0
+            && self.en_passant != Some(m.to)
+        {
+            return MoveError::NothingToCapture { at: m.to };
+        }
 
-    #[test]
-    fn test_bitboards_matching_after_moves() {
-        let fen = "rnbqkbnr/pppp1ppp/8/8/8/4p3/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let last_rank = m.to / 8 == 7 - home_rank;
+        let promotes = match m.promotion {
+            None => false,
+            Some(PieceType::Pawn | PieceType::King) => return MoveError::BadPromotion,
+            Some(_) => true,
+        };
+        if promotes != (peice == PieceType::Pawn && last_rank) {
+            return MoveError::BadPromotion;
+        }
 
-        let mut board = Board::fen_to_board(&fen);
+        let mut after = *self;
+        after.make_move_unchecked(m);
+        match after
+            .king_square(color)
+            .and_then(|king_square| after.first_attacker(king_square, !color))
+        {
+            Some(by) => MoveError::LeavesKingInCheck { by },
+            None => MoveError::Illegal,
+        }
+    }
 
-        let m = Move::new("f2e3".to_string());
+    /// Lowest square of a piece of `color` attacking `square`.
+    fn first_attacker(&self, square: u8, color: Color) -> Option<u8> {
+        let attackers = self.attackers_to(square, color);
+        (attackers != 0).then(|| attackers.trailing_zeros() as u8)
+    }
 
-        assert!(board.move_peice(m));
+    /// Plays `m` if it is legal, returning what [`Board::unmake_move`] needs to take
+    /// it back. The board is unchanged on error.
+    pub fn make_move(&mut self, m: &Move) -> Result<Undo, MoveError> {
+        self.check_move(m)?;
+        Ok(self.make_move_unchecked(m))
+    }
 
-        let folded_white_bitboard = board.bitboards[0..6].iter().fold(0, |acc, &bb| acc | bb);
-        let folded_black_bitboard = board.bitboards[6..12].iter().fold(0, |acc, &bb| acc | bb);
+    /// Plays a whitespace-separated list of UCI moves, as GUIs and servers send them.
+    /// On an illegal or malformed move the board is left as it was.
+    pub fn apply_uci_moves(&mut self, moves: &str) -> Result<(), GameError> {
+        let mut board = *self;
+        for (ply, uci) in moves.split_whitespace().enumerate() {
+            Move::from_uci(uci)
+                .ok_or(MoveError::Malformed)
+                .and_then(|m| board.make_move(&m))
+                .map_err(|reason| GameError::IllegalMove {
+                    ply,
+                    uci: uci.to_string(),
+                    reason,
+                })?;
+        }
+        *self = board;
+        Ok(())
+    }
+
+    /// Number of leaf positions `depth` plies below this one, for checking move
+    /// generation against known counts and for timing it.
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut board = *self;
+        let mut nodes = 0;
+        for m in moves.iter() {
+            let undo = board.make_move_unchecked(&m);
+            nodes += board.perft(depth - 1);
+            board.unmake_move(&m, undo);
+        }
+        nodes
+    }
+
+    /// Legal captures, en passant captures and promotions for the side to move, for
+    /// quiescence search.
+    ///
+    /// Targets come from intersecting each piece's attacks with the enemy occupancy
+    /// rather than filtering the full move list.
+    pub fn capture_moves(&self) -> MoveList {
+        let color = self.active_color;
+        let pinned = self.pinned_pieces(color);
+        let in_check = self.is_in_check(color);
+
+        let (friendly, enemy) = (self.occupied_by(color), self.occupied_by(!color));
+        // The enemy king is never captured, and a square we occupy is never a target
+        let targets = enemy & !friendly & !self.pieces(!color, PieceType::King);
+        let occupancy = friendly | enemy;
+
+        let mut moves = MoveList::new();
+        let mut push = |from: u8, to: u8, promotion: Option<PieceType>| {
+            let m = Move::new_from_indices(from, to, promotion);
+            if self.is_legal_with(&m, pinned, in_check) {
+                moves.push(m);
+            }
+        };
+
+        let mut pieces = friendly;
+        while pieces != 0 {
+            let from = pieces.trailing_zeros() as u8;
+            pieces &= pieces - 1;
+            let from_bb = 1u64 << from;
+
+            let mut attacks = if self.pieces(color, PieceType::Pawn) & from_bb != 0 {
+                let attacks = Self::get_pawn_attack_bitboard(from_bb, color);
+                let last_rank_ahead = match color {
+                    Color::White => from / 8 == 6,
+                    Color::Black => from / 8 == 1,
+                };
+
+                // Quiet promotions straight ahead
+                let ahead = match color {
+                    Color::White => from + 8,
+                    Color::Black => from - 8,
+                };
+                if last_rank_ahead && occupancy & (1u64 << ahead) == 0 {
+                    for p in PROMOTION_PIECES {
+                        push(from, ahead, Some(p));
+                    }
+                }
+
+                if let Some(en_passant) = self.en_passant {
+                    if attacks & (1u64 << en_passant) != 0 && occupancy & (1u64 << en_passant) == 0
+                    {
+                        push(from, en_passant, None);
+                    }
+                }
+
+                // Capture-promotions
+                let mut promotion_captures = if last_rank_ahead {
+                    attacks & targets
+                } else {
+                    0
+                };
+                while promotion_captures != 0 {
+                    let to = promotion_captures.trailing_zeros() as u8;
+                    promotion_captures &= promotion_captures - 1;
+                    for p in PROMOTION_PIECES {
+                        push(from, to, Some(p));
+                    }
+                }
+                if last_rank_ahead {
+                    0
+                } else {
+                    attacks
+                }
+            } else if self.pieces(color, PieceType::Knight) & from_bb != 0 {
+                Self::get_knight_attack_bitboard(from_bb)
+            } else if self.pieces(color, PieceType::King) & from_bb != 0 {
+                Self::get_king_attack_bitboard(from_bb)
+            } else {
+                let peice_type = match find_peice_for_color(self, color, from) {
+                    Some(peice_type) => peice_type,
+                    None => continue,
+                };
+                generate_sliding_moves_for_color(self, color, peice_type, from)
+            };
+
+            attacks &= targets;
+            while attacks != 0 {
+                let to = attacks.trailing_zeros() as u8;
+                attacks &= attacks - 1;
+                push(from, to, None);
+            }
+        }
+        moves
+    }
+
+    /// Legal moves for the side to move when its king is in check: king moves,
+    /// captures of the checking piece and interpositions. In double check only the
+    /// king may move. When not in check this is the same as [`Board::legal_moves`].
+    pub fn evasion_moves(&self) -> MoveList {
+        let color = self.active_color;
+        let king_square = match self.king_square(color) {
+            Some(square) if self.is_in_check(color) => square,
+            _ => return self.legal_moves(),
+        };
+        let pinned = self.pinned_pieces(color);
+
+        let mut king_moves = MoveList::new();
+        generate_king_moves(self, color, king_square, &mut king_moves);
+        let mut moves: MoveList = king_moves
+            .into_iter()
+            .filter(|m| self.is_legal_with(m, pinned, true))
+            .collect();
+
+        let checkers = self.attackers_to(king_square, !color);
+        // With two kings the one in check may not be the first
+        if checkers == 0 {
+            return self.legal_moves();
+        }
+        if checkers.count_ones() > 1 {
+            return moves;
+        }
+        let checker = checkers.trailing_zeros() as u8;
+
+        // Squares that resolve the check: the checker itself or a square between it and
+        // the king. An en passant capture resolves a check given by the captured pawn.
+        let block_or_capture = checkers | squares_between(king_square, checker);
+        let en_passant_victim = self.en_passant.map(|square| match color {
+            Color::White => square - 8,
+            Color::Black => square + 8,
+        });
+
+        let friendly = self.occupied_by(color);
+        let mut pieces = friendly & !(1u64 << king_square) & !pinned;
+        while pieces != 0 {
+            let from = pieces.trailing_zeros() as u8;
+            pieces &= pieces - 1;
+
+            let peice_type = match find_peice_for_color(self, color, from) {
+                Some(peice_type) => peice_type,
+                None => continue,
+            };
+            let mut peice_moves = MoveList::new();
+            generate_moves_for_peice(self, color, peice_type, from, &mut peice_moves);
+            for m in peice_moves {
+                let resolves_check = block_or_capture & (1u64 << m.to) != 0
+                    || (peice_type == PieceType::Pawn
+                        && self.en_passant == Some(m.to)
+                        && en_passant_victim == Some(checker));
+
+                if resolves_check && self.is_legal_with(&m, pinned, true) {
+                    moves.push(m);
+                }
+            }
+        }
+        moves
+    }
+
+    /// Panics, saying what is wrong, if the board is not a position play could
+    /// reach: everything [`Board::assert_well_formed`] checks, plus a king count
+    /// other than one per side, pawns on the first or last rank, an en passant square
+    /// without the pawn that just moved beyond it, or castling rights without the
+    /// king and rook on their home squares. Also checks that the evaluation's
+    /// piece-square sums and phase match the pieces.
+    ///
+    /// Debug builds run this after every move.
+    pub fn assert_consistent(&self) {
+        self.assert_well_formed();
+
+        for color in Color::BOTH {
+            let kings = self.pieces(color, PieceType::King).count_ones();
+            assert!(
+                kings == 1,
+                "{} has {} kings: {}",
+                color,
+                kings,
+                self.board_to_fen()
+            );
+        }
+
+        const BACK_RANKS: u64 = 0xff00_0000_0000_00ff;
+        let pawns =
+            self.pieces(Color::White, PieceType::Pawn) | self.pieces(Color::Black, PieceType::Pawn);
+        assert!(
+            pawns & BACK_RANKS == 0,
+            "pawn on the first or last rank: {}",
+            self.board_to_fen()
+        );
+
+        if let Some(square) = self.en_passant {
+            let (pawn_color, pawn_square) = match square / 8 {
+                2 => (Color::White, square + 8),
+                _ => (Color::Black, square - 8),
+            };
+            assert!(
+                self.pieces(pawn_color, PieceType::Pawn) & (1 << pawn_square) != 0,
+                "en passant square {} has no {} pawn in front of it: {}",
+                square,
+                pawn_color,
+                self.board_to_fen()
+            );
+        }
+
+        let possible_rights = self.possible_castling_rights();
+        assert!(
+            self.castling_rights & !possible_rights == 0,
+            "castling rights {:04b} without the king and rook at home: {}",
+            self.castling_rights,
+            self.board_to_fen()
+        );
+
+        assert_eq!(
+            self.eval_terms,
+            IncrementalTerms::from_bitboards(&self.bitboards),
+            "piece-square sums or phase are stale: {}",
+            self.board_to_fen()
+        );
+    }
+
+    /// Panics, saying what is wrong, if the board's redundant state disagrees with
+    /// itself: overlapping piece bitboards, occupancy bitboards that are not the
+    /// union of the piece bitboards, or an impossible en passant square. Any FEN that
+    /// parses passes this, legal position or not.
+    pub fn assert_well_formed(&self) {
+        let mut seen = 0u64;
+        for (index, bitboard) in self.bitboards.iter().enumerate() {
+            assert!(
+                seen & bitboard == 0,
+                "bitboard {} overlaps another piece: {}",
+                index,
+                self.board_to_fen()
+            );
+            seen |= bitboard;
+        }
+
+        let white = self.bitboards[..6].iter().fold(0, |all, bb| all | bb);
+        let black = self.bitboards[6..].iter().fold(0, |all, bb| all | bb);
+        assert_eq!(
+            self.all_white_bitboard,
+            white,
+            "white occupancy is stale: {}",
+            self.board_to_fen()
+        );
+        assert_eq!(
+            self.all_black_bitboard,
+            black,
+            "black occupancy is stale: {}",
+            self.board_to_fen()
+        );
+
+        if let Some(square) = self.en_passant {
+            assert!(
+                square / 8 == 2 || square / 8 == 5,
+                "en passant square {} is not on the third or sixth rank",
+                square
+            );
+        }
+        assert!(self.castling_rights < 16, "bad castling rights");
+        assert!(
+            self.promoted & !(white | black) == 0,
+            "promoted piece on an empty square: {}",
+            self.board_to_fen()
+        );
+        assert!(self.position_count <= 100, "position history overflowed");
+    }
+
+    pub fn is_insufficient_material(&self) -> bool {
+        // Only kings left
+        if self.all_white_bitboard.count_ones() == 1 && self.all_black_bitboard.count_ones() == 1 {
+            return true;
+        }
+
+        // King and bishop/knight vs king
+        if (self.all_white_bitboard.count_ones() == 2 && self.all_black_bitboard.count_ones() == 1)
+            || (self.all_white_bitboard.count_ones() == 1
+                && self.all_black_bitboard.count_ones() == 2)
+        {
+            let minor_pieces = Color::BOTH
+                .iter()
+                .map(|&color| {
+                    self.pieces(color, PieceType::Bishop) | self.pieces(color, PieceType::Knight)
+                })
+                .fold(0, |all, bb| all | bb);
+            if minor_pieces.count_ones() == 1 {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// How many pieces of each type `color` has.
+    pub fn material(&self, color: Color) -> MaterialCount {
+        let mut material = MaterialCount::default();
+        for peice_type in PieceType::ALL {
+            material.counts[peice_type as usize] = self.pieces(color, peice_type).count_ones();
+        }
+        material
+    }
+
+    /// Whether the rules can be applied to the position, and whether play could
+    /// reach it. Illegal are a king count other than one a side, kings next to each
+    /// other, pawns on the first or last rank and the side not to move in check;
+    /// the first found is reported. Warnings are more than eight pawns or sixteen
+    /// pieces a side, more promoted pieces than missing pawns, and checks from more
+    /// than two pieces.
+    pub fn validity(&self) -> PositionValidity {
+        for color in Color::BOTH {
+            let kings = self.pieces(color, PieceType::King).count_ones();
+            if kings != 1 {
+                return PositionValidity::Illegal(IllegalPosition::KingCount { color, kings });
+            }
+        }
+        let (white_king, black_king) = (
+            self.pieces(Color::White, PieceType::King).trailing_zeros() as u8,
+            self.pieces(Color::Black, PieceType::King).trailing_zeros() as u8,
+        );
+        if (white_king % 8).abs_diff(black_king % 8) <= 1
+            && (white_king / 8).abs_diff(black_king / 8) <= 1
+        {
+            return PositionValidity::Illegal(IllegalPosition::KingsAdjacent);
+        }
+        const BACK_RANKS: u64 = 0xff00_0000_0000_00ff;
+        let pawns =
+            self.pieces(Color::White, PieceType::Pawn) | self.pieces(Color::Black, PieceType::Pawn);
+        if pawns & BACK_RANKS != 0 {
+            let square = (pawns & BACK_RANKS).trailing_zeros() as u8;
+            return PositionValidity::Illegal(IllegalPosition::PawnOnBackRank { square });
+        }
+        let waiting = !self.active_color;
+        if self.is_in_check(waiting) {
+            return PositionValidity::Illegal(IllegalPosition::NotToMoveInCheck { color: waiting });
+        }
+
+        let mut warnings = Vec::new();
+        for color in Color::BOTH {
+            let material = self.material(color);
+            let pawns = material.count(PieceType::Pawn);
+            if pawns > 8 {
+                warnings.push(format!("{} has {} pawns", color, pawns));
+            }
+            let pieces = self.occupied_by(color).count_ones();
+            if pieces > 16 {
+                warnings.push(format!("{} has {} pieces", color, pieces));
+            }
+            // Pieces beyond the starting set can only come from promoted pawns
+            let promoted: u32 = [
+                (PieceType::Knight, 2),
+                (PieceType::Bishop, 2),
+                (PieceType::Rook, 2),
+                (PieceType::Queen, 1),
+            ]
+            .into_iter()
+            .map(|(peice_type, start)| material.count(peice_type).saturating_sub(start))
+            .sum();
+            if pawns <= 8 && promoted > 8 - pawns {
+                warnings.push(format!(
+                    "{} has {} promoted pieces but only {} missing pawns",
+                    color,
+                    promoted,
+                    8 - pawns
+                ));
+            }
+        }
+        let color = self.active_color;
+        let king = self.pieces(color, PieceType::King).trailing_zeros() as u8;
+        let checkers = self.attackers_to(king, !color).count_ones();
+        if checkers > 2 {
+            warnings.push(format!("{} is in check from {} pieces", color, checkers));
+        }
+
+        if warnings.is_empty() {
+            PositionValidity::Ok
+        } else {
+            PositionValidity::Warning(warnings)
+        }
+    }
+
+    /// `color`'s material in centipawns, at [`PIECE_VALUES`]. Use
+    /// [`MaterialCount::value`] for other values.
+    pub fn material_value(&self, color: Color) -> i32 {
+        self.material(color).value(&PIECE_VALUES)
+    }
+
+    /// White's material less Black's, in centipawns.
+    pub fn material_imbalance(&self) -> i32 {
+        self.material_value(Color::White) - self.material_value(Color::Black)
+    }
+
+    /// Counts each side's legal moves, by the piece moved and how many capture or
+    /// give check, and says whether the side to move is in check. The side not to
+    /// move is counted as if it were its turn, as after [`Board::make_null_move`].
+    pub fn move_stats(&self) -> MoveStats {
+        let mut passed = *self;
+        passed.make_null_move();
+        let (mover, other) = (self.side_move_stats(), passed.side_move_stats());
+        let (white, black) = match self.active_color {
+            Color::White => (mover, other),
+            Color::Black => (other, mover),
+        };
+        MoveStats {
+            white,
+            black,
+            in_check: self.is_in_check(self.active_color),
+        }
+    }
+
+    /// The side to move's part of [`Board::move_stats`].
+    fn side_move_stats(&self) -> SideMoveStats {
+        let mut stats = SideMoveStats::default();
+        for m in self.legal_moves().iter() {
+            stats.moves += 1;
+            if let Some(peice_type) = self.piece_on(self.active_color, m.from) {
+                stats.by_piece[peice_type as usize] += 1;
+            }
+            if self.classify(&m).is_capture() {
+                stats.captures += 1;
+            }
+            if self.gives_check(&m) {
+                stats.checks += 1;
+            }
+        }
+        stats
+    }
+
+    /// False if `color` has only its king, or its king and one bishop or knight, so
+    /// it could never mate; a flag fall against such a side is a draw.
+    pub fn has_mating_material(&self, color: Color) -> bool {
+        let minors = self.pieces(color, PieceType::Bishop) | self.pieces(color, PieceType::Knight);
+        let others = self.occupied_by(color) & !minors & !self.pieces(color, PieceType::King);
+        others != 0 || minors.count_ones() > 1
+    }
+
+    pub fn is_50_move_rule(&self) -> bool {
+        self.king_moves >= 50
+    }
+
+    pub fn is_3_fold_repetition(&self) -> bool {
+        if self.position_count < 3 {
+            return false;
+        }
+
+        let current_hash = self.calculate_position_hash();
+        let mut repetition_count = 0;
+
+        for i in 0..self.position_count {
+            if self.hashed_board_states[i as usize] == current_hash {
+                repetition_count += 1;
+                if repetition_count >= 3 {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Where the game stands for the side to move: mated, stalemated, drawn by rule,
+    /// in check or simply in progress. Mate and stalemate come before the draws. A
+    /// position without exactly one king a side is [`GameStatus::Invalid`].
+    pub fn status(&self) -> GameStatus {
+        let kings = |color| self.pieces(color, PieceType::King).count_ones();
+        if kings(Color::White) != 1 || kings(Color::Black) != 1 {
+            return GameStatus::Invalid;
+        }
+        let in_check = self.is_in_check(self.active_color);
+        if !self.has_legal_moves() {
+            return if in_check {
+                GameStatus::Checkmate {
+                    winner: !self.active_color,
+                }
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+        if self.is_insufficient_material() {
+            GameStatus::InsufficientMaterial
+        } else if self.halfmove_clock >= 100 {
+            GameStatus::FiftyMoveRule
+        } else if self.is_3_fold_repetition() {
+            GameStatus::Repetition
+        } else if in_check {
+            GameStatus::Check
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// The position without its clocks or history. See [`PositionKey`].
+    pub fn position_key(&self) -> PositionKey {
+        PositionKey {
+            zobrist: self.calculate_position_hash(),
+            bitboards: self.bitboards,
+            active_color: self.active_color,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant_square(),
+            castling: self.castling,
+        }
+    }
+
+    /// The Polyglot key, the same key the book and the search use.
+    fn calculate_position_hash(&self) -> u64 {
+        crate::book::polyglot_key(self)
+    }
+}
+
+// -------------------------------
+// Tests
+// -------------------------------
+/// Serialized as its FEN string. Position history is not kept.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.board_to_fen())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        Board::from_fen(&fen).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::polyglot_key;
+
+    #[test]
+    fn test_fen_to_positions_for_one_pawn() {
+        // White pawn on e2
+        let fen = "8/8/8/8/8/8/4P3/8 w - - 0 1";
+        let board = Board::fen_to_board(fen);
+        // e2 => rank=1, file=4 => index = 1*8 + 4 = 12
+        assert_eq!(board.bitboards[0], 1 << 12); // White Pawns are bitboards[0]
+    }
+
+    #[test]
+    fn test_fen_to_position_for_two_pawns() {
+        // White pawns on d2, e2 => (rank=1, file=3) and (rank=1, file=4)
+        let fen = "8/8/8/8/8/8/3PP3/8 w - - 0 1";
+        let board = Board::fen_to_board(fen);
+
+        let expected_bitboard: u64 = (1 << 11) | (1 << 12);
+        assert_eq!(board.bitboards[0], expected_bitboard);
+    }
+
+    #[test]
+    fn test_fen_to_position_for_many_random_pawns() {
+        let fen = "P6P/8/3pp3/8/8/8/3PP3/P6P w - - 0 1";
+        let board = Board::fen_to_board(fen);
+
+        let expected_white_bitboard: u64 =
+            (1 << 11) | (1 << 12) | 1 << 0 | 1 << 7 | 1 << 56 | 1 << 63;
+        let expected_black_bitboard: u64 = (1 << 44) | (1 << 43);
+        assert_eq!(board.bitboards[0], expected_white_bitboard);
+        assert_eq!(board.bitboards[6], expected_black_bitboard);
+    }
+
+    #[test]
+
+    fn test_pawn_capture() {
+        let fen = "k7/8/8/8/8/5p2/4P3/K7 w - - 0 1".to_string();
+
+        let mut board = Board::fen_to_board(&fen);
+
+        let m = Move {
+            from: 12,
+            to: 21,
+            promotion: None,
+        };
+
+        assert!(board.move_peice(m));
+
+        let expected_white_bitboard: u64 = 1 << 21;
+        let expected_black_bitboard: u64 = 0;
+
+        assert_eq!(board.bitboards[0], expected_white_bitboard);
+        assert_eq!(board.bitboards[6], expected_black_bitboard);
+    }
+
+    #[test]
+    fn test_pawn_promotion() {
+        let fen = "8/4P3/8/8/8/8/8/k1K5 w - - 0 1".to_string();
+
+        let mut board = Board::fen_to_board(&fen);
+
+        let m = Move::new("e7e8q");
+
+        assert!(board.move_peice(m));
+
+        let expected_white_bitboard: u64 = 1 << 60;
+        assert_eq!(board.bitboards[4], expected_white_bitboard);
+        assert_eq!(board.bitboards[0], 0);
+    }
+
+    #[test]
+    fn test_pawn_promotion_and_capture() {
+        let fen = "5p2/4P3/8/8/8/8/8/k1K5 w - - 0 1".to_string();
+
+        let mut board = Board::fen_to_board(&fen);
+
+        let m = Move::new("e7f8q");
+
+        assert!(board.move_peice(m));
+
+        let expected_white_bitboard: u64 = 1 << 61;
+        assert_eq!(board.bitboards[4], expected_white_bitboard);
+        assert_eq!(board.bitboards[0], 0);
+        assert_eq!(board.bitboards[6], 0);
+    }
+
+    #[test]
+    fn test_all_bitboard_matches_combination_of_bitboards() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let folded_white_bitboard = board.bitboards[0..6].iter().fold(0, |acc, &bb| acc | bb);
+        let folded_black_bitboard = board.bitboards[6..12].iter().fold(0, |acc, &bb| acc | bb);
+
+        assert_eq!(board.all_white_bitboard, folded_white_bitboard);
+        assert_eq!(board.all_black_bitboard, folded_black_bitboard);
+    }
+
+    #[test]
+    fn test_bitboards_matching_after_moves() {
+        let fen = "rnbqkbnr/pppp1ppp/8/8/8/4p3/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        let mut board = Board::fen_to_board(fen);
+
+        let m = Move::new("f2e3");
+
+        assert!(board.move_peice(m));
+
+        let folded_white_bitboard = board.bitboards[0..6].iter().fold(0, |acc, &bb| acc | bb);
+        let folded_black_bitboard = board.bitboards[6..12].iter().fold(0, |acc, &bb| acc | bb);
+
+        assert_eq!(board.all_white_bitboard, folded_white_bitboard);
+        assert_eq!(board.all_black_bitboard, folded_black_bitboard);
+    }
+
+    #[test]
+    fn test_white_bishops_attacking_bitboards() {
+        let fen = "7B/8/8/8/8/8/8/8 b - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+
+        let expected_attack_bitboard: u64 = 0x40201008040201;
+
+        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    }
+
+    #[test]
+    fn test_black_bishops_attacking_bitboards() {
+        let fen = "7b/8/8/8/8/8/8/8 b - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let attack_bitboard = board.get_attack_bitboard_by_color(Color::Black);
+
+        let expected_attack_bitboard: u64 = 0x40201008040201;
+
+        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    }
+
+    #[test]
+    fn test_white_bishop_attacking_bitboards_when_spaces_occupied() {
+        let fen = "7B/8/5p2/8/8/8/8/8 w - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+
+        let expected_attack_bitboard: u64 = 1 << 54 | 1 << 45;
+
+        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    }
+
+    #[test]
+    fn test_pawn_attacking_bitboards() {
+        let fen = "8/7P/8/8/8/8/8/8 w - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+
+        let expected_attack_bitboard: u64 = 1 << 62;
+
+        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    }
+
+    #[test]
+    fn test_pawn_attacking_bitboards_two_squares() {
+        let fen = "8/6P1/8/8/8/8/8/8 b - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+
+        let expected_attack_bitboard: u64 = 1 << 61 | 1 << 63;
+
+        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    }
+
+    #[test]
+    fn test_pawn_attacking_bitboards_two_squares_overlapping() {
+        let fen = "8/4P1P1/8/8/8/8/8/8 b - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+
+        let expected_attack_bitboard: u64 = 1 << 61 | 1 << 63 | 1 << 59;
+
+        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    }
+
+    #[test]
+    fn test_if_black_king_in_check() {
+        let fen = "7k/8/8/8/8/2B5/8/8 w - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        assert!(board.is_in_check(Color::Black));
+    }
+
+    #[test]
+    fn test_if_black_king_is_not_in_check_due_to_wrong_diagonal() {
+        let fen = "6k1/8/8/8/8/2B5/8/8 w - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        // Assert we are not in check
+        assert!(!board.is_in_check(Color::Black));
+    }
+
+    #[test]
+    fn test_if_white_queen_in_check() {
+        let fen = "8/8/8/8/8/5pp1/5pp1/7K w - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+
+        assert!(board.is_in_check(Color::White));
+    }
+
+    #[test]
+    fn test_board_size_does_not_grow() {
+        // Checked at compile time: the board is copied for every legality check,
+        // so any new field should be a deliberate decision.
+        const _: () = assert!(std::mem::size_of::<Board>() == 968);
+        assert_eq!(std::mem::size_of::<Board>(), 968);
+    }
+
+    #[test]
+    fn test_piece_accessors() {
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/3PP3/R3K1N1 w Q - 0 1");
+        assert_eq!(board.pieces(Color::White, PieceType::Pawn), 0x1800);
+        assert_eq!(board.pieces(Color::White, PieceType::Knight), 1 << 6);
+        assert_eq!(board.pieces(Color::Black, PieceType::Pawn), 0);
+        assert_eq!(board.occupied_by(Color::Black), 1 << 60);
+        assert_eq!(board.occupied(), 0x1800 | 0b0101_0001 | 1 << 60);
+        assert_eq!(board.king_square(Color::White), Some(4));
+        assert_eq!(board.king_square(Color::Black), Some(60));
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps another piece")]
+    fn test_assert_consistent_catches_overlapping_pieces() {
+        let mut board = Board::new();
+        // A knight on a1 as well as the rook
+        board.bitboards[PieceType::Knight as usize] |= 1;
+        board.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "black occupancy is stale")]
+    fn test_assert_consistent_catches_stale_occupancy() {
+        let mut board = Board::new();
+        // The e7 pawn is gone but its occupancy bit is left behind
+        board.bitboards[6 + PieceType::Pawn as usize] &= !(1 << 52);
+        board.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "White has 2 kings")]
+    fn test_assert_consistent_catches_a_second_king() {
+        let mut board = Board::new();
+        board.bitboards[PieceType::King as usize] |= 1 << 28;
+        board.all_white_bitboard |= 1 << 28;
+        board.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "pawn on the first or last rank")]
+    fn test_assert_consistent_catches_a_pawn_on_the_last_rank() {
+        // The pawn promoted but its bit was never cleared
+        let mut board = Board::fen_to_board("1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        board.bitboards[PieceType::Pawn as usize] |= 1 << 57;
+        board.bitboards[PieceType::Queen as usize] &= !(1 << 57);
+        board.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "en passant square 20 has no White pawn in front of it")]
+    fn test_assert_consistent_catches_an_en_passant_square_without_a_pawn() {
+        let mut board = Board::new();
+        board.active_color = Color::Black;
+        board.en_passant = Some(20);
+        board.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "castling rights 0001 without the king and rook at home")]
+    fn test_assert_consistent_catches_castling_rights_without_a_rook() {
+        let mut board = Board::fen_to_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        board.castling_rights = 1;
+        board.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "piece-square sums or phase are stale")]
+    fn test_assert_consistent_catches_stale_eval_terms() {
+        let mut board = Board::fen_to_board("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        // The pawn moves to e4 behind the evaluation's back
+        board.bitboards[PieceType::Pawn as usize] = 1 << 28;
+        board.all_white_bitboard = (1 << 4) | (1 << 28);
+        board.assert_consistent();
+    }
+
+    #[test]
+    fn test_attacks_from_stops_sliders_at_blockers() {
+        let board = Board::new();
+        assert_eq!(
+            board.attacks_from(0),
+            (1 << 1) | (1 << 8),
+            "Ra1 sees b1 and a2"
+        );
+        assert_eq!(board.attacks_from(3), 0x1c14, "Qd1 sees its neighbours");
+        assert_eq!(board.attacks_from(6), (1 << 12) | (1 << 21) | (1 << 23));
+        assert_eq!(board.attacks_from(52), (1 << 43) | (1 << 45), "e7 pawn");
+        assert_eq!(board.attacks_from(28), 0, "empty square");
+
+        // The bishop on c4 reaches f7 and stops there; the rook stops at e5
+        let board = Board::fen_to_board("4k3/5p2/8/4p3/2B5/8/8/4RK2 w - - 0 1");
+        let bishop = board.attacks_from(26);
+        assert_ne!(bishop & (1 << 53), 0);
+        assert_eq!(bishop & (1 << 62), 0);
+        let rook = board.attacks_from(4);
+        assert_ne!(rook & (1 << 36), 0);
+        assert_eq!(rook & (1 << 44), 0);
+    }
+
+    #[test]
+    fn test_hanging_pieces() {
+        // The bishop on g5 is attacked by h6 with nothing guarding it; the h6 pawn it
+        // attacks back is defended by g7, the g8 knight and the h8 rook
+        let board =
+            Board::fen_to_board("rnbqkbnr/ppppppp1/7p/6B1/3P4/8/PPP1PPPP/RN1QKBNR w KQkq - 0 3");
+        assert_eq!(board.hanging_pieces(Color::White), 1 << 38);
+        assert_eq!(board.hanging_pieces(Color::Black), 0);
+        assert_eq!(board.attackers_to(38, Color::Black), 1 << 47);
+        assert_eq!(
+            board.attackers_to(47, Color::Black),
+            (1 << 54) | (1 << 62) | (1 << 63)
+        );
+
+        // A king in check is not hanging
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/8/r3K3 w - - 0 1");
+        assert_eq!(board.hanging_pieces(Color::White), 0);
+    }
+
+    #[test]
+    fn test_null_move() {
+        // After 1. e4 d5 2. e5 f5, with exd6 en passant possible
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        let mut board = Board::fen_to_board(fen);
+        let key = polyglot_key(&board);
+
+        let undo = board.make_null_move();
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 3"
+        );
+        // Black to move in the same position, with Black's moves
+        let passed = Board::fen_to_board(&board.board_to_fen());
+        assert_eq!(polyglot_key(&board), polyglot_key(&passed));
+        let mut moves: Vec<String> = board.legal_moves().iter().map(|m| m.to_string()).collect();
+        let mut expected: Vec<String> =
+            passed.legal_moves().iter().map(|m| m.to_string()).collect();
+        moves.sort();
+        expected.sort();
+        assert_eq!(moves, expected);
+
+        board.unmake_null_move(undo);
+        assert_eq!(board.board_to_fen(), fen);
+        assert_eq!(polyglot_key(&board), key);
+
+        // Passing twice gives the same position but for the clocks and the lapsed
+        // en passant square
+        let fen = "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2";
+        let mut board = Board::fen_to_board(fen);
+        let key = polyglot_key(&board);
+        board.make_null_move();
+        assert_ne!(polyglot_key(&board), key);
+        board.make_null_move();
+        assert_eq!(polyglot_key(&board), key);
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 4 3"
+        );
+    }
+
+    #[test]
+    fn test_material() {
+        let board = Board::new();
+        assert_eq!(board.material(Color::White).counts, [8, 2, 2, 2, 1, 1]);
+        assert_eq!(board.material(Color::Black), board.material(Color::White));
+        assert_eq!(board.material_value(Color::White), 3900);
+        assert_eq!(board.material_imbalance(), 0);
+        assert_eq!(
+            board.material(Color::White).to_string(),
+            "Q, 2R, 2B, 2N, 8P"
+        );
+
+        // The exchange and a pawn up for White
+        let board = Board::fen_to_board("r5k1/5ppp/8/8/8/8/1B3PPP/3R2K1 w - - 0 1");
+        assert_eq!(board.material(Color::White).to_string(), "R, B, 3P");
+        assert_eq!(board.material(Color::Black).to_string(), "R, 3P");
+        assert_eq!(board.material_imbalance(), 300);
+        assert_eq!(
+            Board::fen_to_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+                .material(Color::Black)
+                .to_string(),
+            "-"
+        );
+
+        // Other values for the same pieces
+        let knights_first = [100, 350, 300, 500, 900, 0];
+        assert_eq!(board.material(Color::White).value(&knights_first), 1100);
+    }
+
+    #[test]
+    fn test_material_follows_captures_and_promotions() {
+        let mut board = Board::fen_to_board("4k3/P7/8/3n4/8/4N3/8/4K3 w - - 0 1");
+        assert_eq!(board.material_imbalance(), 100);
+
+        board.move_peice(Move::from_uci("e3d5").unwrap());
+        assert_eq!(board.material(Color::Black).to_string(), "-");
+        assert_eq!(board.material_imbalance(), 400);
+
+        board.move_peice(Move::from_uci("e8d7").unwrap());
+        board.move_peice(Move::from_uci("a7a8q").unwrap());
+        assert_eq!(board.material(Color::White).to_string(), "Q, N");
+        assert_eq!(board.material_imbalance(), 1200);
+    }
+
+    #[test]
+    fn test_status_is_for_the_side_to_move() {
+        // Qb6 stalemates Black; the side that just moved is not the one stuck
+        let mut board = Board::fen_to_board("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1");
+        assert_eq!(board.status(), GameStatus::InProgress);
+        board.make_move(&Move::from_uci("b1b6").unwrap()).unwrap();
+        assert_eq!(board.status(), GameStatus::Stalemate);
+        assert_eq!(board.status().to_string(), "Stalemate — draw");
+
+        let mut board = Board::fen_to_board("k7/2K5/8/8/8/8/8/1R6 w - - 0 1");
+        board.make_move(&Move::from_uci("b1a1").unwrap()).unwrap();
+        let status = board.status();
+        assert_eq!(
+            status,
+            GameStatus::Checkmate {
+                winner: Color::White
+            }
+        );
+        assert_eq!(status.to_string(), "Checkmate — White wins");
+        assert!(status.is_over());
+
+        let board = Board::fen_to_board("k7/8/2K5/8/8/8/8/R7 b - - 0 1");
+        assert_eq!(board.status(), GameStatus::Check);
+        assert!(!board.status().is_over());
+        let board = Board::fen_to_board("k7/8/2K5/8/8/8/8/8 b - - 0 1");
+        assert_eq!(board.status(), GameStatus::InsufficientMaterial);
+    }
+
+    #[test]
+    fn test_status_after_castling_and_en_passant() {
+        let white_mates = GameStatus::Checkmate {
+            winner: Color::White,
+        };
+        let cases = [
+            // The rook lands on d1 and mates along the d-file
+            ("2rkr3/2p1p3/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1", white_mates),
+            // The same on the f-file after O-O
+            ("4rkr1/4p1p1/8/8/8/8/8/4K2R w K - 0 1", "e1g1", white_mates),
+            // exd6 e.p. opens the e-file for the rook; the knight covers d7
+            (
+                "3rkr2/5p2/8/2NpP3/8/8/8/4R2K w - d6 0 1",
+                "e5d6",
+                white_mates,
+            ),
+            // After e4 Black's only move is dxe3 e.p...
+            (
+                "7k/5K2/6P1/8/3p4/3P4/4P3/8 w - - 0 1",
+                "e2e4",
+                GameStatus::InProgress,
+            ),
+            // ...and without the en passant right it is stalemate
+            (
+                "7k/5K2/6P1/8/3p4/3PP3/8/8 w - - 0 1",
+                "e3e4",
+                GameStatus::Stalemate,
+            ),
+        ];
+        for (fen, uci, status) in cases {
+            let mut board = Board::fen_to_board(fen);
+            board.make_move(&Move::from_uci(uci).unwrap()).unwrap();
+            assert_eq!(board.status(), status, "{} after {}", fen, uci);
+        }
+    }
+
+    #[test]
+    fn test_move_stats() {
+        let stats = Board::new().move_stats();
+        let start = SideMoveStats {
+            moves: 20,
+            by_piece: [16, 4, 0, 0, 0, 0],
+            captures: 0,
+            checks: 0,
+        };
+        assert_eq!(stats.white, start);
+        assert_eq!(stats.black, start);
+        assert!(!stats.in_check);
+
+        let kiwipete = Board::fen_to_board(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .move_stats();
+        assert_eq!(
+            (
+                kiwipete.white.moves,
+                kiwipete.white.captures,
+                kiwipete.white.checks
+            ),
+            (48, 8, 0)
+        );
+        assert_eq!(kiwipete.white.by_piece.iter().sum::<u32>(), 48);
+
+        // White must deal with the rook. Were it Black's turn, every move would leave
+        // White in check but the rook's along the second rank
+        let stats = Board::fen_to_board("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").move_stats();
+        assert!(stats.in_check);
+        assert_eq!(
+            stats.white,
+            SideMoveStats {
+                moves: 3,
+                by_piece: [0, 0, 0, 0, 0, 3],
+                captures: 1,
+                checks: 0,
+            }
+        );
+        assert_eq!(
+            stats.black,
+            SideMoveStats {
+                moves: 17,
+                by_piece: [0, 0, 0, 12, 0, 5],
+                captures: 0,
+                checks: 10,
+            }
+        );
+        assert_eq!(stats.side(Color::Black), &stats.black);
+        assert_eq!(
+            stats.to_json(),
+            "{\"white\":{\"moves\":3,\"by_piece\":[0,0,0,0,0,3],\"captures\":1,\"checks\":0},\
+             \"black\":{\"moves\":17,\"by_piece\":[0,0,0,12,0,5],\"captures\":0,\"checks\":10},\
+             \"in_check\":true}"
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_string(&stats).unwrap(), stats.to_json());
+    }
+
+    #[test]
+    fn test_rejected_move_leaves_the_board_untouched() {
+        let cases = [
+            // The knight on d2 is pinned by the bishop on a5
+            ("r3k2r/8/8/b7/8/8/3N4/R3K2R w KQkq - 7 21", "d2f3"),
+            // The rook giving check is defended by the queen behind it
+            ("4k3/8/8/8/q2rK3/8/8/8 w - - 12 40", "e4d4"),
+            // Taking the knight does nothing about the check from h1
+            ("4k3/8/8/8/3n4/4P3/8/4K2q w - - 0 9", "e3d4"),
+            ("4q3/8/8/8/4r3/8/4B3/4K3 w - - 3 30", "e2d3"),
+            // Walking into check, and castling out of it
+            ("r3k2r/8/8/8/8/8/8/3RK2R b Kkq - 4 18", "e8d7"),
+            ("r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 4 18", "e1g1"),
+            // En passant that uncovers the rook on h5
+            ("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 33", "b5c6"),
+            // Not a move the piece can make at all
+            (Board::START_FEN, "b1b3"),
+        ];
+
+        for (fen, uci) in cases {
+            let mut board = Board::fen_to_board(fen);
+            let before = format!("{:?}", board);
+            assert!(
+                !board.move_peice(Move::from_uci(uci).unwrap()),
+                "{} {}",
+                fen,
+                uci
+            );
+            // Debug shows every field, the private ones included
+            assert_eq!(format!("{:?}", board), before, "{} {}", fen, uci);
+            assert_eq!(
+                board.board_to_fen(),
+                Board::fen_to_board(fen).board_to_fen()
+            );
+        }
+    }
+
+    #[test]
+    fn test_en_passant_and_promotion_escapes_decide_mate_and_stalemate() {
+        let check = GameStatus::Check;
+        let in_progress = GameStatus::InProgress;
+        let mate = |winner| GameStatus::Checkmate { winner };
+        let cases: [(&str, GameStatus, &[&str]); 10] = [
+            // d7-d5 gave check, and only exd6 e.p. removes the pawn
+            ("5r1k/8/4p3/3pP3/4K3/r7/8/3r4 w - d6 0 2", check, &["e5d6"]),
+            // The same position a move later, when the capture is gone
+            (
+                "5r1k/8/4p3/3pP3/4K3/r7/8/3r4 w - - 0 2",
+                mate(Color::Black),
+                &[],
+            ),
+            // The same for Black: d2-d4 gave check, and only exd3 e.p. answers it
+            ("3R4/8/R7/4k3/3Pp3/4P3/8/5R1K b - d3 0 2", check, &["e4d3"]),
+            (
+                "3R4/8/R7/4k3/3Pp3/4P3/8/5R1K b - - 0 2",
+                mate(Color::White),
+                &[],
+            ),
+            // Only taking the checking rook, which promotes, gets out of check
+            (
+                "r6k/1P6/8/8/8/3n4/3n4/K7 w - - 0 1",
+                check,
+                &["b7a8b", "b7a8n", "b7a8q", "b7a8r"],
+            ),
+            // Only promoting in the rook's way does, to any piece
+            (
+                "K6r/3P4/2n5/2n5/8/8/8/7k w - - 0 1",
+                check,
+                &["d7d8b", "d7d8n", "d7d8q", "d7d8r"],
+            ),
+            // The king is boxed in and the pawn blocked: exd6 e.p. is the only move
+            (
+                "k7/8/4p3/3pP3/8/8/5q2/7K w - d6 0 2",
+                in_progress,
+                &["e5d6"],
+            ),
+            (
+                "k7/8/4p3/3pP3/8/8/5q2/7K w - - 0 2",
+                GameStatus::Stalemate,
+                &[],
+            ),
+            // The same for Black after d2-d4
+            (
+                "7k/5Q2/8/8/3Pp3/4P3/8/K7 b - d3 0 2",
+                in_progress,
+                &["e4d3"],
+            ),
+            // gxf6 e.p. would take both pawns off the fifth rank and expose the king
+            // to the rook on a5, so this is stalemate all the same
+            (
+                "5b2/5k2/6n1/r4pPK/8/8/8/6r1 w - f6 0 2",
+                GameStatus::Stalemate,
+                &[],
+            ),
+        ];
+
+        for (fen, status, moves) in cases {
+            let board = Board::fen_to_board(fen);
+            assert_eq!(board.status(), status, "{}", fen);
+            let uci: Vec<Move> = moves.iter().map(|m| Move::from_uci(m).unwrap()).collect();
+            assert_eq!(
+                sorted_keys(board.legal_moves()),
+                sorted_keys(uci),
+                "{}",
+                fen
+            );
+            assert_eq!(
+                crate::chess_move::is_in_checkmate(&board),
+                matches!(status, GameStatus::Checkmate { .. }),
+                "{}",
+                fen
+            );
+            assert_eq!(
+                crate::chess_move::is_in_stalemate(&board),
+                status == GameStatus::Stalemate,
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_has_mating_material() {
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/8/2B1K1N1 w - - 0 1");
+        assert!(board.has_mating_material(Color::White));
+        assert!(!board.has_mating_material(Color::Black));
+
+        let board = Board::fen_to_board("4k3/4n3/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert!(board.has_mating_material(Color::White));
+        assert!(!board.has_mating_material(Color::Black));
+    }
+
+    #[test]
+    fn test_classify_moves() {
+        let uci = |s: &str| Move::from_uci(s).unwrap();
+        let cases = [
+            (Board::START_FEN, "g1f3", MoveKind::Quiet),
+            (Board::START_FEN, "e2e4", MoveKind::DoublePawnPush),
+            ("4k3/8/8/3q4/4N3/8/8/4K3 w - - 0 1", "e4d6", MoveKind::Quiet),
+            (
+                "4k3/8/8/3q4/4N3/8/8/4K3 b - - 0 1",
+                "d5e4",
+                MoveKind::Capture(PieceType::Knight),
+            ),
+            (
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+                "e5d6",
+                MoveKind::EnPassant,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+                "e1g1",
+                MoveKind::CastleKingside,
+            ),
+            (
+                "r3k3/8/8/8/8/8/8/4K3 b q - 0 1",
+                "e8c8",
+                MoveKind::CastleQueenside,
+            ),
+            (
+                "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1",
+                "b7b8n",
+                MoveKind::Promotion(PieceType::Knight),
+            ),
+            (
+                "r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1",
+                "b7a8q",
+                MoveKind::CapturePromotion(PieceType::Rook, PieceType::Queen),
+            ),
+        ];
+        for (fen, m, kind) in cases {
+            let board = Board::fen_to_board(fen);
+            assert_eq!(board.classify(&uci(m)), kind, "{} in {}", m, fen);
+        }
+
+        assert!(MoveKind::EnPassant.is_capture());
+        assert!(MoveKind::CapturePromotion(PieceType::Pawn, PieceType::Queen).is_capture());
+        assert!(!MoveKind::Promotion(PieceType::Queen).is_capture());
+        assert!(MoveKind::CastleQueenside.is_castle());
+        assert!(!MoveKind::Quiet.is_castle());
+    }
+
+    #[test]
+    fn test_halfmove_clock_resets_on_every_pawn_move_and_capture_only() {
+        // Every position starts with the clock at 7, so a move either resets it or
+        // makes it 8
+        let cases = [
+            ("4k3/8/8/8/8/8/8/4K1N1 w - - 7 40", "g1f3", 8),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 7 40", "e1d2", 8),
+            ("4k3/8/8/8/8/8/8/4K2R w K - 7 40", "e1g1", 8),
+            ("r3k3/8/8/8/8/8/8/4K3 b q - 7 40", "e8c8", 8),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 7 40", "e2e3", 0),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 7 40", "e2e4", 0),
+            // Captures of another piece type than the mover's
+            ("4k3/8/8/3b4/4N3/8/8/4K3 b - - 7 40", "d5e4", 0),
+            ("4k3/8/8/3q4/4N3/8/8/4K3 w - - 7 40", "e4d6", 8),
+            ("4k3/8/3q4/8/4N3/8/8/4K3 w - - 7 40", "e4d6", 0),
+            ("4k3/8/8/8/8/8/3p4/4K3 w - - 7 40", "e1d2", 0),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 7 40", "e5d6", 0),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 7 40", "b7b8n", 0),
+            ("r3k3/1P6/8/8/8/8/8/4K3 w - - 7 40", "b7a8q", 0),
+        ];
+        for (fen, uci, clock) in cases {
+            let m = Move::from_uci(uci).unwrap();
+            let board = Board::fen_to_board(fen);
+
+            // Every way of playing a move agrees
+            let mut validated = board;
+            assert!(validated.move_peice(m), "{} in {}", uci, fen);
+            let mut made = board;
+            made.make_move(&m).unwrap();
+            let mut unchecked = board;
+            let undo = unchecked.make_move_unchecked(&m);
+            for after in [validated, made, unchecked] {
+                assert_eq!(after.halfmove_clock, clock, "{} in {}", uci, fen);
+            }
+
+            unchecked.unmake_move(&m, undo);
+            assert_eq!(unchecked.halfmove_clock, 7, "{} in {}", uci, fen);
+        }
+    }
+
+    #[test]
+    fn test_captured_piece_leaves_its_own_bitboard() {
+        // The queen on e5 checks the king down the e-file until the knight takes her
+        let board = Board::fen_to_board("4k3/8/8/4q3/8/3N4/8/4K3 w - - 0 1");
+        assert!(board.is_in_check(Color::White));
+        let m = Move::from_uci("d3e5").unwrap();
+
+        let mut validated = board;
+        assert!(validated.move_peice(m));
+        let mut unchecked = board;
+        unchecked.make_move_unchecked(&m);
+        for after in [validated, unchecked] {
+            assert_eq!(after.pieces(Color::Black, PieceType::Queen), 0);
+            assert_eq!(after.pieces(Color::White, PieceType::Knight), 1 << 36);
+            assert_eq!(after.occupied_by(Color::Black), 1 << 60);
+            let attacked = after.get_attack_bitboard_by_color(Color::Black);
+            assert_eq!(attacked, Board::get_king_attack_bitboard(1 << 60));
+            assert!(!after.is_in_check(Color::White));
+        }
+    }
 
-        assert_eq!(board.all_white_bitboard, folded_white_bitboard);
-        assert_eq!(board.all_black_bitboard, folded_black_bitboard);
+    #[test]
+    fn test_en_passant_attackers() {
+        let both = Board::fen_to_board("4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(both.en_passant_attackers(), (1 << 34) | (1 << 36));
+        // Neither attacks the pawn on d5 they can take
+        assert_eq!(both.attackers_to(35, Color::White), 0);
+
+        let black = Board::fen_to_board("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        assert_eq!(black.en_passant_attackers(), 1 << 27);
+
+        // A pawn two files away, or no square to take on, is no attacker
+        for fen in [
+            "4k3/8/8/1P1pP3/8/8/8/4K3 w - d6 0 1",
+            "4k3/8/8/2PpP3/8/8/8/4K3 w - - 0 1",
+        ] {
+            let board = Board::fen_to_board(fen);
+            assert_eq!(board.en_passant_attackers() & (1 << 33), 0, "{}", fen);
+        }
+        assert_eq!(
+            Board::fen_to_board("4k3/8/8/2PpP3/8/8/8/4K3 w - - 0 1").en_passant_attackers(),
+            0
+        );
     }
 
     #[test]
-    fn test_white_bishops_attacking_bitboards() {
-        let fen = "7B/8/8/8/8/8/8/8 b - - 0 1";
+    fn test_see() {
+        let cases = [
+            // An undefended knight, and a queen that takes a pawn defended by a pawn
+            ("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5", 300),
+            ("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", "d1d5", -800),
+            // Rook takes rook and is taken back, unless only the king could take
+            // back, with the queen behind the rook
+            ("3rk3/3q4/8/8/8/8/8/3RK3 w - - 0 1", "d1d8", 0),
+            ("3rk3/8/8/8/8/8/3R4/3QK3 w - - 0 1", "d2d8", 500),
+            // A king that takes a defended pawn is lost
+            ("4k3/8/8/8/8/2p5/3pK3/8 w - - 0 1", "e2d2", 100 - 20_000),
+            // En passant wins a pawn, unless a pawn defends the square
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            ("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 0),
+            // The pawn taken en passant no longer shields the rook on d2 from d6
+            ("4k3/8/8/3pP3/8/8/3r4/7K w - d6 0 1", "e5d6", 0),
+        ];
+        for (fen, uci, expected) in cases {
+            let board = Board::fen_to_board(fen);
+            let m = Move::from_uci(uci).unwrap();
+            assert_eq!(board.see(&m), expected, "{} in {}", uci, fen);
+        }
+    }
 
-        let board = Board::fen_to_board(&fen);
+    #[test]
+    fn test_gives_check() {
+        let cases = [
+            // Direct checks by a knight and a pawn
+            ("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", "b1d2", false),
+            ("4k3/8/8/8/8/5N2/8/4K3 w - - 0 1", "f3d6", true),
+            ("4k3/8/5P2/8/8/8/8/4K3 w - - 0 1", "f6f7", true),
+            // The bishop steps off the rook's file
+            ("4k3/8/8/8/4B3/8/8/4RK2 w - - 0 1", "e4c6", true),
+            // En passant uncovers the bishop on b2 by removing the black pawn
+            ("8/8/5k2/3pP3/8/8/1B6/4K3 w - d6 0 1", "e5d6", true),
+            // Both pawns leave the fifth rank, uncovering the rook along it
+            ("8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1", "e5d6", true),
+            ("8/8/8/R2pP1Pk/8/8/8/4K3 w - d6 0 1", "e5d6", false),
+            // Promotion to a knight checks, to a queen does not
+            ("8/3P4/2k5/8/8/8/8/4K3 w - - 0 1", "d7d8n", true),
+            ("8/3P4/2k5/8/8/8/8/4K3 w - - 0 1", "d7d8q", false),
+            // Castling puts the rook on f1, looking up at the king on f8
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", true),
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", false),
+        ];
+        for (fen, m, expected) in cases {
+            let board = Board::fen_to_board(fen);
+            let m = Move::from_uci(m).unwrap();
+            assert_eq!(board.gives_check(&m), expected, "{} in {}", m, fen);
+
+            let mut after = board;
+            after.make_move_unchecked(&m);
+            assert_eq!(after.is_in_check(after.active_color), expected);
+        }
+    }
 
-        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+    #[test]
+    fn test_piece_chars_round_trip() {
+        for c in "PNBRQKpnbrqk".chars() {
+            let (color, piece_type) = PieceType::try_from_char(c).unwrap();
+            assert_eq!(piece_type.to_char(color), c);
+        }
+        for color in Color::BOTH {
+            for piece_type in PieceType::ALL {
+                let c = piece_type.to_char(color);
+                assert_eq!(PieceType::try_from_char(c), Ok((color, piece_type)));
+            }
+        }
+        assert_eq!(PieceType::try_from_char('x'), Err(PieceCharError('x')));
+        assert_eq!(PieceType::from("n"), PieceType::Knight);
 
-        let expected_attack_bitboard: u64 = 0x40201008040201;
+        assert_eq!(PieceType::Knight.to_string(), "Knight");
+        assert_eq!(Color::White.to_string(), "White");
+    }
 
-        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    #[test]
+    fn test_default_is_the_parsed_starting_position() {
+        let parsed = Board::fen_to_board(Board::START_FEN);
+        for board in [
+            Board::default(),
+            Board::new(),
+            Board::fen_to_board("startpos"),
+        ] {
+            assert_eq!(board.bitboards, parsed.bitboards);
+            assert_eq!(board.all_white_bitboard, parsed.all_white_bitboard);
+            assert_eq!(board.all_black_bitboard, parsed.all_black_bitboard);
+            assert_eq!(board.active_color, parsed.active_color);
+            assert_eq!(board.castling_rights, parsed.castling_rights);
+            assert_eq!(board.en_passant, parsed.en_passant);
+            assert_eq!(board.halfmove_clock, parsed.halfmove_clock);
+            assert_eq!(board.fullmove_number, parsed.fullmove_number);
+            assert_eq!(board.king_moves, parsed.king_moves);
+            assert_eq!(board.position_count, parsed.position_count);
+            assert_eq!(board.hashed_board_states, parsed.hashed_board_states);
+        }
+        assert_eq!(Board::default().board_to_fen(), Board::START_FEN);
     }
 
     #[test]
-    fn test_black_bishops_attacking_bitboards() {
-        let fen = "7b/8/8/8/8/8/8/8 b - - 0 1";
+    fn test_is_in_check_borrows_the_board() {
+        let fen = "7k/8/8/8/8/2B5/8/8 w - - 0 1";
+
+        let board = Board::fen_to_board(fen);
+        let board_ref = &board;
 
-        let board = Board::fen_to_board(&fen);
+        assert!(board_ref.is_in_check(Color::Black));
+        assert!(!board_ref.is_in_check(Color::White));
+    }
 
-        let attack_bitboard = board.get_attack_bitboard_by_color(Color::Black);
+    const KIWIPETE_FEN: &str =
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
 
-        let expected_attack_bitboard: u64 = 0x40201008040201;
+    fn sorted_keys(moves: impl IntoIterator<Item = Move>) -> Vec<String> {
+        let mut keys: Vec<String> = moves.into_iter().map(|m| format!("{:?}", m)).collect();
+        keys.sort();
+        keys
+    }
 
-        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    fn is_capture_or_promotion(board: &Board, m: &Move) -> bool {
+        let enemy = match board.active_color {
+            Color::White => board.all_black_bitboard,
+            Color::Black => board.all_white_bitboard,
+        };
+        let is_pawn =
+            find_peice_for_color(board, board.active_color, m.from) == Some(PieceType::Pawn);
+
+        enemy & (1u64 << m.to) != 0
+            || m.promotion.is_some()
+            || (is_pawn && board.en_passant == Some(m.to))
     }
 
     #[test]
-    fn test_white_bishop_attacking_bitboards_when_spaces_occupied() {
-        let fen = "7B/8/5p2/8/8/8/8/8 w - - 0 1";
+    fn test_legal_moves_come_in_canonical_order() {
+        let uci = |fen: &str| -> String {
+            let moves = Board::fen_to_board(fen).legal_moves();
+            let moves: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+            moves.join(" ")
+        };
+        assert_eq!(
+            uci(START_FEN_FOR_TESTS),
+            "b1a3 b1c3 g1f3 g1h3 a2a3 a2a4 b2b3 b2b4 c2c3 c2c4 \
+             d2d3 d2d4 e2e3 e2e4 f2f3 f2f4 g2g3 g2g4 h2h3 h2h4"
+        );
+        assert_eq!(
+            uci(KIWIPETE_FEN),
+            "a1b1 a1c1 a1d1 e1c1 e1d1 e1f1 e1g1 h1f1 h1g1 a2a3 a2a4 b2b3 \
+             d2c1 d2e3 d2f4 d2g5 d2h6 e2d1 e2f1 e2d3 e2c4 e2b5 e2a6 g2g3 \
+             g2h3 g2g4 c3b1 c3d1 c3a4 c3b5 f3d3 f3e3 f3g3 f3h3 f3f4 f3g4 \
+             f3f5 f3h5 f3f6 d5d6 d5e6 e5d3 e5c4 e5g4 e5c6 e5g6 e5d7 e5f7"
+        );
+        // Promotions from knight up to queen
+        assert_eq!(
+            uci("4k3/1P6/8/8/8/8/8/7K w - - 0 1"),
+            "h1g1 h1g2 h1h2 b7b8n b7b8b b7b8r b7b8q"
+        );
 
-        let board = Board::fen_to_board(&fen);
+        // The list handed in is replaced, in the same order
+        let mut moves = Board::fen_to_board(KIWIPETE_FEN).legal_moves();
+        Board::new().legal_moves_into(&mut moves);
+        assert_eq!(moves.to_vec(), Board::new().legal_moves().to_vec());
+    }
 
-        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+    /// Plays seeded random games from a few start positions and hands every position
+    /// reached to `check`.
+    fn for_random_positions(mut check: impl FnMut(&Board)) {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next_random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
 
-        let expected_attack_bitboard: u64 = 1 << 54 | 1 << 45;
+        for fen in [START_FEN_FOR_TESTS, KIWIPETE_FEN] {
+            for _ in 0..25 {
+                let mut board = Board::fen_to_board(fen);
+                for _ in 0..60 {
+                    board.assert_consistent();
+                    check(&board);
 
-        assert_eq!(attack_bitboard, expected_attack_bitboard);
+                    let moves = board.legal_moves();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let m = moves
+                        .get((next_random() % moves.len() as u64) as usize)
+                        .unwrap();
+                    board.apply_move_unchecked(&m);
+                }
+            }
+        }
     }
 
+    const START_FEN_FOR_TESTS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
     #[test]
-    fn test_pawn_attacking_bitboards() {
-        let fen = "8/7P/8/8/8/8/8/8 w - - 0 1";
+    fn test_capture_moves_match_filtered_legal_moves() {
+        for_random_positions(|board| {
+            let legal = board.legal_moves();
+            let expected = legal.iter().filter(|m| is_capture_or_promotion(board, m));
+
+            assert_eq!(
+                sorted_keys(board.capture_moves()),
+                sorted_keys(expected),
+                "{}",
+                board.board_to_fen()
+            );
+        });
+    }
 
-        let board = Board::fen_to_board(&fen);
+    #[test]
+    fn test_evasion_moves_match_legal_moves() {
+        let mut positions_in_check = 0;
+        for_random_positions(|board| {
+            if board.is_in_check(board.active_color) {
+                positions_in_check += 1;
+            }
 
-        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+            assert_eq!(
+                sorted_keys(board.evasion_moves()),
+                sorted_keys(board.legal_moves()),
+                "{}",
+                board.board_to_fen()
+            );
+        });
+        assert!(positions_in_check > 0);
+    }
 
-        let expected_attack_bitboard: u64 = 1 << 62;
+    #[test]
+    fn test_legal_destinations() {
+        let board = Board::new();
+        assert_eq!(
+            board.legal_destinations(6),
+            (1 << 21) | (1 << 23),
+            "Nf3 and Nh3"
+        );
+        assert_eq!(board.legal_destinations(0), 0, "the rook is boxed in");
+        assert_eq!(board.legal_destinations(28), 0, "empty square");
+        assert_eq!(board.legal_destinations(57), 0, "Black's knight");
+        assert_eq!(board.legal_destinations(64), 0);
+
+        // Castling lands the king two squares over; a promotion counts once
+        let board = Board::fen_to_board("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1");
+        assert_ne!(board.legal_destinations(4) & (1 << 6), 0);
+        assert_eq!(board.legal_destinations(49), 1 << 57);
+        assert_eq!(board.legal_moves_from(49).len(), 4);
+
+        // A pinned knight cannot move at all
+        let board = Board::fen_to_board("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert!(board.legal_moves_from(12).is_empty());
+    }
 
-        assert_eq!(attack_bitboard, expected_attack_bitboard);
+    #[test]
+    fn test_has_legal_moves_matches_legal_moves() {
+        for_random_positions(|board| {
+            assert_eq!(
+                board.has_legal_moves(),
+                !board.legal_moves().is_empty(),
+                "{}",
+                board.board_to_fen()
+            );
+        });
+
+        // Rook mate, queen stalemate, and a pinned bishop beside a king that can move
+        for (fen, expected) in [
+            ("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1", true),
+            ("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", false),
+            ("k7/8/1Q6/8/8/8/8/7K b - - 0 1", false),
+            ("k7/b7/8/8/8/8/8/R6K b - - 0 1", true),
+        ] {
+            let board = Board::fen_to_board(fen);
+            assert_eq!(board.has_legal_moves(), expected, "{}", fen);
+            assert_eq!(
+                board.has_legal_moves(),
+                !board.legal_moves().is_empty(),
+                "{}",
+                fen
+            );
+        }
     }
 
     #[test]
-    fn test_pawn_attacking_bitboards_two_squares() {
-        let fen = "8/6P1/8/8/8/8/8/8 b - - 0 1";
+    fn test_no_captures_from_the_starting_position() {
+        let board = Board::fen_to_board(START_FEN_FOR_TESTS);
 
-        let board = Board::fen_to_board(&fen);
+        assert!(board.capture_moves().is_empty());
+        assert_eq!(board.legal_moves().len(), 20);
+    }
 
-        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+    #[test]
+    fn test_capture_moves_include_quiet_promotions_and_en_passant() {
+        let board = Board::fen_to_board("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        let captures = sorted_keys(board.capture_moves());
 
-        let expected_attack_bitboard: u64 = 1 << 61 | 1 << 63;
+        let mut expected: Vec<Move> = PROMOTION_PIECES
+            .iter()
+            .map(|&p| Move::new_from_indices(49, 57, Some(p)))
+            .collect();
+        expected.push(Move::new("e5d6"));
 
-        assert_eq!(attack_bitboard, expected_attack_bitboard);
+        assert_eq!(captures, sorted_keys(expected));
     }
 
     #[test]
-    fn test_pawn_attacking_bitboards_two_squares_overlapping() {
-        let fen = "8/4P1P1/8/8/8/8/8/8 b - - 0 1";
+    fn test_double_check_only_allows_king_moves() {
+        // Rook on e8 and bishop on b4 both check the king on e1
+        let board = Board::fen_to_board("4r2k/8/8/8/1b6/8/3N4/4K3 w - - 0 1");
+        let evasions = board.evasion_moves();
 
-        let board = Board::fen_to_board(&fen);
+        assert!(!evasions.is_empty());
+        assert!(evasions.iter().all(|m| m.from == 4));
+    }
 
-        let attack_bitboard = board.get_attack_bitboard_by_color(Color::White);
+    #[test]
+    fn test_evasions_include_interpositions_and_checker_captures() {
+        // Rook on e8 checks the king on e1; the knight can block on e3 and the
+        // bishop can take the rook
+        let board = Board::fen_to_board("4r2k/8/8/1B6/8/8/3N4/4K3 w - - 0 1");
+        let evasions = sorted_keys(board.evasion_moves());
+
+        assert!(evasions.contains(&format!("{:?}", Move::new("d2e4"))));
+        assert!(evasions.contains(&format!("{:?}", Move::new("b5e8"))));
+        assert!(!evasions.contains(&format!("{:?}", Move::new("d2b3"))));
+        assert_eq!(evasions, sorted_keys(board.legal_moves()));
+    }
 
-        let expected_attack_bitboard: u64 = 1 << 61 | 1 << 63 | 1 << 59;
+    #[test]
+    fn test_pinned_piece_cannot_leave_the_pin() {
+        let board = Board::fen_to_board("4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1");
 
-        assert_eq!(attack_bitboard, expected_attack_bitboard);
+        assert!(board.legal_moves().iter().all(|m| m.from != 12));
+        assert!(!board.is_legal(&Move::new("e2c3")));
     }
 
     #[test]
-    fn test_if_black_king_in_check() {
-        let fen = "7k/8/8/8/8/2B5/8/8 w - - 0 1";
+    fn test_knight_attacks_do_not_wrap_around_the_board() {
+        let attacks = Board::get_knight_attack_bitboard(1 << 6);
 
-        let board = Board::fen_to_board(&fen);
+        assert_eq!(attacks, 1 << 23 | 1 << 21 | 1 << 12);
+    }
 
-        assert!(board.is_in_check(Color::Black));
+    #[test]
+    fn test_unmake_move_restores_the_board() {
+        for_random_positions(|board| {
+            let mut scratch = *board;
+            for m in board.legal_moves() {
+                let undo = scratch.make_move_unchecked(&m);
+                scratch.unmake_move(&m, undo);
+
+                assert_eq!(scratch.board_to_fen(), board.board_to_fen(), "{:?}", m);
+                assert_eq!(scratch.bitboards, board.bitboards);
+                assert_eq!(scratch.all_white_bitboard, board.all_white_bitboard);
+                assert_eq!(scratch.all_black_bitboard, board.all_black_bitboard);
+                assert_eq!(scratch.position_count, board.position_count);
+            }
+        });
     }
 
     #[test]
-    fn test_if_black_king_is_not_in_check_due_to_wrong_diagonal() {
-        let fen = "6k1/8/8/8/8/2B5/8/8 w - - 0 1";
+    fn test_repetition_survives_a_full_history() {
+        let mut board = Board::fen_to_board(START_FEN_FOR_TESTS);
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"].map(Move::new);
+
+        // 120 knight moves fill the history; the oldest entries drop out
+        let mut undos = Vec::new();
+        for m in shuffle.iter().cycle().take(120) {
+            undos.push((*m, board.make_move_unchecked(m)));
+        }
+        assert_eq!(board.position_count, 100);
+        assert!(board.is_repetition());
+        let history = board.hashed_board_states;
+
+        let (m, undo) = undos.pop().unwrap();
+        board.unmake_move(&m, undo);
+        assert!(board.is_repetition());
+        undos.push((m, board.make_move_unchecked(&m)));
+        assert_eq!(board.hashed_board_states, history);
+
+        while let Some((m, undo)) = undos.pop() {
+            board.unmake_move(&m, undo);
+        }
+        assert_eq!(board.position_count, 0);
+        assert!(!board.is_repetition());
 
-        let board = Board::fen_to_board(&fen);
+        // A pawn move makes earlier positions unreachable
+        board.make_move_unchecked(&shuffle[0]);
+        board.make_move_unchecked(&Move::new("e7e5"));
+        assert!(!board.is_repetition());
+    }
 
-        // Assert we are not in check
-        assert!(!board.is_in_check(Color::Black));
+    #[test]
+    fn test_make_move_castles_and_captures() {
+        let mut board = Board::fen_to_board(KIWIPETE_FEN);
+
+        board.make_move_unchecked(&Move::new("e1g1"));
+        assert_eq!(
+            board.board_to_fen(),
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1"
+        );
+
+        // The queen takes the f6 knight, and the capture resets the halfmove clock
+        board.make_move_unchecked(&Move::new("e7d6"));
+        board.make_move_unchecked(&Move::new("f3f6"));
+        assert_eq!(
+            board.board_to_fen(),
+            "r3k2r/p1pp1pb1/bn1qpQp1/3PN3/1p2P3/2N4p/PPPBBPPP/R4RK1 b kq - 0 2"
+        );
+        assert_eq!(
+            board.bitboards[6 + PieceType::Knight as usize].count_ones(),
+            1
+        );
     }
 
     #[test]
-    fn test_if_white_queen_in_check() {
-        let fen = "8/8/8/8/8/5pp1/5pp1/7K w - - 0 1";
+    fn test_castling_path_must_be_empty_of_either_color() {
+        // A black knight on g1 blocks O-O just as a white piece would
+        let mut board = Board::fen_to_board("4k3/8/8/8/8/8/8/4K1nR w K - 0 1");
+        assert!(!board.move_peice(Move::new("e1g1")));
+        assert!(!board.legal_moves().iter().any(|m| m.to_string() == "e1g1"));
+    }
 
-        let board = Board::fen_to_board(&fen);
+    #[test]
+    fn test_capturing_promotion_takes_the_rook_and_its_castling_right() {
+        let mut board = Board::fen_to_board("r3k2r/1P4P1/8/8/8/8/8/4K3 w kq - 5 40");
+
+        // bxa8=Q: the rook is gone, Black keeps only the kingside right
+        assert!(board.move_peice(Move::from_uci("b7a8q").unwrap()));
+        assert_eq!(board.board_to_fen(), "Q3k2r/6P1/8/8/8/8/8/4K3 b k - 0 40");
+        assert_eq!(board.pieces(Color::Black, PieceType::Rook), 1 << 63);
+        assert_eq!(board.pieces(Color::White, PieceType::Queen), 1 << 56);
+        assert_eq!(board.all_black_bitboard, (1 << 60) | (1 << 63));
+        board.assert_consistent();
+
+        // gxh8=N takes the other rook and the last right
+        assert!(board.move_peice(Move::from_uci("e8d7").unwrap()));
+        assert!(board.move_peice(Move::from_uci("g7h8n").unwrap()));
+        assert_eq!(board.board_to_fen(), "Q6N/3k4/8/8/8/8/8/4K3 b - - 0 41");
+        assert_eq!(board.material(Color::Black).to_string(), "-");
+    }
 
-        assert!(board.is_in_check(Color::White));
+    #[test]
+    fn test_plain_promotion_leaves_castling_rights() {
+        let mut board = Board::fen_to_board("r3k2r/8/8/8/8/8/2p5/4K3 b kq - 3 30");
+
+        assert!(board.move_peice(Move::from_uci("c2c1r").unwrap()));
+        assert_eq!(board.board_to_fen(), "r3k2r/8/8/8/8/8/8/2r1K3 w kq - 0 31");
+        assert_eq!(
+            board.all_black_bitboard,
+            (1 << 2) | (1 << 56) | (1 << 60) | (1 << 63)
+        );
+    }
+
+    #[test]
+    fn test_move_peice_castling_updates_every_field() {
+        let mut board = Board::fen_to_board("r3k2r/8/8/8/4P3/8/8/R3K2R b KQkq e3 0 12");
+        assert!(board.move_peice(Move::from_uci("e8g8").unwrap()));
+
+        // King e8 -> g8 and rook h8 -> f8, nothing else moves
+        let e4 = 1u64 << 28;
+        assert_eq!(board.pieces(Color::Black, PieceType::King), 1 << 62);
+        assert_eq!(
+            board.pieces(Color::Black, PieceType::Rook),
+            (1 << 56) | (1 << 61)
+        );
+        assert_eq!(
+            board.occupied_by(Color::Black),
+            (1 << 56) | (1 << 61) | (1 << 62)
+        );
+        assert_eq!(board.pieces(Color::White, PieceType::Pawn), e4);
+        assert_eq!(
+            board.occupied_by(Color::White),
+            (1 << 0) | (1 << 4) | (1 << 7) | e4
+        );
+        for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Queen] {
+            assert_eq!(board.pieces(Color::White, piece_type), 0);
+            assert_eq!(board.pieces(Color::Black, piece_type), 0);
+        }
+        assert_eq!(board.pieces(Color::Black, PieceType::Pawn), 0);
+
+        // Black loses both rights, the en passant square is gone, the halfmove clock
+        // counts the castle and the fullmove number moves on after Black's move
+        assert_eq!(board.castling_rights, 0b0011);
+        assert_eq!(board.en_passant, None);
+        assert_eq!(board.halfmove_clock, 1);
+        assert_eq!(board.fullmove_number, 13);
+        assert_eq!(board.active_color, Color::White);
+        assert_eq!(board.king_moves, 1);
+        assert_eq!(board.position_count, 1);
+        assert_eq!(
+            board.hashed_board_states[0],
+            board.calculate_position_hash()
+        );
+        assert_eq!(
+            board.board_to_fen(),
+            "r4rk1/8/8/8/4P3/8/8/R3K2R w KQ - 1 13"
+        );
+
+        // White castles long from there
+        assert!(board.move_peice(Move::from_uci("e1c1").unwrap()));
+        assert_eq!(board.board_to_fen(), "r4rk1/8/8/8/4P3/8/8/2KR3R b - - 2 13");
+    }
+
+    #[test]
+    fn test_perft_matches_known_counts() {
+        let start = Board::fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start.perft(3), 8_902);
+
+        let kiwipete = Board::fen_to_board(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        assert_eq!(kiwipete.perft(2), 2_039);
+
+        let endgame = Board::fen_to_board("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(endgame.perft(3), 2_812);
+    }
+
+    #[test]
+    fn test_is_square_attacked() {
+        let board = Board::fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert!(board.is_square_attacked(21, Color::White)); // f3
+        assert!(!board.is_square_attacked(28, Color::White)); // e4
+        assert!(board.is_square_attacked(42, Color::Black)); // c6
+    }
+
+    #[test]
+    fn test_apply_uci_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut board = Board::fen_to_board(start);
+
+        assert!(board.apply_uci_moves("e2e4 e7e5  g1f3").is_ok());
+        let after = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+        assert_eq!(board.board_to_fen(), after);
+
+        let err = board.apply_uci_moves("b8c6 f3f5").unwrap_err();
+        assert_eq!(
+            err,
+            GameError::IllegalMove {
+                ply: 1,
+                uci: "f3f5".to_string(),
+                reason: MoveError::WrongPattern {
+                    peice: PieceType::Knight
+                },
+            }
+        );
+        assert_eq!(board.board_to_fen(), after);
+
+        let err = board.apply_uci_moves("b8c6 f3").unwrap_err();
+        assert_eq!(
+            err,
+            GameError::IllegalMove {
+                ply: 1,
+                uci: "f3".to_string(),
+                reason: MoveError::Malformed,
+            }
+        );
+    }
+
+    #[test]
+    fn test_make_move_says_why_a_move_is_rejected() {
+        let mut board = Board::new();
+        assert_eq!(
+            board
+                .make_move(&Move::from_uci("e3e4").unwrap())
+                .unwrap_err(),
+            MoveError::EmptySquare { at: 20 }
+        );
+        assert_eq!(
+            board
+                .make_move(&Move::from_uci("e7e5").unwrap())
+                .unwrap_err(),
+            MoveError::NotYourPiece { at: 52 }
+        );
+        assert_eq!(
+            board
+                .make_move(&Move::from_uci("e2e5").unwrap())
+                .unwrap_err(),
+            MoveError::WrongPattern {
+                peice: PieceType::Pawn
+            }
+        );
+        assert_eq!(board.board_to_fen(), Board::START_FEN);
+
+        let m = Move::from_uci("e2e4").unwrap();
+        let undo = board.make_move(&m).unwrap();
+        board.unmake_move(&m, undo);
+        assert_eq!(board.board_to_fen(), Board::START_FEN);
+    }
+
+    #[test]
+    fn test_fen_writes_en_passant_only_when_it_can_be_taken() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut board = Board::fen_to_board(start);
+        board.apply_uci_moves("e2e4 a7a6").unwrap();
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbqkbnr/1ppppppp/p7/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+        );
+
+        let mut board = Board::fen_to_board(start);
+        board.apply_uci_moves("e2e4 d7d5 e4e5 f7f5").unwrap();
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"
+        );
+
+        // The d5 pawn may not take on c6 while it shields its king from the rook
+        let pinned = Board::fen_to_board("8/8/8/rPpP3K/8/8/8/4k3 w - c6 0 1");
+        assert_eq!(pinned.en_passant, Some(42));
+        assert_eq!(pinned.en_passant_square(), Some(42), "b5 can still take");
+        let pinned = Board::fen_to_board("8/8/8/r1pP3K/8/8/8/4k3 w - c6 0 1");
+        assert_eq!(pinned.en_passant_square(), None);
+    }
+
+    #[test]
+    fn test_irrelevant_en_passant_square_does_not_change_the_hash() {
+        let mut after_push = Board::fen_to_board("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        after_push.apply_uci_moves("e2e4").unwrap();
+        assert_eq!(after_push.en_passant, Some(20));
+
+        let without = Board::fen_to_board("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+        assert_eq!(
+            after_push.calculate_position_hash(),
+            without.calculate_position_hash()
+        );
+    }
+
+    #[test]
+    fn test_position_key_ignores_move_order_and_clocks() {
+        use std::collections::hash_map::DefaultHasher;
+        let hash = |key: PositionKey| {
+            let mut hasher = DefaultHasher::new();
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // e4 leaves an en passant square no black pawn can use
+        let mut one = Board::new();
+        one.apply_uci_moves("e2e4 g8f6 g1f3").unwrap();
+        let mut other = Board::new();
+        other.apply_uci_moves("g1f3 g8f6 e2e4").unwrap();
+        assert_ne!(one.en_passant, other.en_passant);
+        assert_eq!(one.position_key(), other.position_key());
+        assert_eq!(hash(one.position_key()), hash(other.position_key()));
+
+        // Back to the start with the clocks moved on
+        let mut shuffled = Board::new();
+        shuffled.apply_uci_moves("g1f3 g8f6 f3g1 f6g8").unwrap();
+        assert_eq!(shuffled.fullmove_number, 3);
+        assert_eq!(shuffled.position_key(), Board::new().position_key());
+
+        let without_rights =
+            Board::fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Qkq - 0 1");
+        assert_ne!(without_rights.position_key(), Board::new().position_key());
+        assert_ne!(
+            without_rights.position_key().zobrist(),
+            Board::new().position_key().zobrist()
+        );
+    }
+
+    #[test]
+    fn test_from_fen_reports_errors() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(Board::from_fen(start).is_ok());
+
+        let cases = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+                "expected 6 fields",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+                "expected 8 ranks",
+            ),
+            (
+                "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "invalid character",
+            ),
+            (
+                "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "more than 8",
+            ),
+            (
+                "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "7 squares",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+                "active color",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1",
+                "castling",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+                "en passant",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+                "counter",
+            ),
+        ];
+        for (fen, message) in cases {
+            let err = Board::from_fen(fen).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", fen, err);
+        }
+    }
+
+    #[test]
+    fn test_try_from_fen_accepts_four_fields_and_extra_whitespace() {
+        let board = Board::try_from_fen(
+            "  rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR   w KQkq -  ",
+            Strictness::Strict,
+        )
+        .unwrap();
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+
+        let err = Board::try_from_fen("8/8/8/8/8/8/8/8 w - - 0", Strictness::Lax).unwrap_err();
+        assert_eq!(err, FenError::FieldCount(5));
+    }
+
+    #[test]
+    fn test_validity_classifies_positions() {
+        assert_eq!(Board::new().validity(), PositionValidity::Ok);
+
+        let warnings = [
+            (
+                "4k3/pppppppp/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1",
+                "White has 9 pawns",
+            ),
+            (
+                "k7/8/8/8/8/8/PPPPPPPP/QQQ1K3 b - - 0 1",
+                "White has 2 promoted pieces but only 0 missing pawns",
+            ),
+            (
+                "4k3/8/3N4/1B6/8/8/8/4RK2 b - - 0 1",
+                "Black is in check from 3 pieces",
+            ),
+        ];
+        for (fen, warning) in warnings {
+            assert_eq!(
+                Board::fen_to_board(fen).validity(),
+                PositionValidity::Warning(vec![warning.to_string()]),
+                "{}",
+                fen
+            );
+            // Strict parsing accepts them
+            assert!(
+                Board::try_from_fen(fen, Strictness::Strict).is_ok(),
+                "{}",
+                fen
+            );
+        }
+
+        let illegal = [
+            (
+                "8/8/8/8/8/8/8/4K3 w - - 0 1",
+                IllegalPosition::KingCount {
+                    color: Color::Black,
+                    kings: 0,
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+                IllegalPosition::KingCount {
+                    color: Color::White,
+                    kings: 2,
+                },
+            ),
+            (
+                "8/8/8/8/8/8/3k4/4K3 w - - 0 1",
+                IllegalPosition::KingsAdjacent,
+            ),
+            (
+                "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+                IllegalPosition::PawnOnBackRank { square: 63 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4RK2 w - - 0 1",
+                IllegalPosition::NotToMoveInCheck {
+                    color: Color::Black,
+                },
+            ),
+        ];
+        for (fen, reason) in illegal {
+            assert_eq!(
+                Board::fen_to_board(fen).validity(),
+                PositionValidity::Illegal(reason),
+                "{}",
+                fen
+            );
+            assert_eq!(
+                Board::try_from_fen(fen, Strictness::Strict).unwrap_err(),
+                FenError::Illegal(reason)
+            );
+            assert!(Board::try_from_fen(fen, Strictness::Lax).is_ok(), "{}", fen);
+        }
+
+        let err =
+            Board::try_from_fen("8/8/8/8/8/8/3k4/4K3 w - - 0 1", Strictness::Strict).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "illegal position: the kings stand next to each other"
+        );
+    }
+
+    #[test]
+    fn test_try_from_fen_repairs_or_rejects_impossible_fields() {
+        // No black pawn can take on e3
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert!(matches!(
+            Board::try_from_fen(fen, Strictness::Strict),
+            Err(FenError::EnPassant(_))
+        ));
+        let board = Board::try_from_fen(fen, Strictness::Lax).unwrap();
+        assert_eq!(board.en_passant, None);
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+
+        // The d-pawn can, so the square stays
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3";
+        let board = Board::try_from_fen(fen, Strictness::Strict).unwrap();
+        assert_eq!(board.en_passant, Some(20));
+
+        // White's h-rook and black's king have moved away
+        let fen = "rnbq1bnr/ppppkppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBN1 w KQkq - 2 3";
+        assert!(matches!(
+            Board::try_from_fen(fen, Strictness::Strict),
+            Err(FenError::Castling(_))
+        ));
+        let board = Board::try_from_fen(fen, Strictness::Lax).unwrap();
+        assert_eq!(
+            board.board_to_fen(),
+            "rnbq1bnr/ppppkppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBN1 w Q - 2 3"
+        );
+    }
+
+    #[test]
+    fn test_crazyhouse_fen_keeps_promoted_markers_and_holdings() {
+        // As lichess exports Crazyhouse positions: the knight on f3 was a pawn
+        let placement = "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/5N~2/PPPP1PPP/RNBQK2R";
+        let fen = format!("{}[Pp] w KQkq - 4 5", placement);
+        let (board, notes) = Board::try_from_fen_with_notes(&fen, Strictness::Strict).unwrap();
+        assert_eq!(board.promoted, 1 << 21);
+        assert_eq!(notes.holdings.as_deref(), Some("Pp"));
+        assert_eq!(notes.warnings.len(), 1);
+        assert_eq!(Board::fen_to_board(&fen).promoted, 1 << 21);
+
+        let marked = board.board_to_fen_with(true);
+        assert_eq!(marked, format!("{} w KQkq - 4 5", placement));
+        assert_eq!(board.board_to_fen(), marked.replace('~', ""));
+        let (back, notes) = Board::try_from_fen_with_notes(&marked, Strictness::Strict).unwrap();
+        assert_eq!(back.board_to_fen_with(true), marked);
+        assert_eq!(notes, FenNotes::default());
+        let (_, notes) =
+            Board::try_from_fen_with_notes(&format!("{}[] w KQkq -", placement), Strictness::Lax)
+                .unwrap();
+        assert_eq!(notes.holdings.as_deref(), Some(""));
+        assert!(notes.warnings.is_empty());
+
+        for bad in [
+            "~7/8/8/8/8/8/8/4K2k w - - 0 1",
+            "8/8/8/8/8/8/P~7/4K2k w - - 0 1",
+            "8/8/8/8/8/8/8/4K2k[Kx] w - - 0 1",
+        ] {
+            assert!(
+                matches!(Board::from_fen(bad), Err(FenError::Placement(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_promoted_pieces_are_tracked_through_moves() {
+        let mut board = Board::fen_to_board("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        let undo = board.make_move(&Move::from_uci("b7b8q").unwrap()).unwrap();
+        assert_eq!(board.promoted, 1 << 57);
+        assert_eq!(
+            board.board_to_fen_with(true),
+            "1Q~2k3/8/8/8/8/8/8/4K3 b - - 0 1"
+        );
+
+        // It keeps the mark where it goes and loses it when taken back
+        let mut later = board;
+        later.make_move(&Move::from_uci("e8d7").unwrap()).unwrap();
+        assert!(later.move_peice(Move::from_uci("b8b5").unwrap()));
+        assert_eq!(later.promoted, 1 << 33);
+        board.unmake_move(&Move::from_uci("b7b8q").unwrap(), undo);
+        assert_eq!(board.promoted, 0);
+
+        // ...or when it is captured
+        let mut board = Board::fen_to_board("4k3/8/8/8/8/8/2q5/1Q~2K3 b - - 0 1");
+        board.make_move(&Move::from_uci("c2b1").unwrap()).unwrap();
+        assert_eq!(board.promoted, 0);
+    }
+
+    #[test]
+    fn test_positions_without_one_king_each_do_not_panic() {
+        let fens = [
+            "8/8/8/8/3B4/8/8/8 w - - 0 1",
+            "8/8/8/8/3B4/8/8/8 b - - 0 1",
+            "8/8/8/8/8/8/8/8 w - - 0 1",
+            "4k3/8/8/8/3B4/8/8/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/R6R w KQ - 0 1",
+            "r3k2r/8/8/8/8/8/1r6/8 b kq - 0 1",
+            // A second white king, in check, beside one that is not
+            "4k3/8/8/8/8/8/8/K3K2r w - - 0 1",
+            "4k3/8/8/8/8/8/8/K3K2r b - - 0 1",
+            "3kk3/8/8/8/8/8/3Q4/4K3 b - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::fen_to_board(fen);
+            let moves = board.legal_moves();
+            assert_eq!(board.has_legal_moves(), !moves.is_empty(), "{}", fen);
+            assert_eq!(
+                sorted_keys(board.evasion_moves()),
+                sorted_keys(moves),
+                "{}",
+                fen
+            );
+            crate::eval::evaluate(&board, &crate::eval::EvalParams::DEFAULT);
+            assert_eq!(board.status(), GameStatus::Invalid, "{}", fen);
+            assert!(!board.status().is_over(), "{}", fen);
+        }
+
+        // With no king there is nothing to check, pin or castle
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/8/R6R w KQ - 0 1");
+        assert!(!board.is_in_check(Color::White));
+        assert_eq!(board.pinned_pieces(Color::White), 0);
+        assert!(board.legal_moves().iter().all(|m| m.from != 4));
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/8/8 w - - 0 1");
+        assert_eq!(board.legal_moves().len(), 0);
     }
 }
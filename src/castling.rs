@@ -0,0 +1,228 @@
+//! Where castling moves the king and rook, as data rather than square numbers spread
+//! over move generation, validation and making moves.
+//!
+//! A [`CastlingConfig`] holds the squares each side's king and rooks start on. Where
+//! they end up follows from the rules: the king on the g- or c-file, the rook next to
+//! it on the f- or d-file, whatever the start. The standard start is the default; a
+//! Chess960 start only needs other starting squares.
+
+use crate::board::Color;
+use crate::chess_move::Move;
+
+/// Which rook the king castles with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastlingSide {
+    /// With the rook on the h-file side of the king, `O-O`.
+    Kingside,
+    /// With the rook on the a-file side of the king, `O-O-O`.
+    Queenside,
+}
+
+impl CastlingSide {
+    pub const BOTH: [CastlingSide; 2] = [CastlingSide::Kingside, CastlingSide::Queenside];
+}
+
+/// The starting squares of both sides' kings and castling rooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingConfig {
+    /// By color.
+    king_start: [u8; 2],
+    /// By color, then kingside and queenside.
+    rook_start: [[u8; 2]; 2],
+}
+
+impl CastlingConfig {
+    /// Kings on e1 and e8, rooks in the corners.
+    pub const STANDARD: CastlingConfig = CastlingConfig {
+        king_start: [4, 60],
+        rook_start: [[7, 0], [63, 56]],
+    };
+
+    /// The king on `king_file` and the rooks on `kingside_rook_file` and
+    /// `queenside_rook_file` of each side's back rank, files counted from a = 0.
+    /// `None` unless the king stands between the rooks.
+    pub fn from_files(
+        king_file: u8,
+        kingside_rook_file: u8,
+        queenside_rook_file: u8,
+    ) -> Option<CastlingConfig> {
+        if !(queenside_rook_file < king_file && king_file < kingside_rook_file)
+            || kingside_rook_file > 7
+        {
+            return None;
+        }
+        Some(CastlingConfig {
+            king_start: [king_file, 56 + king_file],
+            rook_start: [
+                [kingside_rook_file, queenside_rook_file],
+                [56 + kingside_rook_file, 56 + queenside_rook_file],
+            ],
+        })
+    }
+
+    /// Square `color`'s king starts on, and must still be on to castle.
+    pub fn king_start(&self, color: Color) -> u8 {
+        self.king_start[color as usize]
+    }
+
+    /// Square `color`'s rook for castling on `side` starts on.
+    pub fn rook_start(&self, color: Color, side: CastlingSide) -> u8 {
+        self.rook_start[color as usize][side as usize]
+    }
+
+    /// Square the king lands on: the g- or c-file of its back rank.
+    pub fn king_destination(&self, color: Color, side: CastlingSide) -> u8 {
+        back_rank(color)
+            + match side {
+                CastlingSide::Kingside => 6,
+                CastlingSide::Queenside => 2,
+            }
+    }
+
+    /// Square the rook lands on: the f- or d-file of its back rank.
+    pub fn rook_destination(&self, color: Color, side: CastlingSide) -> u8 {
+        back_rank(color)
+            + match side {
+                CastlingSide::Kingside => 5,
+                CastlingSide::Queenside => 3,
+            }
+    }
+
+    /// The bit of [`crate::board::Board::castling_rights`] that allows it: 1 and 2
+    /// for White's kingside and queenside, 4 and 8 for Black's.
+    pub fn right(&self, color: Color, side: CastlingSide) -> u8 {
+        1 << (color as u8 * 2 + side as u8)
+    }
+
+    /// Squares that must be empty, apart from the castling king and rook: every
+    /// square either crosses or lands on.
+    pub fn path(&self, color: Color, side: CastlingSide) -> u64 {
+        let (king, rook) = (self.king_start(color), self.rook_start(color, side));
+        (span(king, self.king_destination(color, side))
+            | span(rook, self.rook_destination(color, side)))
+            & !((1u64 << king) | (1u64 << rook))
+    }
+
+    /// Squares the other side may not attack: the king's start, the squares it
+    /// crosses and the one it lands on.
+    pub fn check_path(&self, color: Color, side: CastlingSide) -> u64 {
+        span(self.king_start(color), self.king_destination(color, side))
+    }
+
+    /// The side `color` castles on with `m`, if `m` takes its king from its start to
+    /// where castling lands it.
+    pub fn side_of(&self, color: Color, m: &Move) -> Option<CastlingSide> {
+        if m.from != self.king_start(color) {
+            return None;
+        }
+        CastlingSide::BOTH
+            .into_iter()
+            .find(|&side| m.to == self.king_destination(color, side))
+    }
+
+    /// The rook's from- and to-squares when `color` castles with `m`, or `None` if
+    /// `m` is no castling move.
+    pub fn rook_squares(&self, color: Color, m: &Move) -> Option<(u8, u8)> {
+        let side = self.side_of(color, m)?;
+        Some((
+            self.rook_start(color, side),
+            self.rook_destination(color, side),
+        ))
+    }
+
+    /// Castling rights lost when a piece moves from, or is captured on, `square`:
+    /// both of a side's when its king leaves its start, one when a rook does.
+    pub fn rights_lost(&self, square: u8) -> u8 {
+        let mut lost = 0;
+        for color in Color::BOTH {
+            for side in CastlingSide::BOTH {
+                if square == self.king_start(color) || square == self.rook_start(color, side) {
+                    lost |= self.right(color, side);
+                }
+            }
+        }
+        lost
+    }
+}
+
+impl Default for CastlingConfig {
+    fn default() -> Self {
+        CastlingConfig::STANDARD
+    }
+}
+
+/// The a-file square of `color`'s back rank.
+fn back_rank(color: Color) -> u8 {
+    match color {
+        Color::White => 0,
+        Color::Black => 56,
+    }
+}
+
+/// Squares from `a` to `b` on one rank, both included.
+fn span(a: u8, b: u8) -> u64 {
+    let (low, high) = (a.min(b), a.max(b));
+    (low..=high).fold(0, |squares, square| squares | 1u64 << square)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(bitboard: u64) -> Vec<u8> {
+        (0..64)
+            .filter(|square| bitboard & (1u64 << square) != 0)
+            .collect()
+    }
+
+    #[test]
+    fn test_standard_squares() {
+        let config = CastlingConfig::default();
+        assert_eq!(config, CastlingConfig::from_files(4, 7, 0).unwrap());
+
+        let white = Color::White;
+        assert_eq!(squares(config.path(white, CastlingSide::Kingside)), [5, 6]);
+        assert_eq!(
+            squares(config.path(white, CastlingSide::Queenside)),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            squares(config.check_path(white, CastlingSide::Queenside)),
+            [2, 3, 4]
+        );
+        assert_eq!(
+            squares(config.check_path(Color::Black, CastlingSide::Kingside)),
+            [60, 61, 62]
+        );
+
+        let castle = Move::from_uci("e8c8").unwrap();
+        assert_eq!(
+            config.side_of(Color::Black, &castle),
+            Some(CastlingSide::Queenside)
+        );
+        assert_eq!(config.side_of(Color::White, &castle), None);
+        assert_eq!(config.rook_squares(Color::Black, &castle), Some((56, 59)));
+
+        assert_eq!(config.right(Color::Black, CastlingSide::Queenside), 8);
+        assert_eq!(config.rights_lost(4), 1 | 2);
+        assert_eq!(config.rights_lost(63), 4);
+        assert_eq!(config.rights_lost(27), 0);
+    }
+
+    #[test]
+    fn test_chess960_squares() {
+        // King on b1 between rooks on a1 and f1: the rook crosses the king's path
+        let config = CastlingConfig::from_files(1, 5, 0).unwrap();
+        let white = Color::White;
+        assert_eq!(
+            squares(config.path(white, CastlingSide::Kingside)),
+            [2, 3, 4, 6]
+        );
+        assert_eq!(squares(config.path(white, CastlingSide::Queenside)), [2, 3]);
+        assert_eq!(config.rights_lost(1), 1 | 2);
+        assert_eq!(config.rights_lost(61), 4);
+
+        assert_eq!(CastlingConfig::from_files(4, 3, 0), None);
+        assert_eq!(CastlingConfig::from_files(4, 8, 0), None);
+    }
+}
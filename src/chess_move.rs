@@ -1,9 +1,24 @@
 use crate::board::{Board, Color, PieceType};
-use crate::utils::convert_board_coordinate_to_idx;
-use crate::utils::EDGE_DISTANCES;
+use crate::castling::CastlingSide;
+use crate::utils::{convert_board_coordinate_to_idx, square_to_str};
+use crate::utils::{
+    EAST, EDGE_DISTANCES, NORTH, NORTH_EAST, NORTH_WEST, SOUTH, SOUTH_EAST, SOUTH_WEST, WEST,
+};
+use log::debug;
+
+use std::fmt;
+use std::num::NonZeroU16;
+
+/// Pieces a pawn may promote to, in the order they are generated.
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
 
 // Uses UCI Notation
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Move {
     pub from: u8,                     // The index of the square the piece is moving from
     pub to: u8,                       // The index of the square the piece is moving to
@@ -11,11 +26,11 @@ pub struct Move {
 }
 
 impl Move {
-    pub fn new(uci_string: String) -> Move {
-        let from = convert_board_coordinate_to_idx(uci_string[0..2].to_string());
-        let to = convert_board_coordinate_to_idx(uci_string[2..4].to_string());
+    pub fn new(uci_string: &str) -> Move {
+        let from = convert_board_coordinate_to_idx(&uci_string[0..2]);
+        let to = convert_board_coordinate_to_idx(&uci_string[2..4]);
         let promotion = if uci_string.len() == 5 {
-            Some(PieceType::from(uci_string[4..5].to_string()))
+            Some(PieceType::from(&uci_string[4..5]))
         } else {
             None
         };
@@ -34,6 +49,375 @@ impl Move {
             promotion,
         }
     }
+
+    /// Parses a move in UCI notation such as `e2e4` or `e7e8q`, or returns `None` if
+    /// the text is not one. Says nothing about whether the move is legal anywhere.
+    pub fn from_uci(uci: &str) -> Option<Move> {
+        let square = |file: u8, rank: u8| {
+            ((b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank))
+                .then(|| (rank - b'1') * 8 + (file - b'a'))
+        };
+        let (squares, promotion) = match uci.as_bytes() {
+            [squares @ .., p] if uci.len() == 5 => {
+                let promotion = match p {
+                    b'n' => PieceType::Knight,
+                    b'b' => PieceType::Bishop,
+                    b'r' => PieceType::Rook,
+                    b'q' => PieceType::Queen,
+                    _ => return None,
+                };
+                (squares, Some(promotion))
+            }
+            squares => (squares, None),
+        };
+        match *squares {
+            [from_file, from_rank, to_file, to_rank] => Some(Move {
+                from: square(from_file, from_rank)?,
+                to: square(to_file, to_rank)?,
+                promotion,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the move in UCI notation, e.g. `e2e4` or `e7e8q`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", square_to_str(self.from), square_to_str(self.to))?;
+        if let Some(promotion) = self.promotion {
+            let c = match promotion {
+                PieceType::Knight => 'n',
+                PieceType::Bishop => 'b',
+                PieceType::Rook => 'r',
+                _ => 'q',
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`Move`] packed into 16 bits, for the tables the search keeps moves in: the
+/// from-square in bits 0-5, the to-square in bits 6-11 and the promotion piece in
+/// bits 12-15, as its [`PieceType`] value or 0 for none.
+///
+/// No move goes from a square to itself, so no packed move is zero and an
+/// `Option<PackedMove>` takes no more room than a `PackedMove`.
+///
+/// ```
+/// use chessbot::chess_move::{Move, PackedMove};
+///
+/// let m = Move::from_uci("e7e8q").unwrap();
+/// assert_eq!(PackedMove::new(m).bits(), 4 << 12 | 60 << 6 | 52);
+/// assert_eq!(PackedMove::new(m).unpack(), m);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedMove(NonZeroU16);
+
+impl PackedMove {
+    /// Packs `m`. Panics if it goes from a square to itself without promoting, as no
+    /// move does.
+    pub fn new(m: Move) -> PackedMove {
+        let promotion = m.promotion.map_or(0, |p| p as u16);
+        PackedMove::from_bits(promotion << 12 | (m.to as u16) << 6 | m.from as u16)
+            .expect("a move changes squares")
+    }
+
+    /// The packed move with these bits, or `None` for zero.
+    pub fn from_bits(bits: u16) -> Option<PackedMove> {
+        NonZeroU16::new(bits).map(PackedMove)
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0.get()
+    }
+
+    /// The from- and to-squares as one index below 4096, for tables by both.
+    pub fn from_to(self) -> usize {
+        (self.bits() & 0xfff) as usize
+    }
+
+    pub fn unpack(self) -> Move {
+        let bits = self.bits();
+        let promotion = match bits >> 12 {
+            0 => None,
+            p => Some(PieceType::ALL[p as usize]),
+        };
+        Move::new_from_indices((bits & 63) as u8, (bits >> 6 & 63) as u8, promotion)
+    }
+}
+
+impl From<Move> for PackedMove {
+    fn from(m: Move) -> PackedMove {
+        PackedMove::new(m)
+    }
+}
+
+impl From<PackedMove> for Move {
+    fn from(m: PackedMove) -> Move {
+        m.unpack()
+    }
+}
+
+/// Name of `square` in algebraic notation, e.g. `e4`.
+pub fn square_name(square: u8) -> String {
+    square_to_str(square).to_string()
+}
+
+/// Why a move cannot be played. See [`Board::make_move`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// The text is not a move in UCI notation.
+    Malformed,
+    /// There is no piece on the from-square.
+    EmptySquare { at: u8 },
+    /// The piece on the from-square belongs to the side not to move.
+    NotYourPiece { at: u8 },
+    /// The piece on the from-square never moves like this, e.g. a knight along a file.
+    WrongPattern { peice: PieceType },
+    /// The piece on `by` stands in the way.
+    Blocked { by: u8 },
+    /// The to-square holds one of the mover's own pieces.
+    OwnPieceOnTarget { at: u8 },
+    /// A pawn moves diagonally only to capture, and there is nothing to take on `at`.
+    NothingToCapture { at: u8 },
+    /// A pawn reaching the last rank must promote, and nothing else may.
+    BadPromotion,
+    /// The move would leave the mover's king attacked by the piece on `by`.
+    LeavesKingInCheck { by: u8 },
+    /// The king or the rook it would castle with has moved.
+    NoCastlingRight,
+    /// The king is in check from the piece on `by`, so it cannot castle.
+    CastlingOutOfCheck { by: u8 },
+    /// The castling king would pass through or land on `square`, which the piece on
+    /// `by` attacks.
+    CastlingThroughCheck { square: u8, by: u8 },
+    /// The side to move's piece cannot make this move, for no reason above.
+    Illegal,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::Malformed => write!(f, "not a move in UCI notation"),
+            MoveError::EmptySquare { at } => write!(f, "no piece on {}", square_name(*at)),
+            MoveError::NotYourPiece { at } => {
+                write!(
+                    f,
+                    "the piece on {} belongs to the other side",
+                    square_name(*at)
+                )
+            }
+            MoveError::WrongPattern { peice } => {
+                write!(
+                    f,
+                    "a {} does not move like that",
+                    peice.to_string().to_lowercase()
+                )
+            }
+            MoveError::Blocked { by } => write!(f, "blocked by the piece on {}", square_name(*by)),
+            MoveError::OwnPieceOnTarget { at } => {
+                write!(f, "your own piece stands on {}", square_name(*at))
+            }
+            MoveError::NothingToCapture { at } => {
+                write!(f, "nothing to capture on {}", square_name(*at))
+            }
+            MoveError::BadPromotion => {
+                write!(f, "pawns promote on reaching the last rank, and only then")
+            }
+            MoveError::LeavesKingInCheck { by } => {
+                write!(f, "the king would be in check from {}", square_name(*by))
+            }
+            MoveError::NoCastlingRight => write!(f, "castling on that side is no longer allowed"),
+            MoveError::CastlingOutOfCheck { by } => {
+                write!(f, "cannot castle out of check from {}", square_name(*by))
+            }
+            MoveError::CastlingThroughCheck { square, by } => write!(
+                f,
+                "cannot castle through {}, attacked from {}",
+                square_name(*square),
+                square_name(*by)
+            ),
+            MoveError::Illegal => write!(f, "not a legal move"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// What a move does, as seen from the position before it is played. See
+/// [`Board::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    Quiet,
+    DoublePawnPush,
+    /// Takes the given piece.
+    Capture(PieceType),
+    EnPassant,
+    CastleKingside,
+    CastleQueenside,
+    /// Promotes to the given piece without capturing.
+    Promotion(PieceType),
+    /// Takes the first piece and promotes to the second.
+    CapturePromotion(PieceType, PieceType),
+}
+
+impl MoveKind {
+    /// True if the move takes a piece, en passant included.
+    pub fn is_capture(self) -> bool {
+        matches!(
+            self,
+            MoveKind::Capture(_) | MoveKind::EnPassant | MoveKind::CapturePromotion(..)
+        )
+    }
+
+    pub fn is_castle(self) -> bool {
+        matches!(self, MoveKind::CastleKingside | MoveKind::CastleQueenside)
+    }
+}
+
+/// Upper bound on the number of moves in a position (the known maximum is 218).
+pub const MAX_MOVES: usize = 256;
+
+/// A fixed-capacity list of moves stored inline, so generating moves does not allocate.
+///
+/// The moves are kept as [`PackedMove`]s and handed out as [`Move`]s, by value.
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [PackedMove; MAX_MOVES],
+    len: usize,
+}
+
+/// Iterator over the moves of a [`MoveList`], from [`MoveList::iter`].
+pub type MoveListIter<'a> =
+    std::iter::Map<std::iter::Copied<std::slice::Iter<'a, PackedMove>>, fn(PackedMove) -> Move>;
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            // Never read: only the first `len` slots hold moves
+            moves: [PackedMove::new(Move::new_from_indices(0, 1, None)); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Appends a move. Panics if the list already holds `MAX_MOVES` moves.
+    pub fn push(&mut self, m: Move) {
+        self.moves[self.len] = PackedMove::new(m);
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The move at `index`, or `None` past the end of the list.
+    pub fn get(&self, index: usize) -> Option<Move> {
+        self.packed().get(index).map(|m| m.unpack())
+    }
+
+    pub fn first(&self) -> Option<Move> {
+        self.get(0)
+    }
+
+    pub fn contains(&self, m: &Move) -> bool {
+        self.iter().any(|other| other == *m)
+    }
+
+    pub fn iter(&self) -> MoveListIter<'_> {
+        self.packed().iter().copied().map(PackedMove::unpack)
+    }
+
+    pub fn to_vec(&self) -> Vec<Move> {
+        self.iter().collect()
+    }
+
+    /// The moves as they are stored.
+    pub fn packed(&self) -> &[PackedMove] {
+        &self.moves[..self.len]
+    }
+
+    /// Sorts the moves by `key`, computing it once per move. Moves with equal keys
+    /// keep their order.
+    pub fn sort_by_cached_key<K: Ord>(&mut self, mut key: impl FnMut(&Move) -> K) {
+        self.moves[..self.len].sort_by_cached_key(|m| key(&m.unpack()));
+    }
+
+    /// Swaps the moves at `a` and `b`. Panics if either is past the end of the list.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.moves[..self.len].swap(a, b);
+    }
+
+    pub fn sort_unstable_by_key<K: Ord>(&mut self, mut key: impl FnMut(&Move) -> K) {
+        self.moves[..self.len].sort_unstable_by_key(|m| key(&m.unpack()));
+    }
+
+    /// Keeps only the moves for which `keep` returns true, in their original order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if keep(&self.moves[i].unpack()) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Map<
+        std::iter::Take<std::array::IntoIter<PackedMove, MAX_MOVES>>,
+        fn(PackedMove) -> Move,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves
+            .into_iter()
+            .take(self.len)
+            .map(PackedMove::unpack)
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> Self {
+        let mut list = MoveList::new();
+        for m in iter {
+            list.push(m);
+        }
+        list
+    }
 }
 
 pub fn validate_move(board: &Board, m: &Move) -> bool {
@@ -41,37 +425,36 @@ pub fn validate_move(board: &Board, m: &Move) -> bool {
     let piece_type = match find_peice_at_from_location(board, m.from) {
         Some(pt) => pt,
         None => {
-            println!("No piece friendly found at '{}'", m.from);
+            debug!("No piece friendly found at '{}'", m.from);
             return false;
         } // No piece found at 'from'
     };
 
     // Ensure that peice is not promoting if its not a pawn
-    if piece_type != PieceType::Pawn {
-        if m.promotion.is_some() {
-            println!("Invalid move: Non-pawn piece attempting to promote");
-            return false;
-        }
+    if piece_type != PieceType::Pawn && m.promotion.is_some() {
+        debug!("Invalid move: Non-pawn piece attempting to promote");
+        return false;
     }
 
     // Validate the move based on the piece type
     // Note: We check if the 'to' location is valid inside the validate functions to handle edge cases (e.g. castling)
+    let color = board.active_color;
     let valid_move = match piece_type {
-        PieceType::Pawn => validate_pawn_move(board, m),
-        PieceType::Knight => validate_knight_move(board, m),
-        PieceType::Bishop => validate_bishop_move(board, m),
-        PieceType::Rook => validate_rook_move(board, m),
-        PieceType::Queen => validate_queen_move(board, m),
-        PieceType::King => validate_king_move(board, m),
+        PieceType::Pawn => validate_pawn_move(board, color, m),
+        PieceType::Knight => validate_knight_move(board, color, m),
+        PieceType::Bishop => validate_bishop_move(board, color, m),
+        PieceType::Rook => validate_rook_move(board, color, m),
+        PieceType::Queen => validate_queen_move(board, color, m),
+        PieceType::King => validate_king_move(board, color, m),
     };
 
     if valid_move {
-        println!(
+        debug!(
             "Valid move: {:?} from index {} to index {}",
             piece_type, m.from, m.to
         );
     } else {
-        println!(
+        debug!(
             "Invalid move: {:?} from index {} to index {}",
             piece_type, m.from, m.to
         );
@@ -82,16 +465,16 @@ pub fn validate_move(board: &Board, m: &Move) -> bool {
 
 // By the time this is called we know the from location is valid
 // TODO: implement en passant
-fn validate_pawn_move(board: &Board, m: &Move) -> bool {
-    let valid_to_location = validate_to_location(board, m);
+fn validate_pawn_move(board: &Board, color: Color, m: &Move) -> bool {
+    let valid_to_location = validate_to_location(board, color, m);
 
     if !valid_to_location {
-        println!("Invalid move: Pawn moving to an invalid location {}", m.to);
+        debug!("Invalid move: Pawn moving to an invalid location {}", m.to);
         return false;
     }
 
     // check to see if the pawn moving to a valid location
-    let direction = match board.active_color {
+    let direction = match color {
         Color::White => 1,
         Color::Black => -1,
     };
@@ -107,18 +490,18 @@ fn validate_pawn_move(board: &Board, m: &Move) -> bool {
 
     // Check to see if the pawn is promoting without moving to the last rank
     if m.promotion.is_some() && to_rank != 0 && to_rank != 7 {
-        println!("Invalid move: Pawn promoting without moving to last rank");
+        debug!("Invalid move: Pawn promoting without moving to last rank");
         return false;
     }
 
     // Check to see if the pawn is not promoting when moving to the last rank
     if m.promotion.is_none() && (to_rank == 0 || to_rank == 7) {
-        println!("Invalid move: Pawn moving to last rank without promotion");
+        debug!("Invalid move: Pawn moving to last rank without promotion");
         return false;
     }
 
     if rank_diff != direction && rank_diff != 2 * direction {
-        println!(
+        debug!(
             "Invalid move: Pawn moving in the wrong direction, {} -> {}",
             from_rank + 1,
             to_rank + 1
@@ -128,97 +511,88 @@ fn validate_pawn_move(board: &Board, m: &Move) -> bool {
 
     // Check if the pawn is moving diagonally more than one square
     if (to_file as i8 - from_file as i8).abs() > 1 {
-        println!("Invalid move: Pawn moving diagonally more than one square");
+        debug!("Invalid move: Pawn moving diagonally more than one square");
         return false;
     }
 
     // If pawn is moving diagonally, it must be capturing an enemy piece unless its en passant
     if (to_file as i8 - from_file as i8).abs() == 1 {
         let to_bit = 1u64 << m.to;
-        let bitboards = match board.active_color {
-            Color::White => &board.bitboards[6..11], // Last 5 bitboards for Black (excluding King)
-            Color::Black => &board.bitboards[0..5],  // First 5 bitboards for White (excluding King)
-        };
+        let enemy_pieces = board.occupied_by(!color) & !board.pieces(!color, PieceType::King);
 
-        let enemy_piece_at_to = bitboards.iter().any(|&bb| bb & to_bit != 0);
+        let enemy_piece_at_to = enemy_pieces & to_bit != 0;
 
         // check for en pessant
-        if (board.en_passant.is_some()) && (m.to == board.en_passant.unwrap()) {
-            if m.to == board.en_passant.unwrap() {
-                return true;
-            }
+        if is_en_passant_capture(board, color, m.from, m.to) {
+            return true;
         }
         // check if the pawn is moving diagonally without capturing
 
         if !enemy_piece_at_to {
-            println!("Invalid move: Pawn moving diagonally without capturing");
+            debug!("Invalid move: Pawn moving diagonally without capturing");
             return false;
         }
     }
 
     // Check if the pawn is moving two squares forward when its not in starting rank
-    if (to_rank as i8 - from_rank as i8).abs() == 2 {
-        if (from_rank != 1 && board.active_color == Color::White)
-            || (from_rank != 6 && board.active_color == Color::Black)
-        {
-            println!(
-                "Invalid move: Pawn moving two squares forward from non starting rank {}",
-                from_rank + 1
-            );
-            return false;
-        }
+    if (to_rank as i8 - from_rank as i8).abs() == 2
+        && ((from_rank != 1 && color == Color::White) || (from_rank != 6 && color == Color::Black))
+    {
+        debug!(
+            "Invalid move: Pawn moving two squares forward from non starting rank {}",
+            from_rank + 1
+        );
+        return false;
     }
 
     // Check if pawn is moving two squares forward and the square in front is occupied
-    if (to_rank as i8 - from_rank as i8).abs() == 2 {
-        if (from_rank != 1 && board.active_color == Color::White)
-            || (from_rank != 6 && board.active_color == Color::Black)
-        {
-            let square_in_front = match board.active_color {
-                Color::White => m.from + 8,
-                Color::Black => m.from - 8,
-            };
+    if (to_rank as i8 - from_rank as i8).abs() == 2
+        && ((from_rank != 1 && color == Color::White) || (from_rank != 6 && color == Color::Black))
+    {
+        let square_in_front = match color {
+            Color::White => m.from + 8,
+            Color::Black => m.from - 8,
+        };
 
-            let square_in_front_bit = 1u64 << square_in_front;
+        let square_in_front_bit = 1u64 << square_in_front;
 
-            let square_in_front_occupied = board
-                .bitboards
-                .iter()
-                .any(|&bb| bb & square_in_front_bit != 0);
+        let square_in_front_occupied = board
+            .bitboards
+            .iter()
+            .any(|&bb| bb & square_in_front_bit != 0);
 
-            if square_in_front_occupied {
-                println!(
-                    "Invalid move: Pawn moving two squares forward when square in front is occupied"
-                );
-                return false;
-            }
+        if square_in_front_occupied {
+            debug!(
+                "Invalid move: Pawn moving two squares forward when square in front is occupied"
+            );
+            return false;
         }
     }
 
     // Check if the pawn is moving to the last rank
     if to_rank == 0 || to_rank == 7 {
-        if m.promotion.is_none() {
-            println!("Invalid move: Pawn moving to last rank without promotion");
-            return false;
-        } else {
+        match m.promotion {
+            None => {
+                debug!("Invalid move: Pawn moving to last rank without promotion");
+                return false;
+            }
             // Check to see if promotion peice type is valid
-            let promotion_piece = m.promotion.unwrap();
-
-            if promotion_piece == PieceType::Pawn || promotion_piece == PieceType::King {
-                println!("Invalid move: Pawn promotion to invalid piece type");
+            Some(PieceType::Pawn) | Some(PieceType::King) => {
+                debug!("Invalid move: Pawn promotion to invalid piece type");
                 return false;
             }
+            Some(_) => {}
         }
     }
 
     true
 }
 
-fn validate_knight_move(board: &Board, m: &Move) -> bool {
-    let valid_to_location = validate_to_location(board, m);
+fn validate_knight_move(board: &Board, color: Color, m: &Move) -> bool {
+    let valid_to_location = validate_to_location(board, color, m);
 
     if !valid_to_location {
-        println!(
+        debug!(
             "Invalid move: Knight moving to an invalid location {}",
             m.to
         );
@@ -241,23 +615,23 @@ fn validate_knight_move(board: &Board, m: &Move) -> bool {
 
     true
 }
-fn validate_bishop_move(board: &Board, m: &Move) -> bool {
+fn validate_bishop_move(board: &Board, color: Color, m: &Move) -> bool {
     // Ensures we are not capturing a friendly piece or the enemy king
-    let valid_to_location = validate_to_location(board, m);
+    let valid_to_location = validate_to_location(board, color, m);
 
     if !valid_to_location {
-        println!(
+        debug!(
             "Invalid move: Bishop moving to an invalid location {}",
             m.to
         );
         return false;
     }
 
-    let moves = generate_sliding_moves(board, PieceType::Bishop, m.from);
+    let moves = generate_sliding_moves_for_color(board, color, PieceType::Bishop, m.from);
 
     let to_bit = 1u64 << m.to;
     if moves & to_bit == 0 {
-        println!(
+        debug!(
             "Invalid move: Bishop moving to an square not within its range {}",
             m.to
         );
@@ -267,20 +641,20 @@ fn validate_bishop_move(board: &Board, m: &Move) -> bool {
 
     true
 }
-fn validate_rook_move(board: &Board, m: &Move) -> bool {
+fn validate_rook_move(board: &Board, color: Color, m: &Move) -> bool {
     // Ensures we are not capturing a friendly piece or the enemy king
-    let valid_to_location = validate_to_location(board, m);
+    let valid_to_location = validate_to_location(board, color, m);
 
     if !valid_to_location {
-        println!("Invalid move: Rook moving to an invalid location {}", m.to);
+        debug!("Invalid move: Rook moving to an invalid location {}", m.to);
         return false;
     }
 
-    let moves = generate_sliding_moves(board, PieceType::Rook, m.from);
+    let moves = generate_sliding_moves_for_color(board, color, PieceType::Rook, m.from);
 
     let to_bit = 1u64 << m.to;
     if moves & to_bit == 0 {
-        println!(
+        debug!(
             "Invalid move: Bishop moving to an invalid location {}",
             m.to
         );
@@ -289,51 +663,36 @@ fn validate_rook_move(board: &Board, m: &Move) -> bool {
 
     true
 }
-fn validate_queen_move(board: &Board, m: &Move) -> bool {
+fn validate_queen_move(board: &Board, color: Color, m: &Move) -> bool {
     // Ensures we are not capturing a friendly piece or the enemy king
-    let valid_to_location = validate_to_location(board, m);
+    let valid_to_location = validate_to_location(board, color, m);
 
     if !valid_to_location {
-        println!("Invalid move: Queen moving to an invalid location {}", m.to);
+        debug!("Invalid move: Queen moving to an invalid location {}", m.to);
         return false;
     }
 
-    let moves = generate_sliding_moves(board, PieceType::Queen, m.from);
+    let moves = generate_sliding_moves_for_color(board, color, PieceType::Queen, m.from);
 
     let to_bit = 1u64 << m.to;
     if moves & to_bit == 0 {
-        println!("Invalid move: Queen moving to an invalid location {}", m.to);
+        debug!("Invalid move: Queen moving to an invalid location {}", m.to);
         return false;
     }
 
     true
 }
-fn validate_king_move(board: &Board, m: &Move) -> bool {
-    // Check white side castling
-    if m.from == 4 && m.to == 6 {
-        // White king side castle
-        return validate_king_side_castle(board, board.active_color);
-    }
-    if m.from == 4 && m.to == 2 {
-        // White queen side castle
-        return validate_queen_side_castle(board, board.active_color);
-    }
-
-    // Check black side castling
-    if m.from == 60 && m.to == 62 {
-        // Black king side castle
-        return validate_king_side_castle(board, board.active_color);
-    }
-    if m.from == 60 && m.to == 58 {
-        // Black queen side castle
-        return validate_queen_side_castle(board, board.active_color);
+fn validate_king_move(board: &Board, color: Color, m: &Move) -> bool {
+    // Only a king still on its original square can castle
+    if board.castling.side_of(color, m).is_some() {
+        return validate_castle(board, color, m);
     }
 
     // Ensures we are not capturing a friendly piece or the enemy king
-    let valid_to_location = validate_to_location(board, m);
+    let valid_to_location = validate_to_location(board, color, m);
 
     if !valid_to_location {
-        println!("Invalid move: King moving to an invalid location {}", m.to);
+        debug!("Invalid move: King moving to an invalid location {}", m.to);
         return false;
     }
 
@@ -342,327 +701,236 @@ fn validate_king_move(board: &Board, m: &Move) -> bool {
     let file_diff = (m.to % 8) as i8 - (m.from % 8) as i8;
 
     if rank_diff.abs() > 1 || file_diff.abs() > 1 {
-        println!("Invalid move: King moving more than one square away");
+        debug!("Invalid move: King moving more than one square away");
         return false;
     }
-    true  // Remove the king_moves increment from here
+    true // Remove the king_moves increment from here
 }
 
-fn validate_king_side_castle(board: &Board, color: Color) -> bool {
-    // check to see if the king has rights to castle
-    let rights = board.castling_rights;
-
-    let number_to_check = match color {
-        Color::White => 1,
-        Color::Black => 4,
-    };
-
-    if rights & number_to_check == 0 {
-        println!("Invalid move: King does not have rights to castle");
-        return false;
-    }
-
-    // check to see if the squares between the king and rook are empty
-    let squares_to_check = match color {
-        Color::White => [5, 6],
-        Color::Black => [61, 62],
-    };
-
-    for square in squares_to_check.iter() {
-        let square_bit = 1u64 << *square;
-        let square_occupied = match color {
-            Color::White => board.all_white_bitboard & square_bit != 0,
-            Color::Black => board.all_black_bitboard & square_bit != 0,
-        };
-
-        if square_occupied {
-            println!("Invalid move: Square {} is occupied", square);
-            return false;
-        }
+/// Whether `color` may castle with `m`: it is one of [`castle_moves`].
+fn validate_castle(board: &Board, color: Color, m: &Move) -> bool {
+    if castle_moves(board, color).any(|castle| castle == *m) {
+        return true;
     }
-
-    // TODO add check to see if the king is in check
-    true
+    debug!("Invalid move: {} cannot castle with {}", color, m);
+    false
 }
 
-fn validate_queen_side_castle(board: &Board, color: Color) -> bool {
-    // check to see if the king has rights to castle
-    let rights = board.castling_rights;
-
-    let number_to_check = match color {
-        Color::White => 2,
-        Color::Black => 8,
-    };
-
-    if rights & number_to_check == 0 {
-        println!("Invalid move: King does not have rights to castle");
-        return false;
-    }
-
-    // check to see if the squares between the king and rook are empty
-    let squares_to_check = match color {
-        Color::White => [3, 2, 1],
-        Color::Black => [59, 58, 57],
-    };
-
-    for square in squares_to_check.iter() {
-        let square_bit = 1u64 << *square;
-        let square_occupied = match color {
-            Color::White => board.all_white_bitboard & square_bit != 0,
-            Color::Black => board.all_black_bitboard & square_bit != 0,
-        };
+/// The castling moves open to `color`, kingside first. For each side the right must
+/// be held, the rook still at home (the rights can be stale), the squares between
+/// empty of either color's pieces, and the king may not start on, cross or land on
+/// a square the other side attacks. Validation and move generation both ask here,
+/// so they cannot disagree.
+pub(crate) fn castle_moves(board: &Board, color: Color) -> impl Iterator<Item = Move> + '_ {
+    let castling = &board.castling;
+    let king = castling.king_start(color);
+    let king_at_home = board.pieces(color, PieceType::King) & (1u64 << king) != 0;
+    CastlingSide::BOTH
+        .into_iter()
+        .filter(move |&side| {
+            king_at_home
+                && board.castling_rights & castling.right(color, side) != 0
+                && has_rook_on(board, color, castling.rook_start(color, side))
+                && board.occupied() & castling.path(color, side) == 0
+                && !squares_attacked(board, castling.check_path(color, side), !color)
+        })
+        .map(move |side| Move {
+            from: king,
+            to: castling.king_destination(color, side),
+            promotion: None,
+        })
+}
 
-        if square_occupied {
-            println!("Invalid move: Square {} is occupied", square);
-            return false;
+/// Whether `color` attacks any of `squares`.
+fn squares_attacked(board: &Board, mut squares: u64, color: Color) -> bool {
+    while squares != 0 {
+        if board.is_square_attacked(squares.trailing_zeros() as u8, color) {
+            return true;
         }
+        squares &= squares - 1;
     }
+    false
+}
 
-    true
+fn has_rook_on(board: &Board, color: Color, square: u8) -> bool {
+    board.pieces(color, PieceType::Rook) & (1u64 << square) != 0
 }
 
+/// Sliding-piece destinations for the side to move. See [`generate_sliding_moves_for_color`].
 pub fn generate_sliding_moves(board: &Board, piece_type: PieceType, from: u8) -> u64 {
+    generate_sliding_moves_for_color(board, board.active_color, piece_type, from)
+}
+
+/// Bitboard of squares a bishop, rook or queen of `color` standing on `from` could move to,
+/// regardless of whose turn it is.
+pub fn generate_sliding_moves_for_color(
+    board: &Board,
+    color: Color,
+    piece_type: PieceType,
+    from: u8,
+) -> u64 {
     let mut moves = 0;
 
-    let capturable_bitboards: &[u64] = match board.active_color {
-        Color::White => &board.bitboards[6..11], // Last 5 bitboards for Black (Excluding King)
-        Color::Black => &board.bitboards[0..5],  // First 5 bitboards for White (Excluding King)
-    };
+    // Enemy pieces other than the king
+    let capturable = board.occupied_by(!color) & !board.pieces(!color, PieceType::King);
 
-    let friendly_bitboard: u64 = match board.active_color {
-        Color::White => board.all_white_bitboard, // First 6 bitboards for White
-        Color::Black => board.all_black_bitboard, // Last 6 bitboards for Black
-    };
+    let friendly_bitboard: u64 = board.occupied_by(color);
 
-    let enemy_king_bitboard = match board.active_color {
-        Color::White => board.bitboards[11], // Black king
-        Color::Black => board.bitboards[5],  // White king
-    };
+    let enemy_king_bitboard = board.pieces(!color, PieceType::King);
 
     match piece_type {
-        PieceType::Bishop => {
-            let distance_to_jump: [i8; 4] = [9, 7, -7, -9]; // [NE, NW, SE, SW]
-            let dir: [u8; 4] = [4, 5, 6, 7];
+        PieceType::Bishop | PieceType::Rook => {
+            // (square step, file step, direction) for each direction
+            let directions: [(i8, i8, usize); 4] = if piece_type == PieceType::Bishop {
+                [
+                    (9, 1, NORTH_EAST),
+                    (7, -1, NORTH_WEST),
+                    (-7, 1, SOUTH_EAST),
+                    (-9, -1, SOUTH_WEST),
+                ]
+            } else {
+                [(8, 0, NORTH), (-8, 0, SOUTH), (1, 1, EAST), (-1, -1, WEST)]
+            };
+            let from_file = (from % 8) as i8;
 
-            // for each direction [NE, NW, SE, SW]
-            for i in 0..4 {
+            for (step, file_step, edge) in directions {
                 // get the max distance to the edge of the board for that given direction
-                let max_distance = EDGE_DISTANCES[dir[i] as usize][from as usize];
+                let max_distance = EDGE_DISTANCES[edge][from as usize] as i8;
 
-                // for each square in that direction jumping by the distance to the edge
-                for hop_distance_multiplier in 1..=max_distance {
-                    let hop_distance = distance_to_jump[i] * hop_distance_multiplier as i8;
-
-                    let to: u8 = ((from as i8) + hop_distance) as u8;
-                    let to_bit: u64 = 1u64 << to;
-                    // if the square is occupied by a friendly piece or the enemy king, stop
-                    if friendly_bitboard & to_bit != 0 || enemy_king_bitboard & to_bit != 0 {
+                for hops in 1..=max_distance {
+                    let to = from as i8 + step * hops;
+                    // Should the table ever be off, stop rather than wrap onto another
+                    // rank or off the board
+                    if !(0..64).contains(&to) || to % 8 - from_file != file_step * hops {
                         break;
                     }
+                    let to_bit: u64 = 1u64 << to;
 
-                    // if the square is occupied by an enemy piece, add it to the moves set and stop
-                    if capturable_bitboards.iter().any(|&bb| bb & to_bit != 0) {
-                        moves |= to_bit;
-                        break;
-                    }
-
-                    // if the square is empty, add it to the moves set
-                    moves |= to_bit;
-                }
-            }
-        }
-        PieceType::Rook => {
-            // Define the distance offsets for Rook movement:
-            // [North, South, East, West]
-            let distance_to_jump: [i8; 4] = [8, -8, 1, -1];
-
-            // Match these directions to EDGE_DISTANCES indices:
-            // 0 = North, 1 = South, 2 = East, 3 = West
-            let dir: [u8; 4] = [0, 1, 2, 3];
-
-            // For each direction, move along that line until blocked or edge is reached.
-            for i in 0..4 {
-                // Get max squares available in this direction from the current square
-                let max_distance = EDGE_DISTANCES[dir[i] as usize][from as usize];
-
-                // Move up to `max_distance` squares in this direction
-                for hop_distance_multiplier in 1..=max_distance {
-                    let hop_distance = distance_to_jump[i] * hop_distance_multiplier as i8;
-
-                    let to: i8 = from as i8 + hop_distance;
-                    // If we go out of the 0..63 range, stop
-                    if to < 0 || to >= 64 {
-                        break;
-                    }
-
-                    let to_u8: u8 = to as u8;
-                    let to_bit: u64 = 1u64 << to_u8;
-
-                    // If friendly piece or enemy king occupies this square, stop.
+                    // if the square is occupied by a friendly piece or the enemy king, stop
                     if friendly_bitboard & to_bit != 0 || enemy_king_bitboard & to_bit != 0 {
                         break;
                     }
 
-                    // dbg!("{:?}", to_u8);
-
-                    // If an enemy piece occupies this square, add it as a capture and stop.
-                    if capturable_bitboards.iter().any(|&bb| bb & to_bit != 0) {
-                        moves |= to_bit;
+                    // an empty square or an enemy piece to capture, which ends the ray
+                    moves |= to_bit;
+                    if capturable & to_bit != 0 {
                         break;
                     }
-
-                    // If it's empty, add this square as a valid move and continue.
-                    moves |= to_bit;
                 }
             }
         }
         PieceType::Queen => {
             // Combine Rook and Bishop moves for the Queen
-            moves |= generate_sliding_moves(board, PieceType::Rook, from);
-            moves |= generate_sliding_moves(board, PieceType::Bishop, from);
+            moves |= generate_sliding_moves_for_color(board, color, PieceType::Rook, from);
+            moves |= generate_sliding_moves_for_color(board, color, PieceType::Bishop, from);
         }
         _ => {
-            println!("Invalid piece type for sliding move generation");
+            debug!("Invalid piece type for sliding move generation");
         }
     }
     moves
 }
+/// Piece type of the side to move standing on `from`, if any.
 pub fn find_peice_at_from_location(board: &Board, from: u8) -> Option<PieceType> {
-    // Obtain a slice of bitboards based on the active color
-    let bitboards: &[u64] = match board.active_color {
-        Color::White => &board.bitboards[0..6], // First 6 bitboards for White
-        Color::Black => &board.bitboards[6..12], // Last 6 bitboards for Black
-    };
+    find_peice_for_color(board, board.active_color, from)
+}
 
-    // dbg!("{}", color_to_move);
-
-    // Create a bitmask for the 'from' square
-    let from_bit = 1u64 << from;
-
-    // Find the index of the bitboard that has the 'from' bit set
-    let piecetype_at_from_idx = bitboards.iter().position(|&bb| bb & from_bit != 0);
-
-    // dbg!("{:?}", piecetype_at_from_idx);
-    // If no piece is found at the 'from' square, the move is invalid
-    let piece_type = match piecetype_at_from_idx {
-        Some(idx) => match board.active_color {
-            Color::White => match idx {
-                0 => PieceType::Pawn,
-                1 => PieceType::Knight,
-                2 => PieceType::Bishop,
-                3 => PieceType::Rook,
-                4 => PieceType::Queen,
-                5 => PieceType::King,
-                _ => return None, // Invalid index
-            },
-            Color::Black => match idx {
-                0 => PieceType::Pawn,
-                1 => PieceType::Knight,
-                2 => PieceType::Bishop,
-                3 => PieceType::Rook,
-                4 => PieceType::Queen,
-                5 => PieceType::King,
-                _ => return None, // Invalid index
-            },
-        },
-        None => {
-            println!("No piece found at 'from'");
-            return None;
-        } // No piece found at 'from'
-    };
+/// Piece type of `color` standing on `square`, if any.
+pub fn find_peice_for_color(board: &Board, color: Color, square: u8) -> Option<PieceType> {
+    let square_bit = 1u64 << square;
+    let piece_type = PieceType::ALL
+        .into_iter()
+        .find(|&piece_type| board.pieces(color, piece_type) & square_bit != 0);
 
-    Some(piece_type)
+    // If no piece is found on the square, the move is invalid
+    if piece_type.is_none() {
+        debug!("No piece found at 'from'");
+    }
+    piece_type
 }
 
-fn validate_to_location(board: &Board, m: &Move) -> bool {
+fn validate_to_location(board: &Board, color: Color, m: &Move) -> bool {
     let to_bit = 1u64 << m.to;
 
     // first check if the 'to' square is occupied by a non capturable piece (e.g. king + friendly piece)
-    let friendly_bitboard: u64 = match board.active_color {
-        Color::White => board.all_white_bitboard, // First 6 bitboards for White
-        Color::Black => board.all_black_bitboard, // Last 6 bitboards for Black
-    };
+    let friendly_bitboard: u64 = board.occupied_by(color);
 
     let friendly_piece_at_to = friendly_bitboard & to_bit != 0;
 
-    let enemy_king_bitboard = match board.active_color {
-        Color::White => board.bitboards[11], // Black king
-        Color::Black => board.bitboards[5],  // White king
-    };
+    let enemy_king_bitboard = board.pieces(!color, PieceType::King);
 
     let enemy_king_at_to = enemy_king_bitboard & to_bit != 0;
 
     if friendly_piece_at_to {
-        println!("Attempting to capture friendly piece at '{}'", m.to);
+        debug!("Attempting to capture friendly piece at '{}'", m.to);
     } else if enemy_king_at_to {
-        println!("Attempting to capture enemy king at '{}'", m.to);
+        debug!("Attempting to capture enemy king at '{}'", m.to);
     }
 
-    return (!friendly_piece_at_to) && (!enemy_king_at_to);
+    (!friendly_piece_at_to) && (!enemy_king_at_to)
 }
 
-// only ran if and only if the king is in check
+/// True if the side to move may take a pawn from `from` to `to`, promoting it: the
+/// move needs a promotion piece, so a GUI should ask for one.
+///
+/// ```
+/// use chessbot::board::Board;
+/// use chessbot::chess_move::is_promotion_move;
+///
+/// // e7 is square 52, e8 is 60
+/// let board = Board::fen_to_board("7k/4P3/8/8/8/8/8/4K3 w - - 0 1");
+/// assert!(is_promotion_move(&board, 52, 60));
+/// assert!(!is_promotion_move(&board, 4, 12));
+/// ```
+pub fn is_promotion_move(board: &Board, from: u8, to: u8) -> bool {
+    find_peice_for_color(board, board.active_color, from) == Some(PieceType::Pawn)
+        && board
+            .legal_moves()
+            .iter()
+            .any(|m| m.from == from && m.to == to && m.promotion.is_some())
+}
+
+/// True if the side to move is in check and has no legal move.
 pub fn is_in_checkmate(board: &Board) -> bool {
-    // Generate all possible moves for the current player
-    let all_moves = generate_all_moves_for_color(board);
+    board.is_in_check(board.active_color) && !board.has_legal_moves()
+}
 
-    // for every move, clone the board and play the move, then check if the king is still in check
-    for m in all_moves {
-        let mut board_clone = board.clone();
-        // play move and check if its in check
+/// Appends the pseudo-legal moves for every piece of `color` to `moves`, whether or
+/// not it is that side's turn. [`crate::movegen::pseudo_legal_moves`] returns them.
+pub fn generate_all_moves_for_color_into(board: &Board, color: Color, moves: &mut MoveList) {
+    let mut friendly_pieces = board.occupied_by(color);
 
-        //TODO: DELETE ME
-        let m_copy = m.clone();
-        let did_move_work = board_clone.move_peice(m);
+    while friendly_pieces != 0 {
+        let from = friendly_pieces.trailing_zeros() as u8;
+        friendly_pieces &= friendly_pieces - 1;
 
-        // if its not in check, that means there is a possible move to get out of check
-        if did_move_work {
-            println!("Move: {:?} worked", m_copy);
-            return false;
+        // The occupancy bitboards and the piece bitboards always agree, so this never
+        // skips a square; move generation stays silent either way
+        if let Some(piece_type) = find_peice_for_color(board, color, from) {
+            generate_moves_for_peice(board, color, piece_type, from, moves);
         }
     }
-
-    true
 }
 
-pub fn generate_all_moves_for_color(board: &Board) -> Vec<Move> {
-    let mut all_moves = Vec::new();
-
-    let bitboards: &u64 = match board.active_color {
-        Color::White => &board.all_white_bitboard, // First 6 bitboards for White
-        Color::Black => &board.all_black_bitboard, // Last 6 bitboards for Black
-    };
-
-    let indexs_of_all_friendly_pieces = convert_bitboards_to_indexs(*bitboards);
-
-    for from in indexs_of_all_friendly_pieces {
-        let piece_type = match find_peice_at_from_location(board, from) {
-            Some(pt) => pt,
-            None => {
-                println!("No piece friendly found at '{}'", from);
-                continue;
-            } // No piece found at 'from'
-        };
-
-        let moves = match piece_type {
-            PieceType::Pawn => generate_pawn_moves(board, from),
-            PieceType::Knight => generate_knight_moves(board, from),
-            PieceType::Bishop => generate_bishop_moves(board, from),
-            PieceType::Rook => generate_rook_moves(board, from),
-            PieceType::Queen => generate_queen_moves(board, from),
-            PieceType::King => generate_king_moves(board, from),
-        };
-
-        all_moves.extend(moves);
+/// Appends the pseudo-legal moves for the `piece_type` of `color` standing on `from`.
+pub(crate) fn generate_moves_for_peice(
+    board: &Board,
+    color: Color,
+    piece_type: PieceType,
+    from: u8,
+    moves: &mut MoveList,
+) {
+    match piece_type {
+        PieceType::Pawn => generate_pawn_moves(board, color, from, moves),
+        PieceType::Knight => generate_knight_moves(board, color, from, moves),
+        PieceType::Bishop => generate_bishop_moves(board, color, from, moves),
+        PieceType::Rook => generate_rook_moves(board, color, from, moves),
+        PieceType::Queen => generate_queen_moves(board, color, from, moves),
+        PieceType::King => generate_king_moves(board, color, from, moves),
     }
-    return all_moves;
 }
 
-fn generate_pawn_moves(board: &Board, from: u8) -> Vec<Move> {
-    let mut moves = Vec::new();
-
-    let color = board.active_color;
-
+fn generate_pawn_moves(board: &Board, color: Color, from: u8, moves: &mut MoveList) {
     let direction: i8 = match color {
         Color::White => 1,
         Color::Black => -1,
@@ -670,147 +938,100 @@ fn generate_pawn_moves(board: &Board, from: u8) -> Vec<Move> {
 
     let from_rank = from / 8;
 
-    let to_rank = (from_rank as i8 + direction) as u8;
-
     let to: u8 = (from as i8 + (direction * 8)) as u8;
 
     // verify that the pawn is not moving to a square that is occupied by a any piece
-    let valid_move_forward_one =
-        (board.all_black_bitboard | board.all_white_bitboard) & (1u64 << to) == 0;
+    let valid_move_forward_one = board.occupied() & (1u64 << to) == 0;
     if valid_move_forward_one {
         // if we are moving to the last rank, we need to promote the pawn
-        if to_rank == 0 || to_rank == 7 {
-            let promotion = vec![
-                PieceType::Queen,
-                PieceType::Rook,
-                PieceType::Bishop,
-                PieceType::Knight,
-            ];
-            for p in promotion {
-                let move_forward_one = Move {
-                    from,
-                    to,
-                    promotion: Some(p),
-                };
-                moves.push(move_forward_one);
-            }
-        }
-        // else we are moving to a place that does not require promotion
-        else {
-            let move_forward_one = Move {
-                from,
-                to,
-                promotion: None,
-            };
-            // check to see if we are moving
-            moves.push(move_forward_one);
-        }
+        push_pawn_moves(moves, from, to);
     }
 
     // if any only if the pawn can move, check to see if the pawn can move forward two
-    if valid_move_forward_one && from_rank == 1 && color == Color::White
-        || from_rank == 6 && color == Color::Black
+    if valid_move_forward_one
+        && ((from_rank == 1 && color == Color::White) || (from_rank == 6 && color == Color::Black))
     {
         let to = (from as i8 + ((2 * direction) * 8)) as u8; // Move two squares forward
-        if to_rank < 8 {
-            let move_forward_two = Move {
+
+        // the landing square must be empty as well
+        if board.occupied() & (1u64 << to) == 0 {
+            moves.push(Move {
                 from,
                 to,
                 promotion: None,
-            };
-
-            // if we are moving to a valid position
-            if validate_move(board, &move_forward_two) {
-                moves.push(move_forward_two);
-            }
+            });
         }
     }
 
     // check enemy occupancy bitboard to see if we can even attempt a capture
+    let enemy_bitboard = board.occupied_by(!color);
 
-    let enemy_bitboards = match color {
-        Color::White => &board.all_black_bitboard, // Last 6 bitboards for Black
-        Color::Black => &board.all_white_bitboard, // First 6 bitboards for White
-    };
+    // we are never allowed to capture the enemy king
+    let enemy_king_bitboard = board.pieces(!color, PieceType::King);
 
-    // check to see if we can capture a piece diagonally
-    let left_diagonal = ((from as i8 + (direction * 8)) - 1) as u8;
-    let right_diagonal = ((from as i8 + (direction * 8)) + 1) as u8;
-
-    let left_diagonal_bit = 1u64 << left_diagonal;
-    let right_diagonal_bit = 1u64 << right_diagonal;
-
-    let left_diagonal_capture_possible = enemy_bitboards & left_diagonal_bit != 0;
-    let right_diagonal_capture_possible = enemy_bitboards & right_diagonal_bit != 0;
+    // check to see if we can capture a piece diagonally, left first then right.
+    // Pawns on the a or h file only have one diagonal.
+    let from_file = from % 8;
+    let forward = from as i8 + direction * 8;
+    let diagonals = [
+        (from_file > 0).then_some((forward - 1) as u8),
+        (from_file < 7).then_some((forward + 1) as u8),
+    ];
 
-    // check if left capture is valid
-    if left_diagonal_capture_possible {
-        // first check to see if we are about to capture the enemy king
-        let enemy_king_bitboard = match color {
-            Color::White => board.bitboards[5],  // Black king
-            Color::Black => board.bitboards[11], // White king
-        };
+    for diagonal in diagonals.into_iter().flatten() {
+        let diagonal_bit = 1u64 << diagonal;
 
-        // ensure that we are not capturing the enemy king
-        if enemy_king_bitboard & left_diagonal_bit == 0 {
-            let left_diagonal_capture_move = Move {
+        if enemy_bitboard & diagonal_bit != 0 {
+            if enemy_king_bitboard & diagonal_bit == 0 {
+                push_pawn_moves(moves, from, diagonal);
+            }
+        }
+        // check en passant
+        else if is_en_passant_capture(board, color, from, diagonal) {
+            moves.push(Move {
                 from,
-                to: left_diagonal,
+                to: diagonal,
                 promotion: None,
-            };
-
-            moves.push(left_diagonal_capture_move);
+            });
         }
     }
-    // check en passant left
-    else if (board.en_passant.is_some()) && (left_diagonal == board.en_passant.unwrap()) {
-        let en_passant_left_move = Move {
-            from,
-            to: board.en_passant.unwrap(),
-            promotion: None,
-        };
-
-        moves.push(en_passant_left_move);
-    }
+}
 
-    // check if right capture is valid
-    if right_diagonal_capture_possible {
-        // first check to see if we are about to capture the enemy king
-        let enemy_king_bitboard = match color {
-            Color::White => board.bitboards[5],  // Black king
-            Color::Black => board.bitboards[11], // White king
-        };
+/// True if a pawn of `color` on `from` captures en passant by moving to `to`: `to` is
+/// the en passant square, and the pawn stands beside it on its fifth rank.
+fn is_en_passant_capture(board: &Board, color: Color, from: u8, to: u8) -> bool {
+    let (from_rank, to_rank) = match color {
+        Color::White => (4, 5),
+        Color::Black => (3, 2),
+    };
+    board.en_passant == Some(to)
+        && from / 8 == from_rank
+        && to / 8 == to_rank
+        && (from % 8).abs_diff(to % 8) == 1
+}
 
-        // ensure that we are not capturing the enemy king
-        if enemy_king_bitboard & right_diagonal_bit == 0 {
-            let right_diagonal_capture_move = Move {
+/// Pushes a pawn move from `from` to `to`, expanding it into the four promotions
+/// when `to` is on the last rank.
+fn push_pawn_moves(moves: &mut MoveList, from: u8, to: u8) {
+    let to_rank = to / 8;
+    if to_rank == 0 || to_rank == 7 {
+        for p in PROMOTION_PIECES {
+            moves.push(Move {
                 from,
-                to: right_diagonal,
-                promotion: None,
-            };
-
-            moves.push(right_diagonal_capture_move);
+                to,
+                promotion: Some(p),
+            });
         }
-    }
-    // check en passant right
-    else if (board.en_passant.is_some()) && (right_diagonal == board.en_passant.unwrap()) {
-        let en_passant_right_move = Move {
+    } else {
+        moves.push(Move {
             from,
-            to: board.en_passant.unwrap(),
+            to,
             promotion: None,
-        };
-
-        moves.push(en_passant_right_move);
+        });
     }
-
-    return moves;
 }
 
-fn generate_knight_moves(board: &Board, from: u8) -> Vec<Move> {
-    let mut moves = Vec::new();
-
-    let color = board.active_color;
-
+fn generate_knight_moves(board: &Board, color: Color, from: u8, moves: &mut MoveList) {
     let from_rank = from / 8;
     let from_file = from % 8;
 
@@ -825,22 +1046,16 @@ fn generate_knight_moves(board: &Board, from: u8) -> Vec<Move> {
         (2, -1),
     ];
 
-    let friendly_bitboard: u64 = match color {
-        Color::White => board.all_white_bitboard, // First 6 bitboards for White
-        Color::Black => board.all_black_bitboard, // Last 6 bitboards for Black
-    };
+    let friendly_bitboard: u64 = board.occupied_by(color);
 
-    let enemy_king_bitboard = match color {
-        Color::White => board.bitboards[11], // Black king
-        Color::Black => board.bitboards[5],  // White king
-    };
+    let enemy_king_bitboard = board.pieces(!color, PieceType::King);
 
     for (rank_diff, file_diff) in directions.iter() {
         let to_rank = from_rank as i8 + rank_diff;
         let to_file = from_file as i8 + file_diff;
 
         // out of bounds check
-        if to_rank < 0 || to_rank >= 8 || to_file < 0 || to_file >= 8 {
+        if !(0..8).contains(&to_rank) || !(0..8).contains(&to_file) {
             continue;
         }
 
@@ -858,13 +1073,11 @@ fn generate_knight_moves(board: &Board, from: u8) -> Vec<Move> {
             promotion: None,
         });
     }
-    return moves;
 }
 
-fn generate_bishop_moves(board: &Board, from: u8) -> Vec<Move> {
-    let mut moves = Vec::new();
-
-    let mut bishop_moves_bitboard = generate_sliding_moves(board, PieceType::Bishop, from);
+fn generate_bishop_moves(board: &Board, color: Color, from: u8, moves: &mut MoveList) {
+    let mut bishop_moves_bitboard =
+        generate_sliding_moves_for_color(board, color, PieceType::Bishop, from);
 
     while bishop_moves_bitboard != 0 {
         let to = bishop_moves_bitboard.trailing_zeros() as u8;
@@ -876,14 +1089,11 @@ fn generate_bishop_moves(board: &Board, from: u8) -> Vec<Move> {
 
         bishop_moves_bitboard &= bishop_moves_bitboard - 1;
     }
-
-    return moves;
 }
 
-fn generate_rook_moves(board: &Board, from: u8) -> Vec<Move> {
-    let mut moves = Vec::new();
-
-    let mut rook_moves_bitboard = generate_sliding_moves(board, PieceType::Rook, from);
+fn generate_rook_moves(board: &Board, color: Color, from: u8, moves: &mut MoveList) {
+    let mut rook_moves_bitboard =
+        generate_sliding_moves_for_color(board, color, PieceType::Rook, from);
 
     while rook_moves_bitboard != 0 {
         let to = rook_moves_bitboard.trailing_zeros() as u8;
@@ -895,12 +1105,11 @@ fn generate_rook_moves(board: &Board, from: u8) -> Vec<Move> {
 
         rook_moves_bitboard &= rook_moves_bitboard - 1;
     }
-    return moves;
 }
 
-fn generate_queen_moves(board: &Board, from: u8) -> Vec<Move> {
-    let mut moves = Vec::new();
-    let mut queen_moves_bitboard = generate_sliding_moves(board, PieceType::Queen, from);
+fn generate_queen_moves(board: &Board, color: Color, from: u8, moves: &mut MoveList) {
+    let mut queen_moves_bitboard =
+        generate_sliding_moves_for_color(board, color, PieceType::Queen, from);
 
     while queen_moves_bitboard != 0 {
         let to = queen_moves_bitboard.trailing_zeros() as u8;
@@ -912,155 +1121,87 @@ fn generate_queen_moves(board: &Board, from: u8) -> Vec<Move> {
 
         queen_moves_bitboard &= queen_moves_bitboard - 1;
     }
-    return moves;
 }
 
-fn generate_king_moves(board: &Board, from: u8) -> Vec<Move> {
-    let mut moves = Vec::new();
-
+pub(crate) fn generate_king_moves(board: &Board, color: Color, from: u8, moves: &mut MoveList) {
     // 1. generate all moves for the king, then filter out the invalid moves (puts king in check)
     let dir: [i32; 8] = [-9, -8, -7, -1, 1, 7, 8, 9];
 
-    let enemy_king_bitboard = match board.active_color {
-        Color::White => board.bitboards[5],  // Black king
-        Color::Black => board.bitboards[11], // White king
-    };
+    let friendly_bitboard: u64 = board.occupied_by(color);
+
+    let enemy_king_bitboard = board.pieces(!color, PieceType::King);
+
+    let from_file = (from % 8) as i32;
 
     for direction in dir {
-        let to = (from as i32 + direction) as u8;
+        let to = from as i32 + direction;
 
-        // out of bounds check
-        if to >= 64 {
+        // out of bounds check, including wrapping around to the other side of the board
+        if !(0..64).contains(&to) || ((to % 8) - from_file).abs() > 1 {
             continue;
         }
+        let to = to as u8;
 
         let to_bit = 1u64 << to;
 
         // If the square is occupied by a friendly piece or the enemy king, skip
-        if board.all_white_bitboard & to_bit != 0 || enemy_king_bitboard & to_bit != 0 {
+        if friendly_bitboard & to_bit != 0 || enemy_king_bitboard & to_bit != 0 {
             continue;
         }
 
-        // If the move puts the king in check, skip
-        let mut board_copy = board.clone();
+        // If the move puts the king in check, skip. The copy is played from `color`'s
+        // point of view so this also works when it is not that side's turn.
         let m = Move {
             from,
             to,
             promotion: None,
         };
-        board_copy.move_peice(m);
-        // invert the color as we moved and the turn has changed
-        if board_copy.is_in_check(match board.active_color {
-            Color::White => Color::Black,
-            Color::Black => Color::White,
-        }) {
+        let mut board_copy = *board;
+        board_copy.active_color = color;
+        if board_copy
+            .pieces_after(&m, PieceType::King)
+            .is_in_check(color)
+        {
             continue;
         }
 
-        moves.push(Move {
-            from,
-            to,
-            promotion: None,
-        });
+        moves.push(m);
     }
 
-    let all_occupied = board.all_white_bitboard | board.all_black_bitboard;
     // 2. check for castling moves
-    let rights = board.castling_rights;
-
-    let king_side_number_to_check = match board.active_color {
-        Color::White => 1,
-        Color::Black => 4,
-    };
-
-    let queen_side_number_to_check = match board.active_color {
-        Color::White => 2,
-        Color::Black => 8,
-    };
-
-    // kingside castle check
-    if rights & king_side_number_to_check != 0 {
-        // check to see if the squares between the king and rook are empty
-        let king_side_squares_to_check = match board.active_color {
-            Color::White => [5, 6],
-            Color::Black => [61, 62],
-        };
-
-        let mut can_castle_king_side = true;
-
-        for square in king_side_squares_to_check.iter() {
-            let square_bit = 1u64 << *square;
-
-            // if square is occupied, we cannot castle
-            if all_occupied & square_bit != 0 {
-                can_castle_king_side = false;
-                break;
-            }
-        }
-
-        if can_castle_king_side {
-            let king_side_castle = Move {
-                from,
-                to: match board.active_color {
-                    Color::White => 6,
-                    Color::Black => 62,
-                },
-                promotion: None,
-            };
-
-            moves.push(king_side_castle);
+    if from == board.castling.king_start(color) {
+        for castle in castle_moves(board, color) {
+            moves.push(castle);
         }
     }
+}
 
-    if rights & queen_side_number_to_check != 0 {
-        let queen_side_squares_to_check = match board.active_color {
-            Color::White => [3, 2, 1],
-            Color::Black => [59, 58, 57],
-        };
-
-        let mut can_castle_queen_side = true;
-
-        for square in queen_side_squares_to_check.iter() {
-            let square_bit = 1u64 << *square;
-
-            // if square is occupied, we cannot castle
-            if all_occupied & square_bit != 0 {
-                can_castle_queen_side = false;
-                break;
-            }
-        }
-
-        if can_castle_queen_side {
-            let queen_side_castle = Move {
-                from,
-                to: match board.active_color {
-                    Color::White => 2,
-                    Color::Black => 58,
-                },
-                promotion: None,
-            };
+/// True if the side to move is not in check but has no legal move.
+pub fn is_in_stalemate(board: &Board) -> bool {
+    !board.is_in_check(board.active_color) && !board.has_legal_moves()
+}
 
-            moves.push(queen_side_castle);
-        }
+/// Serialized as its UCI string, e.g. `"e7e8q"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Move {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
-
-    return moves;
 }
-fn convert_bitboards_to_indexs(bitboard: u64) -> Vec<u8> {
-    let mut indexes = Vec::new();
 
-    for i in 0..64 {
-        if (bitboard >> i) & 1 == 1 {
-            indexes.push(i as u8);
-        }
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Move {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let uci = String::deserialize(deserializer)?;
+        Move::from_uci(&uci)
+            .ok_or_else(|| serde::de::Error::custom(format!("not a UCI move: {}", uci)))
     }
-
-    indexes
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::movegen::pseudo_legal_moves;
 
     /// Initializes a standard chess board for testing.
     fn setup_standard_board() -> Board {
@@ -1076,7 +1217,7 @@ mod tests {
     /// Helper function to create a move and validate it.
     fn validate_move_helper(fen: &str, move_str: &str, expected: bool) -> bool {
         let board = setup_custom_board(fen);
-        let m = Move::new(move_str.to_string());
+        let m = Move::new(move_str);
         let valid = validate_move(&board, &m);
         assert_eq!(
             valid,
@@ -1093,7 +1234,7 @@ mod tests {
     #[test]
     fn test_validitiy_of_pawn_move_forward_one() {
         let board = setup_custom_board("8/8/8/8/8/8/4P3/8 w - - 0 1");
-        let m = Move::new("e2e3".to_string());
+        let m = Move::new("e2e3");
         let valid = validate_move(&board, &m);
         assert!(valid, "Pawn move from e2 to e3 should be valid");
     }
@@ -1102,7 +1243,7 @@ mod tests {
     #[test]
     fn test_validitiy_of_pawn_move_forward_two() {
         let board = setup_standard_board();
-        let m = Move::new("e2e4".to_string());
+        let m = Move::new("e2e4");
         let valid = validate_move(&board, &m);
         assert!(valid, "Pawn move from e2 to e4 should be valid");
     }
@@ -1111,7 +1252,7 @@ mod tests {
     #[test]
     fn test_validitiy_of_pawn_move_forward_three_invalid() {
         let board = setup_standard_board();
-        let m = Move::new("e2e5".to_string());
+        let m = Move::new("e2e5");
         let valid = validate_move(&board, &m);
         assert!(!valid, "Pawn move from e2 to e5 should be invalid");
     }
@@ -1120,7 +1261,7 @@ mod tests {
     #[test]
     fn test_validitiy_of_pawn_move_backward_invalid() {
         let board = setup_standard_board();
-        let m = Move::new("e2e1".to_string());
+        let m = Move::new("e2e1");
         let valid = validate_move(&board, &m);
         assert!(!valid, "Pawn move from e2 to e1 should be invalid");
     }
@@ -1129,7 +1270,7 @@ mod tests {
     #[test]
     fn test_validitiy_of_pawn_move_sideways_invalid() {
         let board = setup_standard_board();
-        let m = Move::new("e2d2".to_string());
+        let m = Move::new("e2d2");
         let valid = validate_move(&board, &m);
         assert!(!valid, "Pawn move from e2 to d2 should be invalid");
     }
@@ -1173,13 +1314,13 @@ mod tests {
         );
     }
 
-    /// Test: En Passant capture (White captures Black pawn).
-    /// TODO: Implement En Passant capture
+    // Test: En Passant capture (White captures Black pawn).
+    // TODO: Implement En Passant capture
 
     // #[test]
     // fn test_validitiy_of_pawn_en_passant_capture_white() {
     //     let fen = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq e6 0 3";
-    //     let m = Move::new("d5e4".to_string()); // Black pawn on d5 captures White pawn on e5 via En Passant
+    //     let m = Move::new("d5e4"); // Black pawn on d5 captures White pawn on e5 via En Passant
     //     let valid = validate_move_helper(fen, "d5e4", true);
     //     assert!(
     //         valid,
@@ -1187,12 +1328,12 @@ mod tests {
     //     );
     // }
 
-    /// Test: En Passant capture (Black captures White pawn).
+    // Test: En Passant capture (Black captures White pawn).
 
     // #[test]
     // fn test_validitiy_of_pawn_en_passant_capture_black() {
     //     let fen = "rnbqkbnr/pppppppp/8/8/4pP2/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 3";
-    //     let m = Move::new("f4e5".to_string()); // White pawn on f4 captures Black pawn on e5 via En Passant
+    //     let m = Move::new("f4e5"); // White pawn on f4 captures Black pawn on e5 via En Passant
     //     let valid = validate_move_helper(fen, "f4e5", true);
     //     assert!(
     //         valid,
@@ -1200,12 +1341,12 @@ mod tests {
     //     );
     // }
 
-    /// Test: En Passant capture attempt when not possible (invalid).
+    // Test: En Passant capture attempt when not possible (invalid).
 
     // #[test]
     // fn test_validitiy_of_pawn_en_passant_invalid() {
     //     let fen = "rnbqkbnr/ppp1pppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-    //     let m = Move::new("d4c5".to_string()); // Attempting En Passant without the necessary conditions
+    //     let m = Move::new("d4c5"); // Attempting En Passant without the necessary conditions
     //     let valid = validate_move_helper(fen, "d4c5", false);
     //     assert!(
     //         !valid,
@@ -1315,7 +1456,7 @@ mod tests {
     #[test]
     fn test_validitiy_of_pawn_move_edge_file_a() {
         let board = setup_standard_board();
-        let m = Move::new("a2a4".to_string()); // Move two squares forward
+        let m = Move::new("a2a4"); // Move two squares forward
         let valid = validate_move(&board, &m);
         assert!(valid, "Pawn move from a2 to a4 on file 'a' should be valid");
     }
@@ -2024,6 +2165,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rook_on_e1_to_g1_is_a_rook_move() {
+        // The stale K right must not turn a rook move from e1 into a castle
+        let fen = "4k3/8/8/8/8/8/8/K3R2R w K - 0 1";
+        validate_move_helper(fen, "e1g1", true);
+
+        let mut board = setup_custom_board(fen);
+        let m = Move::from_uci("e1g1").unwrap();
+        assert_eq!(board.classify(&m), MoveKind::Quiet);
+        board.apply_move_unchecked(&m);
+        assert_eq!(
+            board.pieces(Color::White, PieceType::Rook),
+            (1 << 6) | (1 << 7)
+        );
+        assert_eq!(board.king_square(Color::White), Some(0));
+
+        // Nor may the king on a1 "castle" with those rights
+        let board = setup_custom_board(fen);
+        assert!(board
+            .legal_moves()
+            .iter()
+            .all(|m| m.from != 0 || m.to.abs_diff(m.from) != 6));
+    }
+
+    #[test]
+    fn test_castling_without_the_rook_is_invalid() {
+        let fen = "4k3/8/8/8/8/8/8/4K3 w KQ - 0 1";
+        validate_move_helper(fen, "e1g1", false);
+        validate_move_helper(fen, "e1c1", false);
+
+        let board = setup_custom_board(fen);
+        assert!(board
+            .legal_moves()
+            .iter()
+            .all(|m| m.from.abs_diff(m.to) != 2));
+    }
+
+    fn castles(board: &Board) -> Vec<String> {
+        pseudo_legal_moves(board, board.active_color)
+            .iter()
+            .filter(|m| board.classify(m).is_castle())
+            .map(|m| m.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_enemy_pieces_block_castling() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4Kn1R w K - 0 1",
+            "4k3/8/8/8/8/8/8/4K1nR w K - 0 1",
+        ] {
+            validate_move_helper(fen, "e1g1", false);
+            assert!(castles(&setup_custom_board(fen)).is_empty(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_no_castling_rights_generate_no_castles() {
+        // Everything on its home square with the path clear, but "-" for rights
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w - - 0 1";
+        validate_move_helper(fen, "e1g1", false);
+        validate_move_helper(fen, "e1c1", false);
+        assert!(castles(&setup_custom_board(fen)).is_empty());
+
+        let board = setup_custom_board(&fen.replace(" - ", " KQkq "));
+        assert_eq!(castles(&board), ["e1g1", "e1c1"]);
+    }
+
+    #[test]
+    fn test_castling_through_check_is_neither_valid_nor_generated() {
+        // The rook on f8 covers f1; the queenside is still open
+        let fen = "4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1";
+        validate_move_helper(fen, "e1g1", false);
+        validate_move_helper(fen, "e1c1", true);
+        assert_eq!(castles(&setup_custom_board(fen)), ["e1c1"]);
+    }
+
+    #[test]
+    fn test_is_promotion_move_for_both_colors() {
+        let square = |name: &str| Move::from_uci(&format!("{}a1", name)).unwrap().from;
+        let promotes = |fen: &str, from: &str, to: &str| {
+            is_promotion_move(&setup_custom_board(fen), square(from), square(to))
+        };
+
+        let white = "6n1/P6P/8/8/8/8/p7/4K2k w - - 0 1";
+        assert!(promotes(white, "a7", "a8"));
+        assert!(promotes(white, "h7", "g8"));
+        assert!(promotes(white, "h7", "h8"));
+        assert!(!promotes(white, "a7", "b8"));
+        assert!(!promotes(white, "a2", "a1"));
+        assert!(!promotes(white, "e1", "e2"));
+
+        let black = "6n1/P6P/8/8/8/8/p7/4K2k b - - 0 1";
+        assert!(promotes(black, "a2", "a1"));
+        assert!(!promotes(black, "a7", "a8"));
+
+        // The d-pawn is pinned to its king along the seventh rank
+        assert!(!promotes("8/1K1P3r/8/6k1/8/8/8/8 w - - 0 1", "d7", "d8"));
+    }
+
     #[test]
     fn test_en_pessant_working() {
         let fen = "rnbqkbnr/1pp1pppp/8/p2pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1";
@@ -2047,13 +2288,63 @@ mod tests {
             "White pawn should not be able to capture the black pawn via en pessant due to the FEN not indicating it's possible");
     }
 
+    #[test]
+    fn test_en_passant_from_either_file() {
+        let fen = "4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1";
+        validate_move_helper(fen, "c5d6", true);
+        validate_move_helper(fen, "e5d6", true);
+
+        let fen = "4k3/8/8/8/2pPp3/8/8/4K3 b - d3 0 1";
+        validate_move_helper(fen, "c4d3", true);
+        validate_move_helper(fen, "e4d3", true);
+
+        // The white pawn on d4 is the one taken
+        let mut board = setup_custom_board(fen);
+        assert!(board.move_peice(Move::from_uci("e4d3").unwrap()));
+        assert_eq!(board.pieces(Color::White, PieceType::Pawn), 0);
+        assert_eq!(board.occupied_by(Color::White), 1 << 4);
+    }
+
+    #[test]
+    fn test_en_passant_needs_an_adjacent_pawn_on_the_fifth_rank() {
+        // Two files away
+        let fen = "4k3/8/8/1P1p4/8/8/8/4K3 w - d6 0 1";
+        validate_move_helper(fen, "b5d6", false);
+
+        // Beside the target square but on the wrong rank. FEN parsing rejects such an
+        // en passant square, so set it by hand.
+        let mut board = setup_custom_board("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1");
+        board.en_passant = Some(27);
+        assert!(!validate_move(&board, &Move::from_uci("e3d4").unwrap()));
+        let mut moves = MoveList::new();
+        generate_pawn_moves(&board, Color::White, 20, &mut moves);
+        assert!(moves.iter().all(|m| m.to != 27));
+    }
+
+    #[test]
+    fn test_en_passant_rejected_when_it_exposes_the_king() {
+        // Taking on c6 removes both pawns between the king on a5 and the rook on h5
+        let fen = "8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1";
+        let mut board = setup_custom_board(fen);
+        let m = Move::from_uci("b5c6").unwrap();
+        assert!(!board.legal_moves().contains(&m));
+        assert!(!board.move_peice(m));
+
+        let fen = "4K3/8/8/8/kpP4R/8/8/8 b - c3 0 1";
+        let mut board = setup_custom_board(fen);
+        let m = Move::from_uci("b4c3").unwrap();
+        assert!(!board.legal_moves().contains(&m));
+        assert!(!board.move_peice(m));
+    }
+
     #[test]
     fn test_generate_moves_for_single_white_pawn() {
         let fen = "8/8/8/8/8/8/4P3/8 w - - 0 1";
 
         let board = Board::fen_to_board(fen);
 
-        let moves = generate_pawn_moves(&board, 12);
+        let mut moves = MoveList::new();
+        generate_pawn_moves(&board, Color::White, 12, &mut moves);
 
         // dbg!(&moves);
 
@@ -2070,7 +2361,7 @@ mod tests {
             },
         ];
 
-        assert_eq!(moves, expected_moves);
+        assert_eq!(moves.to_vec(), expected_moves);
     }
 
     #[test]
@@ -2079,7 +2370,8 @@ mod tests {
 
         let board = Board::fen_to_board(fen);
 
-        let moves = generate_pawn_moves(&board, 36);
+        let mut moves = MoveList::new();
+        generate_pawn_moves(&board, Color::White, 36, &mut moves);
 
         // dbg!(&moves);
 
@@ -2095,7 +2387,7 @@ mod tests {
                 promotion: None,
             },
         ];
-        assert_eq!(moves, expected_moves)
+        assert_eq!(moves.to_vec(), expected_moves)
     }
 
     #[test]
@@ -2104,7 +2396,8 @@ mod tests {
 
         let board = Board::fen_to_board(fen);
 
-        let moves = generate_knight_moves(&board, 27);
+        let mut moves = MoveList::new();
+        generate_knight_moves(&board, Color::White, 27, &mut moves);
 
         // dbg!(&moves);
 
@@ -2117,13 +2410,121 @@ mod tests {
 
         let board = Board::fen_to_board(fen);
 
-        let moves = generate_knight_moves(&board, 39);
+        let mut moves = MoveList::new();
+        generate_knight_moves(&board, Color::White, 39, &mut moves);
 
         // dbg!(&moves);
 
         assert_eq!(moves.len(), 4);
     }
 
+    /// Flips the side to move without moving a piece, like a null move.
+    fn pass_turn(board: &Board) -> Board {
+        let mut passed = *board;
+        passed.active_color = !passed.active_color;
+        passed.en_passant = None;
+        passed
+    }
+
+    #[test]
+    fn test_generate_moves_for_non_active_color_matches_null_move() {
+        let board = setup_standard_board();
+
+        let black_moves = pseudo_legal_moves(&board, Color::Black);
+        let black_moves_after_pass = pseudo_legal_moves(&pass_turn(&board), Color::Black);
+
+        assert_eq!(board.active_color, Color::White);
+        assert_eq!(black_moves.len(), 20);
+        assert_eq!(black_moves.to_vec(), black_moves_after_pass.to_vec());
+    }
+
+    #[test]
+    fn test_generate_moves_for_non_active_color_in_middlegame() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let board = setup_custom_board(fen);
+        let passed = pass_turn(&board);
+
+        assert_eq!(
+            pseudo_legal_moves(&board, Color::Black).to_vec(),
+            pseudo_legal_moves(&passed, Color::Black).to_vec()
+        );
+        assert_eq!(
+            pseudo_legal_moves(&passed, Color::White).to_vec(),
+            pseudo_legal_moves(&board, Color::White).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_sliding_moves_for_black_bishop_on_whites_turn() {
+        // Black bishop on c8 with the b7 and d7 pawns moved out of the way
+        let fen = "rnbqkbnr/p1p1pppp/8/1p1p4/8/4P3/PPPP1PPP/RNBQKBNR w KQkq - 0 3";
+        let board = setup_custom_board(fen);
+
+        let moves = generate_sliding_moves_for_color(&board, Color::Black, PieceType::Bishop, 58);
+
+        // b7, a6, d7, e6, f5, g4, h3
+        let expected: u64 = 1 << 49 | 1 << 40 | 1 << 51 | 1 << 44 | 1 << 37 | 1 << 30 | 1 << 23;
+        assert_eq!(moves, expected);
+        assert_eq!(
+            moves,
+            generate_sliding_moves(&pass_turn(&board), PieceType::Bishop, 58)
+        );
+    }
+
+    /// Squares a slider on `from` reaches on an empty board, walking each direction
+    /// by file and rank until it leaves the board.
+    fn reference_slides(from: u8, directions: &[(i8, i8)]) -> u64 {
+        let mut squares = 0;
+        for &(file_step, rank_step) in directions {
+            let (mut file, mut rank) = ((from % 8) as i8, (from / 8) as i8);
+            loop {
+                file += file_step;
+                rank += rank_step;
+                if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+                    break;
+                }
+                squares |= 1u64 << (rank * 8 + file);
+            }
+        }
+        squares
+    }
+
+    #[test]
+    fn test_sliding_moves_from_every_square_match_a_reference() {
+        const DIAGONALS: [(i8, i8); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+        const LINES: [(i8, i8); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        let board = Board::fen_to_board("8/8/8/8/8/8/8/8 w - - 0 1");
+
+        for from in 0..64 {
+            let bishop = reference_slides(from, &DIAGONALS);
+            let rook = reference_slides(from, &LINES);
+            let cases = [
+                (PieceType::Bishop, bishop),
+                (PieceType::Rook, rook),
+                (PieceType::Queen, bishop | rook),
+            ];
+            for (piece_type, expected) in cases {
+                assert_eq!(
+                    generate_sliding_moves(&board, piece_type, from),
+                    expected,
+                    "{} on {}",
+                    piece_type,
+                    square_name(from)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_to_location_for_non_active_color() {
+        let board = setup_standard_board();
+
+        // e7 holds a black pawn: friendly for Black, capturable for White
+        let m = Move::new("d8e7");
+        assert!(!validate_to_location(&board, Color::Black, &m));
+        assert!(validate_to_location(&board, Color::White, &m));
+    }
+
     #[test]
     fn test_queen_in_checkmate() {
         let fen = "q5K1/r7/8/8/8/8/8/8 w - - 0 1";
@@ -2146,7 +2547,7 @@ mod tests {
     fn test_king_alone_stalemate() {
         let fen = "8/8/4k3/1r6/8/8/4r3/K7 w - - 0 1";
         let board = Board::fen_to_board(fen);
-        
+
         // Black king is not in check but has no legal moves
         assert!(!board.is_in_check(Color::Black));
         assert!(is_in_stalemate(&board));
@@ -2156,7 +2557,7 @@ mod tests {
     fn test_not_stalemate_with_legal_moves() {
         let fen = "8/8/4k3/1r6/8/4r3/8/K7 w - - 0 1";
         let board = Board::fen_to_board(fen);
-        
+
         // Black king has legal moves available
         assert!(!is_in_stalemate(&board));
     }
@@ -2165,7 +2566,7 @@ mod tests {
     fn test_stalemate_with_blocked_pawns() {
         let fen = "8/8/4k3/1r6/7p/7P/4r3/K7 w - - 0 1";
         let board = Board::fen_to_board(fen);
-        
+
         // Black's king and pawn are blocked, with no legal moves
         assert!(is_in_stalemate(&board));
     }
@@ -2175,29 +2576,57 @@ mod tests {
         // This is a real stalemate position where Black has no legal moves
         let fen = "7k/5K2/6Q1/8/8/8/8/8 b - - 0 1";
         let board = Board::fen_to_board(fen);
-        
+
         // Black king is not in check but has no legal moves
         assert!(!board.is_in_check(Color::Black));
         assert!(is_in_stalemate(&board));
     }
-}
 
-pub fn is_in_stalemate(board: &Board) -> bool {
-    // If the king is in check, it's not stalemate
-    if board.is_in_check(board.active_color) {
-        return false;
+    #[test]
+    fn test_move_from_uci() {
+        assert_eq!(Move::from_uci("e2e4"), Some(Move::new("e2e4")));
+        assert_eq!(
+            Move::from_uci("a7a8n").unwrap().promotion,
+            Some(PieceType::Knight)
+        );
+        for bad in ["", "e2", "e2e9", "i2e4", "e7e8k", "e2e4q5"] {
+            assert_eq!(Move::from_uci(bad), None, "{}", bad);
+        }
     }
 
-    // Generate all possible moves for the current player
-    let all_moves = generate_all_moves_for_color(board);
+    #[test]
+    fn test_packed_moves_round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq a3 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens {
+            let board = setup_custom_board(fen);
+            let moves = board.legal_moves();
+            assert!(!moves.is_empty(), "{}", fen);
+            for m in &moves {
+                assert_eq!(PackedMove::new(m).unpack(), m, "{}: {}", fen, m);
+                assert_eq!(Move::from(PackedMove::from(m)), m, "{}: {}", fen, m);
+            }
+        }
 
-    // If there are no legal moves and the king is not in check, it's stalemate
-    for m in all_moves {
-        let mut board_clone = board.clone();
-        if board_clone.move_peice(m) {
-            return false;
+        // Every move the encoding can hold, legal anywhere or not
+        for from in 0..64 {
+            for to in (0..64).filter(|&to| to != from) {
+                for promotion in [None].into_iter().chain(PROMOTION_PIECES.map(Some)) {
+                    let m = Move::new_from_indices(from, to, promotion);
+                    let packed = PackedMove::new(m);
+                    assert_eq!(packed.unpack(), m);
+                    assert_eq!(PackedMove::from_bits(packed.bits()), Some(packed));
+                    assert_eq!(packed.from_to(), from as usize | (to as usize) << 6);
+                }
+            }
         }
+        assert_eq!(PackedMove::from_bits(0), None);
+        assert_eq!(std::mem::size_of::<Option<PackedMove>>(), 2);
     }
-
-    true
 }
@@ -0,0 +1,269 @@
+//! A chess clock: base time plus an increment per move for each side, with flag
+//! detection. Time is read through [`TimeSource`] so tests can drive it by hand.
+
+use crate::board::Color;
+use crate::search::move_time_budget;
+
+use std::fmt;
+use std::time::Duration;
+// std's clock panics on wasm32-unknown-unknown; web-time reads the browser's instead
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Where a [`Clock`] reads the time. It must never go backwards.
+pub trait TimeSource {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MonotonicTime;
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Parses a time control written as `minutes+seconds`, e.g. `5+3` for five minutes
+/// plus three seconds a move, into the base time and increment. A bare number of
+/// minutes has no increment.
+pub fn parse_time_control(text: &str) -> Option<(Duration, Duration)> {
+    let (minutes, seconds) = text.split_once('+').unwrap_or((text, "0"));
+    let minutes: f64 = minutes.trim().parse().ok()?;
+    let seconds: u64 = seconds.trim().parse().ok()?;
+    (minutes.is_finite() && minutes > 0.0).then(|| {
+        (
+            Duration::from_secs_f64(minutes * 60.0),
+            Duration::from_secs(seconds),
+        )
+    })
+}
+
+/// Both players' time. At most one side's clock runs at a time.
+#[derive(Debug, Clone)]
+pub struct Clock<T: TimeSource = MonotonicTime> {
+    source: T,
+    /// Time left for White and Black as of the last start or stop.
+    remaining: [Duration; 2],
+    increment: Duration,
+    /// The side whose clock is running and since when.
+    running: Option<(Color, Instant)>,
+}
+
+impl Clock {
+    /// Both sides get `base` time and `increment` more after each of their moves.
+    pub fn new(base: Duration, increment: Duration) -> Clock {
+        Clock::with_source(base, increment, MonotonicTime)
+    }
+}
+
+impl<T: TimeSource> Clock<T> {
+    /// A clock reading the time from `source`.
+    pub fn with_source(base: Duration, increment: Duration, source: T) -> Clock<T> {
+        Clock {
+            source,
+            remaining: [base; 2],
+            increment,
+            running: None,
+        }
+    }
+
+    /// Starts `color`'s clock, stopping the other side's if it was running.
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some((color, self.source.now()));
+    }
+
+    /// Stops whichever clock is running, charging it the time used.
+    pub fn stop(&mut self) {
+        if let Some((color, since)) = self.running.take() {
+            let used = self.source.now().saturating_duration_since(since);
+            let remaining = &mut self.remaining[color as usize];
+            *remaining = remaining.saturating_sub(used);
+        }
+    }
+
+    /// Call once `color` has moved: stops its clock, adds the increment unless it has
+    /// already run out, and starts the opponent's.
+    pub fn record_move(&mut self, color: Color) {
+        self.stop();
+        if !self.remaining[color as usize].is_zero() {
+            self.remaining[color as usize] += self.increment;
+        }
+        self.start(!color);
+    }
+
+    /// Time `color` has left, counting the time used so far if its clock is running.
+    pub fn remaining(&self, color: Color) -> Duration {
+        let remaining = self.remaining[color as usize];
+        match self.running {
+            Some((running, since)) if running == color => {
+                remaining.saturating_sub(self.source.now().saturating_duration_since(since))
+            }
+            _ => remaining,
+        }
+    }
+
+    pub fn increment(&self) -> Duration {
+        self.increment
+    }
+
+    /// The side whose time has run out, if either.
+    pub fn flagged(&self) -> Option<Color> {
+        Color::BOTH
+            .into_iter()
+            .find(|&color| self.remaining(color).is_zero())
+    }
+
+    /// How long the engine should think as `color`, from the time it has left, see
+    /// [`move_time_budget`].
+    pub fn think_time(&self, color: Color, overhead: Duration) -> Duration {
+        Duration::from_millis(move_time_budget(
+            self.remaining(color).as_millis() as u64,
+            self.increment.as_millis() as u64,
+            overhead.as_millis() as u64,
+        ))
+    }
+}
+
+/// Writes both clocks as `White 4:57  Black 5:00`, with tenths of a second under
+/// ten seconds.
+impl<T: TimeSource> fmt::Display for Clock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, color) in Color::BOTH.into_iter().enumerate() {
+            let left = self.remaining(color);
+            if i > 0 {
+                write!(f, "  ")?;
+            }
+            let seconds = left.as_secs();
+            if seconds < 10 {
+                write!(f, "{} 0:0{}.{}", color, seconds, left.subsec_millis() / 100)?;
+            } else {
+                write!(f, "{} {}:{:02}", color, seconds / 60, seconds % 60)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// A time source that only moves when the test advances it.
+    #[derive(Clone)]
+    struct FakeTime {
+        start: Instant,
+        elapsed: Rc<Cell<Duration>>,
+    }
+
+    impl FakeTime {
+        fn new() -> FakeTime {
+            FakeTime {
+                start: Instant::now(),
+                elapsed: Rc::new(Cell::new(Duration::ZERO)),
+            }
+        }
+
+        fn advance(&self, secs: f64) {
+            self.elapsed
+                .set(self.elapsed.get() + Duration::from_secs_f64(secs));
+        }
+    }
+
+    impl TimeSource for FakeTime {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+    }
+
+    #[test]
+    fn test_parse_time_control() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(
+            parse_time_control("5+3"),
+            Some((minutes(5), Duration::from_secs(3)))
+        );
+        assert_eq!(
+            parse_time_control("10"),
+            Some((minutes(10), Duration::ZERO))
+        );
+        assert_eq!(
+            parse_time_control("0.5+1"),
+            Some((Duration::from_secs(30), Duration::from_secs(1)))
+        );
+        assert_eq!(parse_time_control("0+1"), None);
+        assert_eq!(parse_time_control("5+x"), None);
+    }
+
+    #[test]
+    fn test_moves_charge_the_mover_and_add_the_increment() {
+        let time = FakeTime::new();
+        let mut clock = Clock::with_source(
+            Duration::from_secs(300),
+            Duration::from_secs(3),
+            time.clone(),
+        );
+
+        clock.start(Color::White);
+        time.advance(10.0);
+        assert_eq!(clock.remaining(Color::White), Duration::from_secs(290));
+        clock.record_move(Color::White);
+        assert_eq!(clock.remaining(Color::White), Duration::from_secs(293));
+
+        time.advance(20.0);
+        assert_eq!(clock.remaining(Color::White), Duration::from_secs(293));
+        assert_eq!(clock.remaining(Color::Black), Duration::from_secs(280));
+        clock.record_move(Color::Black);
+        assert_eq!(clock.remaining(Color::Black), Duration::from_secs(283));
+        assert_eq!(clock.to_string(), "White 4:53  Black 4:43");
+
+        // A stopped clock does not run
+        clock.stop();
+        time.advance(60.0);
+        assert_eq!(clock.remaining(Color::White), Duration::from_secs(293));
+        assert_eq!(clock.flagged(), None);
+    }
+
+    #[test]
+    fn test_flag_falls_when_time_runs_out() {
+        let time = FakeTime::new();
+        let mut clock =
+            Clock::with_source(Duration::from_secs(5), Duration::from_secs(2), time.clone());
+
+        clock.start(Color::White);
+        time.advance(4.5);
+        assert_eq!(clock.to_string(), "White 0:00.5  Black 0:05.0");
+        assert_eq!(clock.flagged(), None);
+        time.advance(1.0);
+        assert_eq!(clock.flagged(), Some(Color::White));
+
+        // Moving after the flag fell earns no increment
+        clock.record_move(Color::White);
+        assert_eq!(clock.remaining(Color::White), Duration::ZERO);
+        assert_eq!(clock.flagged(), Some(Color::White));
+    }
+
+    #[test]
+    fn test_think_time_comes_from_the_time_left() {
+        let time = FakeTime::new();
+        let mut clock = Clock::with_source(
+            Duration::from_secs(60),
+            Duration::from_secs(2),
+            time.clone(),
+        );
+        clock.start(Color::Black);
+        time.advance(30.0);
+
+        // 30 s left: 1 s of it plus half the increment, less the overhead
+        assert_eq!(
+            clock.think_time(Color::Black, Duration::from_millis(100)),
+            Duration::from_millis(1_900)
+        );
+    }
+}
@@ -0,0 +1,626 @@
+//! A store of finished games: one file, `games.db`, in a directory of the player's
+//! choosing, that games are appended to and read back from by number.
+//!
+//! The file starts with an 8 byte magic number, followed by one record per game:
+//!
+//! | bytes  | field                                                  |
+//! |--------|--------------------------------------------------------|
+//! | 4      | `GAME`, marking the start of a record                  |
+//! | 4      | length of the rest of the record, little-endian        |
+//! | 4      | FNV-1a checksum of the rest of the record              |
+//! | 4      | FNV-1a checksum of the 12 bytes before it              |
+//! | 1 + n  | starting position as FEN                               |
+//! | 2 + 2n | moves, packed as [`PackedMove`] bits                   |
+//! | 2      | winner (0 none, 1 White, 2 Black, 3 draw), termination |
+//! | 2 + …  | tags, each name and value with a 2 byte length         |
+//!
+//! A damaged record is skipped, not fatal. If its contents are damaged, its length
+//! still says where the next one starts; if its header is, the next one is found by
+//! looking for the next header that matches its checksum. Only a record whose sound
+//! header promises more bytes than the file has left, or a header cut short, is
+//! taken for what a crash while writing leaves at the end of the file, and dropped
+//! by the next [`GameDb::append`].
+
+use crate::board::Color;
+use crate::chess_move::PackedMove;
+use crate::game::{Game, GameError, GameResult, Termination};
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the file a [`GameDb`] keeps its games in.
+pub const DB_FILE: &str = "games.db";
+const MAGIC: &[u8; 8] = b"CBGAMES1";
+/// Marks the start of each record.
+const RECORD_MARK: &[u8; 4] = b"GAME";
+/// Mark, length and checksums before each record's contents.
+const RECORD_HEADER: usize = 16;
+
+/// Every way a game can end, in the order the file numbers them from 0. Only ever
+/// appended to, so that old files keep their meaning.
+const TERMINATIONS: [Termination; 11] = [
+    Termination::Checkmate,
+    Termination::Resignation,
+    Termination::Timeout,
+    Termination::Stalemate,
+    Termination::InsufficientMaterial,
+    Termination::FiftyMoveRule,
+    Termination::Repetition,
+    Termination::SeventyFiveMoveRule,
+    Termination::FivefoldRepetition,
+    Termination::Agreement,
+    Termination::Adjudication,
+];
+
+/// Why a record of the file could not be read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// The file ends inside the record.
+    Truncated,
+    /// The record's header is damaged, so where it ends was found by looking for the
+    /// next one.
+    Header,
+    /// The record's contents do not match their checksum.
+    Checksum,
+    /// The contents match their checksum but do not make sense, for the given reason.
+    Malformed(&'static str),
+    /// The moves cannot be replayed from the starting position.
+    Game(GameError),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Truncated => write!(f, "the file ends inside the game"),
+            RecordError::Header => write!(f, "the game is damaged (bad header)"),
+            RecordError::Checksum => write!(f, "the game is damaged (checksum mismatch)"),
+            RecordError::Malformed(reason) => write!(f, "the game is damaged ({})", reason),
+            RecordError::Game(err) => write!(f, "the game cannot be replayed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordError::Game(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Why the store could not be read or written.
+#[derive(Debug)]
+pub enum DbError {
+    Io(io::Error),
+    /// The file is not a game store; it does not start with its magic number.
+    NotADatabase,
+    /// [`GameDb::load`] was asked for game `index` of a store of `games`.
+    NoSuchGame {
+        index: usize,
+        games: usize,
+    },
+    /// Game `index` is in the store but cannot be read back.
+    Corrupt {
+        index: usize,
+        reason: RecordError,
+    },
+    /// [`GameDb::append`] was given a game with a part too long for its record,
+    /// `len` bytes or entries where `max` fit.
+    TooLong {
+        part: String,
+        len: usize,
+        max: usize,
+    },
+}
+
+/// Games are numbered from 1, as [`GameDb::list`]'s callers show them.
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Io(err) => err.fmt(f),
+            DbError::NotADatabase => write!(f, "not a ChessBot game store"),
+            DbError::NoSuchGame { index, games } => {
+                write!(f, "no game {}: the store has {} games", index + 1, games)
+            }
+            DbError::Corrupt { index, reason } => write!(f, "game {}: {}", index + 1, reason),
+            DbError::TooLong { part, len, max } => {
+                write!(
+                    f,
+                    "{} is too long to store ({}, at most {})",
+                    part, len, max
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Io(err) => Some(err),
+            DbError::Corrupt { reason, .. } => Some(reason),
+            DbError::NotADatabase | DbError::NoSuchGame { .. } | DbError::TooLong { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(err: io::Error) -> Self {
+        DbError::Io(err)
+    }
+}
+
+/// A game read back from the store.
+#[derive(Debug, Clone)]
+pub struct StoredGame {
+    /// Its place in the store, from 0; the first game appended is 0.
+    pub index: usize,
+    /// PGN tags such as `Event` and `Date`, in the order they were stored.
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+}
+
+impl StoredGame {
+    /// Value of the tag `name`, e.g. `stored.tag("Date")`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The game in PGN with its tags, see [`Game::to_pgn_with_tags`].
+    pub fn to_pgn(&self) -> String {
+        self.game.to_pgn_with_tags(&self.tags)
+    }
+}
+
+/// A game the listing left out, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedGame {
+    pub index: usize,
+    pub reason: RecordError,
+}
+
+/// Warns of the game as a player would number it, e.g. `skipped game 2: ...`.
+impl fmt::Display for SkippedGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "skipped game {}: {}", self.index + 1, self.reason)
+    }
+}
+
+/// Everything [`GameDb::list`] found.
+#[derive(Debug, Clone, Default)]
+pub struct Listing {
+    /// The games that could be read, in the order they were appended.
+    pub games: Vec<StoredGame>,
+    /// The ones that could not.
+    pub skipped: Vec<SkippedGame>,
+}
+
+/// The games stored in a directory. Nothing is read until asked for, and a store
+/// no game was ever appended to is simply empty.
+#[derive(Debug, Clone)]
+pub struct GameDb {
+    path: PathBuf,
+}
+
+impl GameDb {
+    /// The store in `dir`, which is created when the first game is appended.
+    pub fn new<P: AsRef<Path>>(dir: P) -> GameDb {
+        GameDb {
+            path: dir.as_ref().join(DB_FILE),
+        }
+    }
+
+    /// The file the games are kept in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds `game` with `tags` at the end of the store and returns its index. A
+    /// record left cut short by an earlier write is dropped to make room; nothing
+    /// else in the file is ever removed.
+    pub fn append(&self, game: &Game, tags: &[(String, String)]) -> Result<usize, DbError> {
+        let record = encode_record(game, tags)?;
+        let (mut records, complete) = self.records()?;
+        let truncated = matches!(records.last(), Some(Err(RecordError::Truncated)));
+        if truncated {
+            records.pop();
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&self.path)?;
+        if truncated {
+            file.set_len(complete as u64)?;
+        }
+        file.seek(SeekFrom::End(0))?;
+        let mut bytes = Vec::new();
+        if complete == 0 {
+            bytes.extend_from_slice(MAGIC);
+        }
+        bytes.extend(record);
+        file.write_all(&bytes)?;
+        Ok(records.len())
+    }
+
+    /// Every game in the store, skipping the ones that cannot be read back.
+    pub fn list(&self) -> Result<Listing, DbError> {
+        let mut listing = Listing::default();
+        for (index, record) in self.records()?.0.into_iter().enumerate() {
+            match record.and_then(decode_record) {
+                Ok((tags, game)) => listing.games.push(StoredGame { index, tags, game }),
+                Err(reason) => listing.skipped.push(SkippedGame { index, reason }),
+            }
+        }
+        Ok(listing)
+    }
+
+    /// The game at `index`, for replaying or analysis.
+    pub fn load(&self, index: usize) -> Result<StoredGame, DbError> {
+        let (records, _) = self.records()?;
+        let games = records.len();
+        let record = records
+            .into_iter()
+            .nth(index)
+            .ok_or(DbError::NoSuchGame { index, games })?;
+        let (tags, game) = record
+            .and_then(decode_record)
+            .map_err(|reason| DbError::Corrupt { index, reason })?;
+        Ok(StoredGame { index, tags, game })
+    }
+
+    /// The records of the file, see [`split_records`].
+    fn records(&self) -> Result<Records, DbError> {
+        match fs::read(&self.path) {
+            Ok(bytes) => split_records(&bytes),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok((Vec::new(), 0)),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// The contents of each record, or why they cannot be had, and how many bytes of
+/// the file the complete records end at.
+type Records = (Vec<Result<Vec<u8>, RecordError>>, usize);
+
+/// Splits the contents of a store file into its records. A record cut short is the
+/// last, and the complete ones end where it starts. An empty file is an empty store.
+fn split_records(bytes: &[u8]) -> Result<Records, DbError> {
+    if bytes.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+    let body = bytes.strip_prefix(MAGIC).ok_or(DbError::NotADatabase)?;
+    let mut records = Vec::new();
+    let mut at = 0;
+    while at < body.len() {
+        let rest = &body[at..];
+        if rest.len() < RECORD_HEADER {
+            records.push(Err(RecordError::Truncated));
+            return Ok((records, MAGIC.len() + at));
+        }
+        let Some((len, checksum)) = read_header(rest) else {
+            // Whatever the header said is lost; the next sound one starts a record
+            records.push(Err(RecordError::Header));
+            at += (1..rest.len())
+                .find(|&skip| read_header(&rest[skip..]).is_some())
+                .unwrap_or(rest.len());
+            continue;
+        };
+        let Some(contents) = rest[RECORD_HEADER..].get(..len) else {
+            records.push(Err(RecordError::Truncated));
+            return Ok((records, MAGIC.len() + at));
+        };
+        records.push(if fnv1a(contents) == checksum {
+            Ok(contents.to_vec())
+        } else {
+            Err(RecordError::Checksum)
+        });
+        at += RECORD_HEADER + len;
+    }
+    Ok((records, bytes.len()))
+}
+
+/// The length and checksum of the contents of the record `bytes` starts with, if
+/// it starts with a sound header.
+fn read_header(bytes: &[u8]) -> Option<(usize, u32)> {
+    let header = bytes.first_chunk::<RECORD_HEADER>()?;
+    let field = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    if &header[..4] != RECORD_MARK || fnv1a(&header[..12]) != field(12) {
+        return None;
+    }
+    Some((field(4) as usize, field(8)))
+}
+
+/// `contents` with their header in front.
+fn seal(contents: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER + contents.len());
+    record.extend_from_slice(RECORD_MARK);
+    record.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+    record.extend_from_slice(&fnv1a(contents).to_le_bytes());
+    record.extend_from_slice(&fnv1a(&record).to_le_bytes());
+    record.extend_from_slice(contents);
+    record
+}
+
+/// Fails unless `len` of `part` fits in a record field of `max`.
+fn check_len(part: impl FnOnce() -> String, len: usize, max: usize) -> Result<(), DbError> {
+    if len > max {
+        return Err(DbError::TooLong {
+            part: part(),
+            len,
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// The record for `game` and `tags`, header included. Fails if a part of it does
+/// not fit its length field.
+fn encode_record(game: &Game, tags: &[(String, String)]) -> Result<Vec<u8>, DbError> {
+    let max_u16 = u16::MAX as usize;
+    let fen = game.start().board_to_fen();
+    check_len(|| "the start position".into(), fen.len(), u8::MAX as usize)?;
+    check_len(|| "the move list".into(), game.moves().len(), max_u16)?;
+    check_len(|| "the tag list".into(), tags.len(), max_u16)?;
+    for (name, value) in tags {
+        check_len(|| "a tag name".into(), name.len(), max_u16)?;
+        check_len(|| format!("tag {}", name), value.len(), max_u16)?;
+    }
+
+    let mut contents = Vec::new();
+    contents.push(fen.len() as u8);
+    contents.extend_from_slice(fen.as_bytes());
+
+    contents.extend_from_slice(&(game.moves().len() as u16).to_le_bytes());
+    for &m in game.moves() {
+        contents.extend_from_slice(&PackedMove::new(m).bits().to_le_bytes());
+    }
+
+    let (winner, termination) = match game.result() {
+        None => (0, 0),
+        Some(result) => {
+            let winner = match result.winner {
+                Some(Color::White) => 1,
+                Some(Color::Black) => 2,
+                None => 3,
+            };
+            let termination = TERMINATIONS
+                .iter()
+                .position(|&t| t == result.termination)
+                .expect("every termination is numbered");
+            (winner, termination as u8)
+        }
+    };
+    contents.extend_from_slice(&[winner, termination]);
+
+    contents.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+    for (name, value) in tags {
+        for text in [name, value] {
+            contents.extend_from_slice(&(text.len() as u16).to_le_bytes());
+            contents.extend_from_slice(text.as_bytes());
+        }
+    }
+
+    check_len(|| "the game".into(), contents.len(), u32::MAX as usize)?;
+    Ok(seal(&contents))
+}
+
+/// The tags and game in the contents of a record, replayed move by move.
+fn decode_record(contents: Vec<u8>) -> Result<(Vec<(String, String)>, Game), RecordError> {
+    let mut reader = Reader(&contents);
+
+    let fen_len = reader.bytes(1)?[0] as usize;
+    let fen = reader.text(fen_len)?;
+
+    let mut moves = Vec::new();
+    for _ in 0..reader.u16()? {
+        let packed = PackedMove::from_bits(reader.u16()?)
+            .filter(|m| m.bits() >> 12 <= 5)
+            .ok_or(RecordError::Malformed("not a move"))?;
+        moves.push(packed.unpack());
+    }
+    let mut game = Game::replay(&fen, &moves).map_err(RecordError::Game)?;
+
+    let result = reader.bytes(2)?;
+    let winner = match result[0] {
+        0 => None,
+        1 => Some(Some(Color::White)),
+        2 => Some(Some(Color::Black)),
+        3 => Some(None),
+        _ => return Err(RecordError::Malformed("unknown winner")),
+    };
+    if let Some(winner) = winner {
+        let termination = *TERMINATIONS
+            .get(result[1] as usize)
+            .ok_or(RecordError::Malformed("unknown termination"))?;
+        let result = GameResult {
+            winner,
+            termination,
+        };
+        // Results the rules give are not set by hand, as they were not when played
+        if game.outcome() != Some(result) {
+            game.set_result(result);
+        }
+    }
+
+    let mut tags = Vec::new();
+    for _ in 0..reader.u16()? {
+        let len = reader.u16()? as usize;
+        let name = reader.text(len)?;
+        let len = reader.u16()? as usize;
+        tags.push((name, reader.text(len)?));
+    }
+    if !reader.0.is_empty() {
+        return Err(RecordError::Malformed("trailing bytes"));
+    }
+    Ok((tags, game))
+}
+
+/// Reads a record's contents from the front.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], RecordError> {
+        if self.0.len() < len {
+            return Err(RecordError::Malformed("a field runs past the end"));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, RecordError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn text(&mut self, len: usize) -> Result<String, RecordError> {
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| RecordError::Malformed("text is not UTF-8"))
+    }
+}
+
+/// 32-bit FNV-1a hash, enough to notice a damaged record.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// The date `time` falls on in UTC, as the PGN `Date` tag writes it: `2024.03.09`.
+pub fn pgn_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86_400) as i64;
+    // Howard Hinnant's days-to-civil, counting in 400-year eras from 0000-03-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::Move;
+    use std::time::Duration;
+
+    fn game(fen: &str, moves: &str) -> Game {
+        let moves: Vec<Move> = moves
+            .split_whitespace()
+            .map(|m| Move::from_uci(m).unwrap())
+            .collect();
+        Game::replay(fen, &moves).unwrap()
+    }
+
+    fn file_of(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        records.iter().for_each(|record| bytes.extend(record));
+        bytes
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let mut resigned = game(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "e2e4 e8d7 e4e5 d7e6 e1e2",
+        );
+        resigned.set_result(GameResult::win(Color::White, Termination::Resignation));
+        let tags = vec![("Event".to_string(), "Blitz ♔".to_string())];
+
+        let record = encode_record(&resigned, &tags).unwrap();
+        let contents = split_records(&file_of(&[record]))
+            .unwrap()
+            .0
+            .remove(0)
+            .unwrap();
+        let (read_tags, read) = decode_record(contents).unwrap();
+        assert_eq!(read_tags, tags);
+        assert_eq!(read.start().board_to_fen(), resigned.start().board_to_fen());
+        assert_eq!(read.moves(), resigned.moves());
+        assert_eq!(read.result(), resigned.result());
+    }
+
+    #[test]
+    fn test_damaged_records_are_told_apart() {
+        let fools_mate = game(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "f2f3 e7e5 g2g4 d8h4",
+        );
+        let good = encode_record(&fools_mate, &[]).unwrap();
+        let mut flipped = good.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+
+        // A record whose checksum holds but whose second move is illegal
+        let mut illegal = good[RECORD_HEADER..].to_vec();
+        let second_move = 1 + illegal[0] as usize + 2 + 2;
+        illegal[second_move..second_move + 2].copy_from_slice(
+            &PackedMove::new(Move::from_uci("e7e4").unwrap())
+                .bits()
+                .to_le_bytes(),
+        );
+        let resealed = seal(&illegal);
+
+        // A bit of the length flipped: the header no longer matches its checksum
+        let mut bad_length = good.clone();
+        bad_length[5] ^= 1;
+
+        let truncated = good[..good.len() - 3].to_vec();
+        let records = [flipped, resealed, bad_length, good.clone(), truncated];
+        let (records, complete) = split_records(&file_of(&records)).unwrap();
+        assert_eq!(records.len(), 5);
+        assert_eq!(complete, MAGIC.len() + 4 * good.len());
+        assert_eq!(records[0], Err(RecordError::Checksum));
+        assert!(matches!(
+            records[1].clone().and_then(decode_record),
+            Err(RecordError::Game(GameError::IllegalMove { ply: 1, .. }))
+        ));
+        assert_eq!(records[2], Err(RecordError::Header));
+        assert!(records[3].clone().and_then(decode_record).is_ok());
+        assert_eq!(records[4], Err(RecordError::Truncated));
+
+        // A header cut short is a truncated record, but a damaged one never is
+        let (records, complete) = split_records(&file_of(&[good[..10].to_vec()])).unwrap();
+        assert_eq!(records, [Err(RecordError::Truncated)]);
+        assert_eq!(complete, MAGIC.len());
+        let mut last = good.clone();
+        last[0] ^= 1;
+        let (records, complete) = split_records(&file_of(&[last])).unwrap();
+        assert_eq!(records, [Err(RecordError::Header)]);
+        assert_eq!(complete, MAGIC.len() + good.len());
+
+        assert!(matches!(
+            split_records(b"not a store"),
+            Err(DbError::NotADatabase)
+        ));
+    }
+
+    #[test]
+    fn test_pgn_date() {
+        let day = |days: u64| pgn_date(UNIX_EPOCH + Duration::from_secs(days * 86_400 + 3_600));
+        assert_eq!(day(0), "1970.01.01");
+        assert_eq!(day(11_016), "2000.02.29");
+        assert_eq!(day(19_791), "2024.03.09");
+        assert_eq!(day(20_742), "2026.10.16");
+    }
+}
@@ -0,0 +1,215 @@
+//! Pictures of a position for sharing: an SVG image and a plain Unicode diagram.
+//!
+//! Unlike [`Board::display`], these return a `String` and never print, so they can go
+//! straight into a file, a web page or a bug report.
+
+use crate::board::Board;
+use crate::chess_move::Move;
+
+use std::fmt::Write;
+
+/// Piece glyphs in bitboard order: white pawn to king, then black.
+const PIECE_GLYPHS: [char; 12] = [
+    '♙', '♘', '♗', '♖', '♕', '♔', // White
+    '♟', '♞', '♝', '♜', '♛', '♚', // Black
+];
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const HIGHLIGHT: &str = "#cdd16a";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgOptions {
+    /// Side of one square, in pixels.
+    pub square_size: u32,
+    /// Label the files along the bottom edge and the ranks along the left edge.
+    pub coordinates: bool,
+    /// Highlight the from and to squares of this move.
+    pub last_move: Option<Move>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            square_size: 45,
+            coordinates: true,
+            last_move: None,
+        }
+    }
+}
+
+impl Board {
+    /// Index into `bitboards` of the piece on `square`, if any.
+    fn piece_index_at(&self, square: u8) -> Option<usize> {
+        self.bitboards
+            .iter()
+            .position(|bitboard| bitboard & (1u64 << square) != 0)
+    }
+
+    /// The position as lines of Unicode figurines, rank 8 first, with `·` for empty
+    /// squares and the file letters underneath.
+    pub fn to_diagram(&self) -> String {
+        let mut diagram = String::new();
+        for rank in (0..8).rev() {
+            diagram.push_str(&(rank + 1).to_string());
+            for file in 0..8 {
+                let glyph = self
+                    .piece_index_at(rank * 8 + file)
+                    .map_or('·', |index| PIECE_GLYPHS[index]);
+                diagram.push(' ');
+                diagram.push(glyph);
+            }
+            diagram.push('\n');
+        }
+        diagram.push_str("  a b c d e f g h\n");
+        diagram
+    }
+
+    /// Like [`Board::to_diagram`], but with the file letters above and below and the
+    /// rank numbers on both sides, as the command line shows the board.
+    pub fn to_labeled_diagram(&self) -> String {
+        let mut diagram = String::from("  a b c d e f g h\n");
+        for line in self.to_diagram().lines().take(8) {
+            let rank = &line[..1];
+            let _ = writeln!(diagram, "{} {}", line, rank);
+        }
+        diagram.push_str("  a b c d e f g h\n");
+        diagram
+    }
+
+    /// The position as a standalone SVG image, White at the bottom.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let size = options.square_size;
+        let board_size = size * 8;
+        let highlighted = options.last_move.map(|m| [m.from, m.to]);
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+            board_size
+        );
+
+        for square in 0..64u8 {
+            let (file, rank) = (square as u32 % 8, square as u32 / 8);
+            let (x, y) = (file * size, (7 - rank) * size);
+            let fill = if highlighted.is_some_and(|squares| squares.contains(&square)) {
+                HIGHLIGHT
+            } else if (file + rank) % 2 == 0 {
+                DARK_SQUARE
+            } else {
+                LIGHT_SQUARE
+            };
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{2}" height="{2}" fill="{3}"/>"#,
+                x, y, size, fill
+            );
+
+            if let Some(index) = self.piece_index_at(square) {
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    x + size / 2,
+                    y + size / 2,
+                    size * 4 / 5,
+                    PIECE_GLYPHS[index]
+                );
+            }
+        }
+
+        if options.coordinates {
+            let font_size = (size / 4).max(1);
+            for i in 0..8 {
+                // Files in the bottom-right corner of rank 1, ranks in the top-left of file a
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-size="{}" text-anchor="end">{}</text>"#,
+                    (i + 1) * size - 2,
+                    board_size - 2,
+                    font_size,
+                    (b'a' + i as u8) as char
+                );
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="2" y="{}" font-size="{}">{}</text>"#,
+                    (7 - i) * size + font_size,
+                    font_size,
+                    i + 1
+                );
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starting_position_svg_snapshot() {
+        let svg = Board::new().to_svg(&SvgOptions::default());
+
+        assert_eq!(svg, include_str!("../tests/fixtures/starting_position.svg"));
+        let document = roxmltree::Document::parse(&svg).unwrap();
+        assert_eq!(document.root_element().tag_name().name(), "svg");
+    }
+
+    #[test]
+    fn test_svg_highlights_last_move_and_can_drop_coordinates() {
+        let board =
+            Board::fen_to_board("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let options = SvgOptions {
+            coordinates: false,
+            last_move: Some(Move::new("e2e4")),
+            ..SvgOptions::default()
+        };
+        let svg = board.to_svg(&options);
+
+        roxmltree::Document::parse(&svg).unwrap();
+        assert_eq!(svg.matches(HIGHLIGHT).count(), 2);
+        assert!(svg.contains(&format!(
+            r#"<rect x="180" y="270" width="45" height="45" fill="{}"/>"#,
+            HIGHLIGHT
+        )));
+        assert!(!svg.contains(">a</text>"));
+    }
+
+    #[test]
+    fn test_labeled_diagram_snapshot() {
+        assert_eq!(
+            Board::new().to_labeled_diagram(),
+            "  a b c d e f g h\n\
+             8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜ 8\n\
+             7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟ 7\n\
+             6 · · · · · · · · 6\n\
+             5 · · · · · · · · 5\n\
+             4 · · · · · · · · 4\n\
+             3 · · · · · · · · 3\n\
+             2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙ 2\n\
+             1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖ 1\n\
+             \x20 a b c d e f g h\n"
+        );
+    }
+
+    #[test]
+    fn test_diagram() {
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/4P3/4K2R w K - 0 1");
+
+        assert_eq!(
+            board.to_diagram(),
+            "8 · · · · ♚ · · ·\n\
+             7 · · · · · · · ·\n\
+             6 · · · · · · · ·\n\
+             5 · · · · · · · ·\n\
+             4 · · · · · · · ·\n\
+             3 · · · · · · · ·\n\
+             2 · · · · ♙ · · ·\n\
+             1 · · · · ♔ · · ♖\n\
+             \x20 a b c d e f g h\n"
+        );
+    }
+}
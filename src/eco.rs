@@ -0,0 +1,252 @@
+//! Opening names by ECO code (Encyclopaedia of Chess Openings), from a small table of
+//! the best-known lines.
+//!
+//! The table follows the codes and names of the public domain ECO lists, such as the
+//! one Lichess publishes, cut down to the main lines and a few famous byways. Moves
+//! are in SAN without check signs, from the standard starting position.
+
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::san::to_san;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// An ECO code: a volume from A to E and a number from 00 to 99, e.g. `B20`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EcoCode {
+    volume: u8,
+    number: u8,
+}
+
+impl EcoCode {
+    /// The volume letter, `A` to `E`.
+    pub fn volume(&self) -> char {
+        self.volume as char
+    }
+
+    /// The number within the volume, 0 to 99.
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+}
+
+impl FromStr for EcoCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match *s.as_bytes() {
+            [volume @ b'A'..=b'E', tens @ b'0'..=b'9', ones @ b'0'..=b'9'] => Ok(EcoCode {
+                volume,
+                number: (tens - b'0') * 10 + (ones - b'0'),
+            }),
+            _ => Err(format!("not an ECO code: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for EcoCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:02}", self.volume(), self.number)
+    }
+}
+
+/// Code, name and moves of every opening known.
+#[rustfmt::skip]
+const OPENINGS: [(&str, &str, &str); 108] = [
+    ("A00", "Polish Opening", "b4"),
+    ("A00", "Grob Opening", "g4"),
+    ("A00", "Van't Kruijs Opening", "e3"),
+    ("A00", "Hungarian Opening", "g3"),
+    ("A00", "Mieses Opening", "d3"),
+    ("A00", "Saragossa Opening", "c3"),
+    ("A00", "Amar Opening", "Nh3"),
+    ("A00", "Durkin Opening", "Na3"),
+    ("A00", "Dunst Opening", "Nc3"),
+    ("A00", "Barnes Opening", "f3"),
+    ("A00", "Barnes Opening: Fool's Mate", "f3 e5 g4 Qh4"),
+    ("A01", "Nimzo-Larsen Attack", "b3"),
+    ("A02", "Bird Opening", "f4"),
+    ("A03", "Bird Opening: Dutch Variation", "f4 d5"),
+    ("A04", "Zukertort Opening", "Nf3"),
+    ("A07", "King's Indian Attack", "Nf3 d5 g3"),
+    ("A10", "English Opening", "c4"),
+    ("A13", "English Opening: Agincourt Defence", "c4 e6"),
+    ("A15", "English Opening: Anglo-Indian Defence", "c4 Nf6"),
+    ("A20", "English Opening: King's English Variation", "c4 e5"),
+    ("A30", "English Opening: Symmetrical Variation", "c4 c5"),
+    ("A40", "Queen's Pawn Game", "d4"),
+    ("A43", "Old Benoni Defence", "d4 c5"),
+    ("A45", "Indian Defence", "d4 Nf6"),
+    ("A45", "Trompowsky Attack", "d4 Nf6 Bg5"),
+    ("A50", "Indian Defence", "d4 Nf6 c4"),
+    ("A51", "Budapest Defence", "d4 Nf6 c4 e5"),
+    ("A56", "Benoni Defence", "d4 Nf6 c4 c5"),
+    ("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+    ("A60", "Modern Benoni", "d4 Nf6 c4 c5 d5 e6"),
+    ("A80", "Dutch Defence", "d4 f5"),
+    ("B00", "King's Pawn Game", "e4"),
+    ("B00", "Owen Defence", "e4 b6"),
+    ("B00", "Nimzowitsch Defence", "e4 Nc6"),
+    ("B00", "St. George Defence", "e4 a6"),
+    ("B01", "Scandinavian Defence", "e4 d5"),
+    ("B01", "Scandinavian Defence: Main Line", "e4 d5 exd5 Qxd5 Nc3 Qa5"),
+    ("B01", "Scandinavian Defence: Modern Variation", "e4 d5 exd5 Nf6"),
+    ("B02", "Alekhine Defence", "e4 Nf6"),
+    ("B06", "Modern Defence", "e4 g6"),
+    ("B07", "Pirc Defence", "e4 d6 d4 Nf6 Nc3 g6"),
+    ("B10", "Caro-Kann Defence", "e4 c6"),
+    ("B12", "Caro-Kann Defence: Advance Variation", "e4 c6 d4 d5 e5"),
+    ("B13", "Caro-Kann Defence: Exchange Variation", "e4 c6 d4 d5 exd5 cxd5"),
+    ("B15", "Caro-Kann Defence: Main Line", "e4 c6 d4 d5 Nc3 dxe4 Nxe4"),
+    ("B18", "Caro-Kann Defence: Classical Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5"),
+    ("B20", "Sicilian Defence", "e4 c5"),
+    ("B21", "Sicilian Defence: Smith-Morra Gambit", "e4 c5 d4 cxd4 c3"),
+    ("B22", "Sicilian Defence: Alapin Variation", "e4 c5 c3"),
+    ("B23", "Sicilian Defence: Closed", "e4 c5 Nc3"),
+    ("B27", "Sicilian Defence", "e4 c5 Nf3"),
+    ("B30", "Sicilian Defence: Old Sicilian", "e4 c5 Nf3 Nc6"),
+    ("B32", "Sicilian Defence: Open", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4"),
+    ("B33", "Sicilian Defence: Sveshnikov Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5"),
+    ("B40", "Sicilian Defence: French Variation", "e4 c5 Nf3 e6"),
+    ("B50", "Sicilian Defence: Modern Variations", "e4 c5 Nf3 d6"),
+    ("B54", "Sicilian Defence: Open", "e4 c5 Nf3 d6 d4 cxd4 Nxd4"),
+    ("B56", "Sicilian Defence: Classical Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6"),
+    ("B70", "Sicilian Defence: Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6"),
+    ("B80", "Sicilian Defence: Scheveningen Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6"),
+    ("B90", "Sicilian Defence: Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+    ("C00", "French Defence", "e4 e6"),
+    ("C01", "French Defence: Exchange Variation", "e4 e6 d4 d5 exd5"),
+    ("C02", "French Defence: Advance Variation", "e4 e6 d4 d5 e5"),
+    ("C03", "French Defence: Tarrasch Variation", "e4 e6 d4 d5 Nd2"),
+    ("C10", "French Defence: Paulsen Variation", "e4 e6 d4 d5 Nc3"),
+    ("C11", "French Defence: Classical Variation", "e4 e6 d4 d5 Nc3 Nf6"),
+    ("C15", "French Defence: Winawer Variation", "e4 e6 d4 d5 Nc3 Bb4"),
+    ("C20", "King's Pawn Game", "e4 e5"),
+    ("C21", "Centre Game", "e4 e5 d4 exd4"),
+    ("C23", "Bishop's Opening", "e4 e5 Bc4"),
+    ("C25", "Vienna Game", "e4 e5 Nc3"),
+    ("C30", "King's Gambit", "e4 e5 f4"),
+    ("C33", "King's Gambit Accepted", "e4 e5 f4 exf4"),
+    ("C40", "King's Knight Opening", "e4 e5 Nf3"),
+    ("C41", "Philidor Defence", "e4 e5 Nf3 d6"),
+    ("C42", "Petrov's Defence", "e4 e5 Nf3 Nf6"),
+    ("C44", "King's Knight Opening: Normal Variation", "e4 e5 Nf3 Nc6"),
+    ("C44", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    ("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+    ("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    ("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    ("C50", "Italian Game: Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+    ("C51", "Italian Game: Evans Gambit", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4"),
+    ("C55", "Italian Game: Two Knights Defence", "e4 e5 Nf3 Nc6 Bc4 Nf6"),
+    ("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    ("C65", "Ruy Lopez: Berlin Defence", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    ("C68", "Ruy Lopez: Exchange Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6"),
+    ("C70", "Ruy Lopez: Morphy Defence", "e4 e5 Nf3 Nc6 Bb5 a6"),
+    ("C80", "Ruy Lopez: Open", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4"),
+    ("C84", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7"),
+    ("D00", "Queen's Pawn Game", "d4 d5"),
+    ("D00", "London System", "d4 d5 Bf4"),
+    ("D06", "Queen's Gambit", "d4 d5 c4"),
+    ("D07", "Queen's Gambit Declined: Chigorin Defence", "d4 d5 c4 Nc6"),
+    ("D08", "Queen's Gambit Declined: Albin Countergambit", "d4 d5 c4 e5"),
+    ("D10", "Slav Defence", "d4 d5 c4 c6"),
+    ("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    ("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+    ("D43", "Semi-Slav Defence", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6"),
+    ("D80", "Grünfeld Defence", "d4 Nf6 c4 g6 Nc3 d5"),
+    ("D85", "Grünfeld Defence: Exchange Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5"),
+    ("E00", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+    ("E11", "Bogo-Indian Defence", "d4 Nf6 c4 e6 Nf3 Bb4"),
+    ("E12", "Queen's Indian Defence", "d4 Nf6 c4 e6 Nf3 b6"),
+    ("E20", "Nimzo-Indian Defence", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    ("E60", "King's Indian Defence", "d4 Nf6 c4 g6"),
+    ("E61", "King's Indian Defence", "d4 Nf6 c4 g6 Nc3 Bg7"),
+];
+
+/// The deepest opening in the table whose moves begin `moves`, played from the
+/// standard starting position.
+pub fn classify(moves: &[Move]) -> Option<(EcoCode, &'static str)> {
+    let longest = OPENINGS
+        .iter()
+        .map(|(_, _, line)| line.split_whitespace().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut board = Board::new();
+    let mut played = Vec::new();
+    for m in moves.iter().take(longest) {
+        played.push(to_san(&board, m).trim_end_matches(['+', '#']).to_string());
+        board.make_move_unchecked(m);
+    }
+
+    OPENINGS
+        .iter()
+        .filter(|(_, _, line)| {
+            let line: Vec<&str> = line.split_whitespace().collect();
+            line.len() <= played.len() && line.iter().zip(&played).all(|(a, b)| a == b)
+        })
+        .max_by_key(|(_, _, line)| line.split_whitespace().count())
+        .map(|&(code, name, _)| (code.parse().expect("the table holds valid codes"), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::san::parse_san;
+
+    fn san_moves(line: &str) -> Vec<Move> {
+        let mut board = Board::new();
+        line.split_whitespace()
+            .map(|san| {
+                let m = parse_san(&board, san).unwrap_or_else(|err| panic!("{}: {}", line, err));
+                board.make_move_unchecked(&m);
+                m
+            })
+            .collect()
+    }
+
+    fn named(line: &str) -> Option<(String, &'static str)> {
+        classify(&san_moves(line)).map(|(code, name)| (code.to_string(), name))
+    }
+
+    #[test]
+    fn test_every_table_line_is_legal_and_finds_itself() {
+        for (code, name, line) in OPENINGS {
+            assert!(code.parse::<EcoCode>().is_ok(), "{}", code);
+            assert_eq!(named(line), Some((code.to_string(), name)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_deepest_matching_opening() {
+        assert_eq!(
+            named("e4 c5"),
+            Some(("B20".to_string(), "Sicilian Defence"))
+        );
+        assert_eq!(
+            named("d4 d5 c4"),
+            Some(("D06".to_string(), "Queen's Gambit"))
+        );
+        // Past the end of the table line, and a line that leaves it early
+        assert_eq!(
+            named("d4 d5 c4 e6 Nc3 Nf6 Bg5"),
+            Some(("D30".to_string(), "Queen's Gambit Declined"))
+        );
+        assert_eq!(
+            named("e4 e5 Nf3 Nc6 Bb5 a5"),
+            Some(("C60".to_string(), "Ruy Lopez"))
+        );
+        assert_eq!(named(""), None);
+        assert_eq!(named("a4"), None);
+    }
+
+    #[test]
+    fn test_eco_code_parses_and_prints() {
+        let code: EcoCode = "B20".parse().unwrap();
+        assert_eq!((code.volume(), code.number()), ('B', 20));
+        assert_eq!(code.to_string(), "B20");
+        assert!("F00".parse::<EcoCode>().is_err());
+        assert!("B2".parse::<EcoCode>().is_err());
+    }
+}
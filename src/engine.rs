@@ -0,0 +1,828 @@
+//! Interchangeable move-selection strategies.
+//!
+//! Front-ends (the command line, UCI and matches between engines) only talk to
+//! [`Engine`], so a new strategy needs one impl and a name in [`engine_by_name`].
+
+use crate::board::{Board, Color};
+use crate::chess_move::Move;
+use crate::game::{Game, GameResult, Termination};
+use crate::notation::{parse_any, NotationError};
+use crate::pgn::ResultTag;
+use crate::search::{
+    search_with_context, SearchContext, SearchOptions, SearchReporter, SearchStats,
+};
+use crate::tt::TranspositionTable;
+use crate::utils::XorShift64;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Names accepted by [`engine_by_name`].
+pub const ENGINE_NAMES: [&str; 3] = ["alphabeta", "greedy", "random"];
+
+/// How much thinking one move may take. Engines that do not search ignore it.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    /// Deepest iteration, in plies. `None` leaves it to the engine's settings.
+    pub depth: Option<u32>,
+    /// Stop thinking after this long.
+    pub move_time: Option<Duration>,
+    /// The move time is a share of a clock, so forced and obvious moves may be
+    /// played sooner; see [`SearchOptions::easy_moves`].
+    pub easy_moves: bool,
+    /// Look only for a forced mate in at most this many moves.
+    pub mate: Option<u32>,
+    /// Consider only these moves, see [`parse_root_moves`].
+    pub root_moves: Option<Vec<Move>>,
+    /// Stops thinking as soon as another thread sets it.
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+pub trait Engine {
+    fn name(&self) -> &str;
+
+    /// Forgets what was learned in the previous game.
+    fn new_game(&mut self);
+
+    /// The move to play in `game`'s current position, `None` if there is none.
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Option<Move>;
+
+    /// Seeds the engine's random choices for the next game, so that a match can make
+    /// its games differ and still replay them. Engines without any ignore it.
+    fn seed_game(&mut self, _seed: u64) {}
+
+    /// Takes the user's search settings, such as the table size or thread count.
+    /// Engines that do not search ignore them.
+    fn configure(&mut self, _options: &SearchOptions) {}
+
+    /// The score of the position the engine last chose a move in, in centipawns for
+    /// the side it moved for. `None` for engines that do not evaluate.
+    fn last_score(&self) -> Option<i32> {
+        None
+    }
+
+    /// The depth of the search behind [`Engine::last_score`], in plies. `None` for
+    /// engines that do not search.
+    fn last_depth(&self) -> Option<u32> {
+        None
+    }
+
+    /// The line the engine expected after the move it last chose, starting with that
+    /// move. Empty before the first search, if there was no move to choose, or for
+    /// engines that do not look ahead.
+    fn last_pv(&self) -> &[Move] {
+        &[]
+    }
+
+    /// What the search behind [`Engine::last_score`] cost. `None` for engines that
+    /// do not search.
+    fn last_stats(&self) -> Option<SearchStats> {
+        None
+    }
+}
+
+/// Reads `tokens` as the root moves to restrict a search to, in any notation
+/// [`parse_any`] knows. Fails on the first token that is not a legal move in
+/// `board`, naming it.
+pub fn parse_root_moves(board: &Board, tokens: &[&str]) -> Result<Vec<Move>, NotationError> {
+    tokens.iter().map(|token| parse_any(board, token)).collect()
+}
+
+/// The engine called `name`, searching with `options` if it searches at all.
+pub fn engine_by_name(name: &str, options: &SearchOptions) -> Option<Box<dyn Engine + Send>> {
+    match name {
+        "alphabeta" => Some(Box::new(AlphaBetaEngine::new(options.clone()))),
+        "greedy" => Some(Box::new(GreedyEngine)),
+        "random" => Some(Box::new(RandomEngine::new(0x9e37_79b9_7f4a_7c15))),
+        _ => None,
+    }
+}
+
+/// Sets a stop flag once the move time is up, unless dropped first: the flag can be
+/// shared with later searches, which a search that ended early must not stop.
+struct StopTimer {
+    done: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl StopTimer {
+    fn start(stop: Arc<AtomicBool>, move_time: Duration) -> StopTimer {
+        let done = Arc::new((Mutex::new(false), Condvar::new()));
+        let timer_done = Arc::clone(&done);
+        thread::spawn(move || {
+            let (lock, wakeup) = &*timer_done;
+            let guard = lock.lock().unwrap();
+            let (done, _) = wakeup
+                .wait_timeout_while(guard, move_time, |done| !*done)
+                .unwrap();
+            // Still holding the lock, so the search cannot end in between
+            if !*done {
+                stop.store(true, Ordering::Relaxed);
+            }
+        });
+        StopTimer { done }
+    }
+}
+
+impl Drop for StopTimer {
+    fn drop(&mut self) {
+        let (lock, wakeup) = &*self.done;
+        *lock.lock().unwrap() = true;
+        wakeup.notify_one();
+    }
+}
+
+/// The alpha-beta searcher of [`crate::search`], keeping its transposition table
+/// and move tables from one move to the next.
+pub struct AlphaBetaEngine {
+    options: SearchOptions,
+    tt: Arc<TranspositionTable>,
+    context: SearchContext,
+    /// Gives every move of a weakened search its own seed.
+    rng: XorShift64,
+    reporter: Option<Box<dyn SearchReporter + Send>>,
+    last_score: Option<i32>,
+    last_depth: Option<u32>,
+    last_pv: Vec<Move>,
+    last_stats: Option<SearchStats>,
+}
+
+impl AlphaBetaEngine {
+    pub fn new(options: SearchOptions) -> AlphaBetaEngine {
+        AlphaBetaEngine {
+            tt: Arc::new(TranspositionTable::new(options.hash_mb)),
+            context: SearchContext::new(),
+            rng: XorShift64::new(options.skill_seed),
+            options,
+            reporter: None,
+            last_score: None,
+            last_depth: None,
+            last_pv: Vec::new(),
+            last_stats: None,
+        }
+    }
+
+    /// Tells `reporter` how every search goes.
+    pub fn with_reporter(mut self, reporter: Box<dyn SearchReporter + Send>) -> AlphaBetaEngine {
+        self.reporter = Some(reporter);
+        self
+    }
+}
+
+impl Engine for AlphaBetaEngine {
+    fn name(&self) -> &str {
+        "alphabeta"
+    }
+
+    fn new_game(&mut self) {
+        self.tt.clear();
+        self.context.clear();
+        self.last_score = None;
+        self.last_depth = None;
+        self.last_pv.clear();
+        self.last_stats = None;
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Option<Move> {
+        let options = SearchOptions {
+            depth: limits.depth.unwrap_or(self.options.depth),
+            mate: limits.mate.or(self.options.mate),
+            root_moves: limits
+                .root_moves
+                .clone()
+                .or_else(|| self.options.root_moves.clone()),
+            skill_seed: self.rng.next_u64(),
+            easy_moves: limits.easy_moves || self.options.easy_moves,
+            ..self.options.clone()
+        };
+        let stop = limits.stop.clone().unwrap_or_default();
+        let _timer = limits
+            .move_time
+            .map(|move_time| StopTimer::start(Arc::clone(&stop), move_time));
+
+        let reporter: &mut dyn SearchReporter = match self.reporter.as_deref_mut() {
+            Some(reporter) => reporter,
+            None => &mut (),
+        };
+        let result = search_with_context(
+            game.board(),
+            &options,
+            &stop,
+            &self.tt,
+            &mut self.context,
+            reporter,
+        );
+        self.last_score = result.best_move.map(|_| result.score);
+        self.last_depth = result.best_move.map(|_| result.depth);
+        self.last_stats = result.best_move.map(|_| result.stats);
+        self.last_pv = result.pv;
+        result.best_move
+    }
+
+    /// Seeds the evaluation jitter, see
+    /// [`crate::eval::EvalParams::jitter`]. The table is cleared
+    /// if the seed changes, since its scores came from the old jitter.
+    fn seed_game(&mut self, seed: u64) {
+        if seed != self.options.eval_params.jitter_seed {
+            self.options.eval_params.jitter_seed = seed;
+            self.tt.clear();
+        }
+    }
+
+    fn configure(&mut self, options: &SearchOptions) {
+        if options.hash_mb != self.options.hash_mb {
+            self.tt = Arc::new(TranspositionTable::new(options.hash_mb));
+        }
+        if options.skill_seed != self.options.skill_seed {
+            self.rng = XorShift64::new(options.skill_seed);
+        }
+        self.options = options.clone();
+    }
+
+    fn last_score(&self) -> Option<i32> {
+        self.last_score
+    }
+
+    fn last_depth(&self) -> Option<u32> {
+        self.last_depth
+    }
+
+    fn last_pv(&self) -> &[Move] {
+        &self.last_pv
+    }
+
+    fn last_stats(&self) -> Option<SearchStats> {
+        self.last_stats
+    }
+}
+
+/// Plays a uniformly random legal move.
+pub struct RandomEngine {
+    rng: XorShift64,
+}
+
+impl RandomEngine {
+    pub fn new(seed: u64) -> RandomEngine {
+        RandomEngine {
+            rng: XorShift64::new(seed),
+        }
+    }
+}
+
+impl Engine for RandomEngine {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn new_game(&mut self) {}
+
+    fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> Option<Move> {
+        let moves = game.board().legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        moves.get((self.rng.next_u64() % moves.len() as u64) as usize)
+    }
+}
+
+/// Grabs as much material as it can this move, without looking at the reply.
+pub struct GreedyEngine;
+
+impl GreedyEngine {
+    /// The mover's material minus the opponent's, in centipawns.
+    fn material_balance(board: &Board, color: Color) -> i32 {
+        board.material_value(color) - board.material_value(!color)
+    }
+}
+
+impl Engine for GreedyEngine {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn new_game(&mut self) {}
+
+    fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> Option<Move> {
+        let board = game.board();
+        let color = board.active_color;
+        let mut best: Option<(i32, Move)> = None;
+
+        for m in board.legal_moves().iter() {
+            let mut after = *board;
+            after.make_move_unchecked(&m);
+            let score = GreedyEngine::material_balance(&after, color);
+            // The first of equally good moves, so the choice is repeatable
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, m));
+            }
+        }
+        best.map(|(_, m)| m)
+    }
+}
+
+/// How a match is played and when its games are adjudicated, which saves playing out
+/// games that are already decided. Scores are the engines' own, see
+/// [`Engine::last_score`]; a move without one interrupts every streak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchOptions {
+    pub games: u32,
+    /// Games still going after this many plies are drawn by adjudication.
+    pub max_plies: usize,
+    /// A side wins once both engines have scored it at least this many centipawns
+    /// ahead for `win_moves` moves each in a row.
+    pub win_score: i32,
+    /// 0 never adjudicates a win.
+    pub win_moves: u32,
+    /// A game is drawn once both engines have scored it within this many centipawns
+    /// of 0.00 for `draw_moves` moves each in a row, after move `draw_after_move`.
+    pub draw_score: i32,
+    /// 0 never adjudicates a draw.
+    pub draw_moves: u32,
+    pub draw_after_move: u32,
+    /// The engines of each game are seeded from this and the game's number, see
+    /// [`Engine::seed_game`], so a match can be replayed.
+    pub seed: u64,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            games: 2,
+            max_plies: 300,
+            win_score: 1000,
+            win_moves: 3,
+            draw_score: 10,
+            draw_moves: 8,
+            draw_after_move: 40,
+            seed: 0,
+        }
+    }
+}
+
+/// Follows the engines' scores through a game to see when to adjudicate it.
+#[derive(Debug, Default)]
+struct Adjudicator {
+    /// The side both engines have seen winning, and for how many plies in a row.
+    winning: Option<(Color, u32)>,
+    /// How many plies in a row both engines have seen as level.
+    level_plies: u32,
+}
+
+impl Adjudicator {
+    /// Takes the score the engine that just moved gave, from `mover`'s side, and
+    /// returns the adjudicated result if there is one now.
+    fn record(
+        &mut self,
+        options: &MatchOptions,
+        board: &Board,
+        mover: Color,
+        score: Option<i32>,
+    ) -> Option<GameResult> {
+        let score = match score {
+            Some(score) => score,
+            None => {
+                *self = Adjudicator::default();
+                return None;
+            }
+        };
+
+        let ahead = if score >= options.win_score {
+            Some(mover)
+        } else if score <= -options.win_score {
+            Some(!mover)
+        } else {
+            None
+        };
+        self.winning = match (ahead, self.winning) {
+            (Some(side), Some((leader, plies))) if side == leader => Some((side, plies + 1)),
+            (Some(side), _) => Some((side, 1)),
+            (None, _) => None,
+        };
+        self.level_plies = if score.abs() <= options.draw_score {
+            self.level_plies + 1
+        } else {
+            0
+        };
+
+        match self.winning {
+            Some((leader, plies)) if options.win_moves > 0 && plies >= 2 * options.win_moves => {
+                return Some(GameResult::win(leader, Termination::Adjudication));
+            }
+            _ => {}
+        }
+        if options.draw_moves > 0
+            && self.level_plies >= 2 * options.draw_moves
+            && board.fullmove_number > options.draw_after_move
+        {
+            return Some(GameResult::draw(Termination::Adjudication));
+        }
+        None
+    }
+}
+
+/// Plays a game from `start` between `white` and `black` until the rules end it, a
+/// draw can be claimed, `options` adjudicate it, or it reaches `options.max_plies`
+/// and is drawn.
+pub fn play_game(
+    white: &mut dyn Engine,
+    black: &mut dyn Engine,
+    start: Game,
+    limits: &SearchLimits,
+    options: &MatchOptions,
+) -> Game {
+    let mut game = start;
+    let mut adjudicator = Adjudicator::default();
+    white.new_game();
+    black.new_game();
+
+    for _ in 0..options.max_plies {
+        // Engines claim every draw they may, as a player would have to
+        game.claim_draw();
+        if game.result().is_some() {
+            return game;
+        }
+        let mover = game.board().active_color;
+        let engine: &mut dyn Engine = match mover {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+        let m = match engine.choose_move(&game, limits) {
+            Some(m) => m,
+            None => return game,
+        };
+        let score = engine.last_score();
+        game.play(m).expect("engines only choose legal moves");
+        if let Some(result) = adjudicator.record(options, game.board(), mover, score) {
+            if game.result().is_none() {
+                game.set_result(result);
+            }
+        }
+    }
+    game.claim_draw();
+    if game.result().is_none() {
+        game.set_result(GameResult::draw(Termination::Adjudication));
+    }
+    game
+}
+
+/// Points scored by the first engine of a match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Games that ended without a result, when an engine had no move to offer.
+    pub unfinished: u32,
+}
+
+/// Plays `options.games` games between `first` and `second` from the starting
+/// position, swapping colors after every game.
+pub fn play_match(
+    first: &mut dyn Engine,
+    second: &mut dyn Engine,
+    limits: &SearchLimits,
+    options: &MatchOptions,
+) -> MatchScore {
+    let mut score = MatchScore::default();
+    for idx in 0..options.games {
+        let first_is_white = idx % 2 == 0;
+        let seed = XorShift64::new(options.seed ^ u64::from(idx + 1)).next_u64();
+        first.seed_game(seed);
+        second.seed_game(seed);
+        let game = if first_is_white {
+            play_game(first, second, Game::new(), limits, options)
+        } else {
+            play_game(second, first, Game::new(), limits, options)
+        };
+        let result = game
+            .result()
+            .map_or(ResultTag::Unknown, |result| result.tag());
+        match (result, first_is_white) {
+            (ResultTag::WhiteWins, true) | (ResultTag::BlackWins, false) => score.wins += 1,
+            (ResultTag::WhiteWins, false) | (ResultTag::BlackWins, true) => score.losses += 1,
+            (ResultTag::Draw, _) => score.draws += 1,
+            (ResultTag::Unknown, _) => score.unfinished += 1,
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White wins the queen with a knight fork, which takes looking two moves ahead.
+    const FORK: &str = "q3k3/8/8/1N6/8/8/8/4K3 w - - 0 1";
+
+    fn options() -> SearchOptions {
+        SearchOptions {
+            depth: 4,
+            hash_mb: 1,
+            ..SearchOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_engines_on_a_tactic() {
+        let game = Game::from_fen(FORK).unwrap();
+        let limits = SearchLimits::default();
+
+        for name in ENGINE_NAMES {
+            let mut engine = engine_by_name(name, &options()).unwrap();
+            assert_eq!(engine.name(), name);
+            let m = engine.choose_move(&game, &limits).unwrap();
+            assert!(game.board().legal_moves().contains(&m), "{}: {}", name, m);
+            if name == "alphabeta" {
+                assert_eq!(m.to_string(), "b5c7", "the fork");
+                assert!(engine.last_stats().unwrap().nodes > 0);
+            } else {
+                assert_eq!(engine.last_stats(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_lasts_the_game_and_new_game_clears_it() {
+        let mut engine = AlphaBetaEngine::new(options());
+        let mut game = Game::from_fen(FORK).unwrap();
+        let key = |game: &Game| crate::book::polyglot_key(game.board());
+
+        let m = engine.choose_move(&game, &SearchLimits::default()).unwrap();
+        let root = key(&game);
+        game.play(m).unwrap();
+        engine.choose_move(&game, &SearchLimits::default()).unwrap();
+        assert!(engine.tt.probe(root).is_some());
+        assert!(engine.tt.probe(key(&game)).is_some());
+
+        engine.new_game();
+        assert_eq!(engine.tt.probe(root), None);
+        assert_eq!(engine.tt.probe(key(&game)), None);
+        assert_eq!(engine.tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_a_finished_search_does_not_stop_the_next_one() {
+        let mut engine = AlphaBetaEngine::new(options());
+        let game = Game::from_fen(FORK).unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let quick = SearchLimits {
+            depth: Some(1),
+            move_time: Some(Duration::from_millis(100)),
+            stop: Some(Arc::clone(&stop)),
+            ..SearchLimits::default()
+        };
+        engine.choose_move(&game, &quick).unwrap();
+
+        // The first search's move time runs out while the second thinks
+        let long = SearchLimits {
+            depth: Some(crate::search::MAX_DEPTH),
+            move_time: Some(Duration::from_millis(400)),
+            ..quick
+        };
+        let started = std::time::Instant::now();
+        engine.choose_move(&game, &long).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(400));
+        assert!(engine.last_depth().unwrap() > 1);
+    }
+
+    #[test]
+    fn test_move_tables_last_the_game_and_new_game_clears_them() {
+        let mut engine = AlphaBetaEngine::new(options());
+        let mut game = Game::new();
+        let (countermoves, first) = {
+            let m = engine.choose_move(&game, &SearchLimits::default()).unwrap();
+            game.play(m).unwrap();
+            engine.context.learned()
+        };
+        assert!(countermoves > 0);
+        assert!(first.iter().any(|&history| history > 0));
+
+        // The second search builds on what the first learned
+        engine.choose_move(&game, &SearchLimits::default()).unwrap();
+        let (_, second) = engine.context.learned();
+        assert!(first.iter().zip(&second).all(|(a, b)| b >= a));
+        assert_ne!(first, second);
+
+        engine.new_game();
+        let (countermoves, history) = engine.context.learned();
+        assert_eq!(countermoves, 0);
+        assert!(history.iter().all(|&history| history == 0));
+    }
+
+    #[test]
+    fn test_jitter_seeds_make_self_play_games_differ() {
+        let self_play = |jitter: i32, seed: u64| {
+            let mut options = SearchOptions {
+                depth: 2,
+                ..options()
+            };
+            options.eval_params.jitter = jitter;
+            let (mut white, mut black) = (
+                AlphaBetaEngine::new(options.clone()),
+                AlphaBetaEngine::new(options),
+            );
+            white.seed_game(seed);
+            black.seed_game(seed);
+            let match_options = MatchOptions {
+                max_plies: 10,
+                ..MatchOptions::default()
+            };
+            let game = play_game(
+                &mut white,
+                &mut black,
+                Game::new(),
+                &SearchLimits::default(),
+                &match_options,
+            );
+            game.moves().to_vec()
+        };
+
+        assert_ne!(self_play(20, 1), self_play(20, 2));
+        assert_eq!(self_play(20, 1), self_play(20, 1));
+        assert_eq!(self_play(0, 1), self_play(0, 2));
+    }
+
+    #[test]
+    fn test_search_restricted_to_root_moves() {
+        let game = Game::from_fen(FORK).unwrap();
+        let mut engine = AlphaBetaEngine::new(options());
+
+        // Na3 hangs the knight to the queen, but it is the only move allowed
+        let limits = SearchLimits {
+            root_moves: Some(parse_root_moves(game.board(), &["Na3"]).unwrap()),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            engine.choose_move(&game, &limits).unwrap().to_string(),
+            "b5a3"
+        );
+        let score = engine.last_score().unwrap();
+        assert!(score < -700, "{}", score);
+
+        // The table the restricted search filled still finds the fork
+        let m = engine.choose_move(&game, &SearchLimits::default());
+        assert_eq!(m.unwrap().to_string(), "b5c7");
+        assert!(engine.last_score().unwrap() > score + 500);
+    }
+
+    #[test]
+    fn test_illegal_root_moves_are_named() {
+        let board = Board::fen_to_board(FORK);
+        assert_eq!(
+            parse_root_moves(&board, &["b5c7", "e1e3", "Nd6"]),
+            Err(NotationError::Illegal("e1e3".to_string()))
+        );
+        assert_eq!(
+            parse_root_moves(&board, &["b5c7", "Kd1"]),
+            Ok(vec![
+                Move::from_uci("b5c7").unwrap(),
+                Move::from_uci("e1d1").unwrap()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_greedy_engine_takes_the_biggest_piece() {
+        let game = Game::from_fen("4k3/8/8/8/8/1r1q4/2P5/4K3 w - - 0 1").unwrap();
+
+        let m = GreedyEngine.choose_move(&game, &SearchLimits::default());
+        assert_eq!(m.unwrap().to_string(), "c2d3");
+    }
+
+    /// Plays random moves and reports the next of its scripted scores, repeating the
+    /// last one when the script runs out.
+    struct ScriptedEngine {
+        moves: RandomEngine,
+        scores: Vec<Option<i32>>,
+        played: usize,
+    }
+
+    impl ScriptedEngine {
+        fn new(seed: u64, scores: &[Option<i32>]) -> ScriptedEngine {
+            ScriptedEngine {
+                moves: RandomEngine::new(seed),
+                scores: scores.to_vec(),
+                played: 0,
+            }
+        }
+    }
+
+    impl Engine for ScriptedEngine {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn new_game(&mut self) {
+            self.played = 0;
+        }
+
+        fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Option<Move> {
+            self.played += 1;
+            self.moves.choose_move(game, limits)
+        }
+
+        fn last_score(&self) -> Option<i32> {
+            let idx = (self.played - 1).min(self.scores.len() - 1);
+            self.scores[idx]
+        }
+    }
+
+    fn adjudicated_game(start: &str, white: &[Option<i32>], black: &[Option<i32>]) -> Game {
+        let options = MatchOptions {
+            max_plies: 60,
+            win_moves: 3,
+            draw_moves: 4,
+            ..MatchOptions::default()
+        };
+        play_game(
+            &mut ScriptedEngine::new(1, white),
+            &mut ScriptedEngine::new(2, black),
+            Game::from_fen(start).unwrap(),
+            &SearchLimits::default(),
+            &options,
+        )
+    }
+
+    #[test]
+    fn test_win_adjudicated_when_both_engines_agree() {
+        // Black sees itself losing from its first move, White from its second
+        let game = adjudicated_game(Board::START_FEN, &[Some(50), Some(1200)], &[Some(-1500)]);
+        assert_eq!(
+            game.result(),
+            Some(GameResult::win(Color::White, Termination::Adjudication))
+        );
+        // Three moves each, from Black's first
+        assert_eq!(game.moves().len(), 7);
+        assert!(game.to_pgn().contains("[Termination \"adjudication\"]"));
+    }
+
+    #[test]
+    fn test_no_win_adjudicated_while_the_engines_disagree() {
+        // A missing score interrupts the streak too
+        let game = adjudicated_game(Board::START_FEN, &[Some(1200)], &[Some(0)]);
+        assert_eq!(
+            game.result(),
+            Some(GameResult::draw(Termination::Adjudication))
+        );
+        assert_eq!(game.moves().len(), 60, "drawn at the ply limit");
+
+        let game = adjudicated_game(
+            Board::START_FEN,
+            &[Some(1200)],
+            &[Some(-1200), Some(-1200), None, Some(-1200)],
+        );
+        assert_eq!(
+            game.result(),
+            Some(GameResult::win(Color::White, Termination::Adjudication))
+        );
+        assert_eq!(game.moves().len(), 12);
+    }
+
+    #[test]
+    fn test_draw_adjudicated_only_after_move_40() {
+        // Level all game, but the ply limit comes before move 40
+        let game = adjudicated_game(Board::START_FEN, &[Some(0)], &[Some(5)]);
+        assert_eq!(game.moves().len(), 60);
+
+        let late = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 41";
+        let game = adjudicated_game(late, &[Some(0)], &[Some(5)]);
+        assert_eq!(
+            game.result(),
+            Some(GameResult::draw(Termination::Adjudication))
+        );
+        assert_eq!(game.moves().len(), 8);
+    }
+
+    #[test]
+    fn test_rule_draws_are_not_adjudicated() {
+        let game = adjudicated_game("k7/8/8/8/8/8/8/K7 w - - 0 1", &[Some(1200)], &[Some(-1200)]);
+        assert_eq!(
+            game.result(),
+            Some(GameResult::draw(Termination::InsufficientMaterial))
+        );
+        assert!(game.moves().is_empty());
+    }
+
+    #[test]
+    fn test_alphabeta_beats_random() {
+        let mut alphabeta = AlphaBetaEngine::new(SearchOptions {
+            depth: 2,
+            ..options()
+        });
+        let mut random = RandomEngine::new(7);
+
+        let options = MatchOptions {
+            max_plies: 200,
+            ..MatchOptions::default()
+        };
+        let score = play_match(
+            &mut alphabeta,
+            &mut random,
+            &SearchLimits::default(),
+            &options,
+        );
+        assert_eq!(score.losses, 0, "{:?}", score);
+        assert!(score.wins >= 1, "{:?}", score);
+    }
+}
@@ -0,0 +1,211 @@
+//! Every error the library returns, in one place. Each module keeps the error of its
+//! own fallible functions; this gathers them and adds [`ChessError`] for
+//! applications that would rather handle a single type.
+//!
+//! The `Display` messages are meant for players: they quote the input that was
+//! wrong and name squares as `e4`, never as indices.
+
+pub use crate::board::{FenError, PieceCharError};
+pub use crate::chess_move::MoveError;
+pub use crate::game::GameError;
+pub use crate::notation::NotationError;
+pub use crate::san::SanError;
+
+use std::fmt;
+
+/// Any error of the library. Converts from each module's error, so `?` works on
+/// all of them in a function returning `Result<_, ChessError>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChessError {
+    Fen(FenError),
+    PieceChar(PieceCharError),
+    Move(MoveError),
+    San(SanError),
+    Notation(NotationError),
+    Game(GameError),
+}
+
+/// The message of the underlying error, unchanged.
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChessError::Fen(err) => err.fmt(f),
+            ChessError::PieceChar(err) => err.fmt(f),
+            ChessError::Move(err) => err.fmt(f),
+            ChessError::San(err) => err.fmt(f),
+            ChessError::Notation(err) => err.fmt(f),
+            ChessError::Game(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ChessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChessError::Fen(err) => err.source(),
+            ChessError::PieceChar(err) => err.source(),
+            ChessError::Move(err) => err.source(),
+            ChessError::San(err) => err.source(),
+            ChessError::Notation(err) => err.source(),
+            ChessError::Game(err) => err.source(),
+        }
+    }
+}
+
+impl From<FenError> for ChessError {
+    fn from(err: FenError) -> Self {
+        ChessError::Fen(err)
+    }
+}
+
+impl From<PieceCharError> for ChessError {
+    fn from(err: PieceCharError) -> Self {
+        ChessError::PieceChar(err)
+    }
+}
+
+impl From<MoveError> for ChessError {
+    fn from(err: MoveError) -> Self {
+        ChessError::Move(err)
+    }
+}
+
+impl From<SanError> for ChessError {
+    fn from(err: SanError) -> Self {
+        ChessError::San(err)
+    }
+}
+
+impl From<NotationError> for ChessError {
+    fn from(err: NotationError) -> Self {
+        ChessError::Notation(err)
+    }
+}
+
+impl From<GameError> for ChessError {
+    fn from(err: GameError) -> Self {
+        ChessError::Game(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, PieceType, Strictness};
+    use crate::game::Game;
+    use crate::notation::parse_any;
+    use crate::pgn::parse_games;
+    use crate::san::parse_san;
+    use std::error::Error;
+
+    /// Plays `moves`, in any notation, from `fen`, with `?` on every step.
+    fn play(fen: &str, moves: &[&str]) -> Result<Game, ChessError> {
+        let mut board = Board::try_from_fen(fen, Strictness::Strict)?;
+        let mut game = Game::from_fen(fen)?;
+        for text in moves {
+            let m = parse_any(&board, text)?;
+            board.make_move(&m)?;
+            game.play(m)?;
+        }
+        PieceType::try_from_char('Q')?;
+        Ok(game)
+    }
+
+    #[test]
+    fn test_question_mark_converts_every_error() {
+        let start = Board::START_FEN;
+        assert!(play(start, &["e4", "e7e5", "Ng1-f3"]).is_ok());
+        assert!(matches!(
+            play("8/8/8 w - - 0 1", &[]),
+            Err(ChessError::Fen(FenError::Placement(_)))
+        ));
+        assert!(matches!(
+            play(start, &["e5"]),
+            Err(ChessError::Notation(NotationError::Illegal(_)))
+        ));
+
+        let pgn = &parse_games("1. e4 e5 2. Ke3 *")[0];
+        let err = ChessError::from(Game::from_pgn(pgn).unwrap_err());
+        assert!(matches!(
+            err,
+            ChessError::Game(GameError::San { ply: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_display_strings() {
+        let fen = |fen: &str| Board::try_from_fen(fen, Strictness::Strict).unwrap_err();
+        let cases: [(ChessError, &str); 8] = [
+            (
+                fen("8/8/8/8/8/8/8/8 w - - 0").into(),
+                "expected 6 fields, found 5",
+            ),
+            (
+                fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").into(),
+                "bad active color: x",
+            ),
+            (PieceCharError('Z').into(), "'Z' is not a piece"),
+            (
+                MoveError::Blocked { by: 11 }.into(),
+                "blocked by the piece on d2",
+            ),
+            (
+                MoveError::CastlingThroughCheck { square: 5, by: 61 }.into(),
+                "cannot castle through f1, attacked from f8",
+            ),
+            (
+                SanError::Ambiguous("Nd2".to_string()).into(),
+                "ambiguous SAN move: Nd2",
+            ),
+            (
+                NotationError::Malformed("hello".to_string()).into(),
+                "not a move: hello",
+            ),
+            (
+                GameError::IllegalMove {
+                    ply: 0,
+                    uci: "e2e5".to_string(),
+                    reason: MoveError::WrongPattern {
+                        peice: PieceType::Pawn,
+                    },
+                }
+                .into(),
+                "illegal move e2e5 at ply 1: a pawn does not move like that",
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_san_names_the_rule_a_pinned_piece_breaks() {
+        let board = Board::fen_to_board("4k3/8/8/b7/8/8/3N4/4K3 w - - 0 1");
+        let err = parse_san(&board, "Nf3").unwrap_err();
+        assert_eq!(
+            err,
+            SanError::Illegal {
+                san: "Nf3".to_string(),
+                reason: MoveError::LeavesKingInCheck { by: 32 },
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "illegal move Nf3: the king would be in check from a5"
+        );
+
+        let err = ChessError::from(err);
+        assert_eq!(
+            err.source().map(|source| source.to_string()),
+            Some("the king would be in check from a5".to_string())
+        );
+        assert_eq!(
+            parse_any(&board, "Nf3"),
+            Err(NotationError::Illegal("Nf3".to_string()))
+        );
+        assert_eq!(
+            parse_san(&board, "Nd4"),
+            Err(SanError::NoMatchingMove("Nd4".to_string()))
+        );
+    }
+}
@@ -0,0 +1,863 @@
+//! Static evaluation.
+//!
+//! Every term is scored twice, once for the middlegame and once for the endgame, and
+//! the two are blended by the game phase so that e.g. the king hides in the corner
+//! while there is material on the board but walks to the centre once it is gone.
+
+use crate::board::{Board, Color, PieceType};
+use crate::book::polyglot_key;
+use crate::utils::XorShift64;
+
+use std::fmt;
+
+/// Phase of a position with all the starting non-pawn material on the board.
+pub const MAX_PHASE: i32 = 24;
+
+const FILE_A: u64 = 0x0101010101010101;
+const RANK_2: u64 = 0x000000000000FF00;
+const RANK_7: u64 = 0x00FF000000000000;
+
+/// How much each piece type counts towards the phase, indexed by `PieceType`.
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+
+/// Evaluation weights, so they can be adjusted without recompiling.
+///
+/// Per-piece arrays are indexed by `PieceType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalParams {
+    pub mg_piece_values: [i32; 6],
+    pub eg_piece_values: [i32; 6],
+    /// Piece-square table entries are multiplied by this percentage.
+    pub pst_scale: i32,
+    /// Bonus per square a piece can move to that is not guarded by an enemy pawn.
+    pub mg_mobility: [i32; 6],
+    pub eg_mobility: [i32; 6],
+    pub mg_bishop_pair: i32,
+    pub eg_bishop_pair: i32,
+    /// Rook on a file with no pawns of either color.
+    pub mg_rook_open_file: i32,
+    pub eg_rook_open_file: i32,
+    /// Rook on a file with enemy pawns but none of its own.
+    pub mg_rook_semi_open_file: i32,
+    pub eg_rook_semi_open_file: i32,
+    /// Rook on the opponent's second rank.
+    pub mg_rook_on_seventh: i32,
+    pub eg_rook_on_seventh: i32,
+    /// Most centipawns added to or taken from the evaluation of a position in the
+    /// first `jitter_plies` plies of a game, so that self-play games differ. The
+    /// amount depends only on the position and `jitter_seed`. 0 turns it off.
+    pub jitter: i32,
+    pub jitter_plies: u32,
+    pub jitter_seed: u64,
+}
+
+impl EvalParams {
+    pub const DEFAULT: EvalParams = EvalParams {
+        mg_piece_values: [82, 337, 365, 477, 1025, 0],
+        eg_piece_values: [94, 281, 297, 512, 936, 0],
+        pst_scale: 100,
+        mg_mobility: [0, 4, 5, 2, 1, 0],
+        eg_mobility: [0, 4, 5, 4, 2, 0],
+        mg_bishop_pair: 30,
+        eg_bishop_pair: 50,
+        mg_rook_open_file: 25,
+        eg_rook_open_file: 10,
+        mg_rook_semi_open_file: 12,
+        eg_rook_semi_open_file: 6,
+        mg_rook_on_seventh: 20,
+        eg_rook_on_seventh: 30,
+        jitter: 0,
+        jitter_plies: 16,
+        jitter_seed: 0,
+    };
+}
+
+/// Names a single weight in [`EvalParams`], e.g. for the tuner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+    MgPieceValue(PieceType),
+    EgPieceValue(PieceType),
+    PstScale,
+    MgMobility(PieceType),
+    EgMobility(PieceType),
+    MgBishopPair,
+    EgBishopPair,
+    MgRookOpenFile,
+    EgRookOpenFile,
+    MgRookSemiOpenFile,
+    EgRookSemiOpenFile,
+    MgRookOnSeventh,
+    EgRookOnSeventh,
+}
+
+impl EvalParams {
+    pub fn weight_mut(&mut self, weight: Weight) -> &mut i32 {
+        match weight {
+            Weight::MgPieceValue(piece) => &mut self.mg_piece_values[piece as usize],
+            Weight::EgPieceValue(piece) => &mut self.eg_piece_values[piece as usize],
+            Weight::PstScale => &mut self.pst_scale,
+            Weight::MgMobility(piece) => &mut self.mg_mobility[piece as usize],
+            Weight::EgMobility(piece) => &mut self.eg_mobility[piece as usize],
+            Weight::MgBishopPair => &mut self.mg_bishop_pair,
+            Weight::EgBishopPair => &mut self.eg_bishop_pair,
+            Weight::MgRookOpenFile => &mut self.mg_rook_open_file,
+            Weight::EgRookOpenFile => &mut self.eg_rook_open_file,
+            Weight::MgRookSemiOpenFile => &mut self.mg_rook_semi_open_file,
+            Weight::EgRookSemiOpenFile => &mut self.eg_rook_semi_open_file,
+            Weight::MgRookOnSeventh => &mut self.mg_rook_on_seventh,
+            Weight::EgRookOnSeventh => &mut self.eg_rook_on_seventh,
+        }
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams::DEFAULT
+    }
+}
+
+// Piece-square tables from White's point of view, laid out as the board is drawn:
+// the first row is rank 8 and the last is rank 1.
+#[rustfmt::skip]
+const MG_PAWN_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_PAWN_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const MG_KING_TABLE: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const EG_KING_TABLE: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// Middlegame and endgame tables, indexed by `PieceType`.
+const MG_TABLES: [&[i32; 64]; 6] = [
+    &MG_PAWN_TABLE,
+    &KNIGHT_TABLE,
+    &BISHOP_TABLE,
+    &ROOK_TABLE,
+    &QUEEN_TABLE,
+    &MG_KING_TABLE,
+];
+const EG_TABLES: [&[i32; 64]; 6] = [
+    &EG_PAWN_TABLE,
+    &KNIGHT_TABLE,
+    &BISHOP_TABLE,
+    &ROOK_TABLE,
+    &QUEEN_TABLE,
+    &EG_KING_TABLE,
+];
+
+/// Game phase from the remaining non-pawn material, from 0 (pawns and kings only)
+/// up to [`MAX_PHASE`] (all pieces on the board). Promotions cannot push it higher.
+pub fn game_phase(board: &Board) -> i32 {
+    board.eval_terms().phase.min(MAX_PHASE)
+}
+
+/// The parts of the evaluation that only depend on where each piece stands: each
+/// side's piece-square table sum, unscaled, and the game phase before capping at
+/// [`MAX_PHASE`]. [`Board`] keeps them up to date as moves are made and unmade, so
+/// evaluation need not add them up at every leaf.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IncrementalTerms {
+    /// Indexed by `Color`.
+    pub pst: [TaperedScore; 2],
+    pub phase: i32,
+}
+
+impl IncrementalTerms {
+    /// The terms added up from scratch for the pieces on `bitboards`, in
+    /// [`Board::bitboards`] order.
+    pub fn from_bitboards(bitboards: &[u64; 12]) -> IncrementalTerms {
+        let mut terms = IncrementalTerms::default();
+        for (idx, &bitboard) in bitboards.iter().enumerate() {
+            let mut pieces = bitboard;
+            while pieces != 0 {
+                terms.add(idx, pieces.trailing_zeros() as u8);
+                pieces &= pieces - 1;
+            }
+        }
+        terms
+    }
+
+    /// Counts the piece of bitboard `idx` standing on `square`.
+    #[inline]
+    pub(crate) fn add(&mut self, idx: usize, square: u8) {
+        let (pst, phase) = Self::piece_terms(idx, square);
+        self.pst[idx / 6].add((pst.mg, pst.eg));
+        self.phase += phase;
+    }
+
+    /// Stops counting the piece of bitboard `idx` on `square`.
+    #[inline]
+    pub(crate) fn remove(&mut self, idx: usize, square: u8) {
+        let (pst, phase) = Self::piece_terms(idx, square);
+        self.pst[idx / 6].add((-pst.mg, -pst.eg));
+        self.phase -= phase;
+    }
+
+    #[inline]
+    fn piece_terms(idx: usize, square: u8) -> (TaperedScore, i32) {
+        let piece = idx % 6;
+        // Tables are drawn with rank 8 first, so White flips the rank to look up its
+        // square and Black, seeing the board from the other side, does not
+        let table_square = match idx < 6 {
+            true => square as usize ^ 56,
+            false => square as usize,
+        };
+        let pst = TaperedScore {
+            mg: MG_TABLES[piece][table_square],
+            eg: EG_TABLES[piece][table_square],
+        };
+        (pst, PHASE_WEIGHTS[piece])
+    }
+}
+
+/// Blends a middlegame and an endgame score by `phase`.
+pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+/// A middlegame and an endgame score, blended by [`taper`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaperedScore {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl TaperedScore {
+    fn add(&mut self, (mg, eg): (i32, i32)) {
+        self.mg += mg;
+        self.eg += eg;
+    }
+
+    fn taper(self, phase: i32) -> i32 {
+        taper(self.mg, self.eg, phase)
+    }
+}
+
+/// The terms [`evaluate`] adds up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Material,
+    PieceSquares,
+    Mobility,
+    BishopPair,
+    RookPlacement,
+}
+
+impl Term {
+    pub const ALL: [Term; 5] = [
+        Term::Material,
+        Term::PieceSquares,
+        Term::Mobility,
+        Term::BishopPair,
+        Term::RookPlacement,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Term::Material => "Material",
+            Term::PieceSquares => "Piece-square",
+            Term::Mobility => "Mobility",
+            Term::BishopPair => "Bishop pair",
+            Term::RookPlacement => "Rook placement",
+        }
+    }
+}
+
+/// Each side's score for each [`Term`] of a position, as [`explain`] finds them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalBreakdown {
+    pub phase: i32,
+    pub active_color: Color,
+    /// Indexed by `Term`, then `Color`.
+    scores: [[TaperedScore; 2]; 5],
+}
+
+impl EvalBreakdown {
+    /// `color`'s score for `term`, before tapering.
+    pub fn score(&self, term: Term, color: Color) -> TaperedScore {
+        self.scores[term as usize][color as usize]
+    }
+
+    /// White's lead from the terms before `end` in [`Term::ALL`], tapered.
+    fn lead_before(&self, end: usize) -> i32 {
+        let mut lead = TaperedScore::default();
+        for [white, black] in &self.scores[..end] {
+            lead.add((white.mg - black.mg, white.eg - black.eg));
+        }
+        lead.taper(self.phase)
+    }
+
+    /// What `term` adds to [`EvalBreakdown::white_total`], in centipawns. Tapering
+    /// rounds, so this can be a centipawn off tapering the term alone, but the
+    /// terms always sum to the total.
+    pub fn net(&self, term: Term) -> i32 {
+        let idx = term as usize;
+        self.lead_before(idx + 1) - self.lead_before(idx)
+    }
+
+    /// The evaluation from White's point of view.
+    pub fn white_total(&self) -> i32 {
+        self.lead_before(Term::ALL.len())
+    }
+
+    /// The evaluation from the side to move's point of view, as [`evaluate`] gives it.
+    pub fn total(&self) -> i32 {
+        match self.active_color {
+            Color::White => self.white_total(),
+            Color::Black => -self.white_total(),
+        }
+    }
+}
+
+/// Writes a table of the terms, each side's tapered score and White's lead.
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16}{:>7}{:>7}{:>7}", "Term", "White", "Black", "Net")?;
+        for term in Term::ALL {
+            writeln!(
+                f,
+                "{:<16}{:>7}{:>7}{:>7}",
+                term.name(),
+                self.score(term, Color::White).taper(self.phase),
+                self.score(term, Color::Black).taper(self.phase),
+                self.net(term)
+            )?;
+        }
+        write!(
+            f,
+            "{:<30}{:>7}  (White's view, phase {} of {})",
+            "Total",
+            self.white_total(),
+            self.phase,
+            MAX_PHASE
+        )
+    }
+}
+
+/// Splits the evaluation of `board` into its terms for each side. Material and the
+/// piece-square tables come from what the board keeps up to date; the rest is
+/// worked out afresh.
+pub fn explain(board: &Board, params: &EvalParams) -> EvalBreakdown {
+    let mut scores = [[TaperedScore::default(); 2]; 5];
+    let incremental = board.eval_terms();
+
+    for color in Color::BOTH {
+        let side = color as usize;
+        for piece in PieceType::ALL {
+            let count = board.pieces(color, piece).count_ones() as i32;
+            scores[Term::Material as usize][side].add((
+                count * params.mg_piece_values[piece as usize],
+                count * params.eg_piece_values[piece as usize],
+            ));
+        }
+
+        let pst = incremental.pst[side];
+        scores[Term::PieceSquares as usize][side].add((
+            pst.mg * params.pst_scale / 100,
+            pst.eg * params.pst_scale / 100,
+        ));
+
+        scores[Term::Mobility as usize][side].add(mobility(board, color, params));
+
+        if board.pieces(color, PieceType::Bishop).count_ones() >= 2 {
+            scores[Term::BishopPair as usize][side]
+                .add((params.mg_bishop_pair, params.eg_bishop_pair));
+        }
+
+        scores[Term::RookPlacement as usize][side].add(rook_placement(board, color, params));
+    }
+
+    EvalBreakdown {
+        phase: incremental.phase.min(MAX_PHASE),
+        active_color: board.active_color,
+        scores,
+    }
+}
+
+/// Bonuses for `color`'s rooks on open and semi-open files and on the seventh rank.
+fn rook_placement(board: &Board, color: Color, params: &EvalParams) -> (i32, i32) {
+    let own_pawns = board.pieces(color, PieceType::Pawn);
+    let enemy_pawns = board.pieces(!color, PieceType::Pawn);
+    let seventh_rank = match color {
+        Color::White => RANK_7,
+        Color::Black => RANK_2,
+    };
+
+    let mut mg = 0;
+    let mut eg = 0;
+    let mut rooks = board.pieces(color, PieceType::Rook);
+    while rooks != 0 {
+        let square = rooks.trailing_zeros();
+        rooks &= rooks - 1;
+
+        let file_mask = FILE_A << (square % 8);
+        if file_mask & own_pawns == 0 {
+            if file_mask & enemy_pawns == 0 {
+                mg += params.mg_rook_open_file;
+                eg += params.eg_rook_open_file;
+            } else {
+                mg += params.mg_rook_semi_open_file;
+                eg += params.eg_rook_semi_open_file;
+            }
+        }
+
+        if seventh_rank & (1u64 << square) != 0 {
+            mg += params.mg_rook_on_seventh;
+            eg += params.eg_rook_on_seventh;
+        }
+    }
+    (mg, eg)
+}
+
+/// Weighted count of the squares `color`'s pieces can move to, leaving out squares
+/// guarded by enemy pawns. Uses the attack lookups rather than generating moves.
+fn mobility(board: &Board, color: Color, params: &EvalParams) -> (i32, i32) {
+    let friendly = board.occupied_by(color);
+    let enemy_pawns = board.pieces(!color, PieceType::Pawn);
+    let occupancy = board.occupied();
+    let available = !friendly & !Board::get_pawn_attack_bitboard(enemy_pawns, !color);
+
+    let mut mg = 0;
+    let mut eg = 0;
+    for piece in [
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+    ] {
+        let mut pieces = board.pieces(color, piece);
+        while pieces != 0 {
+            let square_bb = pieces & pieces.wrapping_neg();
+            pieces &= pieces - 1;
+
+            let attacks = match piece {
+                PieceType::Knight => Board::get_knight_attack_bitboard(square_bb),
+                PieceType::Bishop => Board::get_bishop_attack_bitboard(square_bb, occupancy),
+                PieceType::Rook => Board::get_rook_attack_bitboard(square_bb, occupancy),
+                _ => {
+                    Board::get_bishop_attack_bitboard(square_bb, occupancy)
+                        | Board::get_rook_attack_bitboard(square_bb, occupancy)
+                }
+            };
+            let squares = (attacks & available).count_ones() as i32;
+
+            mg += squares * params.mg_mobility[piece as usize];
+            eg += squares * params.eg_mobility[piece as usize];
+        }
+    }
+    (mg, eg)
+}
+
+/// Evaluates `board` in centipawns from the point of view of the side to move.
+pub fn evaluate(board: &Board, params: &EvalParams) -> i32 {
+    explain(board, params).total() + jitter(board, params)
+}
+
+/// The random part of the evaluation of `board`, see [`EvalParams::jitter`].
+fn jitter(board: &Board, params: &EvalParams) -> i32 {
+    let ply = (board.fullmove_number.max(1) - 1) * 2 + (board.active_color == Color::Black) as u32;
+    if params.jitter <= 0 || ply >= params.jitter_plies {
+        return 0;
+    }
+    let mut rng = XorShift64::new(polyglot_key(board) ^ params.jitter_seed);
+    let range = 2 * params.jitter as u64 + 1;
+    (rng.next_u64() % range) as i32 - params.jitter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flips the board vertically and swaps the colors, side to move and rights.
+    fn mirror_fen(fen: &str) -> String {
+        let parts: Vec<&str> = fen.split_whitespace().collect();
+        let swap_case = |s: &str| -> String {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+
+        let placement: Vec<String> = parts[0].split('/').rev().map(swap_case).collect();
+        let color = if parts[1] == "w" { "b" } else { "w" };
+        let en_passant = match parts[3] {
+            "-" => "-".to_string(),
+            square => {
+                let rank = if &square[1..] == "3" { "6" } else { "3" };
+                format!("{}{}", &square[..1], rank)
+            }
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            color,
+            swap_case(parts[2]),
+            en_passant,
+            parts[4],
+            parts[5]
+        )
+    }
+
+    #[test]
+    fn test_phase_from_material() {
+        assert_eq!(game_phase(&Board::new()), MAX_PHASE);
+        assert_eq!(
+            game_phase(&Board::fen_to_board("4k3/pppp4/8/8/8/8/4P3/4K3 w - - 0 1")),
+            0
+        );
+        assert_eq!(
+            game_phase(&Board::fen_to_board("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")),
+            2
+        );
+    }
+
+    #[test]
+    fn test_taper_blends_between_phases() {
+        assert_eq!(taper(100, 0, MAX_PHASE), 100);
+        assert_eq!(taper(100, 0, 0), 0);
+        assert_eq!(taper(100, 0, MAX_PHASE / 2), 50);
+    }
+
+    #[test]
+    fn test_startpos_is_balanced() {
+        assert_eq!(evaluate(&Board::new(), &EvalParams::DEFAULT), 0);
+    }
+
+    #[test]
+    fn test_breakdown_splits_the_evaluation_into_terms() {
+        // White has the bishop pair and an extra pawn against Black's bishop and knight,
+        // and Black the move
+        let board = Board::fen_to_board("2b1kn2/pp6/8/8/8/8/PPP5/2B1KB2 b - - 0 1");
+        let params = EvalParams::DEFAULT;
+        let breakdown = explain(&board, &params);
+
+        assert_eq!(breakdown.total(), evaluate(&board, &params));
+        assert_eq!(breakdown.total(), -breakdown.white_total());
+        let net: i32 = Term::ALL.iter().map(|&term| breakdown.net(term)).sum();
+        assert_eq!(net, breakdown.white_total());
+
+        assert_eq!(
+            breakdown.score(Term::BishopPair, Color::White),
+            TaperedScore {
+                mg: params.mg_bishop_pair,
+                eg: params.eg_bishop_pair
+            }
+        );
+        assert_eq!(
+            breakdown.score(Term::BishopPair, Color::Black),
+            TaperedScore::default()
+        );
+        let material = |color| breakdown.score(Term::Material, color);
+        assert_eq!(
+            material(Color::White).mg - material(Color::Black).mg,
+            params.mg_piece_values[PieceType::Pawn as usize]
+                + params.mg_piece_values[PieceType::Bishop as usize]
+                - params.mg_piece_values[PieceType::Knight as usize]
+        );
+
+        let table = breakdown.to_string();
+        assert!(table.starts_with("Term"), "{}", table);
+        for term in Term::ALL {
+            assert!(table.contains(term.name()), "{}", table);
+        }
+        assert!(table.lines().last().unwrap().starts_with("Total"));
+    }
+
+    #[test]
+    fn test_king_centralizes_in_pawn_endgame() {
+        let central = Board::fen_to_board("7k/8/8/8/4K3/8/4P3/8 w - - 0 1");
+        let cornered = Board::fen_to_board("7k/8/8/8/8/8/4P3/K7 w - - 0 1");
+
+        assert!(
+            evaluate(&central, &EvalParams::DEFAULT) > evaluate(&cornered, &EvalParams::DEFAULT)
+        );
+    }
+
+    #[test]
+    fn test_king_prefers_castled_corner_in_middlegame() {
+        let castled = Board::fen_to_board(
+            "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQ1RK1 w - - 0 1",
+        );
+        let uncastled = Board::fen_to_board(
+            "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPPKPPP/R1BQ3R w - - 0 1",
+        );
+
+        assert!(
+            evaluate(&castled, &EvalParams::DEFAULT) > evaluate(&uncastled, &EvalParams::DEFAULT)
+        );
+    }
+
+    #[test]
+    fn test_evaluation_is_color_symmetric() {
+        let fens = [
+            Board::START_FEN,
+            "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQ1RK1 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "7k/8/8/8/4K3/8/4P3/8 b - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::fen_to_board(fen);
+            let mirrored = Board::fen_to_board(&mirror_fen(fen));
+
+            assert_eq!(
+                evaluate(&board, &EvalParams::DEFAULT),
+                evaluate(&mirrored, &EvalParams::DEFAULT),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_blocked_bishop_scores_worse_than_free_bishop() {
+        // Same material: the c1 bishop is walled in by its own pawns on b2 and d2,
+        // while on f4 it sees open diagonals
+        let blocked = Board::fen_to_board("4k3/8/8/8/8/8/1P1P4/2B1K3 w - - 0 1");
+        let free = Board::fen_to_board("4k3/8/8/8/5B2/8/1P1P4/4K3 w - - 0 1");
+
+        assert!(evaluate(&blocked, &EvalParams::DEFAULT) < evaluate(&free, &EvalParams::DEFAULT));
+    }
+
+    #[test]
+    fn test_mobility_ignores_squares_guarded_by_enemy_pawns() {
+        let params = EvalParams {
+            mg_mobility: [0, 10, 0, 0, 0, 0],
+            eg_mobility: [0, 10, 0, 0, 0, 0],
+            ..EvalParams::DEFAULT
+        };
+        let no_mobility = EvalParams {
+            mg_mobility: [0; 6],
+            eg_mobility: [0; 6],
+            ..EvalParams::DEFAULT
+        };
+
+        // Knight on a1 reaches b3 and c2; the pawn on a4 guards b3
+        let board = Board::fen_to_board("4k3/8/8/8/p7/8/8/N3K3 w - - 0 1");
+        assert_eq!(
+            evaluate(&board, &params) - evaluate(&board, &no_mobility),
+            10
+        );
+    }
+
+    #[test]
+    fn test_bishop_pair_bonus() {
+        let no_pair = EvalParams {
+            mg_bishop_pair: 0,
+            eg_bishop_pair: 0,
+            ..EvalParams::DEFAULT
+        };
+
+        // White has the bishop pair, Black bishop and knight
+        let board = Board::fen_to_board("2b1kn2/8/8/8/8/8/8/2B1KB2 w - - 0 1");
+        let bonus = evaluate(&board, &EvalParams::DEFAULT) - evaluate(&board, &no_pair);
+
+        let phase = game_phase(&board);
+        assert_eq!(
+            bonus,
+            taper(
+                EvalParams::DEFAULT.mg_bishop_pair,
+                EvalParams::DEFAULT.eg_bishop_pair,
+                phase
+            )
+        );
+        assert!(bonus > 0);
+    }
+
+    /// Change in evaluation caused by the rook placement weights alone.
+    fn rook_bonus(fen: &str) -> i32 {
+        let no_rook_terms = EvalParams {
+            mg_rook_open_file: 0,
+            eg_rook_open_file: 0,
+            mg_rook_semi_open_file: 0,
+            eg_rook_semi_open_file: 0,
+            mg_rook_on_seventh: 0,
+            eg_rook_on_seventh: 0,
+            ..EvalParams::DEFAULT
+        };
+        let board = Board::fen_to_board(fen);
+
+        evaluate(&board, &EvalParams::DEFAULT) - evaluate(&board, &no_rook_terms)
+    }
+
+    fn tapered(mg: i32, eg: i32, fen: &str) -> i32 {
+        taper(mg, eg, game_phase(&Board::fen_to_board(fen)))
+    }
+
+    #[test]
+    fn test_jitter_is_small_seeded_and_only_in_the_opening() {
+        let jittered = |seed: u64| EvalParams {
+            jitter: 10,
+            jitter_seed: seed,
+            ..EvalParams::DEFAULT
+        };
+        let start = Board::new();
+        let plain = evaluate(&start, &EvalParams::DEFAULT);
+        let offsets: Vec<i32> = (1..=20)
+            .map(|seed| evaluate(&start, &jittered(seed)) - plain)
+            .collect();
+        assert!(offsets.iter().all(|offset| offset.abs() <= 10));
+        assert!(offsets.iter().any(|&offset| offset != offsets[0]));
+        assert_eq!(evaluate(&start, &jittered(3)) - plain, offsets[2]);
+
+        // Ply 16 is past the default jitter_plies
+        let later = Board::fen_to_board(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 9",
+        );
+        assert_eq!(
+            evaluate(&later, &jittered(3)),
+            evaluate(&later, &EvalParams::DEFAULT)
+        );
+    }
+
+    #[test]
+    fn test_rook_on_open_file() {
+        let fen = "4k3/pp6/8/8/8/8/PP6/4R1K1 w - - 0 1";
+        let params = EvalParams::DEFAULT;
+
+        assert_eq!(
+            rook_bonus(fen),
+            tapered(params.mg_rook_open_file, params.eg_rook_open_file, fen)
+        );
+    }
+
+    #[test]
+    fn test_rook_on_semi_open_file() {
+        let fen = "4k3/4p3/8/8/8/8/8/4R1K1 w - - 0 1";
+        let params = EvalParams::DEFAULT;
+
+        assert_eq!(
+            rook_bonus(fen),
+            tapered(
+                params.mg_rook_semi_open_file,
+                params.eg_rook_semi_open_file,
+                fen
+            )
+        );
+    }
+
+    #[test]
+    fn test_rook_on_closed_file_gets_no_bonus() {
+        assert_eq!(rook_bonus("4k3/4p3/8/8/8/8/4P3/4R1K1 w - - 0 1"), 0);
+        // A friendly pawn alone also closes the file for the rook
+        assert_eq!(rook_bonus("4k3/8/8/8/8/8/4P3/4R1K1 w - - 0 1"), 0);
+    }
+
+    #[test]
+    fn test_rook_on_seventh_rank() {
+        // The d7 rook is on the seventh rank and a file closed by a black pawn on d6
+        // and a white pawn on d2
+        let fen = "6k1/3R4/3p4/8/8/8/3P4/6K1 w - - 0 1";
+        let params = EvalParams::DEFAULT;
+
+        assert_eq!(
+            rook_bonus(fen),
+            tapered(params.mg_rook_on_seventh, params.eg_rook_on_seventh, fen)
+        );
+    }
+
+    #[test]
+    fn test_rook_terms_are_color_symmetric() {
+        for fen in [
+            "4k3/pp6/8/8/8/8/PP6/4R1K1 w - - 0 1",
+            "4k3/4p3/8/8/8/8/8/4R1K1 w - - 0 1",
+            "6k1/3R4/3p4/8/8/8/3P4/6K1 w - - 0 1",
+        ] {
+            assert_eq!(rook_bonus(fen), rook_bonus(&mirror_fen(fen)), "{}", fen);
+        }
+    }
+}
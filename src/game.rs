@@ -0,0 +1,1306 @@
+//! A game: the position it started from, the moves played and how it ended.
+
+use crate::board::{Board, Color, FenError, GameStatus};
+use crate::chess_move::{Move, MoveError};
+use crate::eco::{self, EcoCode};
+use crate::pgn::{PgnGame, PgnVariation, ResultTag};
+use crate::san::{parse_san, to_san, SanError};
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    /// The starting position is not valid FEN.
+    Fen(FenError),
+    /// The move at index `ply` of the move list cannot be played in its position,
+    /// for the given reason.
+    IllegalMove {
+        ply: usize,
+        uci: String,
+        reason: MoveError,
+    },
+    /// The SAN move at index `ply` of a PGN game cannot be read in its position.
+    San { ply: usize, reason: SanError },
+    /// [`Game::goto_ply`] was asked for a ply past the `plies` moves of the game.
+    NoSuchPly { ply: usize, plies: usize },
+    /// A move was played while viewing ply `ply` of `plies`; see [`Game::play`].
+    NotAtLastPly { ply: usize, plies: usize },
+    /// A line given to [`GameTree::add_variation`] follows the game for its first
+    /// `ply` moves and then has no other move to play instead of the game's.
+    NotAVariation { ply: usize },
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::Fen(err) => write!(f, "invalid starting position: {}", err),
+            GameError::IllegalMove { ply, uci, reason } => {
+                write!(f, "illegal move {} at ply {}: {}", uci, ply + 1, reason)
+            }
+            GameError::San { ply, reason } => write!(f, "at ply {}: {}", ply + 1, reason),
+            GameError::NoSuchPly { ply, plies } => {
+                write!(f, "no ply {}: the game has {} moves", ply, plies)
+            }
+            GameError::NotAtLastPly { ply, plies } => write!(
+                f,
+                "viewing ply {} of {}: go to the last ply or fork the game to play a move",
+                ply, plies
+            ),
+            GameError::NotAVariation { ply } => write!(
+                f,
+                "not a variation: the line follows the game for {} moves and plays no other",
+                ply
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameError::Fen(err) => Some(err),
+            GameError::IllegalMove { reason, .. } => Some(reason),
+            GameError::San { reason, .. } => Some(reason),
+            GameError::NoSuchPly { .. }
+            | GameError::NotAtLastPly { .. }
+            | GameError::NotAVariation { .. } => None,
+        }
+    }
+}
+
+impl From<FenError> for GameError {
+    fn from(err: FenError) -> Self {
+        GameError::Fen(err)
+    }
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    /// A flag fell. A draw if the other side could never have mated.
+    Timeout,
+    Stalemate,
+    InsufficientMaterial,
+    /// A draw claimed by the fifty-move rule.
+    FiftyMoveRule,
+    /// A draw claimed by threefold repetition.
+    Repetition,
+    SeventyFiveMoveRule,
+    FivefoldRepetition,
+    /// The players agreed to a draw.
+    Agreement,
+    /// A match runner decided the game, as one that was won, dead drawn or too long.
+    Adjudication,
+}
+
+impl Termination {
+    /// The value of the PGN `Termination` tag, one of those the standard defines.
+    pub fn pgn_tag(self) -> &'static str {
+        match self {
+            Termination::Timeout => "time forfeit",
+            Termination::Adjudication => "adjudication",
+            _ => "normal",
+        }
+    }
+}
+
+/// The result of a finished game: who won, if anyone, and how it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameResult {
+    pub winner: Option<Color>,
+    pub termination: Termination,
+}
+
+impl GameResult {
+    pub fn win(winner: Color, termination: Termination) -> GameResult {
+        GameResult {
+            winner: Some(winner),
+            termination,
+        }
+    }
+
+    pub fn draw(termination: Termination) -> GameResult {
+        GameResult {
+            winner: None,
+            termination,
+        }
+    }
+
+    /// The result as PGN writes it, e.g. `1-0`.
+    pub fn tag(&self) -> ResultTag {
+        match self.winner {
+            Some(Color::White) => ResultTag::WhiteWins,
+            Some(Color::Black) => ResultTag::BlackWins,
+            None => ResultTag::Draw,
+        }
+    }
+}
+
+/// Writes the result for a player to read, e.g. `Black wins by checkmate`.
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let how = match self.termination {
+            Termination::Checkmate => "by checkmate",
+            Termination::Resignation => "by resignation",
+            Termination::Timeout if self.winner.is_some() => "on time",
+            Termination::Timeout => "on time against a side that cannot mate",
+            Termination::Stalemate => "by stalemate",
+            Termination::InsufficientMaterial => "by insufficient material",
+            Termination::FiftyMoveRule => "by the fifty-move rule",
+            Termination::Repetition => "by threefold repetition",
+            Termination::SeventyFiveMoveRule => "by the seventy-five-move rule",
+            Termination::FivefoldRepetition => "by fivefold repetition",
+            Termination::Agreement => "by agreement",
+            Termination::Adjudication => "by adjudication",
+        };
+        match self.winner {
+            Some(winner) => write!(f, "{} wins {}", winner, how),
+            None => write!(f, "Draw {}", how),
+        }
+    }
+}
+
+/// A draw a player may claim but need not: the game goes on unless one does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawClaim {
+    /// Fifty moves by each side without a capture or pawn move.
+    FiftyMoveRule,
+    /// The position occurred for the third time.
+    ThreefoldRepetition,
+}
+
+impl DrawClaim {
+    /// How a game drawn by this claim ended.
+    pub fn termination(self) -> Termination {
+        match self {
+            DrawClaim::FiftyMoveRule => Termination::FiftyMoveRule,
+            DrawClaim::ThreefoldRepetition => Termination::Repetition,
+        }
+    }
+}
+
+/// Names the rule, e.g. `the fifty-move rule`.
+impl fmt::Display for DrawClaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawClaim::FiftyMoveRule => write!(f, "the fifty-move rule"),
+            DrawClaim::ThreefoldRepetition => write!(f, "threefold repetition"),
+        }
+    }
+}
+
+/// How far from 0.00, in centipawns, the evaluation may be for the engine to accept
+/// a draw offer.
+pub const DEFAULT_DRAW_WINDOW: i32 = 25;
+
+/// A game played from some starting position. Only legal moves can be added, so
+/// the current position can always be rebuilt by replaying them.
+///
+/// An analysis front-end can step back through the game with [`Game::goto_ply`]
+/// without losing the moves after the position it shows. Moves are only played at
+/// the last ply; to try another line from an earlier one, [`Game::fork`] it.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "GameRecord", into = "GameRecord")
+)]
+pub struct Game {
+    start: Board,
+    board: Board,
+    moves: Vec<Move>,
+    /// A result the rules alone do not give, such as a resignation.
+    result: Option<GameResult>,
+    /// The side that offered a draw, until the offer is answered or a move is made.
+    draw_offer: Option<Color>,
+    /// The earlier ply being viewed and its position, `None` at the last ply.
+    view: Option<(usize, Board)>,
+}
+
+impl Game {
+    /// A game from the standard starting position.
+    pub fn new() -> Game {
+        Game::from_board(Board::new())
+    }
+
+    pub fn from_board(board: Board) -> Game {
+        Game {
+            start: board,
+            board,
+            moves: Vec::new(),
+            result: None,
+            draw_offer: None,
+            view: None,
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        Board::from_fen(fen).map(Game::from_board)
+    }
+
+    /// Plays `moves` from `start_fen`, failing on the first one that is not legal.
+    pub fn replay(start_fen: &str, moves: &[Move]) -> Result<Game, GameError> {
+        let mut game = Game::from_fen(start_fen)?;
+        for &m in moves {
+            game.play(m)?;
+        }
+        Ok(game)
+    }
+
+    /// Replays a game read from PGN, from its `FEN` tag if it has one. A result the
+    /// rules do not give is taken from the PGN, after the moves or in its `Result`
+    /// tag, and ended as its `Termination` tag says: on time, by adjudication, or
+    /// else by resignation, a draw claim or agreement.
+    pub fn from_pgn(pgn: &PgnGame) -> Result<Game, GameError> {
+        let mut game = Game::from_fen(pgn.tag("FEN").unwrap_or(Board::START_FEN))?;
+        for (ply, san) in pgn.moves.iter().enumerate() {
+            let m = parse_san(&game.board, san).map_err(|reason| GameError::San { ply, reason })?;
+            game.play(m)?;
+        }
+        if game.outcome().is_none() {
+            game.set_pgn_result(pgn);
+        }
+        Ok(game)
+    }
+
+    /// Ends the game with the result `pgn` gives, if any.
+    fn set_pgn_result(&mut self, pgn: &PgnGame) {
+        let tag = match pgn.result {
+            ResultTag::Unknown => pgn.tag("Result").and_then(ResultTag::from_token),
+            tag => Some(tag),
+        };
+        let termination = [Termination::Timeout, Termination::Adjudication]
+            .into_iter()
+            .find(|termination| pgn.tag("Termination") == Some(termination.pgn_tag()));
+        let winner = match tag {
+            None | Some(ResultTag::Unknown) => return,
+            Some(ResultTag::WhiteWins) => Color::White,
+            Some(ResultTag::BlackWins) => Color::Black,
+            Some(ResultTag::Draw) => {
+                match termination {
+                    Some(termination) => self.set_result(GameResult::draw(termination)),
+                    None if self.claim_draw().is_some() => {}
+                    None => self.set_result(GameResult::draw(Termination::Agreement)),
+                }
+                return;
+            }
+        };
+        let termination = termination.unwrap_or(Termination::Resignation);
+        self.set_result(GameResult::win(winner, termination));
+    }
+
+    /// The position the game started from.
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    /// The position being viewed: the current one, unless [`Game::goto_ply`] went
+    /// back.
+    pub fn board(&self) -> &Board {
+        self.view.as_ref().map_or(&self.board, |(_, board)| board)
+    }
+
+    /// Every move of the game, however far back the view is.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// How many moves were played to reach the position being viewed.
+    pub fn current_ply(&self) -> usize {
+        self.view.as_ref().map_or(self.moves.len(), |&(ply, _)| ply)
+    }
+
+    /// Views the position after the first `ply` moves, keeping the moves after it;
+    /// the number of moves goes back to the current position.
+    pub fn goto_ply(&mut self, ply: usize) -> Result<(), GameError> {
+        let plies = self.moves.len();
+        if ply > plies {
+            return Err(GameError::NoSuchPly { ply, plies });
+        }
+        self.view = (ply < plies).then(|| {
+            let mut board = self.start;
+            for m in &self.moves[..ply] {
+                board.make_move_unchecked(m);
+            }
+            (ply, board)
+        });
+        Ok(())
+    }
+
+    /// A new game from the same start with the moves up to the ply being viewed, to
+    /// try another line from there. It has no result set by hand or draw offer, and
+    /// can be played on at once.
+    pub fn fork(&self) -> Game {
+        Game {
+            start: self.start,
+            board: *self.board(),
+            moves: self.moves[..self.current_ply()].to_vec(),
+            result: None,
+            draw_offer: None,
+            view: None,
+        }
+    }
+
+    /// Every move of the game with the position it was played in, from the first,
+    /// however far back the view is.
+    pub fn mainline(&self) -> impl Iterator<Item = (Board, Move)> + '_ {
+        let mut board = self.start;
+        self.moves.iter().map(move |&m| {
+            let before = board;
+            board.make_move_unchecked(&m);
+            (before, m)
+        })
+    }
+
+    /// The ECO code and name of the opening played, the deepest line of the table in
+    /// [`crate::eco`] the moves begin with. `None` for games from set-up positions.
+    pub fn opening(&self) -> Option<(EcoCode, &str)> {
+        if self.start.board_to_fen() != Board::START_FEN {
+            return None;
+        }
+        eco::classify(&self.moves)
+    }
+
+    /// How the game ended: the result set by a resignation, a flag or an agreed
+    /// draw, else the one the rules give, or `None` while it goes on.
+    pub fn result(&self) -> Option<GameResult> {
+        self.result.or_else(|| self.outcome())
+    }
+
+    /// Ends the game with `result`, e.g. when a flag falls.
+    pub fn set_result(&mut self, result: GameResult) {
+        self.result = Some(result);
+        self.draw_offer = None;
+    }
+
+    /// How the game stands by the rules: a win for the side that delivered mate, a
+    /// draw by stalemate, insufficient material, the seventy-five-move rule or
+    /// fivefold repetition, or `None` while it goes on. The draws a player has to
+    /// claim are not among them, see [`Game::claim_draw`]. Ignores any result set by
+    /// hand, and which ply is viewed.
+    pub fn outcome(&self) -> Option<GameResult> {
+        let termination = match self.status() {
+            GameStatus::InProgress | GameStatus::Check | GameStatus::Invalid => return None,
+            GameStatus::Checkmate { winner } => {
+                return Some(GameResult::win(winner, Termination::Checkmate))
+            }
+            GameStatus::Stalemate => Termination::Stalemate,
+            GameStatus::InsufficientMaterial => Termination::InsufficientMaterial,
+            GameStatus::FiftyMoveRule => Termination::FiftyMoveRule,
+            GameStatus::Repetition => Termination::Repetition,
+            GameStatus::SeventyFiveMoveRule => Termination::SeventyFiveMoveRule,
+            GameStatus::FivefoldRepetition => Termination::FivefoldRepetition,
+        };
+        Some(GameResult::draw(termination))
+    }
+
+    /// Where the game stands by the rules at its last ply. Unlike [`Board::status`],
+    /// the fifty-move rule and threefold repetition do not end it, as a player has
+    /// to claim them, see [`Game::claimable_draws`]; the seventy-five-move rule and
+    /// fivefold repetition do, whatever the players want.
+    pub fn status(&self) -> GameStatus {
+        let status = self.board.status();
+        if !matches!(
+            status,
+            GameStatus::InProgress
+                | GameStatus::Check
+                | GameStatus::FiftyMoveRule
+                | GameStatus::Repetition
+        ) {
+            return status;
+        }
+        if self.board.halfmove_clock >= 150 {
+            GameStatus::SeventyFiveMoveRule
+        } else if self.repetitions() >= 5 {
+            GameStatus::FivefoldRepetition
+        } else if self.board.is_in_check(self.board.active_color) {
+            GameStatus::Check
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// How many times the position at the last ply has occurred in the game, this
+    /// time included. Positions count as the same when [`Board::position_key`] says
+    /// so.
+    pub fn repetitions(&self) -> usize {
+        let current = self.board.position_key();
+        // Nothing before the last capture or pawn move can come back
+        let reversible = self.board.halfmove_clock as usize;
+        let earlier = self
+            .mainline()
+            .skip(self.moves.len().saturating_sub(reversible))
+            .filter(|(board, _)| board.position_key() == current)
+            .count();
+        earlier + 1
+    }
+
+    /// The draws the side to move may claim at the last ply, by the fifty-move rule
+    /// first. Empty once the game is over.
+    pub fn claimable_draws(&self) -> Vec<DrawClaim> {
+        let mut claims = Vec::new();
+        if self.result().is_some() {
+            return claims;
+        }
+        if self.board.halfmove_clock >= 100 {
+            claims.push(DrawClaim::FiftyMoveRule);
+        }
+        if self.repetitions() >= 3 {
+            claims.push(DrawClaim::ThreefoldRepetition);
+        }
+        claims
+    }
+
+    /// Ends the game in a draw by the first of [`Game::claimable_draws`], and returns
+    /// it. `None`, with the game going on, if there is no draw to claim.
+    pub fn claim_draw(&mut self) -> Option<DrawClaim> {
+        let claim = *self.claimable_draws().first()?;
+        self.set_result(GameResult::draw(claim.termination()));
+        Some(claim)
+    }
+
+    /// `color` resigns, and the other side wins.
+    pub fn resign(&mut self, color: Color) {
+        self.set_result(GameResult::win(!color, Termination::Resignation));
+    }
+
+    /// `color` offers a draw, which stands until it is answered or a move is made.
+    pub fn offer_draw(&mut self, color: Color) {
+        if self.result().is_none() {
+            self.draw_offer = Some(color);
+        }
+    }
+
+    /// The side whose draw offer is waiting for an answer, if any.
+    pub fn draw_offer(&self) -> Option<Color> {
+        self.draw_offer
+    }
+
+    /// Answers the pending draw offer for the engine, whose evaluation of the
+    /// position is `eval` centipawns: it accepts, ending the game, if that is within
+    /// `window` of 0.00, and declines otherwise. Returns whether it accepted.
+    pub fn answer_draw_offer(&mut self, eval: i32, window: i32) -> bool {
+        if self.draw_offer.take().is_none() {
+            return false;
+        }
+        let accepted = eval.abs() <= window;
+        if accepted {
+            self.set_result(GameResult::draw(Termination::Agreement));
+        }
+        accepted
+    }
+
+    /// Plays `m` if it is legal in the current position. Refuses while an earlier
+    /// ply is viewed, rather than drop the moves after it or branch off silently.
+    pub fn play(&mut self, m: Move) -> Result<(), GameError> {
+        if let Some((ply, _)) = self.view {
+            return Err(GameError::NotAtLastPly {
+                ply,
+                plies: self.moves.len(),
+            });
+        }
+        self.board
+            .make_move(&m)
+            .map_err(|reason| GameError::IllegalMove {
+                ply: self.moves.len(),
+                uci: m.to_string(),
+                reason,
+            })?;
+        self.moves.push(m);
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    /// The game in PGN, with the seven required tags left unknown apart from the
+    /// result, the starting position if it is not the usual one, and a
+    /// `Termination` tag once the game is over.
+    pub fn to_pgn(&self) -> String {
+        self.to_pgn_with_tags(&[])
+    }
+
+    /// [`Game::to_pgn`] with the values of `tags` in place of the unknown ones of
+    /// the seven required tags, and any other tags after them. Tags the game itself
+    /// gives, such as `Result` and `FEN`, are not taken from `tags`.
+    pub fn to_pgn_with_tags(&self, tags: &[(String, String)]) -> String {
+        write_pgn(&GameTree::new(self.clone()), tags)
+    }
+}
+
+/// Tags [`write_pgn`] works out from the game rather than being told.
+const DERIVED_TAGS: [&str; 6] = ["Result", "SetUp", "FEN", "ECO", "Opening", "Termination"];
+
+/// Writes `tree` as [`Game::to_pgn_with_tags`] describes, with its comments, NAGs
+/// and variations.
+fn write_pgn(tree: &GameTree, tags: &[(String, String)]) -> String {
+    let game = &tree.game;
+    let result = game.result();
+    let tag = result.map_or(ResultTag::Unknown, |result| result.tag());
+    let given = |name: &str| {
+        tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut pgn = String::new();
+    let roster = [
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+    ];
+    for (name, unknown) in roster {
+        pgn.push_str(&tag_pair(name, given(name).unwrap_or(unknown)));
+    }
+    pgn.push_str(&format!("[Result \"{}\"]\n", tag));
+    for (name, value) in tags {
+        let known =
+            roster.iter().any(|(tag, _)| tag == name) || DERIVED_TAGS.contains(&name.as_str());
+        if !known {
+            pgn.push_str(&tag_pair(name, value));
+        }
+    }
+    let start_fen = game.start.board_to_fen();
+    if start_fen != Board::START_FEN {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", start_fen));
+    }
+    if let Some((code, name)) = game.opening() {
+        pgn.push_str(&format!("[ECO \"{}\"]\n", code));
+        pgn.push_str(&format!("[Opening \"{}\"]\n", name));
+    }
+    let termination = result.map_or("unterminated", |result| result.termination.pgn_tag());
+    pgn.push_str(&format!("[Termination \"{}\"]\n\n", termination));
+
+    // Movetext, wrapped before 80 columns
+    let mut tokens = Vec::new();
+    if let Some(comment) = &tree.comment {
+        push_comment(comment, &mut tokens);
+    }
+    push_movetext(tree, 0, &mut tokens);
+    tokens.push(tag.to_string());
+
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > 79 {
+            pgn.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            pgn.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+/// A tag pair line, `[White "Carlsen, Magnus"]`, with quotes in the value escaped.
+fn tag_pair(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('"', "\\\""))
+}
+
+/// Adds the SAN tokens of `tree`'s moves from ply `from` on to `tokens`, each
+/// followed by its NAGs, its comment and its variations in parentheses. Black's
+/// moves get a number of their own, `12...`, where they start a line or follow a
+/// comment or variation.
+fn push_movetext(tree: &GameTree, from: usize, tokens: &mut Vec<String>) {
+    let mut number_black = true;
+    for (ply, (board, m)) in tree.game.mainline().enumerate().skip(from) {
+        match board.active_color {
+            Color::White => tokens.push(format!("{}.", board.fullmove_number)),
+            Color::Black if number_black => tokens.push(format!("{}...", board.fullmove_number)),
+            Color::Black => {}
+        }
+        tokens.push(to_san(&board, &m));
+        number_black = false;
+
+        if let Some(notes) = tree.notes.get(&ply) {
+            tokens.extend(notes.nags.iter().map(|nag| format!("${}", nag)));
+            if let Some(comment) = &notes.comment {
+                push_comment(comment, tokens);
+                number_black = true;
+            }
+        }
+
+        for variation in &tree.variations {
+            if branch_ply(&tree.game, &variation.game) != ply {
+                continue;
+            }
+            let first = tokens.len();
+            push_movetext(variation, ply, tokens);
+            tokens[first].insert(0, '(');
+            tokens.last_mut().expect("a variation has a move").push(')');
+            number_black = true;
+        }
+    }
+}
+
+/// Adds `comment` in braces, a word to a token so that it wraps like the moves.
+/// A closing brace in it would end it early, so it is dropped.
+fn push_comment(comment: &str, tokens: &mut Vec<String>) {
+    let comment = comment.replace('}', "");
+    let first = tokens.len();
+    tokens.extend(comment.split_whitespace().map(String::from));
+    if tokens.len() == first {
+        tokens.push(String::new());
+    }
+    tokens[first].insert(0, '{');
+    tokens.last_mut().expect("pushed above").push('}');
+}
+
+/// How many moves `line` has in common with `game` before they part.
+fn branch_ply(game: &Game, line: &Game) -> usize {
+    game.moves
+        .iter()
+        .zip(&line.moves)
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// A game with its variations: other lines from its positions, each a fork of the
+/// game that may have variations of its own. PGN writes a variation in parentheses
+/// after the move it replaces, `12... Nf6 (12... Nd7 13. f4) 13. e5`.
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    game: Game,
+    variations: Vec<GameTree>,
+    /// A comment before the first move.
+    comment: Option<String>,
+    /// NAGs and comments on moves, by ply.
+    notes: BTreeMap<usize, MoveNotes>,
+}
+
+/// What PGN says about a move besides the move itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveNotes {
+    /// Numeric annotation glyphs, written `$2` after the move: 1 for a good move, 2
+    /// for a mistake, 4 for a blunder and so on.
+    pub nags: Vec<u8>,
+    /// Written in braces after the move and its NAGs.
+    pub comment: Option<String>,
+}
+
+impl GameTree {
+    /// `game` with no variations yet.
+    pub fn new(game: Game) -> GameTree {
+        GameTree {
+            game,
+            variations: Vec::new(),
+            comment: None,
+            notes: BTreeMap::new(),
+        }
+    }
+
+    /// Reads a PGN game and its variations, however deeply they nest.
+    pub fn from_pgn(pgn: &PgnGame) -> Result<GameTree, GameError> {
+        let mut tree = GameTree::new(Game::from_pgn(pgn)?);
+        tree.add_pgn_variations(&pgn.variations)?;
+        Ok(tree)
+    }
+
+    fn add_pgn_variations(&mut self, variations: &[PgnVariation]) -> Result<(), GameError> {
+        for variation in variations {
+            let mut line = self.game.clone();
+            line.goto_ply(variation.ply)?;
+            let mut line = line.fork();
+            for (idx, san) in variation.moves.iter().enumerate() {
+                let ply = variation.ply + idx;
+                let m = parse_san(line.board(), san)
+                    .map_err(|reason| GameError::San { ply, reason })?;
+                line.play(m)?;
+            }
+            let mut tree = GameTree::new(line);
+            tree.add_pgn_variations(&variation.variations)?;
+            self.add_variation(tree)?;
+        }
+        Ok(())
+    }
+
+    /// The main line.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The variations, in the order they were added.
+    pub fn variations(&self) -> &[GameTree] {
+        &self.variations
+    }
+
+    /// Adds `variation`, usually a [`Game::fork`] of the main line played on, after
+    /// the variations already branching at the same ply. It must start from the same
+    /// position and play another move than the main line at some ply, which is the
+    /// one it is written after.
+    pub fn add_variation(&mut self, variation: GameTree) -> Result<(), GameError> {
+        let ply = branch_ply(&self.game, &variation.game);
+        if variation.game.start.board_to_fen() != self.game.start.board_to_fen()
+            || ply == self.game.moves.len()
+            || ply == variation.game.moves.len()
+        {
+            return Err(GameError::NotAVariation { ply });
+        }
+        self.variations.push(variation);
+        Ok(())
+    }
+
+    /// The comment before the first move, if any.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Sets the comment written before the first move, e.g. a summary of the game.
+    pub fn set_comment(&mut self, comment: impl Into<String>) {
+        self.comment = Some(comment.into());
+    }
+
+    /// The NAGs and comment on the move at index `ply`, if it has any.
+    pub fn notes(&self, ply: usize) -> Option<&MoveNotes> {
+        self.notes.get(&ply)
+    }
+
+    /// The NAGs and comment on the move at index `ply`, to change. Plies past the
+    /// last move are never written.
+    pub fn notes_mut(&mut self, ply: usize) -> &mut MoveNotes {
+        self.notes.entry(ply).or_default()
+    }
+
+    /// The game in PGN as [`Game::to_pgn`] writes it, with the comments, NAGs and
+    /// variations.
+    pub fn to_pgn(&self) -> String {
+        write_pgn(self, &[])
+    }
+}
+
+impl From<Game> for GameTree {
+    fn from(game: Game) -> Self {
+        GameTree::new(game)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+/// How a [`Game`] is serialized: compact and readable, with moves in UCI notation.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameRecord {
+    start_fen: String,
+    moves: Vec<Move>,
+    result: ResultTag,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    termination: Option<Termination>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Game> for GameRecord {
+    fn from(game: Game) -> Self {
+        let result = game.result();
+        GameRecord {
+            start_fen: game.start.board_to_fen(),
+            moves: game.moves,
+            result: result.map_or(ResultTag::Unknown, |result| result.tag()),
+            termination: result.map(|result| result.termination),
+        }
+    }
+}
+
+/// Deserializing replays the moves, so a record with an illegal move is rejected.
+#[cfg(feature = "serde")]
+impl TryFrom<GameRecord> for Game {
+    type Error = GameError;
+
+    fn try_from(record: GameRecord) -> Result<Self, Self::Error> {
+        let mut game = Game::replay(&record.start_fen, &record.moves)?;
+        let winner = match record.result {
+            ResultTag::WhiteWins => Some(Color::White),
+            ResultTag::BlackWins => Some(Color::Black),
+            ResultTag::Draw | ResultTag::Unknown => None,
+        };
+        game.result = record.termination.map(|termination| GameResult {
+            winner,
+            termination,
+        });
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_games;
+
+    fn uci_moves(moves: &str) -> Vec<Move> {
+        moves
+            .split_whitespace()
+            .map(|m| Move::from_uci(m).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_replay_plays_legal_moves() {
+        let game = Game::replay(Board::START_FEN, &uci_moves("e2e4 e7e5 g1f3")).unwrap();
+
+        assert_eq!(game.moves().len(), 3);
+        assert_eq!(
+            game.board().board_to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(game.start().board_to_fen(), Board::START_FEN);
+    }
+
+    #[test]
+    fn test_replay_rejects_illegal_move() {
+        let err = Game::replay(Board::START_FEN, &uci_moves("e2e4 e7e5 e4e5")).unwrap_err();
+
+        assert_eq!(
+            err,
+            GameError::IllegalMove {
+                ply: 2,
+                uci: "e4e5".to_string(),
+                reason: MoveError::Blocked { by: 36 },
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "illegal move e4e5 at ply 3: blocked by the piece on e5"
+        );
+    }
+
+    #[test]
+    fn test_goto_ply_keeps_the_moves_and_play_only_at_the_last_ply() {
+        let moves = uci_moves("e2e4 e7e5 g1f3 b8c6");
+        let mut game = Game::replay(Board::START_FEN, &moves).unwrap();
+        let end = game.board().board_to_fen();
+        assert_eq!(game.current_ply(), 4);
+
+        game.goto_ply(2).unwrap();
+        assert_eq!(game.current_ply(), 2);
+        assert_eq!(
+            game.board().board_to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+        );
+        assert_eq!(game.moves(), moves.as_slice());
+
+        // Playing from an earlier ply is refused, and changes nothing
+        let d4 = Move::from_uci("d2d4").unwrap();
+        let err = game.play(d4).unwrap_err();
+        assert_eq!(err, GameError::NotAtLastPly { ply: 2, plies: 4 });
+        assert_eq!(
+            err.to_string(),
+            "viewing ply 2 of 4: go to the last ply or fork the game to play a move"
+        );
+        assert_eq!(game.moves(), moves.as_slice());
+        assert_eq!(game.current_ply(), 2);
+
+        // A fork branches off the ply viewed and leaves the game as it was
+        let mut fork = game.fork();
+        assert_eq!(fork.moves(), &moves[..2]);
+        fork.play(d4).unwrap();
+        assert_eq!(fork.moves().len(), 3);
+        assert_eq!(game.moves(), moves.as_slice());
+
+        assert_eq!(
+            game.goto_ply(5),
+            Err(GameError::NoSuchPly { ply: 5, plies: 4 })
+        );
+        game.goto_ply(0).unwrap();
+        assert_eq!(game.board().board_to_fen(), Board::START_FEN);
+        game.goto_ply(4).unwrap();
+        assert_eq!(game.board().board_to_fen(), end);
+        game.play(Move::from_uci("f1b5").unwrap()).unwrap();
+        assert_eq!(game.current_ply(), 5);
+    }
+
+    #[test]
+    fn test_mainline_pairs_each_move_with_its_position() {
+        let moves = uci_moves("e2e4 e7e5 g1f3");
+        let mut game = Game::replay(Board::START_FEN, &moves).unwrap();
+        game.goto_ply(1).unwrap();
+
+        let mainline: Vec<(String, Move)> = game
+            .mainline()
+            .map(|(board, m)| (board.board_to_fen(), m))
+            .collect();
+        assert_eq!(mainline.len(), 3);
+        assert_eq!(mainline[0], (Board::START_FEN.to_string(), moves[0]));
+        assert_eq!(
+            mainline[2],
+            (
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2".to_string(),
+                moves[2]
+            )
+        );
+    }
+
+    #[test]
+    fn test_outcome() {
+        let fools_mate = Game::replay(Board::START_FEN, &uci_moves("f2f3 e7e5 g2g4 d8h4")).unwrap();
+        assert_eq!(
+            fools_mate.outcome(),
+            Some(GameResult::win(Color::Black, Termination::Checkmate))
+        );
+
+        // The outcome is the game's, whichever position is viewed
+        let mut viewed = fools_mate.clone();
+        viewed.goto_ply(1).unwrap();
+        assert_eq!(viewed.outcome(), fools_mate.outcome());
+        assert_eq!(viewed.fork().outcome(), None);
+
+        let stalemate = Game::from_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1").unwrap();
+        assert_eq!(
+            stalemate.outcome(),
+            Some(GameResult::draw(Termination::Stalemate))
+        );
+
+        assert_eq!(Game::new().outcome(), None);
+    }
+
+    #[test]
+    fn test_move_rules_are_claimed_at_fifty_and_end_the_game_at_seventy_five() {
+        // Rook and king shuffles, one and two plies short of each rule
+        let shuffle = uci_moves("a1a2 e8d8");
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 98 80").unwrap();
+        game.play(shuffle[0]).unwrap();
+        assert_eq!(game.board().halfmove_clock, 99);
+        assert!(game.claimable_draws().is_empty());
+        assert_eq!(game.claim_draw(), None);
+
+        game.play(shuffle[1]).unwrap();
+        assert_eq!(game.claimable_draws(), [DrawClaim::FiftyMoveRule]);
+        assert_eq!(game.status(), GameStatus::InProgress);
+        assert_eq!(game.result(), None);
+        assert_eq!(game.claim_draw(), Some(DrawClaim::FiftyMoveRule));
+        assert_eq!(
+            game.result().unwrap().to_string(),
+            "Draw by the fifty-move rule"
+        );
+        assert!(game.claimable_draws().is_empty());
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 148 105").unwrap();
+        game.play(shuffle[0]).unwrap();
+        assert_eq!(game.status(), GameStatus::InProgress);
+        game.play(shuffle[1]).unwrap();
+        assert_eq!(game.board().halfmove_clock, 150);
+        assert_eq!(game.status(), GameStatus::SeventyFiveMoveRule);
+        assert_eq!(
+            game.result(),
+            Some(GameResult::draw(Termination::SeventyFiveMoveRule))
+        );
+        assert!(game.claimable_draws().is_empty());
+    }
+
+    #[test]
+    fn test_repetitions_are_claimed_at_three_and_end_the_game_at_five() {
+        let knights = uci_moves("g1f3 g8f6 f3g1 f6g8");
+        let mut game = Game::new();
+        assert_eq!(game.repetitions(), 1);
+
+        // Each round of knight moves brings the start back once more
+        for round in 2..=5 {
+            for (idx, &m) in knights.iter().enumerate() {
+                assert_eq!(game.result(), None, "round {}, ply {}", round, idx);
+                game.play(m).unwrap();
+            }
+            assert_eq!(game.repetitions(), round);
+            match round {
+                2 => assert!(game.claimable_draws().is_empty()),
+                3 | 4 => {
+                    assert_eq!(game.claimable_draws(), [DrawClaim::ThreefoldRepetition]);
+                    assert_eq!(game.status(), GameStatus::InProgress);
+                }
+                _ => {
+                    assert_eq!(game.status(), GameStatus::FivefoldRepetition);
+                    assert_eq!(
+                        game.result().unwrap().to_string(),
+                        "Draw by fivefold repetition"
+                    );
+                    assert!(game.claimable_draws().is_empty());
+                }
+            }
+        }
+
+        // Claimed at the third occurrence, the draw is by threefold repetition
+        let mut game =
+            Game::replay(Board::START_FEN, &[knights.clone(), knights].concat()).unwrap();
+        game.claim_draw();
+        assert_eq!(
+            game.result(),
+            Some(GameResult::draw(Termination::Repetition))
+        );
+    }
+
+    #[test]
+    fn test_from_pgn_reads_back_to_pgn() {
+        let game = Game::replay("k7/8/8/8/8/8/8/K6R b - - 0 40", &uci_moves("a8b8 h1h8")).unwrap();
+        let pgn = parse_games(&game.to_pgn()).remove(0);
+        let back = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(back.moves(), game.moves());
+        assert_eq!(back.start().board_to_fen(), game.start().board_to_fen());
+
+        let pgn = parse_games("1. e4 e4 *").remove(0);
+        let err = Game::from_pgn(&pgn).unwrap_err();
+        assert_eq!(err.to_string(), "at ply 2: no legal move matches e4");
+    }
+
+    #[test]
+    fn test_from_pgn_keeps_results_the_rules_do_not_give() {
+        let moves = uci_moves("e2e4 e7e5 g1f3");
+        let results = [
+            GameResult::win(Color::White, Termination::Resignation),
+            GameResult::win(Color::Black, Termination::Timeout),
+            GameResult::draw(Termination::Agreement),
+            GameResult::win(Color::White, Termination::Adjudication),
+        ];
+        for result in results {
+            let mut game = Game::replay(Board::START_FEN, &moves).unwrap();
+            game.set_result(result);
+            let pgn = game.to_pgn();
+            let back = Game::from_pgn(&parse_games(&pgn).remove(0)).unwrap();
+            assert_eq!(back.result(), Some(result));
+            assert_eq!(back.to_pgn(), pgn);
+        }
+
+        // The Result tag stands in for a missing result after the moves
+        let pgn = parse_games("[Result \"0-1\"]\n\n1. e4 e5").remove(0);
+        assert_eq!(
+            Game::from_pgn(&pgn).unwrap().result(),
+            Some(GameResult::win(Color::Black, Termination::Resignation))
+        );
+        let pgn = parse_games("1. e4 e5 *").remove(0);
+        assert_eq!(Game::from_pgn(&pgn).unwrap().result(), None);
+    }
+
+    #[test]
+    fn test_game_tree_writes_forks_as_variations() {
+        let game = Game::replay(Board::START_FEN, &uci_moves("e2e4 e7e5 g1f3")).unwrap();
+        let mut tree = GameTree::new(game.clone());
+
+        let mut viewed = game.clone();
+        viewed.goto_ply(1).unwrap();
+        let mut sicilian = viewed.fork();
+        for m in uci_moves("c7c5 g1f3") {
+            sicilian.play(m).unwrap();
+        }
+        tree.add_variation(sicilian.into()).unwrap();
+        assert!(tree
+            .to_pgn()
+            .ends_with("\n\n1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *\n"));
+
+        // Lines that never leave the game, or only go on past its end, are no variations
+        let err = tree.add_variation(viewed.fork().into()).unwrap_err();
+        assert_eq!(err, GameError::NotAVariation { ply: 1 });
+        assert_eq!(
+            err.to_string(),
+            "not a variation: the line follows the game for 1 moves and plays no other"
+        );
+        let mut longer = game.clone();
+        longer.play(Move::from_uci("b8c6").unwrap()).unwrap();
+        assert!(tree.add_variation(longer.into()).is_err());
+        assert_eq!(tree.variations().len(), 1);
+    }
+
+    #[test]
+    fn test_game_tree_pgn_round_trip() {
+        let movetext = "1. e4 e5 (1... c5 2. Nf3 (2. c3 d5) 2... d6 (2... Nc6) 3. d4) 2. Nf3 \
+                        (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 3. Bb5 *";
+        let pgn = parse_games(movetext).remove(0);
+        let tree = GameTree::from_pgn(&pgn).unwrap();
+        assert_eq!(tree.game().moves().len(), 5);
+        assert_eq!(tree.variations().len(), 2);
+        assert_eq!(tree.variations()[0].variations().len(), 2);
+
+        let written = tree.to_pgn();
+        let (_, written_movetext) = written.split_once("\n\n").unwrap();
+        assert_eq!(written_movetext.trim_end().replace('\n', " "), movetext);
+        let back = parse_games(&written).remove(0);
+        assert_eq!(back.moves, pgn.moves);
+        assert_eq!(back.variations, pgn.variations);
+
+        // Variation moves are read in their own position
+        let pgn = parse_games("1. e4 e5 (1... e4) *").remove(0);
+        let err = GameTree::from_pgn(&pgn).unwrap_err();
+        assert_eq!(err.to_string(), "at ply 2: no legal move matches e4");
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut game = Game::replay(Board::START_FEN, &uci_moves("e2e4")).unwrap();
+        assert_eq!(game.result(), None);
+
+        game.resign(Color::Black);
+        let result = game.result().unwrap();
+        assert_eq!(
+            result,
+            GameResult::win(Color::White, Termination::Resignation)
+        );
+        assert_eq!(result.to_string(), "White wins by resignation");
+        assert!(game.to_pgn().contains("[Result \"1-0\"]\n"));
+    }
+
+    #[test]
+    fn test_draw_offer_accepted_near_equality() {
+        let mut game = Game::new();
+        game.offer_draw(Color::White);
+        assert_eq!(game.draw_offer(), Some(Color::White));
+
+        assert!(game.answer_draw_offer(-20, DEFAULT_DRAW_WINDOW));
+        assert_eq!(game.draw_offer(), None);
+        assert_eq!(
+            game.result(),
+            Some(GameResult::draw(Termination::Agreement))
+        );
+        assert_eq!(game.result().unwrap().to_string(), "Draw by agreement");
+    }
+
+    #[test]
+    fn test_draw_offer_declined_and_withdrawn_by_a_move() {
+        let mut game = Game::new();
+        game.offer_draw(Color::White);
+        assert!(!game.answer_draw_offer(150, DEFAULT_DRAW_WINDOW));
+        assert_eq!(game.draw_offer(), None);
+        assert_eq!(game.result(), None);
+
+        // An offer lapses once a move is made, so there is nothing left to accept
+        game.offer_draw(Color::White);
+        game.play(Move::from_uci("e2e4").unwrap()).unwrap();
+        assert_eq!(game.draw_offer(), None);
+        assert!(!game.answer_draw_offer(0, DEFAULT_DRAW_WINDOW));
+        assert_eq!(game.result(), None);
+    }
+
+    #[test]
+    fn test_opening() {
+        let sicilian = Game::replay(Board::START_FEN, &uci_moves("e2e4 c7c5 g2g3")).unwrap();
+        let (code, name) = sicilian.opening().unwrap();
+        assert_eq!(
+            (code.to_string().as_str(), name),
+            ("B20", "Sicilian Defence")
+        );
+
+        let gambit = Game::replay(Board::START_FEN, &uci_moves("d2d4 d7d5 c2c4")).unwrap();
+        let (code, name) = gambit.opening().unwrap();
+        assert_eq!((code.to_string().as_str(), name), ("D06", "Queen's Gambit"));
+
+        assert_eq!(Game::new().opening(), None);
+        let set_up = Game::replay("k7/8/8/8/8/8/8/K6R w - - 0 1", &uci_moves("h1h8")).unwrap();
+        assert_eq!(set_up.opening(), None);
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        let fools_mate = Game::replay(Board::START_FEN, &uci_moves("f2f3 e7e5 g2g4 d8h4")).unwrap();
+        assert_eq!(
+            fools_mate.to_pgn(),
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"?\"]\n[Black \"?\"]\n[Result \"0-1\"]\n[ECO \"A00\"]\n\
+             [Opening \"Barnes Opening: Fool's Mate\"]\n[Termination \"normal\"]\n\n\
+             1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+
+        // A game from a set-up position with Black to move, lost on time
+        let mut game = Game::replay("k7/8/8/8/8/8/8/K6R b - - 0 40", &uci_moves("a8b8")).unwrap();
+        game.set_result(GameResult::win(Color::Black, Termination::Timeout));
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"k7/8/8/8/8/8/8/K6R b - - 0 40\"]\n"));
+        assert!(pgn.contains("[Termination \"time forfeit\"]\n"));
+        assert!(pgn.ends_with("\n40... Kb8 0-1\n"), "{}", pgn);
+
+        assert!(Game::new()
+            .to_pgn()
+            .contains("[Termination \"unterminated\"]"));
+
+        // Given tags fill in the roster, or follow it, but do not override the game
+        let tags = [
+            ("White", "Paul \"The Pride\" Morphy"),
+            ("Annotator", "ChessBot"),
+            ("Result", "1-0"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let pgn = fools_mate.to_pgn_with_tags(&tags);
+        assert!(pgn.contains(
+            "[White \"Paul \\\"The Pride\\\" Morphy\"]\n[Black \"?\"]\n[Result \"0-1\"]\n\
+             [Annotator \"ChessBot\"]\n[ECO \"A00\"]\n"
+        ));
+        let tag_pairs = &parse_games(&pgn)[0].tags;
+        assert!(tag_pairs.contains(&tags[0]) && tag_pairs.contains(&tags[1]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_board_json_round_trip() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        let board = Board::fen_to_board(fen);
+
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, format!("\"{}\"", fen));
+        let back: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.board_to_fen(), fen);
+
+        assert!(serde_json::from_str::<Board>("\"not a fen\"").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_game_json_round_trip() {
+        // Fool's mate
+        let game = Game::replay(Board::START_FEN, &uci_moves("f2f3 e7e5 g2g4 d8h4")).unwrap();
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"start_fen":"{}","moves":["f2f3","e7e5","g2g4","d8h4"],"result":"0-1","termination":"checkmate"}}"#,
+                Board::START_FEN
+            )
+        );
+
+        let back: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.moves(), game.moves());
+        assert_eq!(
+            back.result(),
+            Some(GameResult::win(Color::Black, Termination::Checkmate))
+        );
+        assert_eq!(back.board().board_to_fen(), game.board().board_to_fen());
+
+        // A result the moves alone do not give survives the round trip too
+        let mut resigned = Game::new();
+        resigned.resign(Color::White);
+        let json = serde_json::to_string(&resigned).unwrap();
+        assert!(json.ends_with(r#""result":"0-1","termination":"resignation"}"#));
+        let back: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.result(), resigned.result());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_game_json_with_illegal_move_is_rejected() {
+        let json = format!(
+            r#"{{"start_fen":"{}","moves":["e2e4","e2e4"],"result":"*"}}"#,
+            Board::START_FEN
+        );
+        let err = serde_json::from_str::<Game>(&json).unwrap_err();
+        assert!(
+            err.to_string().contains("illegal move e2e4 at ply 2"),
+            "{}",
+            err
+        );
+
+        let json = format!(
+            r#"{{"start_fen":"{}","moves":["e2e9"],"result":"*"}}"#,
+            Board::START_FEN
+        );
+        assert!(serde_json::from_str::<Game>(&json).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! Board representation, move validation and move generation for ChessBot.
+//!
+//! The `ChessBot` binary is a thin interactive front-end over this library.
+
+pub mod analysis;
+pub mod board;
+pub mod book;
+pub mod castling;
+pub mod chess_move;
+pub mod clock;
+pub mod db;
+pub mod diagram;
+pub mod eco;
+pub mod engine;
+pub mod errors;
+pub mod eval;
+pub mod game;
+#[cfg(feature = "lichess")]
+pub mod lichess;
+pub mod movegen;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod notation;
+pub mod perft;
+pub mod pgn;
+pub mod progress;
+pub mod san;
+pub mod search;
+pub mod session;
+pub mod teach;
+pub mod trace;
+pub mod tree;
+pub mod tt;
+pub mod tune;
+pub mod uci;
+pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
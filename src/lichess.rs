@@ -0,0 +1,523 @@
+//! Plays on lichess.org as a bot account, enabled with the `lichess` feature.
+//!
+//! The bot listens to the account's event stream, accepts standard-chess
+//! challenges at the configured speeds and plays each game on its own thread,
+//! following the game's state stream and answering with a search sized to the
+//! remaining clock.
+//!
+//! Everything that talks to the network goes through [`LichessApi`], so the logic
+//! that turns events into actions ([`handle_event`] and [`GameHandler`]) runs
+//! without one.
+
+use crate::board::{Board, Color, Strictness};
+use crate::chess_move::Move;
+use crate::game::GameError;
+use crate::search::{move_time_budget, search_with_stop, SearchOptions, MAX_DEPTH};
+
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const BASE_URL: &str = "https://lichess.org";
+
+/// Lines of a streamed response: one JSON object each, or empty for keep-alives.
+pub type Lines = Box<dyn Iterator<Item = io::Result<String>> + Send>;
+
+/// The parts of the lichess Bot API the bot uses.
+pub trait LichessApi {
+    /// `GET /api/stream/event`: challenges and games starting or finishing.
+    fn stream_events(&self) -> io::Result<Lines>;
+    /// `GET /api/bot/game/stream/{id}`: the full game, then each change of state.
+    fn stream_game(&self, game_id: &str) -> io::Result<Lines>;
+    fn accept_challenge(&self, challenge_id: &str) -> io::Result<()>;
+    /// `reason` is one of lichess's decline reasons, like `"variant"`.
+    fn decline_challenge(&self, challenge_id: &str, reason: &str) -> io::Result<()>;
+    fn make_move(&self, game_id: &str, uci: &str) -> io::Result<()>;
+}
+
+/// [`LichessApi`] over HTTPS, authenticated with a bot account's API token.
+#[derive(Clone)]
+pub struct HttpApi {
+    agent: ureq::Agent,
+    token: String,
+}
+
+impl HttpApi {
+    pub fn new(token: &str) -> HttpApi {
+        HttpApi {
+            agent: ureq::Agent::new(),
+            token: token.to_string(),
+        }
+    }
+
+    fn stream(&self, path: &str) -> io::Result<Lines> {
+        let response = self
+            .agent
+            .get(&format!("{}{}", BASE_URL, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call()
+            .map_err(io::Error::other)?;
+        Ok(Box::new(BufReader::new(response.into_reader()).lines()))
+    }
+
+    fn post(&self, path: &str, form: &[(&str, &str)]) -> io::Result<()> {
+        self.agent
+            .post(&format!("{}{}", BASE_URL, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .send_form(form)
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl LichessApi for HttpApi {
+    fn stream_events(&self) -> io::Result<Lines> {
+        self.stream("/api/stream/event")
+    }
+
+    fn stream_game(&self, game_id: &str) -> io::Result<Lines> {
+        self.stream(&format!("/api/bot/game/stream/{}", game_id))
+    }
+
+    fn accept_challenge(&self, challenge_id: &str) -> io::Result<()> {
+        self.post(&format!("/api/challenge/{}/accept", challenge_id), &[])
+    }
+
+    fn decline_challenge(&self, challenge_id: &str, reason: &str) -> io::Result<()> {
+        self.post(
+            &format!("/api/challenge/{}/decline", challenge_id),
+            &[("reason", reason)],
+        )
+    }
+
+    fn make_move(&self, game_id: &str, uci: &str) -> io::Result<()> {
+        self.post(&format!("/api/bot/game/{}/move/{}", game_id, uci), &[])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BotConfig {
+    /// Speeds to accept challenges at, named as lichess does: `bullet`, `blitz`,
+    /// `rapid`, `classical` or `correspondence`.
+    pub speeds: Vec<String>,
+    /// Games played at once. Challenges beyond this are declined for later.
+    pub max_games: usize,
+    /// How each move is searched. The clock stops the search, so the depth only
+    /// caps it.
+    pub search: SearchOptions,
+    /// Time kept back from every move for network lag, in milliseconds.
+    pub move_overhead_ms: u64,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        BotConfig {
+            speeds: vec![
+                "blitz".to_string(),
+                "rapid".to_string(),
+                "classical".to_string(),
+            ],
+            max_games: 1,
+            search: SearchOptions {
+                depth: MAX_DEPTH,
+                ..SearchOptions::default()
+            },
+            move_overhead_ms: 300,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Event {
+    Challenge {
+        challenge: Challenge,
+    },
+    GameStart {
+        game: StartedGame,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    id: String,
+    variant: Variant,
+    speed: String,
+}
+
+#[derive(Deserialize)]
+struct Variant {
+    key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartedGame {
+    game_id: String,
+    color: String,
+}
+
+/// What to do about one line of the event stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventAction {
+    Accept(String),
+    Decline {
+        id: String,
+        reason: &'static str,
+    },
+    /// Play the game with this id, as `color`.
+    PlayGame {
+        id: String,
+        color: Color,
+    },
+}
+
+/// Decides what to do about `line` from the event stream, with `games_in_progress`
+/// games already being played. `None` for events that need nothing done.
+pub fn handle_event(
+    config: &BotConfig,
+    games_in_progress: usize,
+    line: &str,
+) -> Option<EventAction> {
+    match serde_json::from_str(line).ok()? {
+        Event::Challenge { challenge } => {
+            let id = challenge.id;
+            let reason = if challenge.variant.key != "standard" {
+                "standard"
+            } else if !config.speeds.contains(&challenge.speed) {
+                "timeControl"
+            } else if games_in_progress >= config.max_games {
+                "later"
+            } else {
+                return Some(EventAction::Accept(id));
+            };
+            Some(EventAction::Decline { id, reason })
+        }
+        Event::GameStart { game } => {
+            let color = match game.color.as_str() {
+                "white" => Color::White,
+                "black" => Color::Black,
+                _ => return None,
+            };
+            Some(EventAction::PlayGame {
+                id: game.game_id,
+                color,
+            })
+        }
+        Event::Other => None,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum GameEvent {
+    #[serde(rename_all = "camelCase")]
+    GameFull {
+        initial_fen: String,
+        state: GameState,
+    },
+    GameState(GameState),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct GameState {
+    moves: String,
+    wtime: u64,
+    btime: u64,
+    winc: u64,
+    binc: u64,
+    status: String,
+}
+
+/// What to do about one line of a game's stream.
+#[derive(Debug, Clone)]
+pub enum GameAction {
+    /// Nothing; it is the opponent's move, or the line was chat or a keep-alive.
+    Wait,
+    /// Search `board` for up to `think_time` and play the best move.
+    Play {
+        board: Box<Board>,
+        think_time: Duration,
+    },
+    /// The game has ended, with lichess's status such as `mate` or `resign`.
+    Over(String),
+}
+
+/// Follows one game's state stream for the side playing `color`.
+pub struct GameHandler {
+    color: Color,
+    initial: Board,
+    move_overhead_ms: u64,
+}
+
+impl GameHandler {
+    pub fn new(color: Color, move_overhead_ms: u64) -> GameHandler {
+        GameHandler {
+            color,
+            initial: Board::new(),
+            move_overhead_ms,
+        }
+    }
+
+    /// Decides what to do about `line` from the game stream. Fails only if lichess
+    /// sends a position or move list this board cannot follow.
+    pub fn handle(&mut self, line: &str) -> Result<GameAction, GameError> {
+        let state = match serde_json::from_str(line) {
+            Ok(GameEvent::GameFull { initial_fen, state }) => {
+                if initial_fen != "startpos" {
+                    self.initial = Board::try_from_fen(&initial_fen, Strictness::Lax)?;
+                }
+                state
+            }
+            Ok(GameEvent::GameState(state)) => state,
+            Ok(GameEvent::Other) | Err(_) => return Ok(GameAction::Wait),
+        };
+
+        if state.status != "started" && state.status != "created" {
+            return Ok(GameAction::Over(state.status));
+        }
+        let mut board = self.initial;
+        board.apply_uci_moves(&state.moves)?;
+        if board.active_color != self.color {
+            return Ok(GameAction::Wait);
+        }
+
+        let (time_left, increment) = match self.color {
+            Color::White => (state.wtime, state.winc),
+            Color::Black => (state.btime, state.binc),
+        };
+        let think_time = move_time_budget(time_left, increment, self.move_overhead_ms);
+        Ok(GameAction::Play {
+            board: Box::new(board),
+            think_time: Duration::from_millis(think_time),
+        })
+    }
+}
+
+/// Searches `board` until `think_time` runs out or `options.depth` is reached.
+fn think(board: &Board, think_time: Duration, options: &SearchOptions) -> Option<Move> {
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            thread::sleep(think_time);
+            stop.store(true, Ordering::Relaxed);
+        });
+    }
+    let options = SearchOptions {
+        easy_moves: true,
+        ..options.clone()
+    };
+    search_with_stop(board, &options, &stop).best_move
+}
+
+/// Plays the game `game_id` as `color` until it ends or its stream closes.
+pub fn play_game<A: LichessApi>(
+    api: &A,
+    game_id: &str,
+    color: Color,
+    config: &BotConfig,
+) -> io::Result<()> {
+    let mut handler = GameHandler::new(color, config.move_overhead_ms);
+
+    for line in api.stream_game(game_id)? {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match handler.handle(&line) {
+            Ok(GameAction::Wait) => {}
+            Ok(GameAction::Play { board, think_time }) => {
+                if let Some(m) = think(&board, think_time, &config.search) {
+                    api.make_move(game_id, &m.to_string())?;
+                }
+            }
+            Ok(GameAction::Over(_)) => break,
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the bot until the event stream closes, playing every game it starts on its
+/// own thread.
+pub fn run<A: LichessApi + Clone + Send + 'static>(api: A, config: BotConfig) -> io::Result<()> {
+    let config = Arc::new(config);
+    let games_in_progress = Arc::new(AtomicUsize::new(0));
+
+    for line in api.stream_events()? {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let action = handle_event(&config, games_in_progress.load(Ordering::SeqCst), &line);
+        // A challenge can be withdrawn before we answer; that is no reason to stop
+        let answered = match action {
+            Some(EventAction::Accept(id)) => api.accept_challenge(&id),
+            Some(EventAction::Decline { id, reason }) => api.decline_challenge(&id, reason),
+            Some(EventAction::PlayGame { id, color }) => {
+                let (api, config) = (api.clone(), Arc::clone(&config));
+                let games_in_progress = Arc::clone(&games_in_progress);
+                games_in_progress.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    if let Err(err) = play_game(&api, &id, color, &config) {
+                        log::error!("game {}: {}", id, err);
+                    }
+                    games_in_progress.fetch_sub(1, Ordering::SeqCst);
+                });
+                Ok(())
+            }
+            None => Ok(()),
+        };
+        if let Err(err) = answered {
+            log::warn!("{}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const EVENTS: &str = include_str!("../tests/fixtures/lichess/events.ndjson");
+    const GAME: &str = include_str!("../tests/fixtures/lichess/game.ndjson");
+
+    /// Serves the game fixture and records the moves posted.
+    #[derive(Clone, Default)]
+    struct FakeApi {
+        moves: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl LichessApi for FakeApi {
+        fn stream_events(&self) -> io::Result<Lines> {
+            Ok(Box::new(EVENTS.lines().map(|line| Ok(line.to_string()))))
+        }
+
+        fn stream_game(&self, _: &str) -> io::Result<Lines> {
+            Ok(Box::new(GAME.lines().map(|line| Ok(line.to_string()))))
+        }
+
+        fn accept_challenge(&self, _: &str) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn decline_challenge(&self, _: &str, _: &str) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn make_move(&self, _: &str, uci: &str) -> io::Result<()> {
+            self.moves.lock().unwrap().push(uci.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_events_become_actions() {
+        let config = BotConfig::default();
+        let actions: Vec<EventAction> = EVENTS
+            .lines()
+            .filter_map(|line| handle_event(&config, 0, line))
+            .collect();
+
+        assert_eq!(
+            actions,
+            [
+                EventAction::Accept("yhXwLUn2".to_string()),
+                EventAction::Decline {
+                    id: "Qk4TeJwG".to_string(),
+                    reason: "standard"
+                },
+                EventAction::Decline {
+                    id: "bF2cR9aX".to_string(),
+                    reason: "timeControl"
+                },
+                EventAction::PlayGame {
+                    id: "yhXwLUn2".to_string(),
+                    color: Color::Black
+                },
+            ]
+        );
+
+        let busy = handle_event(&config, 1, EVENTS.lines().next().unwrap());
+        assert_eq!(
+            busy,
+            Some(EventAction::Decline {
+                id: "yhXwLUn2".to_string(),
+                reason: "later"
+            })
+        );
+    }
+
+    #[test]
+    fn test_game_stream_becomes_actions() {
+        let mut handler = GameHandler::new(Color::Black, 300);
+        let actions: Vec<GameAction> = GAME
+            .lines()
+            .map(|line| handler.handle(line).unwrap())
+            .collect();
+
+        // gameFull after 1. e4, our reply, 2. Nf3, chat, our reply, resignation
+        assert!(matches!(actions[0], GameAction::Play { .. }));
+        assert!(matches!(actions[1], GameAction::Wait));
+        match &actions[2] {
+            GameAction::Play { board, think_time } => {
+                assert_eq!(
+                    board.board_to_fen(),
+                    "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+                );
+                // 176 s left and a 2 s increment
+                assert_eq!(
+                    *think_time,
+                    Duration::from_millis(176_000 / 30 + 1_000 - 300)
+                );
+            }
+            other => panic!("expected to play, got {:?}", other),
+        }
+        assert!(matches!(actions[3], GameAction::Wait));
+        assert!(matches!(actions[4], GameAction::Wait));
+        assert!(matches!(&actions[5], GameAction::Over(status) if status == "resign"));
+    }
+
+    #[test]
+    fn test_game_from_a_fen() {
+        let mut handler = GameHandler::new(Color::White, 0);
+        let line = r#"{"type":"gameFull","id":"x","initialFen":"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1","state":{"type":"gameState","moves":"","wtime":60000,"btime":60000,"winc":0,"binc":0,"status":"started"}}"#;
+
+        match handler.handle(line).unwrap() {
+            GameAction::Play { board, .. } => {
+                assert_eq!(board.board_to_fen(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")
+            }
+            other => panic!("expected to play, got {:?}", other),
+        }
+
+        let illegal = r#"{"type":"gameState","moves":"a1a9","wtime":60000,"btime":60000,"winc":0,"binc":0,"status":"started"}"#;
+        assert!(handler.handle(illegal).is_err());
+    }
+
+    #[test]
+    fn test_play_game_posts_moves() {
+        let api = FakeApi::default();
+        let config = BotConfig {
+            search: SearchOptions {
+                depth: 2,
+                hash_mb: 1,
+                ..SearchOptions::default()
+            },
+            ..BotConfig::default()
+        };
+
+        play_game(&api, "yhXwLUn2", Color::Black, &config).unwrap();
+
+        let moves = api.moves.lock().unwrap();
+        assert_eq!(moves.len(), 2);
+    }
+}
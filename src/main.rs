@@ -1,12 +1,40 @@
-use board::{Board, Color};
-use chess_move::{is_in_checkmate, validate_move, Move};
-use std::io::{self, Write};
-
-mod board;
-mod chess_move;
-mod utils;
-
-const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+use chessbot::analysis::{self, Thresholds, BLUNDER_THRESHOLD, MISTAKE_THRESHOLD};
+use chessbot::board::{
+    Board, Color, FenError, GameStatus, PieceType, PositionValidity, Strictness,
+};
+use chessbot::book::{self, Book};
+use chessbot::chess_move::{is_promotion_move, square_name, Move};
+use chessbot::clock::{self, Clock};
+use chessbot::db::{self, GameDb, StoredGame};
+use chessbot::diagram::SvgOptions;
+use chessbot::engine::{
+    self, parse_root_moves, AlphaBetaEngine, Engine, MatchOptions, SearchLimits, ENGINE_NAMES,
+};
+use chessbot::eval;
+use chessbot::game::{Game, GameResult, Termination, DEFAULT_DRAW_WINDOW};
+use chessbot::notation;
+use chessbot::pgn;
+use chessbot::progress::{format_score, played_line, thinking_line, SPINNER};
+use chessbot::san::to_san;
+use chessbot::search::{
+    bench, mate_in, search_with_table, SearchOptions, SearchReporter, SearchResult, SearchStats,
+    BENCH_DEPTH, MAX_SKILL,
+};
+use chessbot::teach;
+use chessbot::trace::{JsonLogger, SearchReport};
+use chessbot::tree::OpeningTree;
+use chessbot::tt::{Bound, TranspositionTable};
+use chessbot::uci;
+use chessbot::utils::XorShift64;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /*
 A  B  C  D  E  F  G  H
@@ -21,53 +49,1586 @@ A  B  C  D  E  F  G  H
  0  1  2  3  4  5  6  7   1
  */
 fn main() {
-    let mut game_board = Board::fen_to_board(STARTING_FEN);
+    let args: Vec<String> = env::args().collect();
+    if let Some(idx) = args.iter().position(|arg| arg == "--trace-search") {
+        install_search_trace(args.get(idx + 1));
+    }
+    if args.get(1).map(String::as_str) == Some("build-book") {
+        build_book(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("bench") {
+        run_bench(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("evalfile") {
+        run_evalfile(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("features") {
+        run_features(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("match") {
+        run_match(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("analyze") {
+        run_analysis(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("tree") {
+        run_tree(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("games") {
+        run_games(&args[2..]);
+        return;
+    }
+    #[cfg(feature = "net")]
+    if args.get(1).map(String::as_str) == Some("serve") {
+        serve(&args[2..]);
+        return;
+    }
+    #[cfg(feature = "lichess")]
+    if let Some(idx) = args.iter().position(|arg| arg == "--lichess-token") {
+        run_lichess(args.get(idx + 1));
+        return;
+    }
+
+    let book = load_book_from_args();
+    let search_options = search_options_from_args();
+    let display = ThinkingDisplay::new();
+    let mut engine = engine_from_args(&search_options, &display);
+    let mut clock = clock_from_args();
+    let mut rng = XorShift64::new(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    );
+
+    // Ctrl-C stops the engine's search if it is thinking, and exits otherwise
+    let stop = Arc::new(AtomicBool::new(false));
+    let searching = Arc::new(AtomicBool::new(false));
+    {
+        let (stop, searching) = (Arc::clone(&stop), Arc::clone(&searching));
+        ctrlc::set_handler(move || {
+            if searching.load(Ordering::SeqCst) {
+                stop.store(true, Ordering::SeqCst);
+            } else {
+                process::exit(130);
+            }
+        })
+        .expect("failed to install the Ctrl-C handler");
+    }
+
+    let draw_window = draw_window_from_args();
+    // "--teach" explains in full which rule a rejected move breaks
+    let teach = args.iter().any(|arg| arg == "--teach");
+    // "--auto-queen" promotes to a queen when a move like e7e8 names no piece
+    let auto_queen = args.iter().any(|arg| arg == "--auto-queen");
+    let blunder_threshold = blunder_check_from_args();
+    // "--save-dir DIR" keeps every finished game, see "games list"
+    let games_db = games_db_from_args(&args);
+    let mut game = Game::new();
+    let mut last_move: Option<Move> = None;
 
-    game_board.display();
+    if let Some(clock) = clock.as_mut() {
+        clock.start(game.board().active_color);
+        println!("{}", clock);
+    }
+    show_board(game.board());
     loop {
-        print!("Enter your move in UCI format: ");
+        if let Some(m) = book
+            .as_ref()
+            .and_then(|b| b.pick_move(game.board(), &mut rng))
+        {
+            println!("Book move: {}", m);
+        }
+
+        let board = game.board();
+        print!(
+            "{} to move (move {}). Enter your move, e.g. e2e4, Nf3, Ng1-f3 or 7163 \
+             (or go, hint, eval, mate N, offer draw, resign, export svg FILE): ",
+            board.active_color, board.fullmove_number
+        );
         io::stdout().flush().unwrap();
 
         let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+        if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            break;
+        }
         let input = input.trim();
 
         if input == "exit" {
             break;
         }
+        if let Some(clock) = &clock {
+            check_flag(clock, &mut game);
+        }
+        if let Some(result) = game.result() {
+            println!("{}", result);
+            break;
+        }
+
+        if input == "resign" {
+            game.resign(game.board().active_color);
+            println!("{}", game.result().expect("resigning ends the game"));
+            break;
+        }
+
+        // "claim" ends the game by the fifty-move rule or threefold repetition
+        if input == "claim" {
+            match game.claim_draw() {
+                Some(_) => {
+                    println!("{}", game.result().expect("a claimed draw ends the game"));
+                    break;
+                }
+                None => {
+                    println!("No draw to claim");
+                    continue;
+                }
+            }
+        }
+
+        // The engine answers for the other side, so its evaluation is the opposite
+        // of the search's from the point of view of the side to move
+        if input == "offer draw" {
+            game.offer_draw(game.board().active_color);
+            let result =
+                interruptible_search(game.board(), &search_options, &stop, &searching, &display);
+            if game.answer_draw_offer(-result.score, draw_window) {
+                println!("Draw accepted");
+                println!("{}", game.result().expect("an agreed draw ends the game"));
+                break;
+            }
+            println!("Draw declined");
+            continue;
+        }
 
-        let m = Move::new(input.to_string());
-        let chess_move = validate_move(&game_board, &m);
+        // A GUI talking UCI; hand the rest of the session over, replaying "uci"
+        if input == "uci" {
+            uci::run(
+                io::Cursor::new("uci\n").chain(io::stdin().lock()),
+                io::stdout(),
+                book,
+            );
+            return;
+        }
 
-        if !chess_move {
-            println!("Invalid move");
+        // "mate N" looks for a forced mate without playing anything
+        if let Some(moves) = input.strip_prefix("mate") {
+            match moves.trim().parse::<u32>() {
+                Ok(moves) if moves > 0 => {
+                    let options = SearchOptions {
+                        mate: Some(moves),
+                        ..search_options.clone()
+                    };
+                    let result =
+                        interruptible_search(game.board(), &options, &stop, &searching, &display);
+                    let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+                    match mate_in(result.score) {
+                        Some(found) if found > 0 => println!("Mate in {}: {}", found, pv.join(" ")),
+                        _ => println!("No mate in {} found", moves),
+                    }
+                }
+                _ => println!("Usage: mate <moves>"),
+            }
             continue;
         }
 
-        game_board.move_peice(m);
+        // "hint" suggests a move, and "hint --explain" shows how every move scored
+        if let Some(flags) = input.strip_prefix("hint") {
+            let explain = match flags.trim() {
+                "" => false,
+                "--explain" => true,
+                _ => {
+                    println!("Usage: hint [--explain]");
+                    continue;
+                }
+            };
+            let options = SearchOptions {
+                trace_root: explain,
+                ..search_options.clone()
+            };
+            let result = interruptible_search(game.board(), &options, &stop, &searching, &display);
+            match result.best_move {
+                Some(m) => println!(
+                    "Hint: {} ({})",
+                    to_san(game.board(), &m),
+                    format_score(result.score)
+                ),
+                None => println!("No legal moves"),
+            }
+            if let Some(report) = &result.report {
+                print_search_report(game.board(), report);
+            }
+            continue;
+        }
+
+        // "eval" prints the static evaluation, and "eval --verbose" what it is made of
+        if let Some(flags) = input.strip_prefix("eval") {
+            let breakdown = eval::explain(game.board(), &search_options.eval_params);
+            match flags.trim() {
+                "" => println!("Evaluation {}", format_score(breakdown.white_total())),
+                "--verbose" => println!("{}", breakdown),
+                _ => println!("Usage: eval [--verbose]"),
+            }
+            continue;
+        }
 
-        if game_board.is_in_check(match game_board.active_color {
-            Color::White => Color::Black,
-            Color::Black => Color::White,
-        }) {
-            println!("Check!");
+        // "export svg FILE" saves a picture of the position, highlighting the last move
+        if let Some(path) = input.strip_prefix("export svg") {
+            let path = path.trim();
+            if path.is_empty() {
+                println!("Usage: export svg <file>");
+                continue;
+            }
+            let options = SvgOptions {
+                last_move,
+                ..SvgOptions::default()
+            };
+            match fs::write(path, game.board().to_svg(&options)) {
+                Ok(()) => println!("Wrote {}", path),
+                Err(err) => println!("Cannot write {}: {}", path, err),
+            }
+            continue;
+        }
 
-            if is_in_checkmate(&game_board) {
-                println!("Checkmate!");
-                break;
+        // "go" lets the engine pick the move: from the book if it has one, else by search
+        let m = if input == "go" {
+            let book_move = book
+                .as_ref()
+                .and_then(|b| b.pick_move(game.board(), &mut rng));
+            if let Some(m) = book_move {
+                println!("Engine plays {}", m);
+                m
+            } else {
+                let limits = SearchLimits {
+                    move_time: clock
+                        .as_ref()
+                        .map(|clock| clock.think_time(game.board().active_color, MOVE_OVERHEAD)),
+                    easy_moves: clock.is_some(),
+                    stop: Some(Arc::clone(&stop)),
+                    ..SearchLimits::default()
+                };
+                stop.store(false, Ordering::SeqCst);
+                searching.store(true, Ordering::SeqCst);
+                let best_move = display.run(|| engine.choose_move(&game, &limits));
+                searching.store(false, Ordering::SeqCst);
+                let Some(m) = best_move else {
+                    println!("No legal moves");
+                    continue;
+                };
+                match (
+                    engine.last_depth(),
+                    engine.last_score(),
+                    engine.last_stats(),
+                ) {
+                    (Some(depth), Some(score), Some(stats)) => println!(
+                        "{}",
+                        played_line(&to_san(game.board(), &m), depth, score, &stats)
+                    ),
+                    _ => println!("Engine plays {}", m),
+                }
+                m
             }
+        } else {
+            let parsed = match complete_promotion(game.board(), input, auto_queen) {
+                Some(Ok(m)) => Ok(m),
+                Some(Err(err)) => {
+                    println!("{}", err);
+                    continue;
+                }
+                None => notation::parse_any(game.board(), input),
+            };
+            match parsed {
+                Ok(m) => {
+                    let limits = SearchLimits {
+                        move_time: Some(BLUNDER_CHECK_TIME),
+                        ..SearchLimits::default()
+                    };
+                    let report = blunder_threshold.and_then(|threshold| {
+                        analysis::check_blunder(&game, m, &limits, threshold)
+                    });
+                    if let Some(report) = report {
+                        print!("{} — play it anyway? (y/n): ", report);
+                        io::stdout().flush().unwrap();
+                        let mut answer = String::new();
+                        io::stdin().read_line(&mut answer).unwrap();
+                        if !answer.trim().eq_ignore_ascii_case("y") {
+                            println!("Move taken back");
+                            continue;
+                        }
+                    }
+                    m
+                }
+                Err(err) => {
+                    // For coordinates, say which squares were read and what is wrong
+                    let reason = Move::from_uci(&notation::normalize_input(input))
+                        .and_then(|m| Some((m, game.board().check_move(&m).err()?)));
+                    match reason {
+                        Some((m, reason)) if teach => {
+                            println!("{}", teach::explain(game.board(), &m, &reason))
+                        }
+                        Some((m, reason)) => println!(
+                            "Cannot move from {} to {}: {}",
+                            square_name(m.from),
+                            square_name(m.to),
+                            reason
+                        ),
+                        None => println!("{}", err),
+                    }
+                    continue;
+                }
+            }
+        };
+        let mover = game.board().active_color;
+        if let Err(err) = game.play(m) {
+            println!("{}", err);
+            continue;
+        }
+        last_move = Some(m);
+        if let Some(clock) = clock.as_mut() {
+            clock.record_move(mover);
+            check_flag(clock, &mut game);
         }
 
-        if utils::is_stalemate(&game_board, match game_board.active_color {
-            Color::White => Color::Black,
-            Color::Black => Color::White,
-        }) {
-            println!("Game Over - Stalemate!");
+        match game.status() {
+            GameStatus::InProgress | GameStatus::Invalid => {}
+            GameStatus::Check => println!("Check!"),
+            status => {
+                println!("{}", status);
+                break;
+            }
+        }
+        // A flag that fell during the move
+        if let Some(result) = game.result() {
+            println!("{}", result);
             break;
         }
 
         print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-        game_board.display();
+        if let Some(clock) = &clock {
+            println!("{}", clock);
+        }
+        if game.moves().len() <= OPENING_PLIES {
+            if let Some((code, name)) = game.opening() {
+                println!("Opening: {} {}", code, name);
+            }
+        }
+        show_board(game.board());
+        for claim in game.claimable_draws() {
+            println!("You may claim a draw by {} — type 'claim'", claim);
+        }
+    }
+
+    if !game.moves().is_empty() {
+        println!();
+        print!("{}", game.to_pgn());
+    }
+    if let (Some(db), Some(_)) = (&games_db, game.result()) {
+        let tags = [
+            ("Event", "ChessBot game".to_string()),
+            ("Date", db::pgn_date(SystemTime::now())),
+        ]
+        .map(|(name, value)| (name.to_string(), value));
+        match db.append(&game, &tags) {
+            Ok(index) => println!("Saved as game {} in {}", index + 1, db.path().display()),
+            Err(err) => eprintln!(
+                "Could not save the game to {}: {}",
+                db.path().display(),
+                err
+            ),
+        }
+    }
+}
+
+/// The full move for coordinates such as `e7e8` that promote a pawn but name no
+/// piece: a queen with `--auto-queen`, else the piece the player picks when asked.
+/// `None` if `input` is no such move.
+fn complete_promotion(
+    board: &Board,
+    input: &str,
+    auto_queen: bool,
+) -> Option<Result<Move, String>> {
+    let m = Move::from_uci(&notation::normalize_input(input))?;
+    if m.promotion.is_some() || !is_promotion_move(board, m.from, m.to) {
+        return None;
+    }
+    if auto_queen {
+        return Some(Ok(Move::new_from_indices(
+            m.from,
+            m.to,
+            Some(PieceType::Queen),
+        )));
+    }
+    loop {
+        print!("Promote to (q/r/b/n)? ");
+        io::stdout().flush().unwrap();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return Some(Err("No piece chosen, move taken back".to_string()));
+        }
+        let piece = match answer.trim() {
+            "q" | "Q" => PieceType::Queen,
+            "r" | "R" => PieceType::Rook,
+            "b" | "B" => PieceType::Bishop,
+            "n" | "N" => PieceType::Knight,
+            _ => {
+                println!("Answer q, r, b or n");
+                continue;
+            }
+        };
+        return Some(Ok(Move::new_from_indices(m.from, m.to, Some(piece))));
+    }
+}
+
+/// Shows `board` with each side's material and White's lead in pawns.
+fn show_board(board: &Board) {
+    board.display();
+    println!(
+        "Material: White {}  Black {}  ({:+})",
+        board.material(Color::White),
+        board.material(Color::Black),
+        board.material_imbalance() / 100
+    );
+}
+
+/// Time kept back from the engine's clock for printing and reading input.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// How long the blunder check may search each side of a move.
+const BLUNDER_CHECK_TIME: Duration = Duration::from_millis(200);
+
+/// Moves count as the opening, with its name shown, for this many plies.
+const OPENING_PLIES: usize = 20;
+
+/// The clock set with `--clock MINUTES+SECONDS`, e.g. `--clock 5+3`, if any. Exits if
+/// the time control cannot be read.
+fn clock_from_args() -> Option<Clock> {
+    let args: Vec<String> = env::args().collect();
+    let idx = args.iter().position(|arg| arg == "--clock")?;
+    match args
+        .get(idx + 1)
+        .and_then(|tc| clock::parse_time_control(tc))
+    {
+        Some((base, increment)) => Some(Clock::new(base, increment)),
+        None => {
+            eprintln!("--clock needs a time control like 5+3 (minutes+seconds)");
+            process::exit(2);
+        }
+    }
+}
+
+/// Ends `game` on time if a flag has fallen. Running out of time loses, unless the
+/// opponent could never mate, which is a draw.
+fn check_flag(clock: &Clock, game: &mut Game) {
+    if let Some(flagged) = clock.flagged() {
+        println!("{}", clock);
+        let result = if game.board().has_mating_material(!flagged) {
+            GameResult::win(!flagged, Termination::Timeout)
+        } else {
+            GameResult::draw(Termination::Timeout)
+        };
+        game.set_result(result);
+    }
+}
+
+/// How far from 0.00 the engine's evaluation may be, in centipawns, for it to
+/// accept a draw offer: `--draw-window CP`, 25 unless given.
+fn draw_window_from_args() -> i32 {
+    let args: Vec<String> = env::args().collect();
+    match args.iter().position(|arg| arg == "--draw-window") {
+        Some(idx) => match args.get(idx + 1).and_then(|n| n.parse::<i32>().ok()) {
+            Some(window) if window >= 0 => window,
+            _ => {
+                eprintln!("--draw-window needs a number of centipawns");
+                process::exit(2);
+            }
+        },
+        None => DEFAULT_DRAW_WINDOW,
+    }
+}
+
+/// The threshold in centipawns for warning about a blundered move before playing it:
+/// `--blunder-check [CP]`, [`BLUNDER_THRESHOLD`] if no number follows. `None`
+/// without the flag.
+fn blunder_check_from_args() -> Option<i32> {
+    let args: Vec<String> = env::args().collect();
+    let idx = args.iter().position(|arg| arg == "--blunder-check")?;
+    match args.get(idx + 1).filter(|arg| !arg.starts_with("--")) {
+        Some(n) => match n.parse::<i32>() {
+            Ok(threshold) if threshold > 0 => Some(threshold),
+            _ => {
+                eprintln!("--blunder-check takes a number of centipawns");
+                process::exit(2);
+            }
+        },
+        None => Some(BLUNDER_THRESHOLD),
+    }
+}
+
+/// `ChessBot build-book games.pgn book.bin [--max-plies N]`: writes a Polyglot book
+/// built from the games in a PGN file.
+fn build_book(args: &[String]) {
+    let (pgn_path, book_path) = match args {
+        [pgn_path, book_path, ..] => (pgn_path, book_path),
+        _ => {
+            eprintln!("usage: ChessBot build-book games.pgn book.bin [--max-plies N]");
+            process::exit(2);
+        }
+    };
+    let max_plies = match args.iter().position(|arg| arg == "--max-plies") {
+        Some(idx) => match args.get(idx + 1).and_then(|n| n.parse().ok()) {
+            Some(max_plies) => max_plies,
+            None => {
+                eprintln!("--max-plies needs a number");
+                process::exit(2);
+            }
+        },
+        None => 20,
+    };
+
+    let pgn_text = match fs::read_to_string(pgn_path) {
+        Ok(pgn_text) => pgn_text,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", pgn_path, err);
+            process::exit(1);
+        }
+    };
+
+    let (book, skipped) = book::build_from_pgn_counting_skipped(&pgn_text, max_plies);
+    if skipped > 0 {
+        eprintln!("warning: skipped {} malformed game(s)", skipped);
+    }
+    if let Err(err) = book.write(book_path) {
+        eprintln!("Could not write {}: {}", book_path, err);
+        process::exit(1);
+    }
+    println!("Wrote {} entries to {}", book.len(), book_path);
+}
+
+/// `ChessBot bench [depth]`: searches the built-in bench positions and prints the
+/// total node count, which changes only when the search does, and the speed.
+fn run_bench(args: &[String]) {
+    let depth = match args.first() {
+        Some(depth) => match depth.parse() {
+            Ok(depth) if depth > 0 => depth,
+            _ => {
+                eprintln!("usage: ChessBot bench [depth]");
+                process::exit(2);
+            }
+        },
+        None => BENCH_DEPTH,
+    };
+
+    let stats = bench(depth);
+    println!("Nodes searched: {}", stats.nodes);
+    println!("Nodes/second: {}", stats.nps());
+    println!("Time: {} ms", stats.elapsed.as_millis());
+}
+
+/// `ChessBot features fens.txt`: prints one JSON object per FEN in the file, with
+/// the position's [`MoveStats`](chessbot::board::MoveStats), as it reads them. Blank
+/// lines and `#` comments are skipped, and other lines that are not a FEN with a
+/// warning. En passant squares and castling rights that cannot be right are dropped,
+/// as the FEN written back shows.
+fn run_features(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: ChessBot features fens.txt");
+            process::exit(2);
+        }
+    };
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let mut stdout = io::stdout().lock();
+    for (idx, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("Could not read {}: {}", path, err);
+                process::exit(1);
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_fen_line(line, idx + 1) {
+            Ok(board) => {
+                let stats = board.move_stats().to_json();
+                // A FEN needs no escaping, so it can be written as it is
+                let written = writeln!(
+                    stdout,
+                    "{{\"fen\":\"{}\",{}",
+                    board.board_to_fen(),
+                    &stats[1..]
+                );
+                if written.is_err() {
+                    return;
+                }
+            }
+            Err(err) => eprintln!("warning: {}", err),
+        }
+    }
+}
+
+/// Search depth of `evalfile` unless given one.
+const EVALFILE_DEPTH: u32 = 4;
+/// `evalfile` reports its progress every this many positions.
+const EVALFILE_PROGRESS_EVERY: usize = 10_000;
+
+/// Reads line `line_number` of a FEN file as other tools write FENs. A position the
+/// rules cannot be applied to is an error; what is odd about one play could not
+/// reach is printed as a warning and the position kept.
+fn parse_fen_line(line: &str, line_number: usize) -> Result<Board, String> {
+    let board = Board::try_from_fen(line, Strictness::Lax)
+        .map_err(|err| format!("line {}: {}", line_number, err))?;
+    match board.validity() {
+        PositionValidity::Ok => {}
+        PositionValidity::Warning(warnings) => {
+            for warning in warnings {
+                eprintln!("warning: line {}: {}", line_number, warning);
+            }
+        }
+        PositionValidity::Illegal(reason) => {
+            return Err(format!(
+                "line {}: {}",
+                line_number,
+                FenError::Illegal(reason)
+            ))
+        }
+    }
+    Ok(board)
+}
+
+/// `ChessBot evalfile input output [--depth N | --static] [--threads N]`: scores every
+/// FEN in `input` for a training set, by a search to depth N or by the static
+/// evaluation, and writes the FEN, the score in centipawns for the side to move and
+/// the best move to `output`: as JSON lines if its name ends in `.jsonl`, as CSV
+/// otherwise. The static evaluation has no best move. Each worker thread searches
+/// with its own transposition table; rows are written in the order of the input.
+/// Lines that are not a FEN are reported with their number and skipped, like blank
+/// lines and `#` comments.
+fn run_evalfile(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: ChessBot evalfile input output [--depth N | --static] [--threads N]");
+        process::exit(2);
+    };
+    let (input_path, output_path) = match args {
+        [input, output, ..] if !input.starts_with("--") && !output.starts_with("--") => {
+            (input, output)
+        }
+        _ => usage(),
+    };
+    let number_after = |flag: &str| {
+        let idx = args.iter().position(|arg| arg == flag)?;
+        Some(
+            args.get(idx + 1)
+                .and_then(|n| n.parse::<u32>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or_else(|| usage()),
+        )
+    };
+    let threads = number_after("--threads").unwrap_or(1) as usize;
+    let depth = match (
+        number_after("--depth"),
+        args.iter().any(|arg| arg == "--static"),
+    ) {
+        (Some(_), true) => usage(),
+        (depth, false) => Some(depth.unwrap_or(EVALFILE_DEPTH)),
+        (None, true) => None,
+    };
+    let options = SearchOptions {
+        depth: depth.unwrap_or(EVALFILE_DEPTH),
+        threads: 1,
+        ..SearchOptions::default()
+    };
+    let jsonl = output_path.ends_with(".jsonl");
+
+    let input = match fs::File::open(input_path) {
+        Ok(file) => io::BufReader::new(file),
+        Err(err) => {
+            eprintln!("Could not read {}: {}", input_path, err);
+            process::exit(1);
+        }
+    };
+    let mut output = match fs::File::create(output_path) {
+        Ok(file) => io::BufWriter::new(file),
+        Err(err) => {
+            eprintln!("Could not create {}: {}", output_path, err);
+            process::exit(1);
+        }
+    };
+
+    // Positions go to the workers numbered in input order, and come back as rows, or
+    // as the error of a line that was not a FEN, in whatever order they finish
+    let (job_sender, jobs) = mpsc::sync_channel::<(usize, usize, String)>(threads * 64);
+    let jobs = Arc::new(Mutex::new(jobs));
+    let (row_sender, rows) = mpsc::channel::<(usize, Result<String, String>)>();
+    let started = Instant::now();
+    let (written, skipped) = thread::scope(|scope| {
+        for _ in 0..threads {
+            let (jobs, row_sender, options) = (Arc::clone(&jobs), row_sender.clone(), &options);
+            scope.spawn(move || {
+                let tt = TranspositionTable::new(options.hash_mb);
+                let stop = AtomicBool::new(false);
+                loop {
+                    let job = jobs.lock().unwrap().recv();
+                    let (idx, line_number, line) = match job {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    let row = parse_fen_line(&line, line_number).map(|board| {
+                        let (score, best_move) = match depth {
+                            Some(_) => {
+                                let result =
+                                    search_with_table(&board, options, &stop, &tt, &mut ());
+                                (result.score, result.best_move)
+                            }
+                            None => (eval::evaluate(&board, &options.eval_params), None),
+                        };
+                        evalfile_row(&board.board_to_fen(), score, best_move, jsonl)
+                    });
+                    if row_sender.send((idx, row)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(row_sender);
+
+        scope.spawn(move || {
+            let mut idx = 0;
+            for (number, line) in input.lines().enumerate() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(err) => {
+                        eprintln!("Could not read {}: {}", input_path, err);
+                        process::exit(1);
+                    }
+                };
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if job_sender
+                    .send((idx, number + 1, line.to_string()))
+                    .is_err()
+                {
+                    return;
+                }
+                idx += 1;
+            }
+        });
+
+        let write_failed = |err: io::Error| -> ! {
+            eprintln!("Could not write {}: {}", output_path, err);
+            process::exit(1);
+        };
+        if !jsonl {
+            writeln!(output, "fen,score_cp,best_move").unwrap_or_else(|err| write_failed(err));
+        }
+        let (mut written, mut skipped) = (0, 0);
+        let mut pending = BTreeMap::new();
+        for (idx, row) in rows {
+            pending.insert(idx, row);
+            while let Some(row) = pending.remove(&(written + skipped)) {
+                match row {
+                    Ok(row) => {
+                        writeln!(output, "{}", row).unwrap_or_else(|err| write_failed(err));
+                        written += 1;
+                    }
+                    Err(err) => {
+                        eprintln!("warning: {}", err);
+                        skipped += 1;
+                    }
+                }
+                if (written + skipped) % EVALFILE_PROGRESS_EVERY == 0 {
+                    let seconds = started.elapsed().as_secs_f64().max(1e-3);
+                    eprintln!(
+                        "{} positions, {:.0} a second",
+                        written + skipped,
+                        (written + skipped) as f64 / seconds
+                    );
+                }
+            }
+        }
+        output.flush().unwrap_or_else(|err| write_failed(err));
+        (written, skipped)
+    });
+    eprintln!(
+        "Wrote {} positions to {} in {:.1}s, skipped {}",
+        written,
+        output_path,
+        started.elapsed().as_secs_f64(),
+        skipped
+    );
+}
+
+/// One row of `evalfile`'s output. A FEN needs no quoting in either format.
+fn evalfile_row(fen: &str, score: i32, best_move: Option<Move>, jsonl: bool) -> String {
+    match (jsonl, best_move) {
+        (true, Some(m)) => format!(
+            "{{\"fen\":\"{}\",\"score_cp\":{},\"best_move\":\"{}\"}}",
+            fen, score, m
+        ),
+        (true, None) => format!(
+            "{{\"fen\":\"{}\",\"score_cp\":{},\"best_move\":null}}",
+            fen, score
+        ),
+        (false, Some(m)) => format!("{},{},{}", fen, score, m),
+        (false, None) => format!("{},{},", fen, score),
+    }
+}
+
+/// `ChessBot match ENGINE ENGINE [games] [--seed N]`: plays the two engines against
+/// each other from the starting position, alternating colors, and prints the first
+/// one's score. The seed picks the games' evaluation jitter, see `--jitter`.
+fn run_match(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!(
+            "usage: ChessBot match ENGINE ENGINE [games] [--seed N]  (engines: {})",
+            ENGINE_NAMES.join(", ")
+        );
+        process::exit(2);
+    };
+    let options = search_options_from_args();
+    let (mut first, mut second) = match args {
+        [first, second, ..] => match (
+            engine::engine_by_name(first, &options),
+            engine::engine_by_name(second, &options),
+        ) {
+            (Some(first), Some(second)) => (first, second),
+            _ => usage(),
+        },
+        _ => usage(),
+    };
+    let mut match_options = MatchOptions::default();
+    if let Some(games) = args.get(2).filter(|arg| !arg.starts_with("--")) {
+        match_options.games = games.parse().unwrap_or_else(|_| usage());
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--seed") {
+        match_options.seed = args
+            .get(idx + 1)
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_else(|| usage());
+    }
+
+    let score = engine::play_match(
+        first.as_mut(),
+        second.as_mut(),
+        &SearchLimits::default(),
+        &match_options,
+    );
+    println!(
+        "{} vs {}: +{} ={} -{} ({} unfinished)",
+        first.name(),
+        second.name(),
+        score.wins,
+        score.draws,
+        score.losses,
+        score.unfinished
+    );
+}
+
+/// `ChessBot serve [addr]`: plays games over TCP in newline-delimited JSON, see
+/// `chessbot::net`. Listens on 127.0.0.1:7878 unless given an address.
+#[cfg(feature = "net")]
+fn serve(args: &[String]) {
+    let addr = args.first().map_or("127.0.0.1:7878", String::as_str);
+    let server = match chessbot::net::Server::bind(addr, search_options_from_args()) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Could not listen on {}: {}", addr, err);
+            process::exit(2);
+        }
+    };
+    println!("Listening on {}", addr);
+    if let Err(err) = server.serve() {
+        eprintln!("Server stopped: {}", err);
+        process::exit(1);
+    }
+}
+
+/// `ChessBot --lichess-token TOKEN [--threads N]`: plays as the lichess bot account
+/// the token belongs to until the event stream closes.
+#[cfg(feature = "lichess")]
+fn run_lichess(token: Option<&String>) {
+    use chessbot::lichess::{self, BotConfig, HttpApi};
+
+    let token = match token {
+        Some(token) => token,
+        None => {
+            eprintln!("--lichess-token needs a bot account's API token");
+            process::exit(2);
+        }
+    };
+    let config = BotConfig {
+        search: SearchOptions {
+            depth: chessbot::search::MAX_DEPTH,
+            ..search_options_from_args()
+        },
+        ..BotConfig::default()
+    };
+    // A search trace keeps the logger; failed games then only go to the trace
+    if log::set_boxed_logger(Box::new(StderrLogger)).is_ok() {
+        log::set_max_level(log::LevelFilter::Warn);
+    }
+    if let Err(err) = lichess::run(HttpApi::new(token), config) {
+        eprintln!("Lost the connection to lichess: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Writes warnings and errors to stderr, like the rest of the command line, for
+/// the games and challenges the lichess bot gives up on.
+#[cfg(feature = "lichess")]
+struct StderrLogger;
+
+#[cfg(feature = "lichess")]
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let level = record.level().as_str().to_lowercase();
+            eprintln!("{}: {}", level, record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Plies an opening tree keeps unless `--depth` says otherwise.
+const TREE_DEPTH: usize = 8;
+
+/// `ChessBot tree games.pgn [--depth N] [--top N] [--json OUT]`: builds the opening
+/// tree of the games and prints the most played moves from each position, with how
+/// often they were played and how those games ended.
+fn run_tree(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: ChessBot tree games.pgn [--depth N] [--top N] [--json OUT]");
+        process::exit(2);
+    };
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => usage(),
+    };
+    let number_after = |flag: &str| {
+        let idx = args.iter().position(|arg| arg == flag)?;
+        Some(
+            args.get(idx + 1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or_else(|| usage()),
+        )
+    };
+    let depth = number_after("--depth").unwrap_or(TREE_DEPTH);
+    let top = number_after("--top").unwrap_or(3);
+
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+    let pgns = pgn::parse_games(&text);
+    let games: Vec<Game> = pgns
+        .iter()
+        .filter_map(|pgn| Game::from_pgn(pgn).ok())
+        .collect();
+    if games.len() < pgns.len() {
+        eprintln!(
+            "warning: skipped {} malformed game(s)",
+            pgns.len() - games.len()
+        );
+    }
+    let tree = OpeningTree::from_games(&games, depth);
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--json") {
+        let out = args.get(idx + 1).unwrap_or_else(|| usage());
+        if let Err(err) = fs::write(out, tree.to_json(&Board::new())) {
+            eprintln!("Could not write {}: {}", out, err);
+            process::exit(1);
+        }
+        println!("Wrote the tree to {}", out);
+    }
+    let start = Board::new();
+    println!("{} games", tree.stats(&start).games);
+    // Deep enough for the last ply's indent, so the numbers line up
+    let column = 2 * depth + 12;
+    print_tree(&tree, &start, depth, top, 0, column);
+}
+
+/// Prints the `top` most played moves from `board`, and those after them down to
+/// `depth` plies, indented by ply with the numbers from `column` on.
+fn print_tree(
+    tree: &OpeningTree,
+    board: &Board,
+    depth: usize,
+    top: usize,
+    indent: usize,
+    column: usize,
+) {
+    if depth == 0 {
+        return;
+    }
+    let games = tree.stats(board);
+    for (m, stats) in tree.continuations(board).into_iter().take(top) {
+        let number = match board.active_color {
+            Color::White => format!("{}.", board.fullmove_number),
+            Color::Black => format!("{}...", board.fullmove_number),
+        };
+        let line = format!("{:indent$}{} {}", "", number, to_san(board, &m));
+        println!(
+            "{:<column$} {:5.1}% ({})  +{:.0}% ={:.0}% -{:.0}%",
+            line,
+            games.percent(stats.games),
+            stats.games,
+            stats.percent(stats.white_wins),
+            stats.percent(stats.draws),
+            stats.percent(stats.black_wins),
+        );
+        let mut after = *board;
+        after.make_move_unchecked(&m);
+        print_tree(tree, &after, depth - 1, top, indent + 2, column);
+    }
+}
+
+/// `ChessBot analyze game.pgn [--depth N] [--blunder CP] [--pgn OUT]`: has the engine
+/// score every move of the first game in the file, then steps through it on command,
+/// and ends with the moves that lost at least the blunder threshold. `--pgn` also
+/// writes the game annotated: the engine's preferred lines as variations, its scores
+/// as comments, and `$2` and `$4` on mistakes and blunders (`--mistake CP`).
+/// `--game N [--save-dir DIR]` in place of the file analyzes a saved game.
+fn run_analysis(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!(
+            "usage: ChessBot analyze (game.pgn | --game N [--save-dir DIR]) [--depth N] [--blunder CP] [--mistake CP] [--pgn OUT] [--only MOVE,...]"
+        );
+        process::exit(2);
+    };
+    let number_after = |flag: &str| {
+        let idx = args.iter().position(|arg| arg == flag)?;
+        Some(
+            args.get(idx + 1)
+                .and_then(|n| n.parse::<u32>().ok())
+                .unwrap_or_else(|| usage()),
+        )
+    };
+    let limits = SearchLimits {
+        depth: number_after("--depth"),
+        ..SearchLimits::default()
+    };
+    let threshold = number_after("--blunder").map_or(BLUNDER_THRESHOLD, |cp| cp as i32);
+    let thresholds = Thresholds {
+        mistake: number_after("--mistake").map_or(MISTAKE_THRESHOLD, |cp| cp as i32),
+        blunder: threshold,
+    };
+
+    let game = match (args.first(), number_after("--game")) {
+        (Some(path), _) if !path.starts_with("--") => read_first_game(path),
+        (_, Some(number)) if number > 0 => {
+            let db = games_db_from_args(args).unwrap_or_else(|| GameDb::new("."));
+            load_stored_game(&db, number as usize).game
+        }
+        _ => usage(),
+    };
+
+    // "--only Nf3,e4" scores just those moves where the game stops
+    if let Some(idx) = args.iter().position(|arg| arg == "--only") {
+        let tokens: Vec<&str> = match args.get(idx + 1) {
+            Some(list) => list.split(',').collect(),
+            None => usage(),
+        };
+        let candidates = match parse_root_moves(game.board(), &tokens) {
+            Ok(candidates) => candidates,
+            Err(err) => {
+                eprintln!("--only: {}", err);
+                process::exit(2);
+            }
+        };
+        show_board(game.board());
+        for (m, score) in analysis::score_candidates(&game, &candidates, &limits) {
+            println!("  {:<8} {}", to_san(game.board(), &m), format_score(score));
+        }
+        return;
+    }
+
+    println!("Analyzing {} plies...", game.moves().len());
+    let annotations = analysis::annotate(&game, &limits);
+    if let Some(idx) = args.iter().position(|arg| arg == "--pgn") {
+        let out = args.get(idx + 1).unwrap_or_else(|| usage());
+        let tree = analysis::annotated_game(&game, &annotations, &thresholds);
+        if let Err(err) = fs::write(out, tree.to_pgn()) {
+            eprintln!("Could not write {}: {}", out, err);
+            process::exit(1);
+        }
+        println!("Wrote the annotated game to {}", out);
+    }
+    let mut positions = vec![*game.start()];
+    for m in game.moves() {
+        let mut board = *positions.last().expect("starts with the start");
+        board.make_move_unchecked(m);
+        positions.push(board);
+    }
+
+    let mut ply = 0;
+    loop {
+        show_board(&positions[ply]);
+        match ply.checked_sub(1).map(|idx| &annotations[idx]) {
+            Some(annotation) => {
+                println!(
+                    "{}  eval {}",
+                    annotation,
+                    format_score(annotation.eval_after)
+                );
+                if let Some(best) = &annotation.best {
+                    println!("Engine preferred {}", best);
+                }
+            }
+            None => match annotations.first() {
+                Some(first) => println!("Start  eval {}", format_score(first.eval_before)),
+                None => println!("Start"),
+            },
+        }
+
+        print!("n(ext), p(revious), goto PLY or q(uit): ");
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            break;
+        }
+        match input.trim() {
+            "n" if ply < annotations.len() => ply += 1,
+            "p" if ply > 0 => ply -= 1,
+            "n" | "p" => println!("No more moves that way"),
+            "q" => break,
+            other => match other
+                .strip_prefix("goto")
+                .map(|n| n.trim().parse::<usize>())
+            {
+                Some(Ok(target)) if target <= annotations.len() => ply = target,
+                _ => println!("Usage: n, p, goto 0-{} or q", annotations.len()),
+            },
+        }
+    }
+
+    let swings = analysis::largest_swings(&annotations, threshold);
+    if swings.is_empty() {
+        println!("No move lost {} centipawns or more", threshold);
+        return;
+    }
+    println!("Largest swings:");
+    for annotation in swings {
+        println!(
+            "  {}  {} -> {}{}",
+            annotation,
+            format_score(annotation.eval_before),
+            format_score(annotation.eval_after),
+            annotation
+                .best
+                .as_ref()
+                .map_or(String::new(), |best| format!("  (best {})", best))
+        );
+    }
+}
+
+/// The first game of the PGN file at `path`. Exits if there is none or it cannot be
+/// replayed.
+fn read_first_game(path: &str) -> Game {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+    match pgn::parse_games(&text).first().map(Game::from_pgn) {
+        Some(Ok(game)) => game,
+        Some(Err(err)) => {
+            eprintln!("Could not replay the game in {}: {}", path, err);
+            process::exit(1);
+        }
+        None => {
+            eprintln!("No game in {}", path);
+            process::exit(1);
+        }
+    }
+}
+
+/// `ChessBot games (list | show N) [--save-dir DIR]`: lists the games saved with
+/// `--save-dir`, in the current directory unless given, or prints game `N` of the
+/// list as PGN. Games that cannot be read back are left out with a warning.
+fn run_games(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: ChessBot games (list | show N) [--save-dir DIR]");
+        process::exit(2);
+    };
+    let db = games_db_from_args(args).unwrap_or_else(|| GameDb::new("."));
+    match args.first().map(String::as_str) {
+        Some("list") => {
+            let listing = match db.list() {
+                Ok(listing) => listing,
+                Err(err) => {
+                    eprintln!("Could not read {}: {}", db.path().display(), err);
+                    process::exit(1);
+                }
+            };
+            for skipped in &listing.skipped {
+                eprintln!("warning: {}", skipped);
+            }
+            if listing.games.is_empty() {
+                println!("No games saved in {}", db.path().display());
+            }
+            for stored in &listing.games {
+                println!("{}", game_summary(stored));
+            }
+        }
+        Some("show") => {
+            let number = args
+                .get(1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or_else(|| usage());
+            print!("{}", load_stored_game(&db, number).to_pgn());
+        }
+        _ => usage(),
+    }
+}
+
+/// One line of `games list`, e.g.
+/// `  2. 2024.03.09  Anna - Ben  0-1  (Black wins by resignation, 41 plies)`.
+fn game_summary(stored: &StoredGame) -> String {
+    let result = stored.game.result();
+    format!(
+        "{:>3}. {}  {} - {}  {}  ({}, {} {})",
+        stored.index + 1,
+        stored.tag("Date").unwrap_or("????.??.??"),
+        stored.tag("White").unwrap_or("?"),
+        stored.tag("Black").unwrap_or("?"),
+        result.map_or("*".to_string(), |result| result.tag().to_string()),
+        result.map_or("unfinished".to_string(), |result| result.to_string()),
+        stored.game.moves().len(),
+        if stored.game.moves().len() == 1 {
+            "ply"
+        } else {
+            "plies"
+        }
+    )
+}
+
+/// Game `number` of `db`, counting from 1 as `games list` does. Exits if there is
+/// no such game or it cannot be read back.
+fn load_stored_game(db: &GameDb, number: usize) -> StoredGame {
+    match db.load(number - 1) {
+        Ok(stored) => stored,
+        Err(err) => {
+            eprintln!("Could not load from {}: {}", db.path().display(), err);
+            process::exit(1);
+        }
+    }
+}
+
+/// The store in the directory given with `--save-dir DIR`, if any. Exits if no
+/// directory follows the flag.
+fn games_db_from_args(args: &[String]) -> Option<GameDb> {
+    let idx = args.iter().position(|arg| arg == "--save-dir")?;
+    match args.get(idx + 1).filter(|arg| !arg.starts_with("--")) {
+        Some(dir) => Some(GameDb::new(dir)),
+        None => {
+            eprintln!("--save-dir needs a directory to keep games in");
+            process::exit(2);
+        }
+    }
+}
+
+/// Prints every root move of `report` with its score, depth and line, best first.
+fn print_search_report(board: &Board, report: &SearchReport) {
+    println!(
+        "{:<8} {:>9}  {:>5}  {:<9}  line",
+        "move", "score", "depth", "bound"
+    );
+    for root in &report.moves {
+        let bound = match root.bound {
+            Bound::Exact => "exact",
+            Bound::Upper => "fail low",
+            Bound::Lower => "fail high",
+        };
+        let pv: Vec<String> = root.pv.iter().map(|m| m.to_string()).collect();
+        println!(
+            "{:<8} {:>9}  {:>5}  {:<9}  {}",
+            to_san(board, &root.root_move),
+            format_score(root.score),
+            root.depth,
+            bound,
+            pv.join(" ")
+        );
+    }
+}
+
+/// What the search thread tells the main thread while the engine thinks.
+enum SearchUpdate {
+    /// A depth was completed with this score.
+    Iteration {
+        depth: u32,
+        score: i32,
+        stats: SearchStats,
+    },
+    /// About once a second while a depth runs.
+    Progress(SearchStats),
+    /// The search thread is done.
+    Done,
+}
+
+/// Passes a search's progress to the [`ThinkingDisplay`] that shows it.
+struct ChannelReporter(mpsc::Sender<SearchUpdate>);
+
+impl SearchReporter for ChannelReporter {
+    fn iteration(&mut self, result: &SearchResult) {
+        // The display only goes away with the program
+        let _ = self.0.send(SearchUpdate::Iteration {
+            depth: result.depth,
+            score: result.score,
+            stats: result.stats,
+        });
+    }
+
+    fn progress(&mut self, stats: &SearchStats) {
+        let _ = self.0.send(SearchUpdate::Progress(*stats));
+    }
+}
+
+/// How often the spinner turns while the engine thinks.
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+/// Shows how the engine's thinking is going while it searches on another thread: a
+/// spinner line redrawn in place on a terminal, or a plain line about once a second
+/// when the output goes to a pipe or file, which a redrawn line would clutter.
+struct ThinkingDisplay {
+    sender: mpsc::Sender<SearchUpdate>,
+    receiver: mpsc::Receiver<SearchUpdate>,
+    terminal: bool,
+}
+
+impl ThinkingDisplay {
+    fn new() -> ThinkingDisplay {
+        let (sender, receiver) = mpsc::channel();
+        ThinkingDisplay {
+            sender,
+            receiver,
+            terminal: io::stdout().is_terminal(),
+        }
+    }
+
+    /// A reporter for the searches whose progress this shows.
+    fn reporter(&self) -> ChannelReporter {
+        ChannelReporter(self.sender.clone())
+    }
+
+    /// Runs `work` on another thread, showing what its searches report until it is
+    /// done, and returns what it returns.
+    fn run<T: Send>(&self, work: impl FnOnce() -> T + Send) -> T {
+        let sender = self.sender.clone();
+        thread::scope(|scope| {
+            let worker = scope.spawn(move || {
+                let done = work();
+                let _ = sender.send(SearchUpdate::Done);
+                done
+            });
+
+            let started = Instant::now();
+            let (mut depth, mut score) = (1, None);
+            let mut stats = SearchStats::default();
+            let mut frame = 0;
+            // A panicking search never says it is done
+            while !worker.is_finished() {
+                match self.receiver.recv_timeout(SPINNER_TICK) {
+                    Ok(SearchUpdate::Done) => break,
+                    Ok(SearchUpdate::Iteration {
+                        depth: completed,
+                        score: completed_score,
+                        stats: completed_stats,
+                    }) => {
+                        depth = completed + 1;
+                        score = Some(completed_score);
+                        stats = completed_stats;
+                    }
+                    Ok(SearchUpdate::Progress(progress)) => {
+                        stats = progress;
+                        if !self.terminal {
+                            println!("{}", thinking_line(depth, score, &stats));
+                        }
+                    }
+                    Err(_) => {}
+                }
+                if self.terminal {
+                    let shown = SearchStats {
+                        elapsed: started.elapsed(),
+                        ..stats
+                    };
+                    print!(
+                        "\r{} {}\x1b[K",
+                        SPINNER[frame % SPINNER.len()],
+                        thinking_line(depth, score, &shown)
+                    );
+                    io::stdout().flush().unwrap();
+                    frame += 1;
+                }
+            }
+            if self.terminal {
+                print!("\r\x1b[K");
+                io::stdout().flush().unwrap();
+            }
+            let done = worker.join().expect("the search thread panicked");
+            // Nothing left over may show up during the next search
+            while self.receiver.try_recv().is_ok() {}
+            done
+        })
+    }
+}
+
+/// Searches `board`, showing the progress on `display` and letting Ctrl-C stop the
+/// search early.
+fn interruptible_search(
+    board: &Board,
+    options: &SearchOptions,
+    stop: &AtomicBool,
+    searching: &AtomicBool,
+    display: &ThinkingDisplay,
+) -> SearchResult {
+    let tt = TranspositionTable::new(options.hash_mb);
+    let mut reporter = display.reporter();
+    stop.store(false, Ordering::SeqCst);
+    searching.store(true, Ordering::SeqCst);
+    let result = display.run(|| search_with_table(board, options, stop, &tt, &mut reporter));
+    searching.store(false, Ordering::SeqCst);
+    result
+}
+
+/// The engine chosen with `--engine NAME`, alpha-beta unless told otherwise. Exits
+/// if there is no engine by that name.
+fn engine_from_args(options: &SearchOptions, display: &ThinkingDisplay) -> Box<dyn Engine + Send> {
+    let args: Vec<String> = env::args().collect();
+    let name = match args.iter().position(|arg| arg == "--engine") {
+        Some(idx) => args.get(idx + 1).map_or("", String::as_str),
+        None => "alphabeta",
+    };
+    if name == "alphabeta" {
+        return Box::new(
+            AlphaBetaEngine::new(options.clone()).with_reporter(Box::new(display.reporter())),
+        );
+    }
+    match engine::engine_by_name(name, options) {
+        Some(engine) => engine,
+        None => {
+            eprintln!("--engine needs one of: {}", ENGINE_NAMES.join(", "));
+            process::exit(2);
+        }
+    }
+}
+
+/// Search options set on the command line: `--threads N`, `--skill 0-20` and
+/// `--jitter N`, which varies the evaluation of opening positions by up to N
+/// centipawns.
+fn search_options_from_args() -> SearchOptions {
+    let args: Vec<String> = env::args().collect();
+    let mut options = SearchOptions::default();
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--skill") {
+        match args.get(idx + 1).and_then(|n| n.parse::<u32>().ok()) {
+            Some(skill) if skill <= MAX_SKILL => {
+                options.skill = skill;
+                options.skill_seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0);
+            }
+            _ => {
+                eprintln!("--skill needs a level from 0 to {}", MAX_SKILL);
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--threads") {
+        match args.get(idx + 1).and_then(|n| n.parse::<usize>().ok()) {
+            Some(threads) if threads > 0 => options.threads = threads,
+            _ => {
+                eprintln!("--threads needs a positive number");
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--jitter") {
+        match args.get(idx + 1).and_then(|n| n.parse::<i32>().ok()) {
+            Some(jitter) if jitter >= 0 => options.eval_params.jitter = jitter,
+            _ => {
+                eprintln!("--jitter needs a number of centipawns");
+                process::exit(2);
+            }
+        }
+    }
+    options
+}
+
+/// Logs every search event to the file given with `--trace-search`, one JSON object
+/// per line. Exits if the file cannot be created.
+fn install_search_trace(path: Option<&String>) {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("--trace-search needs a file to write the trace to");
+            process::exit(2);
+        }
+    };
+    let file = match fs::File::create(path) {
+        Ok(file) => io::BufWriter::new(file),
+        Err(err) => {
+            eprintln!("Could not create {}: {}", path, err);
+            process::exit(2);
+        }
+    };
+    JsonLogger::new(file)
+        .install()
+        .expect("no other logger is installed");
+}
+
+/// Loads the Polyglot book given with `--book path.bin`, if any. Exits if the file
+/// cannot be read.
+fn load_book_from_args() -> Option<Book> {
+    let args: Vec<String> = env::args().collect();
+    let idx = args.iter().position(|arg| arg == "--book")?;
+
+    let path = match args.get(idx + 1) {
+        Some(path) => path,
+        None => {
+            eprintln!("--book needs a path to a Polyglot .bin file");
+            process::exit(2);
+        }
+    };
+
+    match Book::open(path) {
+        Ok(book) => Some(book),
+        Err(err) => {
+            eprintln!("Could not read book {}: {}", path, err);
+            process::exit(2);
+        }
     }
 }
 
@@ -1,5 +0,0 @@
-pub mod board;
-pub mod game;
-pub mod pieces;
-pub mod player;
-pub mod utils;
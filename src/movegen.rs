@@ -0,0 +1,128 @@
+//! Move generation for custom analysis, such as listing every knight move on the
+//! board.
+//!
+//! Everything here is pseudo-legal: moves follow how each piece moves, never land
+//! on a piece of the mover's own or on the enemy king, and include promotions, en
+//! passant and castling with the right still held and the squares between empty.
+//! They do not care whether the mover's king is left in check, with two exceptions:
+//! the king itself never steps onto an attacked square, and never castles out of,
+//! through or into check. [`Board::legal_moves`] and [`Board::is_legal`] apply the
+//! rest of the rules.
+//!
+//! The functions work for either color, whoever is to move. Squares are indices
+//! from a1 = 0 to h8 = 63; a square outside the board has no moves.
+
+use crate::board::{Board, Color, PieceType};
+use crate::chess_move::{
+    generate_all_moves_for_color_into, generate_moves_for_peice, Move, MoveList,
+};
+
+/// Every pseudo-legal move of `color`'s pieces.
+///
+/// ```
+/// use chessbot::board::{Board, Color};
+/// use chessbot::movegen::pseudo_legal_moves;
+///
+/// // The knight on d2 is pinned by the bishop on a5: Nf3 is generated, but not legal
+/// let board = Board::fen_to_board("4k3/8/8/b7/8/8/3N4/4K3 w - - 0 1");
+/// let moves: Vec<String> = pseudo_legal_moves(&board, Color::White)
+///     .iter()
+///     .map(|m| m.to_string())
+///     .collect();
+/// assert!(moves.contains(&"d2f3".to_string()));
+/// assert!(!board.legal_moves().iter().any(|m| m.to_string() == "d2f3"));
+/// // Black's moves can be listed although it is White's turn
+/// assert!(!pseudo_legal_moves(&board, Color::Black).is_empty());
+/// ```
+pub fn pseudo_legal_moves(board: &Board, color: Color) -> MoveList {
+    let mut moves = MoveList::new();
+    generate_all_moves_for_color_into(board, color, &mut moves);
+    moves
+}
+
+/// The pseudo-legal moves of a `peice_type` of `color` standing on `from`, whatever
+/// is really there.
+pub fn piece_moves(board: &Board, color: Color, peice_type: PieceType, from: u8) -> Vec<Move> {
+    if from >= 64 {
+        return Vec::new();
+    }
+    let mut moves = MoveList::new();
+    generate_moves_for_peice(board, color, peice_type, from, &mut moves);
+    moves.to_vec()
+}
+
+/// The pseudo-legal moves of a pawn of `color` on `from`: pushes, captures, en
+/// passant, and one move for each promotion piece.
+pub fn pawn_moves(board: &Board, color: Color, from: u8) -> Vec<Move> {
+    piece_moves(board, color, PieceType::Pawn, from)
+}
+
+/// The pseudo-legal moves of a knight of `color` on `from`.
+///
+/// ```
+/// use chessbot::board::{Board, Color};
+/// use chessbot::movegen::knight_moves;
+///
+/// // d4 is square 27. The knight reaches all eight squares, but on a1 only two.
+/// let board = Board::fen_to_board("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+/// assert_eq!(knight_moves(&board, Color::White, 27).len(), 8);
+/// assert_eq!(knight_moves(&board, Color::White, 0).len(), 2);
+/// ```
+pub fn knight_moves(board: &Board, color: Color, from: u8) -> Vec<Move> {
+    piece_moves(board, color, PieceType::Knight, from)
+}
+
+/// The pseudo-legal moves of a bishop of `color` on `from`.
+pub fn bishop_moves(board: &Board, color: Color, from: u8) -> Vec<Move> {
+    piece_moves(board, color, PieceType::Bishop, from)
+}
+
+/// The pseudo-legal moves of a rook of `color` on `from`.
+pub fn rook_moves(board: &Board, color: Color, from: u8) -> Vec<Move> {
+    piece_moves(board, color, PieceType::Rook, from)
+}
+
+/// The pseudo-legal moves of a queen of `color` on `from`.
+pub fn queen_moves(board: &Board, color: Color, from: u8) -> Vec<Move> {
+    piece_moves(board, color, PieceType::Queen, from)
+}
+
+/// The moves of a king of `color` on `from`, to squares the other side does not
+/// attack, and castling where the right is held, the way is clear and the king
+/// passes no attacked square.
+pub fn king_moves(board: &Board, color: Color, from: u8) -> Vec<Move> {
+    piece_moves(board, color, PieceType::King, from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::find_peice_for_color;
+
+    #[test]
+    fn test_piece_moves_add_up_to_every_pseudo_legal_move() {
+        let board = Board::fen_to_board(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        for color in [Color::White, Color::Black] {
+            let mut by_piece = Vec::new();
+            for from in 0..64 {
+                if let Some(peice_type) = find_peice_for_color(&board, color, from) {
+                    by_piece.extend(piece_moves(&board, color, peice_type, from));
+                }
+            }
+            assert_eq!(by_piece, pseudo_legal_moves(&board, color).to_vec());
+        }
+
+        // Castling is a king move; squares off the board have none
+        let castles: Vec<String> = king_moves(&board, Color::White, 4)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert!(castles.contains(&"e1g1".to_string()) && castles.contains(&"e1c1".to_string()));
+        assert!(queen_moves(&board, Color::White, 64).is_empty());
+        assert_eq!(rook_moves(&Board::new(), Color::White, 0).len(), 0);
+        assert_eq!(bishop_moves(&Board::new(), Color::Black, 58).len(), 0);
+        assert_eq!(pawn_moves(&Board::new(), Color::White, 12).len(), 2);
+    }
+}
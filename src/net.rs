@@ -0,0 +1,279 @@
+//! A blocking TCP server for playing the engine from another program, enabled with
+//! the `net` feature.
+//!
+//! Each line the client sends is one JSON request, and each gets exactly one JSON
+//! reply line:
+//!
+//! ```text
+//! > {"type":"newgame","fen":"7k/8/5K2/8/8/8/8/R7 w - - 0 1"}
+//! < {"type":"ready","fen":"7k/8/5K2/8/8/8/8/R7 w - - 0 1","status":"ongoing"}
+//! > {"type":"move","uci":"a1a7"}
+//! < {"type":"move","uci":"h8g8","fen":"6k1/R7/5K2/8/8/8/8/8 w - - 2 2","status":"ongoing"}
+//! > {"type":"status"}
+//! < {"type":"status","fen":"6k1/R7/5K2/8/8/8/8/8 w - - 2 2","status":"ongoing","moves":["a1a7","h8g8"]}
+//! ```
+//!
+//! `newgame` without a `fen` starts from the standard position. A `move` reply's
+//! `uci` is the engine's answer, or `null` if the client's move ended the game.
+//! Anything that cannot be done gets `{"type":"error","message":…,"fen":…}` and leaves
+//! the game as it was. `status` is one of `ongoing`, `check`, `checkmate`,
+//! `stalemate` or `draw`.
+
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::game::Game;
+use crate::search::{search, SearchOptions};
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Request {
+    NewGame { fen: Option<String> },
+    Move { uci: String },
+    Status,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Response {
+    Ready {
+        fen: String,
+        status: &'static str,
+    },
+    Move {
+        uci: Option<Move>,
+        fen: String,
+        status: &'static str,
+    },
+    Status {
+        fen: String,
+        status: &'static str,
+        moves: Vec<Move>,
+    },
+    Error {
+        message: String,
+        fen: String,
+    },
+}
+
+/// Whether the game in `board` is over, and how.
+fn status(board: &Board) -> &'static str {
+    let in_check = board.is_in_check(board.active_color);
+    if !board.has_legal_moves() {
+        if in_check {
+            "checkmate"
+        } else {
+            "stalemate"
+        }
+    } else if board.is_insufficient_material()
+        || board.halfmove_clock >= 100
+        || board.is_3_fold_repetition()
+    {
+        "draw"
+    } else if in_check {
+        "check"
+    } else {
+        "ongoing"
+    }
+}
+
+fn is_over(status: &str) -> bool {
+    !matches!(status, "ongoing" | "check")
+}
+
+/// One client's game against the engine.
+pub struct Session {
+    game: Game,
+    options: SearchOptions,
+}
+
+impl Session {
+    /// A session that starts from the standard position and searches with `options`.
+    pub fn new(options: SearchOptions) -> Session {
+        Session {
+            game: Game::new(),
+            options,
+        }
+    }
+
+    /// Answers one request line with one reply line, without the newline.
+    pub fn handle(&mut self, line: &str) -> String {
+        let response = match serde_json::from_str::<Request>(line) {
+            Ok(request) => self.respond(request),
+            Err(err) => self.error(format!("bad request: {}", err)),
+        };
+        serde_json::to_string(&response).expect("responses always serialize")
+    }
+
+    fn respond(&mut self, request: Request) -> Response {
+        match request {
+            Request::NewGame { fen } => {
+                let game = match fen {
+                    Some(fen) => Game::from_fen(&fen),
+                    None => Ok(Game::new()),
+                };
+                match game {
+                    Ok(game) => {
+                        self.game = game;
+                        Response::Ready {
+                            fen: self.fen(),
+                            status: status(self.game.board()),
+                        }
+                    }
+                    Err(err) => self.error(format!("invalid fen: {}", err)),
+                }
+            }
+            Request::Move { uci } => self.play(&uci),
+            Request::Status => Response::Status {
+                fen: self.fen(),
+                status: status(self.game.board()),
+                moves: self.game.moves().to_vec(),
+            },
+        }
+    }
+
+    /// Plays the client's move, then the engine's answer if the game goes on.
+    fn play(&mut self, uci: &str) -> Response {
+        if is_over(status(self.game.board())) {
+            return self.error("the game is over".to_string());
+        }
+        let played = match Move::from_uci(uci) {
+            Some(m) => self.game.play(m),
+            None => return self.error(format!("not a move: {}", uci)),
+        };
+        if let Err(err) = played {
+            return self.error(err.to_string());
+        }
+
+        let mut reply = None;
+        if !is_over(status(self.game.board())) {
+            reply = search(self.game.board(), &self.options).best_move;
+            if let Some(m) = reply {
+                self.game
+                    .play(m)
+                    .expect("the search only returns legal moves");
+            }
+        }
+        Response::Move {
+            uci: reply,
+            fen: self.fen(),
+            status: status(self.game.board()),
+        }
+    }
+
+    fn fen(&self) -> String {
+        self.game.board().board_to_fen()
+    }
+
+    fn error(&self, message: String) -> Response {
+        Response::Error {
+            message,
+            fen: self.fen(),
+        }
+    }
+}
+
+/// Plays one game per connection, one connection at a time.
+pub struct Server {
+    listener: TcpListener,
+    options: SearchOptions,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A, options: SearchOptions) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            options,
+        })
+    }
+
+    /// The address the server is listening on, useful after binding port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves connections until accepting one fails. A connection that breaks only
+    /// ends that client's game.
+    pub fn serve(&self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let _ = self.handle_connection(stream);
+        }
+    }
+
+    /// Accepts a single connection and plays until the client disconnects.
+    pub fn serve_one(&self) -> io::Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        self.handle_connection(stream)
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let mut session = Session::new(self.options.clone());
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(writer, "{}", session.handle(&line))?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session::new(SearchOptions {
+            depth: 2,
+            hash_mb: 1,
+            ..SearchOptions::default()
+        })
+    }
+
+    fn reply(session: &mut Session, request: &str) -> serde_json::Value {
+        serde_json::from_str(&session.handle(request)).unwrap()
+    }
+
+    #[test]
+    fn test_bad_requests_get_errors() {
+        let mut session = session();
+        let start = session.fen();
+
+        for request in [
+            "not json",
+            r#"{"type":"resign"}"#,
+            r#"{"type":"move","uci":"e2e5"}"#,
+            r#"{"type":"move","uci":"xyz"}"#,
+            r#"{"type":"newgame","fen":"8/8 w - - 0 1"}"#,
+        ] {
+            let reply = reply(&mut session, request);
+            assert_eq!(reply["type"], "error", "{}", request);
+            assert_eq!(reply["fen"], start.as_str());
+        }
+    }
+
+    #[test]
+    fn test_no_moves_after_the_game_ends() {
+        let mut session = session();
+        reply(
+            &mut session,
+            r#"{"type":"newgame","fen":"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"}"#,
+        );
+
+        let mate = reply(&mut session, r#"{"type":"move","uci":"a1a8"}"#);
+        assert_eq!(mate["status"], "checkmate");
+        assert!(mate["uci"].is_null());
+
+        let after = reply(&mut session, r#"{"type":"move","uci":"g1g2"}"#);
+        assert_eq!(after["type"], "error");
+        assert_eq!(after["message"], "the game is over");
+    }
+}
@@ -0,0 +1,268 @@
+//! Reading moves in whichever notation a player types: UCI (`g1f3`), SAN (`Nf3`),
+//! long algebraic (`Ng1-f3`, `e7xd8=Q+`) or ICCF numeric (`7163`).
+
+use crate::board::{Board, PieceType};
+use crate::chess_move::{find_peice_for_color, Move};
+use crate::san::{parse_san, SanError};
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    /// The text is not a move in the notation, or in any notation for [`parse_any`].
+    Malformed(String),
+    /// The text is a move, but not a legal one in the position.
+    Illegal(String),
+    /// More than one legal move matches, e.g. SAN `Nd2` with knights on b1 and f3.
+    Ambiguous(String),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::Malformed(text) => write!(f, "not a move: {}", text),
+            NotationError::Illegal(text) => write!(f, "illegal move: {}", text),
+            NotationError::Ambiguous(text) => write!(f, "ambiguous move: {}", text),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<SanError> for NotationError {
+    fn from(err: SanError) -> Self {
+        match err {
+            SanError::Malformed(san) => NotationError::Malformed(san),
+            SanError::NoMatchingMove(san) => NotationError::Illegal(san),
+            SanError::Ambiguous(san) => NotationError::Ambiguous(san),
+            SanError::Illegal { san, .. } => NotationError::Illegal(san),
+        }
+    }
+}
+
+impl Move {
+    /// Parses a move in long algebraic notation, e.g. `e2-e4`, `Ng1-f3`, `Bb5xc6` or
+    /// `e7xd8=Q+`, into the legal move it names. The piece letter and the `-` or `x`
+    /// must agree with the position. Castling is written `O-O` or `O-O-O`.
+    pub fn from_lan(board: &Board, lan: &str) -> Result<Move, NotationError> {
+        let malformed = || NotationError::Malformed(lan.to_string());
+        let text = lan.trim_end_matches(['+', '#', '!', '?']);
+        if matches!(text, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            return Ok(parse_san(board, text)?);
+        }
+
+        let mut bytes = text.as_bytes();
+        let peice_type = match bytes.first() {
+            Some(&letter) if letter.is_ascii_uppercase() => {
+                bytes = &bytes[1..];
+                match PieceType::try_from_char(letter as char) {
+                    Ok((_, peice_type)) if peice_type != PieceType::Pawn => peice_type,
+                    _ => return Err(malformed()),
+                }
+            }
+            _ => PieceType::Pawn,
+        };
+
+        let square = |file: u8, rank: u8| {
+            ((b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank))
+                .then(|| (rank - b'1') * 8 + (file - b'a'))
+                .ok_or_else(malformed)
+        };
+        let (from, separator, to, promotion) = match *bytes {
+            [from_file, from_rank, separator @ (b'-' | b'x'), to_file, to_rank, ref rest @ ..] => {
+                let promotion = match rest.strip_prefix(b"=").unwrap_or(rest) {
+                    [] => None,
+                    [letter] => match PieceType::try_from_char(*letter as char) {
+                        Ok((_, p)) if !matches!(p, PieceType::Pawn | PieceType::King) => Some(p),
+                        _ => return Err(malformed()),
+                    },
+                    _ => return Err(malformed()),
+                };
+                (
+                    square(from_file, from_rank)?,
+                    separator,
+                    square(to_file, to_rank)?,
+                    promotion,
+                )
+            }
+            _ => return Err(malformed()),
+        };
+
+        let m = Move {
+            from,
+            to,
+            promotion,
+        };
+        let illegal = || NotationError::Illegal(lan.to_string());
+        if find_peice_for_color(board, board.active_color, from) != Some(peice_type)
+            || !board.legal_moves().contains(&m)
+            || board.classify(&m).is_capture() != (separator == b'x')
+        {
+            return Err(illegal());
+        }
+        Ok(m)
+    }
+
+    /// Parses a move in ICCF numeric notation into the legal move it names: the from-
+    /// and to-squares as file and rank digits, e.g. `5254` for e2-e4, then for a
+    /// promotion `1` (queen), `2` (rook), `3` (bishop) or `4` (knight). Castling is
+    /// written as the king's move, e.g. `5171`.
+    pub fn from_iccf(board: &Board, iccf: &str) -> Result<Move, NotationError> {
+        let malformed = || NotationError::Malformed(iccf.to_string());
+        let square = |file: u8, rank: u8| {
+            ((b'1'..=b'8').contains(&file) && (b'1'..=b'8').contains(&rank))
+                .then(|| (rank - b'1') * 8 + (file - b'1'))
+        };
+
+        let (squares, promotion) = match iccf.as_bytes() {
+            [squares @ .., digit] if iccf.len() == 5 => {
+                let promotion = match digit {
+                    b'1' => PieceType::Queen,
+                    b'2' => PieceType::Rook,
+                    b'3' => PieceType::Bishop,
+                    b'4' => PieceType::Knight,
+                    _ => return Err(malformed()),
+                };
+                (squares, Some(promotion))
+            }
+            squares => (squares, None),
+        };
+        let m = match *squares {
+            [from_file, from_rank, to_file, to_rank] => Move {
+                from: square(from_file, from_rank).ok_or_else(malformed)?,
+                to: square(to_file, to_rank).ok_or_else(malformed)?,
+                promotion,
+            },
+            _ => return Err(malformed()),
+        };
+
+        if !board.legal_moves().contains(&m) {
+            return Err(NotationError::Illegal(iccf.to_string()));
+        }
+        Ok(m)
+    }
+}
+
+/// Tidies a move as a player typed it: drops surrounding whitespace, and lowercases
+/// coordinates typed in capitals, e.g. `E2E4` or `E7E8Q`, into UCI. Anything else is
+/// left alone, since capitals mean pieces in SAN.
+pub fn normalize_input(text: &str) -> String {
+    let text = text.trim();
+    let file = |c: &u8| matches!(c.to_ascii_lowercase(), b'a'..=b'h');
+    let rank = |c: &u8| matches!(c, b'1'..=b'8');
+    match text.as_bytes() {
+        [from_file, from_rank, to_file, to_rank, rest @ ..]
+            if rest.len() <= 1
+                && file(from_file)
+                && rank(from_rank)
+                && file(to_file)
+                && rank(to_rank) =>
+        {
+            text.to_ascii_lowercase()
+        }
+        _ => text.to_string(),
+    }
+}
+
+type Parser = fn(&Board, &str) -> Result<Move, NotationError>;
+
+/// Parses `text` as a legal move in UCI, SAN, long algebraic or ICCF notation, trying
+/// them in that order, after [`normalize_input`]. If no notation reads it, the error is from the first one that
+/// recognised the text as a move, so `Nd2` reports the ambiguity rather than
+/// "not a move".
+pub fn parse_any(board: &Board, text: &str) -> Result<Move, NotationError> {
+    let normalized = normalize_input(text);
+    let text = normalized.as_str();
+    let uci = |board: &Board, text: &str| match Move::from_uci(text) {
+        Some(m) if board.legal_moves().contains(&m) => Ok(m),
+        Some(_) => Err(NotationError::Illegal(text.to_string())),
+        None => Err(NotationError::Malformed(text.to_string())),
+    };
+    let san = |board: &Board, text: &str| Ok(parse_san(board, text)?);
+    let parsers: [Parser; 4] = [uci, san, Move::from_lan, Move::from_iccf];
+
+    let mut first_error = None;
+    for parse in parsers {
+        match parse(board, text) {
+            Ok(m) => return Ok(m),
+            Err(NotationError::Malformed(_)) => {}
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| NotationError::Malformed(text.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_notation_gives_the_same_move() {
+        let cases = [
+            (Board::START_FEN, ["g1f3", "Nf3", "Ng1-f3", "7163"]),
+            (
+                "6n1/5P2/8/8/8/8/8/k3K3 w - - 0 1",
+                ["f7g8q", "fxg8=Q", "f7xg8=Q", "67781"],
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+                ["e1g1", "O-O", "O-O", "5171"],
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                ["e4d5", "exd5", "e4xd5", "5445"],
+            ),
+        ];
+
+        for (fen, [uci, san, lan, iccf]) in cases {
+            let board = Board::fen_to_board(fen);
+            let expected = Move::from_uci(uci).unwrap();
+            assert_eq!(Move::from_lan(&board, lan), Ok(expected), "{}", lan);
+            assert_eq!(Move::from_iccf(&board, iccf), Ok(expected), "{}", iccf);
+            for text in [uci, san, lan, iccf] {
+                assert_eq!(parse_any(&board, text), Ok(expected), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_input() {
+        assert_eq!(normalize_input("  E2E4 \n"), "e2e4");
+        assert_eq!(normalize_input("E7E8Q"), "e7e8q");
+        assert_eq!(normalize_input("\tg1f3"), "g1f3");
+        // Pieces in SAN and long algebraic keep their capitals
+        assert_eq!(normalize_input(" Nf3 "), "Nf3");
+        assert_eq!(normalize_input("Bxc4"), "Bxc4");
+        assert_eq!(normalize_input("Ng1-f3"), "Ng1-f3");
+        assert_eq!(normalize_input("O-O-O"), "O-O-O");
+
+        let board = Board::new();
+        assert_eq!(
+            parse_any(&board, " E2E4 "),
+            Ok(Move::from_uci("e2e4").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_bad_moves_are_reported() {
+        let board = Board::fen_to_board("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1");
+        let err = |text: &str| parse_any(&board, text).unwrap_err();
+
+        assert_eq!(err("Nd2"), NotationError::Ambiguous("Nd2".to_string()));
+        assert_eq!(err("b1d3"), NotationError::Illegal("b1d3".to_string()));
+        assert_eq!(err("Nb1-d3"), NotationError::Illegal("Nb1-d3".to_string()));
+        assert_eq!(err("1234"), NotationError::Illegal("1234".to_string()));
+        assert_eq!(err("hello"), NotationError::Malformed("hello".to_string()));
+        assert_eq!(err("9254"), NotationError::Malformed("9254".to_string()));
+
+        // The piece letter and capture sign of long algebraic must fit the position
+        assert!(Move::from_lan(&board, "Bb1-d2").is_err());
+        assert!(Move::from_lan(&board, "Nb1xd2").is_err());
+        assert_eq!(
+            Move::from_lan(&board, "Nb1-d2"),
+            Ok(Move::from_uci("b1d2").unwrap())
+        );
+    }
+}
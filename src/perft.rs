@@ -0,0 +1,141 @@
+//! Hashed perft: counts leaf nodes like [`Board::perft`], but remembers the count
+//! below each position it finishes so transpositions are only walked once. Makes
+//! depth 6 and 7 runs practical.
+
+use crate::board::Board;
+use crate::book::polyglot_key;
+
+/// Slots in the table a [`perft_fast`] run uses, about 24 MB.
+const TABLE_SLOTS: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PerftEntry {
+    key: u64,
+    depth: u32,
+    nodes: u64,
+}
+
+/// Node counts by position key and depth. A new entry always replaces the old one
+/// in its slot.
+struct PerftTable {
+    slots: Vec<PerftEntry>,
+}
+
+impl PerftTable {
+    fn new(len: usize) -> PerftTable {
+        PerftTable {
+            slots: vec![PerftEntry::default(); len],
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+
+    fn probe(&self, key: u64, depth: u32) -> Option<u64> {
+        let entry = self.slots[self.index(key)];
+        (entry.key == key && entry.depth == depth).then_some(entry.nodes)
+    }
+
+    fn store(&mut self, key: u64, depth: u32, nodes: u64) {
+        let index = self.index(key);
+        self.slots[index] = PerftEntry { key, depth, nodes };
+    }
+}
+
+/// Number of leaf positions `depth` plies below `board`, the same as
+/// [`Board::perft`] but sharing counts between transpositions.
+pub fn perft_fast(board: &Board, depth: u32) -> u64 {
+    if depth <= 1 {
+        return board.perft(depth);
+    }
+    let mut board = *board;
+    let mut table = PerftTable::new(TABLE_SLOTS);
+    perft_hashed(&mut board, depth, &mut table)
+}
+
+fn perft_hashed(board: &mut Board, depth: u32, table: &mut PerftTable) -> u64 {
+    let moves = board.legal_moves();
+    // Bulk count: the leaves are just the legal moves
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let key = polyglot_key(board);
+    if let Some(nodes) = table.probe(key, depth) {
+        return nodes;
+    }
+
+    let mut nodes = 0;
+    for m in moves.iter() {
+        let undo = board.make_move_unchecked(&m);
+        nodes += perft_hashed(board, depth - 1, table);
+        board.unmake_move(&m, undo);
+    }
+    table.store(key, depth, nodes);
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The usual perft test positions: the start, "kiwipete" and three endgames and
+    /// middlegames full of promotions, castling and en passant.
+    const POSITIONS: [&str; 5] = [
+        Board::START_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ];
+
+    #[test]
+    fn test_perft_fast_matches_perft() {
+        for fen in POSITIONS {
+            let board = Board::fen_to_board(fen);
+            for depth in 1..=3 {
+                assert_eq!(
+                    perft_fast(&board, depth),
+                    board.perft(depth),
+                    "{} depth {}",
+                    fen,
+                    depth
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "slow; run with --release -- --ignored"]
+    fn test_perft_fast_matches_perft_to_depth_5() {
+        for fen in POSITIONS {
+            let board = Board::fen_to_board(fen);
+            for depth in 4..=5 {
+                assert_eq!(
+                    perft_fast(&board, depth),
+                    board.perft(depth),
+                    "{} depth {}",
+                    fen,
+                    depth
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "slow; run with --release -- --ignored"]
+    fn test_deep_perft_counts() {
+        let cases = [
+            (POSITIONS[0], 6, 119_060_324),
+            (POSITIONS[1], 5, 193_690_690),
+            (POSITIONS[2], 7, 178_633_661),
+            (POSITIONS[3], 5, 15_833_292),
+            (POSITIONS[4], 5, 89_941_194),
+        ];
+        for (fen, depth, nodes) in cases {
+            let board = Board::fen_to_board(fen);
+            assert_eq!(perft_fast(&board, depth), nodes, "{} depth {}", fen, depth);
+        }
+    }
+}
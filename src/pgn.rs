@@ -0,0 +1,280 @@
+//! Reading games in Portable Game Notation (PGN).
+//!
+//! Only what is needed to replay games is kept: the tag pairs, the main line and
+//! its variations as SAN strings, and the result. Comments and NAGs are skipped.
+
+use std::fmt;
+
+/// The result as PGN writes it, in the `Result` tag and after the moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultTag {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// `*`, or no result given.
+    Unknown,
+}
+
+impl ResultTag {
+    pub(crate) fn from_token(token: &str) -> Option<ResultTag> {
+        match token {
+            "1-0" => Some(ResultTag::WhiteWins),
+            "0-1" => Some(ResultTag::BlackWins),
+            "1/2-1/2" => Some(ResultTag::Draw),
+            "*" => Some(ResultTag::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResultTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResultTag::WhiteWins => "1-0",
+            ResultTag::BlackWins => "0-1",
+            ResultTag::Draw => "1/2-1/2",
+            ResultTag::Unknown => "*",
+        })
+    }
+}
+
+/// Serialized as its PGN token, e.g. `"1-0"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ResultTag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ResultTag {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let token = String::deserialize(deserializer)?;
+        ResultTag::from_token(&token)
+            .ok_or_else(|| serde::de::Error::custom(format!("not a game result: {}", token)))
+    }
+}
+
+/// One game from a PGN file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// Main line moves in SAN, without move numbers.
+    pub moves: Vec<String>,
+    /// The variations to main line moves, in the order they appear.
+    pub variations: Vec<PgnVariation>,
+    pub result: ResultTag,
+}
+
+/// A variation, written in parentheses after the move it replaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnVariation {
+    /// Index in the game of the move it replaces, from 0. Its moves are played from
+    /// the position before that one.
+    pub ply: usize,
+    /// Its moves in SAN, without move numbers.
+    pub moves: Vec<String>,
+    /// Variations to its own moves, with `ply` counted from the start of the game too.
+    pub variations: Vec<PgnVariation>,
+}
+
+impl PgnGame {
+    fn new() -> PgnGame {
+        PgnGame {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            variations: Vec::new(),
+            result: ResultTag::Unknown,
+        }
+    }
+
+    /// Value of the tag `name`, e.g. `game.tag("White")`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.moves.is_empty()
+    }
+}
+
+/// Parses every game in `text`.
+///
+/// The parser is lenient: a game ends at its result token or where the next game's
+/// tags begin, and text it does not understand is kept as a move so that replaying
+/// the game reports it. A variation left open at the end of a game is closed there.
+pub fn parse_games(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::new();
+    let mut chars = text.chars().peekable();
+    // The variations being read, innermost last
+    let mut open: Vec<PgnVariation> = Vec::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' if open.is_empty() => {
+                // A tag after movetext starts a new game
+                if !game.moves.is_empty() {
+                    games.push(std::mem::replace(&mut game, PgnGame::new()));
+                }
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if let Some((name, value)) = tag.trim().split_once(char::is_whitespace) {
+                    let value = value.trim().trim_matches('"').replace("\\\"", "\"");
+                    game.tags.push((name.to_string(), value));
+                }
+            }
+            '{' => {
+                chars.by_ref().find(|&c| c == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' => {
+                // The variation replaces the last move of the line it is in
+                let ply = match open.last() {
+                    Some(line) => line.ply + line.moves.len(),
+                    None => game.moves.len(),
+                };
+                open.push(PgnVariation {
+                    ply: ply.saturating_sub(1),
+                    moves: Vec::new(),
+                    variations: Vec::new(),
+                });
+            }
+            ')' => close_variation(&mut game, &mut open),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}();[".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+
+                if token.starts_with('$') {
+                    continue;
+                }
+                if let Some(result) = ResultTag::from_token(&token) {
+                    // Only the main line's result ends the game
+                    if open.is_empty() {
+                        game.result = result;
+                        games.push(std::mem::replace(&mut game, PgnGame::new()));
+                    }
+                    continue;
+                }
+
+                // Drop move numbers such as "12." or "12...", which may be glued to
+                // the move ("1.e4")
+                let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if !san.is_empty() {
+                    match open.last_mut() {
+                        Some(line) => line.moves.push(san.to_string()),
+                        None => game.moves.push(san.to_string()),
+                    }
+                }
+            }
+        }
+    }
+
+    while !open.is_empty() {
+        close_variation(&mut game, &mut open);
+    }
+    if !game.is_empty() {
+        games.push(game);
+    }
+    games
+}
+
+/// Ends the innermost open variation and adds it to the line it branches from.
+fn close_variation(game: &mut PgnGame, open: &mut Vec<PgnVariation>) {
+    if let Some(variation) = open.pop() {
+        match open.last_mut() {
+            Some(line) => line.variations.push(variation),
+            None => game.variations.push(variation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags_moves_and_result() {
+        let pgn = r#"[Event "Casual"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {a comment} Nc6 $1 3. Bb5 (3. Bc4 Bc5) a6 ; rest of line
+4.Ba4 1-0
+"#;
+        let games = parse_games(pgn);
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("White"), Some("Alice"));
+        assert_eq!(games[0].result, ResultTag::WhiteWins);
+        assert_eq!(
+            games[0].moves,
+            vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]
+        );
+        assert_eq!(
+            games[0].variations,
+            vec![PgnVariation {
+                ply: 4,
+                moves: vec!["Bc4".to_string(), "Bc5".to_string()],
+                variations: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_variations() {
+        let pgn = "1. e4 e5 (1... c5 2. Nf3 (2. c3 d5) 2... d6 (2... Nc6) 3. d4 *) 2. Nf3 \
+                   (2. f4 exf4 1-0) Nc6 *";
+        let games = parse_games(pgn);
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].moves, vec!["e4", "e5", "Nf3", "Nc6"]);
+        assert_eq!(games[0].result, ResultTag::Unknown);
+        let line = |ply: usize, moves: &str, variations: Vec<PgnVariation>| PgnVariation {
+            ply,
+            moves: moves.split_whitespace().map(String::from).collect(),
+            variations,
+        };
+        assert_eq!(
+            games[0].variations,
+            vec![
+                line(
+                    1,
+                    "c5 Nf3 d6 d4",
+                    vec![line(2, "c3 d5", Vec::new()), line(3, "Nc6", Vec::new())]
+                ),
+                line(2, "f4 exf4", Vec::new()),
+            ]
+        );
+
+        // An unclosed variation ends with the game
+        let games = parse_games("1. d4 (1. c4 e5");
+        assert_eq!(games[0].variations, vec![line(0, "c4 e5", Vec::new())]);
+    }
+
+    #[test]
+    fn test_parse_several_games() {
+        let pgn =
+            "[Event \"A\"]\n\n1. d4 d5 1/2-1/2\n\n[Event \"B\"]\n\n1. c4 *\n[Event \"C\"]\n1. e4";
+        let games = parse_games(pgn);
+
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].result, ResultTag::Draw);
+        assert_eq!(games[1].tag("Event"), Some("B"));
+        assert_eq!(games[1].moves, vec!["c4"]);
+        assert_eq!(games[2].result, ResultTag::Unknown);
+        assert_eq!(games[2].moves, vec!["e4"]);
+    }
+}
@@ -0,0 +1,100 @@
+//! How a search reads to someone watching the command line: the line shown while
+//! the engine thinks, and the one after it plays.
+//!
+//! These only format [`SearchStats`]; where the lines go, and whether they are
+//! redrawn in place, is up to the front-end.
+
+use crate::search::{mate_in, SearchStats};
+
+/// Frames of the spinner shown in front of the thinking line, one per redraw.
+pub const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// A score in pawns, e.g. `+0.35`, or the moves to mate, e.g. `mate -3`.
+pub fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("{:+.2}", score as f64 / 100.0),
+    }
+}
+
+/// A node count rounded for reading at a glance: `950`, `420k`, `1.2M`.
+pub fn format_nodes(nodes: u64) -> String {
+    match nodes {
+        0..=999 => nodes.to_string(),
+        1_000..=999_999 => format!("{}k", nodes / 1_000),
+        _ => format!("{:.1}M", nodes as f64 / 1e6),
+    }
+}
+
+/// How the search is going: the depth being searched, the score of the last
+/// completed depth if there is one yet, the time taken and the nodes visited.
+///
+/// ```
+/// use chessbot::progress::thinking_line;
+/// use chessbot::search::SearchStats;
+/// use std::time::Duration;
+///
+/// let stats = SearchStats {
+///     nodes: 310_000,
+///     elapsed: Duration::from_millis(1_200),
+///     ..SearchStats::default()
+/// };
+/// assert_eq!(
+///     thinking_line(9, Some(32), &stats),
+///     "thinking: depth 9, +0.32, 1.2s, 310k nodes"
+/// );
+/// ```
+pub fn thinking_line(depth: u32, score: Option<i32>, stats: &SearchStats) -> String {
+    let score = score.map_or(String::new(), |score| format!(", {}", format_score(score)));
+    format!(
+        "thinking: depth {}{}, {:.1}s, {} nodes",
+        depth,
+        score,
+        stats.elapsed.as_secs_f64(),
+        format_nodes(stats.nodes)
+    )
+}
+
+/// The move the engine played, `san`, with the depth and score of the search that
+/// chose it and what it cost: `played Nf3 (depth 11, +0.32, 1.8s, 420k nodes)`.
+pub fn played_line(san: &str, depth: u32, score: i32, stats: &SearchStats) -> String {
+    format!(
+        "played {} (depth {}, {}, {:.1}s, {} nodes)",
+        san,
+        depth,
+        format_score(score),
+        stats.elapsed.as_secs_f64(),
+        format_nodes(stats.nodes)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MATE_SCORE;
+    use std::time::Duration;
+
+    fn stats(nodes: u64, millis: u64) -> SearchStats {
+        SearchStats {
+            nodes,
+            elapsed: Duration::from_millis(millis),
+            ..SearchStats::default()
+        }
+    }
+
+    #[test]
+    fn test_played_line() {
+        assert_eq!(
+            played_line("Nf3", 11, 32, &stats(420_512, 1_800)),
+            "played Nf3 (depth 11, +0.32, 1.8s, 420k nodes)"
+        );
+        assert_eq!(
+            played_line("Qh7#", 1, MATE_SCORE - 1, &stats(35, 0)),
+            "played Qh7# (depth 1, mate 1, 0.0s, 35 nodes)"
+        );
+        assert_eq!(
+            thinking_line(1, None, &stats(2_345_678, 12_340)),
+            "thinking: depth 1, 12.3s, 2.3M nodes"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Standard Algebraic Notation (SAN), e.g. `Nf3`, `exd5`, `O-O` or `e8=Q+`.
+
+use crate::board::{Board, PieceType};
+use crate::castling::CastlingSide;
+use crate::chess_move::{find_peice_for_color, square_name, Move, MoveError, MoveKind};
+use crate::movegen::pseudo_legal_moves;
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The text is not SAN at all.
+    Malformed(String),
+    /// The text is well formed but no legal move matches it.
+    NoMatchingMove(String),
+    /// More than one legal move matches, e.g. `Nd2` with knights on b1 and f3.
+    Ambiguous(String),
+    /// The text names exactly one move a piece could make, but the rules forbid it,
+    /// e.g. `Nd2` with the knight pinned.
+    Illegal { san: String, reason: MoveError },
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Malformed(san) => write!(f, "malformed SAN move: {}", san),
+            SanError::NoMatchingMove(san) => write!(f, "no legal move matches {}", san),
+            SanError::Ambiguous(san) => write!(f, "ambiguous SAN move: {}", san),
+            SanError::Illegal { san, reason } => write!(f, "illegal move {}: {}", san, reason),
+        }
+    }
+}
+
+impl std::error::Error for SanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SanError::Illegal { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+fn piece_from_letter(letter: u8) -> Option<PieceType> {
+    match letter {
+        b'N' => Some(PieceType::Knight),
+        b'B' => Some(PieceType::Bishop),
+        b'R' => Some(PieceType::Rook),
+        b'Q' => Some(PieceType::Queen),
+        b'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn piece_letter(peice_type: PieceType) -> &'static str {
+    match peice_type {
+        PieceType::Pawn => "",
+        PieceType::Knight => "N",
+        PieceType::Bishop => "B",
+        PieceType::Rook => "R",
+        PieceType::Queen => "Q",
+        PieceType::King => "K",
+    }
+}
+
+/// Parses `san` into the legal move it describes for the side to move.
+///
+/// Check and annotation suffixes (`+`, `#`, `!`, `?`) are ignored, and castling may
+/// be written with letter O or digit zero.
+pub fn parse_san(board: &Board, san: &str) -> Result<Move, SanError> {
+    let malformed = || SanError::Malformed(san.to_string());
+    let text = san.trim_end_matches(['+', '#', '!', '?']);
+    let color = board.active_color;
+
+    let matches: Box<dyn Fn(&Move) -> bool> = match text {
+        "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+            let side = if text.len() == 3 {
+                CastlingSide::Kingside
+            } else {
+                CastlingSide::Queenside
+            };
+            let from = board.castling.king_start(color);
+            let to = board.castling.king_destination(color, side);
+            Box::new(move |m| {
+                m.from == from
+                    && m.to == to
+                    && find_peice_for_color(board, color, m.from) == Some(PieceType::King)
+            })
+        }
+        _ => {
+            let mut bytes = text.as_bytes();
+
+            // Promotion, written "e8=Q" or "e8Q"
+            let mut promotion = None;
+            if let Some((&last, rest)) = bytes.split_last() {
+                if let Some(peice_type) = piece_from_letter(last).filter(|&p| p != PieceType::King)
+                {
+                    promotion = Some(peice_type);
+                    bytes = rest.strip_suffix(b"=").unwrap_or(rest);
+                }
+            }
+
+            let peice_type = match bytes.first().and_then(|&b| piece_from_letter(b)) {
+                Some(peice_type) => {
+                    bytes = &bytes[1..];
+                    peice_type
+                }
+                None => PieceType::Pawn,
+            };
+
+            if bytes.len() < 2 {
+                return Err(malformed());
+            }
+            let (qualifiers, target) = bytes.split_at(bytes.len() - 2);
+            if !(b'a'..=b'h').contains(&target[0]) || !(b'1'..=b'8').contains(&target[1]) {
+                return Err(malformed());
+            }
+            let to = (target[1] - b'1') * 8 + (target[0] - b'a');
+
+            // Whatever is left is an optional from-file and/or from-rank, then 'x'
+            let qualifiers = qualifiers.strip_suffix(b"x").unwrap_or(qualifiers);
+            let mut from_file = None;
+            let mut from_rank = None;
+            for &b in qualifiers {
+                match b {
+                    b'a'..=b'h' if from_file.is_none() && from_rank.is_none() => {
+                        from_file = Some(b - b'a')
+                    }
+                    b'1'..=b'8' if from_rank.is_none() => from_rank = Some(b - b'1'),
+                    _ => return Err(malformed()),
+                }
+            }
+
+            Box::new(move |m| {
+                m.to == to
+                    && m.promotion == promotion
+                    && from_file.is_none_or(|file| m.from % 8 == file)
+                    && from_rank.is_none_or(|rank| m.from / 8 == rank)
+                    && find_peice_for_color(board, color, m.from) == Some(peice_type)
+            })
+        }
+    };
+
+    let candidates: Vec<Move> = board
+        .legal_moves()
+        .into_iter()
+        .filter(|m| matches(m))
+        .collect();
+    match candidates.as_slice() {
+        [m] => Ok(*m),
+        [] => {
+            // One move the piece could make but for the rules, like a pinned piece's,
+            // is worth saying what is wrong with
+            let mut forbidden = pseudo_legal_moves(board, color);
+            forbidden.retain(|m| matches(m));
+            match &forbidden.to_vec()[..] {
+                [m] => match board.check_move(m) {
+                    Err(reason) => Err(SanError::Illegal {
+                        san: san.to_string(),
+                        reason,
+                    }),
+                    Ok(()) => Err(SanError::NoMatchingMove(san.to_string())),
+                },
+                _ => Err(SanError::NoMatchingMove(san.to_string())),
+            }
+        }
+        _ => Err(SanError::Ambiguous(san.to_string())),
+    }
+}
+
+/// Writes the legal move `m` in SAN, including a `+` or `#` suffix.
+pub fn to_san(board: &Board, m: &Move) -> String {
+    let color = board.active_color;
+    let peice_type = match find_peice_for_color(board, color, m.from) {
+        Some(peice_type) => peice_type,
+        None => return m.to_string(),
+    };
+
+    let kind = board.classify(m);
+    let mut san = String::new();
+    if kind == MoveKind::CastleKingside {
+        san.push_str("O-O");
+    } else if kind == MoveKind::CastleQueenside {
+        san.push_str("O-O-O");
+    } else {
+        let is_capture = kind.is_capture();
+
+        san.push_str(piece_letter(peice_type));
+        if peice_type == PieceType::Pawn {
+            if is_capture {
+                san.push((b'a' + m.from % 8) as char);
+            }
+        } else {
+            // Disambiguate against other pieces of the same type reaching the same square
+            let rivals: Vec<Move> = board
+                .legal_moves()
+                .iter()
+                .filter(|other| other.to == m.to && other.from != m.from)
+                .filter(|other| find_peice_for_color(board, color, other.from) == Some(peice_type))
+                .collect();
+
+            if !rivals.is_empty() {
+                let same_file = rivals.iter().any(|other| other.from % 8 == m.from % 8);
+                let same_rank = rivals.iter().any(|other| other.from / 8 == m.from / 8);
+                if !same_file {
+                    san.push((b'a' + m.from % 8) as char);
+                } else if !same_rank {
+                    san.push((b'1' + m.from / 8) as char);
+                } else {
+                    san.push_str(&square_name(m.from));
+                }
+            }
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(m.to));
+        if let Some(promotion) = m.promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+    }
+
+    let mut after = *board;
+    after.apply_move_unchecked(m);
+    if after.is_in_check(after.active_color) {
+        san.push(if after.legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_parse_simple_moves() {
+        let board = Board::fen_to_board(STARTING_FEN);
+
+        assert_eq!(parse_san(&board, "e4"), Ok(Move::new("e2e4")));
+        assert_eq!(parse_san(&board, "Nf3"), Ok(Move::new("g1f3")));
+        assert_eq!(
+            parse_san(&board, "Nf4"),
+            Err(SanError::NoMatchingMove("Nf4".to_string()))
+        );
+        assert_eq!(
+            parse_san(&board, "Zz9"),
+            Err(SanError::Malformed("Zz9".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_disambiguation_round_trips() {
+        // Knights on b1 and f3 can both reach d2; rooks on a1 and a5 can both reach a3
+        let board = Board::fen_to_board("4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1");
+
+        assert_eq!(
+            parse_san(&board, "Nd2"),
+            Err(SanError::Ambiguous("Nd2".to_string()))
+        );
+        for (san, uci) in [
+            ("Nbd2", "b1d2"),
+            ("Nfd2", "f3d2"),
+            ("R1a3", "a1a3"),
+            ("R5a3", "a5a3"),
+        ] {
+            let m = Move::new(uci);
+            assert_eq!(parse_san(&board, san), Ok(m));
+            assert_eq!(to_san(&board, &m), san);
+        }
+    }
+
+    #[test]
+    fn test_castling_promotion_and_check_suffixes() {
+        let board = Board::fen_to_board("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1");
+
+        let castle = Move::new("e1g1");
+        assert_eq!(parse_san(&board, "O-O"), Ok(castle));
+        assert_eq!(parse_san(&board, "0-0"), Ok(castle));
+        assert_eq!(to_san(&board, &castle), "O-O");
+
+        let promotion = Move::new("b7a8q");
+        assert_eq!(parse_san(&board, "bxa8=Q+"), Ok(promotion));
+        assert_eq!(parse_san(&board, "bxa8Q"), Ok(promotion));
+        assert_eq!(to_san(&board, &promotion), "bxa8=Q+");
+    }
+
+    #[test]
+    fn test_checkmate_suffix() {
+        let board = Board::fen_to_board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+
+        assert_eq!(to_san(&board, &Move::new("a1a8")), "Ra8#");
+    }
+}
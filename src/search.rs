@@ -0,0 +1,2316 @@
+//! Alpha-beta search.
+//!
+//! Iterative deepening drives a negamax alpha-beta search with a quiescence search
+//! at the leaves, a transposition table and killer/history move ordering. Late move
+//! reductions and futility pruning can be switched off through [`SearchOptions`].
+//!
+//! With [`SearchOptions::threads`] above one, helper threads search the same
+//! position at staggered depths and share the transposition table (Lazy SMP). They
+//! only fill the table; the result always comes from the main thread.
+//!
+//! A search can be cut short from another thread with [`search_with_stop`]; it then
+//! returns the result of the last iteration it completed. A [`SearchReporter`] hears
+//! about every completed iteration and, during long ones, about progress.
+//!
+//! With a logger enabled for debug records from this module (see
+//! [`crate::trace`]), the search also logs table probes and stores, beta cutoffs,
+//! pruning decisions and a summary of every iteration. Whether to log is decided
+//! once per search, so a search without one pays nothing per node.
+//!
+//! With [`SearchOptions::trace_root`] the result also carries a [`SearchReport`] on
+//! every root move, for working out why the engine chose what it did.
+
+use crate::board::{Board, Color, GameStatus, PieceType};
+use crate::book::polyglot_key;
+use crate::chess_move::{find_peice_for_color, Move, MoveList, PackedMove, MAX_MOVES};
+use crate::eval::{evaluate, EvalParams};
+use crate::trace::{RootMoveReport, SearchReport};
+use crate::tt::{Bound, TranspositionTable, TtEntry};
+use crate::utils::XorShift64;
+
+use log::{debug, log_enabled, Level};
+
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+// std's clock panics on wasm32-unknown-unknown; web-time reads the browser's instead
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Score of being checkmated at the root. Mates further away score closer to zero.
+pub const MATE_SCORE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+/// Scores beyond this are mates.
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000;
+
+/// Deepest ply the search tracks killers for; quiescence stops here as well.
+const MAX_PLY: usize = 128;
+
+/// Deepest iteration an unbounded search (e.g. UCI `go infinite`) runs to.
+pub const MAX_DEPTH: u32 = 64;
+
+/// Full playing strength; lower [`SearchOptions::skill`] values play weaker.
+pub const MAX_SKILL: u32 = 20;
+
+/// Root moves a weakened search chooses between.
+const SKILL_CANDIDATES: usize = 4;
+
+/// How far below the best move a weakened search may still pick one, per skill
+/// level under [`MAX_SKILL`], in centipawns.
+const SKILL_MARGIN_STEP: i32 = 15;
+
+/// How many nodes are searched between checks of the stop flag.
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// How often a [`SearchReporter`] hears about progress within an iteration.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a search runs before a [`SearchReporter`] hears which root move it is
+/// on. Shorter searches would only flood the GUI.
+const CURRENT_MOVE_DELAY: Duration = Duration::from_secs(3);
+
+/// Added to the history score of the quiet move that last refuted the previous
+/// move. Ordering it ahead of every other quiet move, as killers are, searched more
+/// nodes in the bench.
+const COUNTERMOVE_BONUS: i32 = 16;
+
+/// Futility margins by remaining depth.
+const FUTILITY_MARGINS: [i32; 3] = [0, 200, 500];
+
+/// Reverse futility margin per ply of remaining depth, up to the depth it is tried.
+const REVERSE_FUTILITY_MARGIN: i32 = 120;
+const REVERSE_FUTILITY_DEPTH: i32 = 3;
+
+/// Razoring margins by remaining depth.
+const RAZOR_MARGINS: [i32; 3] = [0, 300, 550];
+
+/// Shallowest iteration after which a search with [`SearchOptions::easy_moves`]
+/// may stop on an easy move.
+const EASY_MOVE_DEPTH: u32 = 6;
+/// How far the best root move must stand above every other to be an easy move.
+const EASY_MOVE_MARGIN: i32 = 150;
+/// Iterations running the same easy move must be found before the search stops.
+const EASY_MOVE_ITERATIONS: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Depth of the last iteration, in plies.
+    pub depth: u32,
+    /// Transposition table size in megabytes.
+    pub hash_mb: usize,
+    /// Search quiet moves late in the move order to a reduced depth first.
+    pub late_move_reductions: bool,
+    /// Skip quiet moves near the leaves that cannot raise the score to alpha.
+    pub futility_pruning: bool,
+    /// Cut off a node near the leaves whose static evaluation beats beta by a margin
+    /// that grows with depth.
+    pub reverse_futility_pruning: bool,
+    /// Drop into the quiescence search a node near the leaves whose static
+    /// evaluation is far below alpha.
+    pub razoring: bool,
+    /// Play a forced move without searching, and stop deepening once the same root
+    /// move has stood far above the rest for a few iterations. For moves played
+    /// against a clock, where time saved now is time for later.
+    pub easy_moves: bool,
+    /// Number of search threads, including the main one.
+    pub threads: usize,
+    /// Number of best root moves to find, each with its own line.
+    pub multi_pv: usize,
+    /// How much worse than equal a draw is for the side to move at the root, in
+    /// centipawns. Positive values avoid draws, negative values seek them.
+    pub contempt: i32,
+    /// Look only for a forced mate in at most this many moves. Searches exactly deep
+    /// enough, without the pruning that could miss one.
+    pub mate: Option<u32>,
+    /// Playing strength from 0 to [`MAX_SKILL`]. Below full strength the search
+    /// stops shallower and picks at random between the best few root moves.
+    pub skill: u32,
+    /// Seeds the random choice of a weakened search.
+    pub skill_seed: u64,
+    pub eval_params: EvalParams,
+    /// Search only these root moves, as UCI `go searchmoves` asks. Moves that are
+    /// not legal are left out, and if none is legal every move is searched.
+    pub root_moves: Option<Vec<Move>>,
+    /// Record the score, depth, bound and line of every root move in
+    /// [`SearchResult::report`].
+    pub trace_root: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            depth: 5,
+            hash_mb: 16,
+            late_move_reductions: true,
+            futility_pruning: true,
+            reverse_futility_pruning: true,
+            razoring: true,
+            easy_moves: false,
+            threads: 1,
+            multi_pv: 1,
+            contempt: 0,
+            mate: None,
+            skill: MAX_SKILL,
+            skill_seed: 0,
+            eval_params: EvalParams::DEFAULT,
+            root_moves: None,
+            trace_root: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// A legal move whenever there is one, even if the search was stopped before its
+    /// first iteration finished. `None` only when the side to move has no legal
+    /// moves, see `status`.
+    pub best_move: Option<Move>,
+    /// Score in centipawns from the side to move's point of view.
+    pub score: i32,
+    /// Depth of the last completed iteration.
+    pub depth: u32,
+    pub stats: SearchStats,
+    /// Principal variation, starting with `best_move`.
+    pub pv: Vec<Move>,
+    /// The best `multi_pv` lines, best first. The first is `score` and `pv`.
+    pub lines: Vec<PvLine>,
+    /// The root moves as of the last completed iteration, with
+    /// [`SearchOptions::trace_root`].
+    pub report: Option<SearchReport>,
+    /// Why there is no best move: the checkmate or stalemate at the root. `None`
+    /// when there is a move to play.
+    pub status: Option<GameStatus>,
+}
+
+/// Counters kept while searching. Nodes count every thread; the others only the
+/// main thread until the search is over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Positions visited, quiescence positions included.
+    pub nodes: u64,
+    /// Positions visited by the quiescence search.
+    pub qnodes: u64,
+    /// Positions found in the transposition table.
+    pub tt_hits: u64,
+    /// Deepest ply reached, quiescence included.
+    pub seldepth: u32,
+    /// Nodes cut off by reverse futility pruning.
+    pub reverse_futility_cutoffs: u64,
+    /// Nodes razoring settled with the quiescence search.
+    pub razor_cutoffs: u64,
+    /// Why the search stopped before its depth or time ran out, if it did.
+    pub early_stop: Option<EarlyStop>,
+    /// Thousandths of the transposition table filled by this search, see
+    /// [`TranspositionTable::hashfull`].
+    pub hashfull: u32,
+    pub elapsed: Duration,
+}
+
+/// Why a search with [`SearchOptions::easy_moves`] stopped early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EarlyStop {
+    /// There was only one legal move, so nothing was searched.
+    OnlyMove,
+    /// The best move beat every other by a wide margin, iteration after iteration.
+    EasyMove,
+}
+
+impl SearchStats {
+    /// Nodes per second.
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-3)) as u64
+    }
+}
+
+/// Receives progress from a running search, on the thread that called it. Every
+/// method does nothing by default.
+pub trait SearchReporter {
+    /// Called with the result so far after every completed iteration.
+    fn iteration(&mut self, _result: &SearchResult) {}
+
+    /// Called about once a second while an iteration runs.
+    fn progress(&mut self, _stats: &SearchStats) {}
+
+    /// Called before each root move is searched, once the search has run for a few
+    /// seconds, with the move and its place in the search order, counting from 1.
+    fn current_move(&mut self, _m: Move, _number: usize) {}
+}
+
+/// Reports nothing.
+impl SearchReporter for () {}
+
+/// One root move's line when searching several with [`SearchOptions::multi_pv`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+/// Number of moves to mate for a mate score, negative when the side to move is
+/// getting mated. `None` for ordinary scores.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() <= MATE_THRESHOLD {
+        return None;
+    }
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// How long to think about one move with `time_left_ms` on the clock and
+/// `increment_ms` added per move: a small slice of the clock, plus most of the
+/// increment, less `overhead_ms` for whatever the GUI and network may eat.
+pub fn move_time_budget(time_left_ms: u64, increment_ms: u64, overhead_ms: u64) -> u64 {
+    (time_left_ms / 30 + increment_ms / 2)
+        .saturating_sub(overhead_ms)
+        .max(1)
+}
+
+/// Searches `board` to `options.depth` and returns the best move found.
+pub fn search(board: &Board, options: &SearchOptions) -> SearchResult {
+    search_with_stop(board, options, &AtomicBool::new(false))
+}
+
+/// Like [`search`], but gives up as soon as `stop` is set.
+///
+/// The result always comes from the last fully searched iteration, so a stopped
+/// search still returns a sound move. If not even the first iteration finished,
+/// the best move is just the first legal move.
+pub fn search_with_stop(board: &Board, options: &SearchOptions, stop: &AtomicBool) -> SearchResult {
+    let tt = TranspositionTable::new(options.hash_mb);
+    search_with_table(board, options, stop, &tt, &mut ())
+}
+
+/// Like [`search_with_stop`], but uses `tt` instead of a fresh table of
+/// `options.hash_mb`, so what one search learns carries over to the next, and tells
+/// `reporter` how the search is going. Entries from earlier searches are kept, but
+/// give way to this one's.
+pub fn search_with_table(
+    board: &Board,
+    options: &SearchOptions,
+    stop: &AtomicBool,
+    tt: &TranspositionTable,
+    reporter: &mut dyn SearchReporter,
+) -> SearchResult {
+    search_with_context(
+        board,
+        options,
+        stop,
+        tt,
+        &mut SearchContext::new(),
+        reporter,
+    )
+}
+
+/// Like [`search_with_table`], but also keeps the countermoves and continuation
+/// history in `context`, for the next search of the same game.
+pub fn search_with_context(
+    board: &Board,
+    options: &SearchOptions,
+    stop: &AtomicBool,
+    tt: &TranspositionTable,
+    context: &mut SearchContext,
+    reporter: &mut dyn SearchReporter,
+) -> SearchResult {
+    let mate_options;
+    let options = match options.mate {
+        Some(moves) => {
+            mate_options = SearchOptions {
+                depth: (2 * moves).saturating_sub(1).max(1),
+                late_move_reductions: false,
+                futility_pruning: false,
+                reverse_futility_pruning: false,
+                razoring: false,
+                ..options.clone()
+            };
+            &mate_options
+        }
+        None => options,
+    };
+    // A weakened search looks at a few root moves, not as deep, to choose from
+    let skill_options;
+    let options = if options.skill < MAX_SKILL && options.mate.is_none() {
+        skill_options = SearchOptions {
+            depth: options.depth.min(skill_depth(options.skill)),
+            multi_pv: options.multi_pv.max(SKILL_CANDIDATES),
+            ..options.clone()
+        };
+        &skill_options
+    } else {
+        options
+    };
+
+    tt.new_search();
+    let helpers_stop = AtomicBool::new(false);
+    let helper_nodes = AtomicU64::new(0);
+    let started = Instant::now();
+    let helper_options = SearchOptions {
+        depth: MAX_DEPTH,
+        ..options.clone()
+    };
+
+    let (main_tables, helper_tables) = context
+        .tables(options.threads.max(1))
+        .split_first_mut()
+        .unwrap();
+
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..options.threads)
+            .zip(helper_tables)
+            .map(|(id, tables)| {
+                let (helpers_stop, helper_options) = (&helpers_stop, &helper_options);
+                let helper_nodes = &helper_nodes;
+                scope.spawn(move || {
+                    let mut searcher = Searcher::new(
+                        helper_options,
+                        helpers_stop,
+                        tt,
+                        tables,
+                        helper_nodes,
+                        started,
+                    );
+                    searcher.helper = true;
+                    // Every other helper runs one ply deeper, so the threads spread out
+                    searcher.depth_offset = id as u32 % 2;
+                    searcher.iterative_deepening(board);
+                    searcher.flush_helper_nodes();
+                    searcher.stats
+                })
+            })
+            .collect();
+
+        let mut searcher = Searcher::new(options, stop, tt, main_tables, &helper_nodes, started);
+        searcher.reporter = Some(reporter);
+        let mut result = searcher.iterative_deepening(board);
+
+        helpers_stop.store(true, Ordering::Relaxed);
+        for helper in helpers {
+            let stats = helper.join().unwrap();
+            result.stats.qnodes += stats.qnodes;
+            result.stats.tt_hits += stats.tt_hits;
+        }
+        result.stats.nodes = searcher.stats.nodes + helper_nodes.load(Ordering::Relaxed);
+        result.stats.elapsed = started.elapsed();
+        result
+    })
+}
+
+/// Deepest iteration at `skill`, in plies.
+fn skill_depth(skill: u32) -> u32 {
+    1 + skill / 2
+}
+
+/// Replaces the best line of `result` with one drawn from those within the skill's
+/// margin of it, closer ones more likely. Lines that get mated are only played if
+/// nothing else is left.
+fn pick_skill_line(result: &mut SearchResult, skill: u32, seed: u64) {
+    let best = match result.lines.first() {
+        Some(line) => line.score,
+        None => return,
+    };
+    let margin = (MAX_SKILL.saturating_sub(skill)) as i32 * SKILL_MARGIN_STEP;
+    let weights: Vec<u64> = result
+        .lines
+        .iter()
+        .map(|line| {
+            let behind = best - line.score;
+            if behind > margin || line.score < -MATE_THRESHOLD {
+                0
+            } else {
+                (margin - behind + 1) as u64
+            }
+        })
+        .collect();
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return;
+    }
+
+    let mut draw = XorShift64::new(seed).next_u64() % total;
+    for (line, weight) in result.lines.iter().zip(weights) {
+        if draw < weight {
+            result.best_move = line.pv.first().copied().or(result.best_move);
+            result.score = line.score;
+            result.pv = line.pv.clone();
+            return;
+        }
+        draw -= weight;
+    }
+}
+
+/// True if `m` takes a piece, including en passant.
+fn is_capture(board: &Board, m: &Move) -> bool {
+    board.occupied_by(!board.active_color) & (1u64 << m.to) != 0
+        || (board.en_passant == Some(m.to)
+            && find_peice_for_color(board, board.active_color, m.from) == Some(PieceType::Pawn))
+}
+
+/// True if `m` neither captures nor promotes.
+fn is_quiet(board: &Board, m: &Move) -> bool {
+    m.promotion.is_none() && !is_capture(board, m)
+}
+
+/// Number of distinct [`piece_to`] values.
+const PIECE_TO_COUNT: usize = 12 * 64;
+
+/// Which of the side to move's pieces `m` moves, and where to, as one index: the
+/// piece's [`Board::bitboards`] index times 64 plus the to-square.
+fn piece_to(board: &Board, m: &Move) -> usize {
+    let piece =
+        find_peice_for_color(board, board.active_color, m.from).map_or(0, |piece| piece as usize);
+    (board.active_color as usize * 6 + piece) * 64 + m.to as usize
+}
+
+/// What the search learns about quiet moves that stays true for the rest of a game:
+/// the countermoves and continuation history of each search thread. Keep one per
+/// game and [`clear`](SearchContext::clear) it for the next.
+#[derive(Default)]
+pub struct SearchContext {
+    threads: Vec<MoveTables>,
+}
+
+impl SearchContext {
+    pub fn new() -> SearchContext {
+        SearchContext::default()
+    }
+
+    /// Forgets everything learned, keeping the tables.
+    pub fn clear(&mut self) {
+        for tables in &mut self.threads {
+            tables.countermoves = [None; PIECE_TO_COUNT];
+            tables.continuation_history.fill(0);
+        }
+    }
+
+    /// The tables of the first `threads` threads, made as needed.
+    fn tables(&mut self, threads: usize) -> &mut [MoveTables] {
+        while self.threads.len() < threads {
+            self.threads.push(MoveTables::new());
+        }
+        &mut self.threads[..threads]
+    }
+}
+
+#[cfg(test)]
+impl SearchContext {
+    /// How many countermoves are known, and the continuation history, of all threads.
+    pub(crate) fn learned(&self) -> (usize, Vec<i32>) {
+        let countermoves = self
+            .threads
+            .iter()
+            .map(|tables| tables.countermoves.iter().flatten().count())
+            .sum();
+        let continuation_history = self
+            .threads
+            .iter()
+            .flat_map(|tables| tables.continuation_history.iter().copied())
+            .collect();
+        (countermoves, continuation_history)
+    }
+}
+
+/// The move tables of one search thread, kept from one search to the next.
+struct MoveTables {
+    /// The quiet move that last refuted each move, by [`piece_to`] of the move.
+    countermoves: [Option<PackedMove>; PIECE_TO_COUNT],
+    /// History of quiet moves following each move, indexed by [`piece_to`] of the
+    /// previous move times [`PIECE_TO_COUNT`] plus that of the quiet move.
+    continuation_history: Vec<i32>,
+}
+
+impl MoveTables {
+    fn new() -> MoveTables {
+        MoveTables {
+            countermoves: [None; PIECE_TO_COUNT],
+            continuation_history: vec![0; PIECE_TO_COUNT * PIECE_TO_COUNT],
+        }
+    }
+}
+
+fn piece_at(board: &Board, square: u8) -> Option<PieceType> {
+    let bit = 1u64 << square;
+    PieceType::ALL.into_iter().find(|&piece_type| {
+        Color::BOTH
+            .iter()
+            .any(|&color| board.pieces(color, piece_type) & bit != 0)
+    })
+}
+
+/// Scores stored in the table are relative to the node they were stored at, so mate
+/// distances stay right when the position is reached at a different ply.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score + ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score - ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// What the search keeps for one ply, reused by every node searched at that ply.
+#[derive(Clone, Copy)]
+struct StackEntry {
+    /// The node's moves, in the order they are searched.
+    moves: MoveList,
+    /// Ordering score of each of `moves`.
+    scores: [i32; MAX_MOVES],
+    /// The first `pv_len` moves are the best line found from the node so far.
+    pv: [Option<PackedMove>; MAX_PLY],
+    pv_len: usize,
+    killers: [Option<PackedMove>; 2],
+    /// Static evaluation of the node, once it has been taken.
+    static_eval: i32,
+    /// [`piece_to`] of the move being searched from the node.
+    current_move: usize,
+}
+
+impl StackEntry {
+    fn new() -> StackEntry {
+        StackEntry {
+            moves: MoveList::new(),
+            scores: [0; MAX_MOVES],
+            pv: [None; MAX_PLY],
+            pv_len: 0,
+            killers: [None; 2],
+            static_eval: 0,
+            current_move: 0,
+        }
+    }
+}
+
+/// One [`StackEntry`] for each ply up to [`MAX_PLY`], allocated once per search so
+/// the recursion allocates nothing. The principal variation is built in it from the
+/// leaves up: a node that raises alpha takes the line of the ply after it.
+struct SearchStack(Vec<StackEntry>);
+
+impl SearchStack {
+    fn new() -> SearchStack {
+        SearchStack(vec![StackEntry::new(); MAX_PLY + 1])
+    }
+
+    /// Makes `m` followed by the line found at the next ply the line from `ply`.
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        let (entry, rest) = self.0[ply..].split_first_mut().unwrap();
+        let next = &rest[0];
+        entry.pv[0] = Some(PackedMove::new(m));
+        entry.pv[1..=next.pv_len].copy_from_slice(&next.pv[..next.pv_len]);
+        entry.pv_len = next.pv_len + 1;
+    }
+
+    /// The line found from `ply`.
+    fn pv(&self, ply: usize) -> Vec<Move> {
+        let entry = &self.0[ply];
+        entry.pv[..entry.pv_len]
+            .iter()
+            .flatten()
+            .map(|m| m.unpack())
+            .collect()
+    }
+}
+
+impl Index<usize> for SearchStack {
+    type Output = StackEntry;
+
+    fn index(&self, ply: usize) -> &StackEntry {
+        &self.0[ply]
+    }
+}
+
+impl IndexMut<usize> for SearchStack {
+    fn index_mut(&mut self, ply: usize) -> &mut StackEntry {
+        &mut self.0[ply]
+    }
+}
+
+struct Searcher<'a> {
+    options: &'a SearchOptions,
+    /// Only the main thread reports.
+    reporter: Option<&'a mut dyn SearchReporter>,
+    /// Helpers add their nodes here in batches; the main thread reads it to report.
+    helper_nodes: &'a AtomicU64,
+    helper: bool,
+    /// Nodes of this helper already added to `helper_nodes`.
+    flushed_nodes: u64,
+    started: Instant,
+    last_progress: Instant,
+    /// How long the search runs before root moves are reported as they are searched.
+    current_move_delay: Duration,
+    /// Time every evaluation takes, to make the search slow.
+    #[cfg(test)]
+    eval_delay: Duration,
+    /// Nodes after which the search stops, to solve test positions the same way
+    /// however fast the machine is.
+    #[cfg(test)]
+    node_limit: u64,
+    stop: &'a AtomicBool,
+    /// Set once `stop` has been seen; every node then returns straight away.
+    stopped: bool,
+    tt: &'a TranspositionTable,
+    /// Added to the depth of every iteration.
+    depth_offset: u32,
+    /// Root moves left out of the search, the lines already found with MultiPV.
+    excluded: Vec<Move>,
+    /// The only root moves to search, if restricted to some.
+    root_moves: Option<Vec<Move>>,
+    /// Best root move of the last call to `negamax`.
+    root_best: Option<Move>,
+    /// Side to move at the root, which contempt is applied for.
+    root_color: Color,
+    /// What the current iteration made of each root move, with `trace_root`.
+    root_reports: Vec<RootMoveReport>,
+    stats: SearchStats,
+    /// Log every node-level event, not just iteration summaries.
+    trace: bool,
+    stack: SearchStack,
+    /// History of quiet moves by [`PackedMove::from_to`].
+    history: [i32; 64 * 64],
+    /// Countermoves and continuation history, from earlier searches of the game too.
+    tables: &'a mut MoveTables,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(
+        options: &'a SearchOptions,
+        stop: &'a AtomicBool,
+        tt: &'a TranspositionTable,
+        tables: &'a mut MoveTables,
+        helper_nodes: &'a AtomicU64,
+        started: Instant,
+    ) -> Searcher<'a> {
+        Searcher {
+            options,
+            reporter: None,
+            helper_nodes,
+            helper: false,
+            flushed_nodes: 0,
+            started,
+            last_progress: started,
+            current_move_delay: CURRENT_MOVE_DELAY,
+            #[cfg(test)]
+            eval_delay: Duration::ZERO,
+            #[cfg(test)]
+            node_limit: u64::MAX,
+            stop,
+            stopped: false,
+            tt,
+            depth_offset: 0,
+            excluded: Vec::new(),
+            root_moves: None,
+            root_best: None,
+            root_color: Color::White,
+            root_reports: Vec::new(),
+            stats: SearchStats::default(),
+            trace: log_enabled!(Level::Debug),
+            stack: SearchStack::new(),
+            history: [0; 64 * 64],
+            tables,
+        }
+    }
+
+    fn iterative_deepening(&mut self, board: &Board) -> SearchResult {
+        let mut board = *board;
+        self.root_color = board.active_color;
+        let mut legal = MoveList::new();
+        board.generate_legal_moves_into(&mut legal);
+        let status = legal.is_empty().then(|| board.status());
+        if let Some(only) = &self.options.root_moves {
+            if legal.iter().any(|m| only.contains(&m)) {
+                legal.retain(|m| only.contains(m));
+                self.root_moves = Some(legal.to_vec());
+            }
+        }
+        let mut result = SearchResult {
+            best_move: legal.first(),
+            score: 0,
+            depth: 0,
+            stats: SearchStats::default(),
+            pv: Vec::new(),
+            lines: Vec::new(),
+            report: None,
+            status,
+        };
+        result.pv.extend(result.best_move);
+
+        if self.options.easy_moves && !self.helper && legal.len() == 1 {
+            self.stats.early_stop = Some(EarlyStop::OnlyMove);
+            result.stats = self.current_stats();
+            return result;
+        }
+
+        let line_count = self.options.multi_pv.clamp(1, legal.len().max(1));
+        let offset = self.depth_offset;
+        let mut easy_move: Option<(Move, u32)> = None;
+        for depth in (1..=self.options.depth.max(1)).map(|d| d + offset) {
+            // Each line searches the root without the moves of the lines before it
+            let mut lines = Vec::with_capacity(line_count);
+            self.excluded.clear();
+            self.root_reports.clear();
+            while lines.len() < line_count {
+                let score = self.negamax(&mut board, depth as i32, -INFINITY, INFINITY, 0);
+                if self.stopped {
+                    break;
+                }
+
+                let mut pv = Vec::new();
+                if let Some(m) = self.root_best {
+                    pv = self.stack.pv(0);
+                    // Every root move failed low, so none of them left a line
+                    if pv.first() != Some(&m) {
+                        pv = vec![m];
+                    }
+                    // A line cut short by a table hit goes on as the table has it
+                    let mut after = board;
+                    for m in &pv {
+                        after.make_move_unchecked(m);
+                    }
+                    let rest = (depth as usize).saturating_sub(pv.len());
+                    pv.extend(self.principal_variation(&after, rest));
+                    self.excluded.push(m);
+                }
+                lines.push(PvLine { score, pv });
+            }
+            self.excluded.clear();
+
+            // A stopped iteration may not have looked at the best move at all
+            if self.stopped {
+                break;
+            }
+            lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+            let score = lines[0].score;
+
+            let best_move = lines[0].pv.first().copied().or(result.best_move);
+            result = SearchResult {
+                best_move,
+                score,
+                depth,
+                stats: self.current_stats(),
+                pv: lines[0].pv.clone(),
+                lines,
+                report: self.root_report(&board, best_move),
+                status,
+            };
+            if !self.helper {
+                let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+                debug!(
+                    depth = depth,
+                    score = score,
+                    nodes = result.stats.nodes,
+                    seldepth = result.stats.seldepth,
+                    elapsed_ms = result.stats.elapsed.as_millis() as u64,
+                    pv = pv.join(" ").as_str();
+                    "iteration"
+                );
+            }
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.iteration(&result);
+            }
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // No point searching deeper once a forced mate has been found
+            if score.abs() > MATE_THRESHOLD {
+                break;
+            }
+
+            if self.options.easy_moves
+                && !self.helper
+                && line_count == 1
+                && depth >= EASY_MOVE_DEPTH
+            {
+                easy_move = match (best_move, easy_move) {
+                    (Some(best), _) if !self.is_easy_move(&mut board, best, score, depth) => None,
+                    (Some(best), Some((m, iterations))) if m == best => Some((m, iterations + 1)),
+                    (Some(best), _) => Some((best, 1)),
+                    (None, _) => None,
+                };
+                if easy_move.is_some_and(|(_, iterations)| iterations >= EASY_MOVE_ITERATIONS) {
+                    self.stats.early_stop = Some(EarlyStop::EasyMove);
+                    break;
+                }
+            }
+        }
+        if !self.helper && self.options.skill < MAX_SKILL && self.options.mate.is_none() {
+            pick_skill_line(&mut result, self.options.skill, self.options.skill_seed);
+        }
+        result.stats = self.current_stats();
+        result
+    }
+
+    /// Whether every root move but `best` scores below `score` by at least
+    /// [`EASY_MOVE_MARGIN`], by a null-window search of the others at half `depth`.
+    fn is_easy_move(&mut self, board: &mut Board, best: Move, score: i32, depth: u32) -> bool {
+        let bound = score - EASY_MOVE_MARGIN;
+        let reports = std::mem::take(&mut self.root_reports);
+        self.excluded.push(best);
+        let others = self.negamax(board, (depth / 2) as i32, bound - 1, bound, 0);
+        self.excluded.clear();
+        self.root_reports = reports;
+        !self.stopped && others < bound
+    }
+
+    /// The root moves of the iteration just completed, best first, if the options ask
+    /// for them.
+    fn root_report(&self, board: &Board, best_move: Option<Move>) -> Option<SearchReport> {
+        if !self.options.trace_root || self.helper {
+            return None;
+        }
+        let mut moves = self.root_reports.clone();
+        moves.sort_by_key(|report| {
+            (
+                Some(report.root_move) != best_move,
+                std::cmp::Reverse(report.score),
+            )
+        });
+        Some(SearchReport {
+            fen: board.board_to_fen(),
+            moves,
+        })
+    }
+
+    /// Records what the root search made of `m`, replacing what an earlier line of
+    /// the same iteration found.
+    fn record_root_move(&mut self, board: &Board, m: Move, score: i32, bound: Bound, depth: i32) {
+        let mut after = *board;
+        after.make_move_unchecked(&m);
+        let mut pv = vec![m];
+        pv.extend(self.principal_variation(&after, depth as usize - 1));
+
+        let report = RootMoveReport {
+            root_move: m,
+            score,
+            depth: depth as u32,
+            bound,
+            pv,
+        };
+        match self.root_reports.iter_mut().find(|r| r.root_move == m) {
+            Some(existing) => *existing = report,
+            None => self.root_reports.push(report),
+        }
+    }
+
+    /// This thread's counters, with the nodes of the helpers added in so far.
+    fn current_stats(&self) -> SearchStats {
+        SearchStats {
+            nodes: self.stats.nodes + self.helper_nodes.load(Ordering::Relaxed),
+            hashfull: self.tt.hashfull(),
+            elapsed: self.started.elapsed(),
+            ..self.stats
+        }
+    }
+
+    fn flush_helper_nodes(&mut self) {
+        self.helper_nodes
+            .fetch_add(self.stats.nodes - self.flushed_nodes, Ordering::Relaxed);
+        self.flushed_nodes = self.stats.nodes;
+    }
+
+    /// Counts a node at `ply`.
+    fn visit(&mut self, ply: usize) {
+        self.stats.nodes += 1;
+        self.stats.seldepth = self.stats.seldepth.max(ply as u32);
+    }
+
+    /// Score of a draw for the side to move in `board`.
+    fn draw_score(&self, board: &Board) -> i32 {
+        if board.active_color == self.root_color {
+            -self.options.contempt
+        } else {
+            self.options.contempt
+        }
+    }
+
+    /// Checks the stop flag every [`STOP_CHECK_INTERVAL`] nodes. Helpers share their
+    /// node count and the main thread reports progress at the same time.
+    fn should_stop(&mut self) -> bool {
+        if self.stopped || !self.stats.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            return self.stopped;
+        }
+        self.stopped = self.stop.load(Ordering::Relaxed);
+        #[cfg(test)]
+        {
+            self.stopped |= self.stats.nodes >= self.node_limit;
+        }
+
+        if self.helper {
+            self.flush_helper_nodes();
+        } else if self.last_progress.elapsed() >= PROGRESS_INTERVAL {
+            self.last_progress = Instant::now();
+            let stats = self.current_stats();
+            if let Some(reporter) = self.reporter.as_mut() {
+                reporter.progress(&stats);
+            }
+        }
+        self.stopped
+    }
+
+    /// Follows best moves through the transposition table.
+    fn principal_variation(&self, board: &Board, max_len: usize) -> Vec<Move> {
+        let mut board = *board;
+        let mut pv = Vec::new();
+
+        while pv.len() < max_len {
+            let m = match self
+                .tt
+                .probe(polyglot_key(&board))
+                .and_then(|e| e.best_move)
+                .map(PackedMove::unpack)
+            {
+                Some(m) if board.legal_moves().contains(&m) => m,
+                _ => break,
+            };
+            pv.push(m);
+            board.make_move_unchecked(&m);
+        }
+        pv
+    }
+
+    /// [`piece_to`] of the move that led to the node at `ply`, if the search made it.
+    fn previous_move(&self, ply: usize) -> Option<usize> {
+        (ply > 0 && ply < MAX_PLY).then(|| self.stack[ply - 1].current_move)
+    }
+
+    /// Static evaluation of `board` for the side to move.
+    fn evaluate(&self, board: &Board) -> i32 {
+        #[cfg(test)]
+        if !self.eval_delay.is_zero() {
+            thread::sleep(self.eval_delay);
+        }
+        evaluate(board, &self.options.eval_params)
+    }
+
+    /// Remembers that the quiet move `m` caused a cutoff at `ply`, `depth` plies
+    /// from the leaves: as a killer, in the history, and as the answer to the
+    /// previous move.
+    fn record_quiet_cutoff(&mut self, board: &Board, m: &Move, ply: usize, depth: i32) {
+        let packed = PackedMove::new(*m);
+        if ply < MAX_PLY && self.stack[ply].killers[0] != Some(packed) {
+            let killers = &mut self.stack[ply].killers;
+            *killers = [Some(packed), killers[0]];
+        }
+        self.history[packed.from_to()] += depth * depth;
+        if let Some(previous) = self.previous_move(ply) {
+            self.tables.countermoves[previous] = Some(packed);
+            let continuation = &mut self.tables.continuation_history
+                [previous * PIECE_TO_COUNT + piece_to(board, m)];
+            *continuation = continuation.saturating_add(depth * depth);
+        }
+    }
+
+    /// Orders the moves at `ply` of the stack best first: the table move, captures by
+    /// most valuable victim and least valuable attacker, killers, then quiet moves by
+    /// history and continuation history, with a bonus for the countermove to the
+    /// previous move. The quiescence search has no killers or previous move to go by.
+    fn order_moves(
+        &mut self,
+        board: &Board,
+        ply: usize,
+        tt_move: Option<PackedMove>,
+        quiescence: bool,
+    ) {
+        let context = if quiescence { MAX_PLY } else { ply };
+        for i in 0..self.stack[ply].moves.len() {
+            let m = self.stack[ply].moves.get(i).unwrap();
+            self.stack[ply].scores[i] = self.order_score(board, &m, tt_move, context);
+        }
+
+        // Insertion sort, best first, keeping equally scored moves in their order
+        let entry = &mut self.stack[ply];
+        for i in 1..entry.moves.len() {
+            let mut j = i;
+            while j > 0 && entry.scores[j - 1] < entry.scores[j] {
+                entry.scores.swap(j - 1, j);
+                entry.moves.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    /// How early `m` is searched at `ply`; see [`Searcher::order_moves`].
+    fn order_score(&self, board: &Board, m: &Move, tt_move: Option<PackedMove>, ply: usize) -> i32 {
+        const VALUES: [i32; 6] = [1, 3, 3, 5, 9, 20];
+
+        let packed = PackedMove::new(*m);
+        if Some(packed) == tt_move {
+            return 1_000_000;
+        }
+        if let Some(promotion) = m.promotion {
+            return 900_000 + VALUES[promotion as usize];
+        }
+        if is_capture(board, m) {
+            let victim = piece_at(board, m.to).map_or(1, |p| VALUES[p as usize]);
+            let attacker = piece_at(board, m.from).map_or(1, |p| VALUES[p as usize]);
+            return 800_000 + victim * 100 - attacker;
+        }
+        if ply < MAX_PLY && self.stack[ply].killers.contains(&Some(packed)) {
+            return 700_000;
+        }
+        let history = self.history[packed.from_to()];
+        match self.previous_move(ply) {
+            Some(previous) => {
+                let countermove = self.tables.countermoves[previous] == Some(packed);
+                history
+                    + self.tables.continuation_history
+                        [previous * PIECE_TO_COUNT + piece_to(board, m)]
+                    + if countermove { COUNTERMOVE_BONUS } else { 0 }
+            }
+            None => history,
+        }
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: i32,
+        mut alpha: i32,
+        mut beta: i32,
+        ply: usize,
+    ) -> i32 {
+        if depth <= 0 || ply >= MAX_PLY {
+            return self.quiescence(board, alpha, beta, ply);
+        }
+        self.stack[ply].pv_len = 0;
+        self.visit(ply);
+        if self.should_stop() {
+            return 0;
+        }
+        // Repeating a position, even once, is as good as a draw: neither side can do
+        // better from it than it could the first time
+        if ply > 0 && (board.is_repetition() || board.is_insufficient_material()) {
+            return self.draw_score(board);
+        }
+
+        // Mate distance pruning: nothing here can beat being mated right now or mating
+        // next move, so a window outside those bounds is already decided
+        if ply > 0 {
+            alpha = alpha.max(-MATE_SCORE + ply as i32);
+            beta = beta.min(MATE_SCORE - ply as i32 - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        let key = polyglot_key(board);
+        let tt_entry = self.tt.probe(key);
+        if tt_entry.is_some() {
+            self.stats.tt_hits += 1;
+        }
+        if self.trace {
+            debug!(ply = ply, depth = depth, hit = tt_entry.is_some(); "tt_probe");
+        }
+        if let Some(entry) = tt_entry {
+            if ply > 0 && entry.depth as i32 >= depth {
+                let score = score_from_tt(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let color = board.active_color;
+        let in_check = board.is_in_check(color);
+        if !board.has_legal_moves() {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                self.draw_score(board)
+            };
+        }
+        // Checkmate takes precedence over the fifty-move rule, so this comes second
+        if ply > 0 && board.halfmove_clock >= 100 {
+            return self.draw_score(board);
+        }
+
+        let static_eval = self.evaluate(board);
+        self.stack[ply].static_eval = static_eval;
+        // Neither pruning below can be trusted to keep a mate score right
+        let ordinary_window = alpha.abs() < MATE_THRESHOLD && beta.abs() < MATE_THRESHOLD;
+
+        // Reverse futility pruning: this close to the leaves, a position this far
+        // above beta is not going to fall below it
+        if self.options.reverse_futility_pruning
+            && ply > 0
+            && !in_check
+            && ordinary_window
+            && depth <= REVERSE_FUTILITY_DEPTH
+            && static_eval - REVERSE_FUTILITY_MARGIN * depth >= beta
+        {
+            self.stats.reverse_futility_cutoffs += 1;
+            if self.trace {
+                debug!(ply = ply, depth = depth, eval = static_eval, beta = beta; "reverse_futility_prune");
+            }
+            return beta;
+        }
+
+        // Razoring: a position far below alpha needs a capture to catch up, so let
+        // the quiescence search decide, and trust it if it cannot reach alpha either
+        if self.options.razoring
+            && ply > 0
+            && !in_check
+            && ordinary_window
+            && (depth as usize) < RAZOR_MARGINS.len()
+            && static_eval + RAZOR_MARGINS[depth as usize] <= alpha
+        {
+            let score = self.quiescence(board, alpha, beta, ply);
+            if score <= alpha {
+                self.stats.razor_cutoffs += 1;
+                if self.trace {
+                    debug!(ply = ply, depth = depth, eval = static_eval, alpha = alpha; "razor");
+                }
+                return score;
+            }
+        }
+
+        // Generated only now, as razoring's quiescence search shares this ply's entry
+        let moves = &mut self.stack[ply].moves;
+        moves.clear();
+        board.generate_legal_moves_into(moves);
+        if ply == 0 {
+            if let Some(only) = &self.root_moves {
+                moves.retain(|m| only.contains(m));
+            }
+        }
+        let tt_move = tt_entry.and_then(|entry| entry.best_move);
+        self.order_moves(board, ply, tt_move, false);
+
+        let alpha_orig = alpha;
+        let trace_root = ply == 0 && self.options.trace_root && !self.helper;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+
+        for idx in 0..self.stack[ply].moves.len() {
+            let m = self.stack[ply].moves.get(idx).unwrap();
+            if ply == 0 && self.excluded.contains(&m) {
+                continue;
+            }
+            if ply == 0 && self.started.elapsed() >= self.current_move_delay {
+                if let Some(reporter) = self.reporter.as_mut() {
+                    reporter.current_move(m, idx + 1);
+                }
+            }
+            let quiet = is_quiet(board, &m);
+            self.stack[ply].current_move = piece_to(board, &m);
+            let undo = board.make_move_unchecked(&m);
+            let gives_check = board.is_in_check(board.active_color);
+
+            // Futility pruning: a quiet move this close to the leaves will not make
+            // up a large deficit
+            if self.options.futility_pruning
+                && idx > 0
+                && quiet
+                && !in_check
+                && !gives_check
+                && (depth as usize) < FUTILITY_MARGINS.len()
+                && self.stack[ply].static_eval + FUTILITY_MARGINS[depth as usize] <= alpha
+                && alpha.abs() < MATE_THRESHOLD
+            {
+                board.unmake_move(&m, undo);
+                // Pruned because it cannot reach alpha, so alpha bounds it
+                if trace_root {
+                    self.record_root_move(board, m, alpha, Bound::Upper, depth);
+                }
+                if self.trace {
+                    debug!(
+                        ply = ply,
+                        depth = depth,
+                        uci:% = m,
+                        eval = self.stack[ply].static_eval,
+                        alpha = alpha;
+                        "futility_prune"
+                    );
+                }
+                continue;
+            }
+
+            let score = if self.options.late_move_reductions
+                && depth >= 3
+                && idx >= 3
+                && quiet
+                && !in_check
+                && !gives_check
+            {
+                // Late move reduction: search with a reduced depth and a null window,
+                // and only search properly if the move turns out better than alpha
+                let reduced = -self.negamax(board, depth - 2, -alpha - 1, -alpha, ply + 1);
+                if reduced > alpha {
+                    if self.trace {
+                        debug!(ply = ply, depth = depth, uci:% = m; "lmr_research");
+                    }
+                    -self.negamax(board, depth - 1, -beta, -alpha, ply + 1)
+                } else {
+                    reduced
+                }
+            } else {
+                -self.negamax(board, depth - 1, -beta, -alpha, ply + 1)
+            };
+            board.unmake_move(&m, undo);
+
+            // The score of an interrupted subtree means nothing; keep it out of the
+            // table and let the caller discard this iteration
+            if self.stopped {
+                return 0;
+            }
+
+            if trace_root {
+                let bound = if score >= beta {
+                    Bound::Lower
+                } else if score <= alpha {
+                    Bound::Upper
+                } else {
+                    Bound::Exact
+                };
+                self.record_root_move(board, m, score, bound, depth);
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+            }
+            if score > alpha {
+                alpha = score;
+                self.stack.update_pv(ply, m);
+            }
+            if alpha >= beta {
+                if self.trace {
+                    debug!(
+                        ply = ply,
+                        depth = depth,
+                        uci:% = m,
+                        move_number = idx + 1,
+                        quiet = quiet;
+                        "beta_cutoff"
+                    );
+                }
+                if quiet {
+                    self.record_quiet_cutoff(board, &m, ply, depth);
+                }
+                break;
+            }
+        }
+
+        if ply == 0 {
+            self.root_best = best_move;
+        }
+        // Every move was pruned: fall back to the static evaluation
+        if best_move.is_none() {
+            return alpha;
+        }
+
+        // A root search that left out moves has not found the position's best move
+        if ply == 0 && (!self.excluded.is_empty() || self.root_moves.is_some()) {
+            return best_score;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > alpha_orig {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(TtEntry {
+            key,
+            depth: depth as u8,
+            score: score_to_tt(best_score, ply),
+            bound,
+            best_move: best_move.map(PackedMove::new),
+        });
+        if self.trace {
+            debug!(ply = ply, depth = depth, score = best_score, bound:? = bound; "tt_store");
+        }
+
+        best_score
+    }
+
+    /// Searches captures and promotions (or every evasion when in check) until the
+    /// position is quiet, so the evaluation is not taken in the middle of an exchange.
+    fn quiescence(&mut self, board: &mut Board, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.stack[ply].pv_len = 0;
+        self.visit(ply);
+        self.stats.qnodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if board.is_insufficient_material() {
+            return self.draw_score(board);
+        }
+
+        let in_check = board.is_in_check(board.active_color);
+        if !in_check || ply >= MAX_PLY {
+            let stand_pat = self.evaluate(board);
+            if stand_pat >= beta || ply >= MAX_PLY {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        }
+
+        self.stack[ply].moves = if in_check {
+            board.evasion_moves()
+        } else {
+            board.capture_moves()
+        };
+        if in_check && self.stack[ply].moves.is_empty() {
+            return -MATE_SCORE + ply as i32;
+        }
+        self.order_moves(board, ply, None, true);
+
+        for idx in 0..self.stack[ply].moves.len() {
+            let m = self.stack[ply].moves.get(idx).unwrap();
+            let undo = board.make_move_unchecked(&m);
+            let score = -self.quiescence(board, -beta, -alpha, ply + 1);
+            board.unmake_move(&m, undo);
+
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+/// Positions searched by [`bench`]: openings, middlegames, endgames and a few with
+/// castling, en passant and promotion tricks.
+pub const BENCH_FENS: [&str; 20] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "rnbqkb1r/pp1p1ppp/4pn2/2p5/2PP4/2N5/PP2PPPP/R1BQKBNR w KQkq - 0 4",
+    "r1bq1rk1/ppp2ppp/2np1n2/2b1p3/2B1P3/2PP1N2/PP3PPP/RNBQ1RK1 w - - 0 7",
+    "2rq1rk1/pb1nbppp/1p2pn2/2pp4/2PP4/1PN1PN2/PB2BPPP/2RQ1RK1 w - - 0 11",
+    "r2q1rk1/pp2ppbp/2n2np1/2pp4/5Bb1/2PP1NP1/PP1NPPBP/R2QR1K1 w - - 0 10",
+    "3r1rk1/p4ppp/1pn1p3/2p5/2P5/1P3NP1/P3PPKP/3R1R2 w - - 0 20",
+    "r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - 0 1",
+    "4r1k1/pp3ppp/8/3q4/3Q4/8/PP3PPP/4R1K1 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/5pk1/6p1/7p/7P/6P1/5PK1/8 w - - 0 1",
+    "8/k7/3p4/p2P1p2/P2P1P2/8/8/K7 w - - 0 1",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+    "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1",
+    "1k6/1b6/8/8/7R/8/8/4K2R b K - 0 1",
+];
+
+/// Depth [`bench`] searches to unless told otherwise.
+pub const BENCH_DEPTH: u32 = 6;
+
+/// Transposition table size [`bench`] always uses, so node counts do not depend on
+/// the engine's Hash setting.
+const BENCH_HASH_MB: usize = 16;
+
+/// Searches every position in [`BENCH_FENS`] to `depth` on one thread, each with a
+/// fresh table, and returns the totals.
+///
+/// The search is deterministic, so the total node count is a fingerprint of the
+/// search: a change that should not alter it (a speed-up, a refactor) can be checked
+/// by comparing counts, and one that does alter it shows up.
+pub fn bench(depth: u32) -> SearchStats {
+    let options = SearchOptions {
+        depth,
+        hash_mb: BENCH_HASH_MB,
+        ..SearchOptions::default()
+    };
+    let stop = AtomicBool::new(false);
+    let started = Instant::now();
+    let mut total = SearchStats::default();
+
+    for fen in BENCH_FENS {
+        let board = Board::fen_to_board(fen);
+        let tt = TranspositionTable::new(BENCH_HASH_MB);
+        let stats = search_with_table(&board, &options, &stop, &tt, &mut ()).stats;
+        total.nodes += stats.nodes;
+        total.qnodes += stats.qnodes;
+        total.tt_hits += stats.tt_hits;
+        total.reverse_futility_cutoffs += stats.reverse_futility_cutoffs;
+        total.razor_cutoffs += stats.razor_cutoffs;
+        total.seldepth = total.seldepth.max(stats.seldepth);
+    }
+    total.elapsed = started.elapsed();
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    /// What `bench(3)` searches today.
+    const BENCH_NODES_AT_DEPTH_3: u64 = 101_470;
+
+    /// The first 20 positions of Win at Chess, with their best move in SAN.
+    const TACTICS: [(&str, &str); 20] = [
+        (
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+            "Qg6",
+        ),
+        ("8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - 0 1", "Rxb2"),
+        (
+            "5rk1/1ppb3p/p1pb4/6q1/3P1p1r/2P1R2P/PP1BQ1P1/5RKN w - - 0 1",
+            "Rg3",
+        ),
+        (
+            "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1",
+            "Qxh7+",
+        ),
+        ("5k2/6pp/p1qN4/1p1p4/3P4/2PKP2Q/PP3r2/3R4 b - - 0 1", "Qc4+"),
+        ("7k/p7/1R5K/6r1/6p1/6P1/8/8 w - - 0 1", "Rb7"),
+        (
+            "rnbqkb1r/pppp1ppp/8/4P3/6n1/7P/PPPNPPP1/R1BQKBNR b KQkq - 0 1",
+            "Ne3",
+        ),
+        (
+            "r4q1k/p2bR1rp/2p2Q1N/5p2/5p2/2P5/PP3PPP/R5K1 w - - 0 1",
+            "Rf7",
+        ),
+        (
+            "3q1rk1/p4pp1/2pb3p/3p4/6Pr/1PNQ4/P1PB1PP1/4RRK1 b - - 0 1",
+            "Bh2+",
+        ),
+        (
+            "2br2k1/2q3rn/p2NppQ1/2p1P3/Pp5R/4P3/1P3PPP/3R2K1 w - - 0 1",
+            "Rxh7",
+        ),
+        (
+            "r1b1kb1r/3q1ppp/pBp1pn2/8/Np3P2/5B2/PPP3PP/R2Q1RK1 w kq - 0 1",
+            "Bxc6",
+        ),
+        (
+            "4k1r1/2p3r1/1pR1p3/3pP2p/3P2qP/P4N2/1PQ4P/5R1K b - - 0 1",
+            "Qxf3+",
+        ),
+        (
+            "5rk1/pp4p1/2n1p2p/2Npq3/2p5/6P1/P3P1BP/R4Q1K w - - 0 1",
+            "Qxf8+",
+        ),
+        (
+            "r2rb1k1/pp1q1p1p/2n1p1p1/2bp4/5P2/PP1BPR1Q/1BPN2PP/R5K1 w - - 0 1",
+            "Qxh7+",
+        ),
+        (
+            "1R6/1brk2p1/4p2p/p1P1Pp2/P7/6P1/1P4P1/2R3K1 w - - 0 1",
+            "Rxb7",
+        ),
+        (
+            "r4rk1/ppp2ppp/2n5/2bqp3/8/P2PB3/1PP1NPPP/R2Q1RK1 w - - 0 1",
+            "Nc3",
+        ),
+        (
+            "1k5r/pppbn1pp/4q1r1/1P3p2/2NPp3/1QP5/P4PPP/R1B1R1K1 w - - 0 1",
+            "Ne5",
+        ),
+        ("R7/P4k2/8/8/8/8/r7/6K1 w - - 0 1", "Rh8"),
+        (
+            "r1b2rk1/ppbn1ppp/4p3/1QP4q/3P4/N4N2/5PPP/R1B2RK1 w - - 0 1",
+            "c6",
+        ),
+        (
+            "r2qkb1r/1ppb1ppp/p7/4p3/P1Q1P3/2P5/5PPP/R1B2KNR b kq - 0 1",
+            "Bb5",
+        ),
+    ];
+
+    /// Nodes each of [`TACTICS`] is searched for.
+    const TACTICS_NODES: u64 = 20_000;
+    /// How many of [`TACTICS`] the pruned search solves in [`TACTICS_NODES`] today;
+    /// the full-width search solves 15.
+    const TACTICS_SOLVED: usize = 17;
+
+    fn options(depth: u32, pruning: bool) -> SearchOptions {
+        SearchOptions {
+            depth,
+            hash_mb: 1,
+            late_move_reductions: pruning,
+            futility_pruning: pruning,
+            reverse_futility_pruning: pruning,
+            razoring: pruning,
+            ..SearchOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let board = Board::fen_to_board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = search(&board, &options(3, true));
+
+        assert_eq!(result.best_move, Some(Move::new("a1a8")));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_no_move_when_checkmated_or_stalemated() {
+        let mated = Board::fen_to_board("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        let result = search(&mated, &options(3, true));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE_SCORE);
+
+        assert_eq!(
+            result.status,
+            Some(GameStatus::Checkmate {
+                winner: Color::White
+            })
+        );
+
+        let stalemate = Board::fen_to_board("k7/8/1QK5/8/8/8/8/8 b - - 0 1");
+        let result = search(&stalemate, &options(3, true));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+        assert_eq!(result.status, Some(GameStatus::Stalemate));
+
+        let result = search(&Board::new(), &options(3, true));
+        assert_eq!(result.status, None);
+    }
+
+    #[test]
+    fn test_stop_before_the_search_starts_still_gives_a_legal_move() {
+        let stop = AtomicBool::new(true);
+        for fen in [
+            Board::START_FEN,
+            // In check, where the first generated move need not be legal
+            "4k3/8/8/8/8/8/3P1P2/r3K3 w - - 0 1",
+        ] {
+            let board = Board::fen_to_board(fen);
+            for threads in [1, 2] {
+                let result = search_with_stop(
+                    &board,
+                    &SearchOptions {
+                        threads,
+                        ..options(MAX_DEPTH, true)
+                    },
+                    &stop,
+                );
+                // The stop is polled every so many nodes, so a short first
+                // iteration may still finish
+                assert!(result.depth <= 1, "{}", fen);
+                let best = result.best_move.expect("a legal move exists");
+                assert!(board.legal_moves().contains(&best), "{}", fen);
+                assert_eq!(result.pv.first(), Some(&best));
+                assert_eq!(result.status, None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pv_starts_with_best_move() {
+        let board = Board::new();
+        let result = search(&board, &options(3, true));
+
+        assert_eq!(result.pv.first().copied(), result.best_move);
+        assert!(board.legal_moves().contains(&result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn test_root_report_has_every_move_once_and_the_best_first() {
+        let fens = [
+            Board::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::fen_to_board(fen);
+            for multi_pv in [1, 3] {
+                let result = search(
+                    &board,
+                    &SearchOptions {
+                        trace_root: true,
+                        multi_pv,
+                        ..options(3, true)
+                    },
+                );
+                let report = result.report.expect("trace_root asks for a report");
+                assert_eq!(report.fen, board.board_to_fen());
+
+                let mut reported: Vec<String> = report
+                    .moves
+                    .iter()
+                    .map(|r| r.root_move.to_string())
+                    .collect();
+                let mut legal: Vec<String> =
+                    board.legal_moves().iter().map(|m| m.to_string()).collect();
+                reported.sort();
+                legal.sort();
+                assert_eq!(reported, legal, "{}", fen);
+
+                let best = &report.moves[0];
+                assert_eq!(Some(best.root_move), result.best_move);
+                assert_eq!(best.score, result.score);
+                assert_eq!(best.bound, Bound::Exact);
+                assert_eq!(best.pv, result.pv);
+                assert!(report.moves.iter().all(|r| r.pv[0] == r.root_move));
+            }
+        }
+
+        let result = search(&Board::new(), &options(2, true));
+        assert_eq!(result.report, None);
+    }
+
+    #[test]
+    fn test_lowest_skill_sometimes_plays_another_legal_move() {
+        let board = Board::new();
+        let best = search(&board, &options(1, true)).best_move.unwrap();
+
+        let mut others = 0;
+        for seed in 1..=40 {
+            let weak = SearchOptions {
+                skill: 0,
+                skill_seed: seed,
+                ..options(4, true)
+            };
+            let m = search(&board, &weak).best_move.unwrap();
+            assert!(board.legal_moves().contains(&m), "{}", m);
+            if m != best {
+                others += 1;
+            }
+        }
+        assert!(others > 0, "skill 0 always played {}", best);
+    }
+
+    #[test]
+    fn test_full_skill_plays_the_best_move() {
+        let board =
+            Board::fen_to_board("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let best = search(&board, &options(4, true)).best_move;
+
+        for seed in [1, 2, 3] {
+            let full = SearchOptions {
+                skill: MAX_SKILL,
+                skill_seed: seed,
+                ..options(4, true)
+            };
+            assert_eq!(search(&board, &full).best_move, best);
+        }
+    }
+
+    #[test]
+    fn test_stop_flag_returns_promptly_with_a_legal_move() {
+        use std::sync::Arc;
+        use std::time::Duration;
+        // std's clock panics on wasm32-unknown-unknown; web-time reads the browser's instead
+        #[cfg(not(target_arch = "wasm32"))]
+        use std::time::Instant;
+        #[cfg(target_arch = "wasm32")]
+        use web_time::Instant;
+
+        let board = Board::new();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let worker = {
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || search_with_stop(&board, &options(MAX_DEPTH, true), &stop))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        let stopped_at = Instant::now();
+        stop.store(true, Ordering::Relaxed);
+        let result = worker.join().unwrap();
+
+        assert!(stopped_at.elapsed() < Duration::from_millis(500));
+        assert!(result.depth < MAX_DEPTH);
+        assert!(board.legal_moves().contains(&result.best_move.unwrap()));
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        iterations: Vec<SearchResult>,
+        current_moves: Vec<(Move, usize)>,
+        /// When each of `current_moves` was reported.
+        current_move_times: Vec<Instant>,
+        /// Time each iteration takes to report, to make the search slow.
+        iteration_delay: Duration,
+    }
+
+    impl SearchReporter for Recorder {
+        fn iteration(&mut self, result: &SearchResult) {
+            self.iterations.push(result.clone());
+            std::thread::sleep(self.iteration_delay);
+        }
+
+        fn current_move(&mut self, m: Move, number: usize) {
+            self.current_moves.push((m, number));
+            self.current_move_times.push(Instant::now());
+        }
+    }
+
+    #[test]
+    fn test_reporter_hears_every_depth_with_growing_node_counts() {
+        let board = Board::new();
+        let tt = TranspositionTable::new(1);
+        let mut recorder = Recorder::default();
+        let stop = AtomicBool::new(false);
+        let result = search_with_table(&board, &options(4, true), &stop, &tt, &mut recorder);
+
+        let depths: Vec<u32> = recorder.iterations.iter().map(|r| r.depth).collect();
+        assert_eq!(depths, vec![1, 2, 3, 4]);
+        assert!(recorder
+            .iterations
+            .windows(2)
+            .all(|w| w[0].stats.nodes < w[1].stats.nodes));
+
+        let stats = result.stats;
+        assert_eq!(stats.nodes, recorder.iterations[3].stats.nodes);
+        assert!(stats.qnodes > 0 && stats.qnodes < stats.nodes);
+        assert!(stats.tt_hits > 0);
+        assert!(stats.seldepth >= 4);
+        // Too quick to say which root move it is on
+        assert!(recorder.current_moves.is_empty());
+    }
+
+    #[test]
+    fn test_long_searches_report_each_root_move() {
+        let options = options(2, true);
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let helper_nodes = AtomicU64::new(0);
+        let mut recorder = Recorder {
+            iteration_delay: Duration::from_millis(60),
+            ..Recorder::default()
+        };
+        let mut tables = MoveTables::new();
+        let mut searcher = Searcher::new(
+            &options,
+            &stop,
+            &tt,
+            &mut tables,
+            &helper_nodes,
+            Instant::now(),
+        );
+        searcher.current_move_delay = Duration::from_millis(50);
+        searcher.reporter = Some(&mut recorder);
+        let result = searcher.iterative_deepening(&Board::new());
+
+        // The first iteration is over before the delay, the second goes move by move,
+        // starting with the best move of the first
+        let board = Board::new();
+        let numbers: Vec<usize> = recorder.current_moves.iter().map(|&(_, n)| n).collect();
+        assert_eq!(numbers, (1..=20).collect::<Vec<_>>());
+        assert_eq!(
+            Some(recorder.current_moves[0].0),
+            recorder.iterations[0].best_move
+        );
+        let mut moves: Vec<String> = recorder
+            .current_moves
+            .iter()
+            .map(|(m, _)| m.to_string())
+            .collect();
+        let mut legal: Vec<String> = board.legal_moves().iter().map(|m| m.to_string()).collect();
+        moves.sort();
+        legal.sort();
+        assert_eq!(moves, legal);
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    fn test_slow_evaluations_get_root_moves_reported_after_the_delay() {
+        let options = options(3, true);
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let helper_nodes = AtomicU64::new(0);
+        let mut recorder = Recorder::default();
+        let mut tables = MoveTables::new();
+        let started = Instant::now();
+        let mut searcher = Searcher::new(&options, &stop, &tt, &mut tables, &helper_nodes, started);
+        searcher.eval_delay = Duration::from_millis(2);
+        searcher.current_move_delay = Duration::from_millis(150);
+        searcher.reporter = Some(&mut recorder);
+        let result = searcher.iterative_deepening(&Board::new());
+
+        // Nothing is said until the delay is up, then every root move is, in order
+        assert_eq!(result.depth, 3);
+        assert!(!recorder.current_moves.is_empty());
+        assert!(recorder
+            .current_move_times
+            .iter()
+            .all(|&at| at >= started + Duration::from_millis(150)));
+        let numbers: Vec<usize> = recorder.current_moves.iter().map(|&(_, n)| n).collect();
+        assert!(numbers.windows(2).all(|w| w[1] == w[0] + 1 || w[1] == 1));
+        assert_eq!(numbers.last(), Some(&20));
+    }
+
+    #[test]
+    fn test_pruning_keeps_the_solve_rate_on_tactics() {
+        let solved = |pruning: bool| {
+            TACTICS
+                .iter()
+                .filter(|(fen, san)| {
+                    let board = Board::fen_to_board(fen);
+                    let m = crate::san::parse_san(&board, san).unwrap();
+                    solves(&board, m, pruning, TACTICS_NODES)
+                })
+                .count()
+        };
+        let (pruned, full) = (solved(true), solved(false));
+
+        // Pruning must find at least as many in the same nodes, and no fewer than
+        // it did
+        assert!(
+            pruned >= full,
+            "{} solved with pruning, {} without",
+            pruned,
+            full
+        );
+        assert!(pruned >= TACTICS_SOLVED, "{} solved", pruned);
+    }
+
+    #[test]
+    fn test_reverse_futility_pruning_and_razoring_are_taken() {
+        let board = Board::fen_to_board(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        let stats = search(&board, &options(5, true)).stats;
+        assert!(stats.reverse_futility_cutoffs > 0);
+        assert!(stats.razor_cutoffs > 0);
+
+        // Each is counted only when switched on
+        let without = SearchOptions {
+            reverse_futility_pruning: false,
+            razoring: false,
+            ..options(4, true)
+        };
+        let stats = search(&board, &without).stats;
+        assert_eq!(stats.reverse_futility_cutoffs, 0);
+        assert_eq!(stats.razor_cutoffs, 0);
+
+        // A mate search keeps every line
+        let mate = SearchOptions {
+            mate: Some(2),
+            ..options(4, true)
+        };
+        let stats = search(&board, &mate).stats;
+        assert_eq!(stats.reverse_futility_cutoffs + stats.razor_cutoffs, 0);
+    }
+
+    #[test]
+    #[ignore = "slow; run with --release -- --ignored"]
+    fn bench_pruning_reduces_nodes_at_depth_7() {
+        let board = Board::new();
+        let pruned = search(&board, &options(7, true));
+        let full = search(&board, &options(7, false));
+
+        println!(
+            "nodes with pruning {}, without {}",
+            pruned.stats.nodes, full.stats.nodes
+        );
+        assert!((pruned.stats.nodes as f64) < full.stats.nodes as f64 * 0.7);
+    }
+
+    #[test]
+    fn test_helper_threads_agree_on_mate() {
+        let board = Board::fen_to_board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let options = SearchOptions {
+            threads: 4,
+            ..options(4, true)
+        };
+        let result = search(&board, &options);
+
+        assert_eq!(result.best_move, Some(Move::new("a1a8")));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_mate_search_proves_mate_in_two() {
+        // 1. Kc7 Ka7 2. Ra1#; every other move lets the king out or stalemates it
+        let board = Board::fen_to_board("k7/8/2K5/8/8/8/8/1R6 w - - 0 1");
+        let mate = |moves| SearchOptions {
+            mate: Some(moves),
+            ..options(1, true)
+        };
+
+        let result = search(&board, &mate(2));
+        assert_eq!(result.best_move, Some(Move::new("c6c7")));
+        assert_eq!(mate_in(result.score), Some(2));
+        assert_eq!(result.pv.len(), 3);
+
+        let result = search(&board, &mate(1));
+        assert_eq!(mate_in(result.score), None);
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn test_mate_search_stops_at_its_depth_without_a_mate() {
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        let options = SearchOptions {
+            mate: Some(3),
+            ..options(MAX_DEPTH, true)
+        };
+        let result = search(&board, &options);
+
+        assert_eq!(result.depth, 5);
+        assert_eq!(mate_in(result.score), None);
+        assert!(result.best_move.is_some());
+    }
+
+    /// Plays the UCI `moves` from `fen`, keeping the position history.
+    fn play(fen: &str, moves: &[&str]) -> Board {
+        let mut board = Board::fen_to_board(fen);
+        board.assert_consistent();
+        for uci in moves {
+            board.make_move_unchecked(&Move::new(uci));
+        }
+        board
+    }
+
+    #[test]
+    fn test_losing_side_takes_a_repetition() {
+        // White is a queen down; going back to f3 repeats a position
+        let board = play(
+            "k7/8/8/8/8/8/q7/6NK w - - 0 1",
+            &["g1f3", "a8b8", "f3g1", "b8a8"],
+        );
+        let result = search(&board, &options(4, true));
+
+        assert_eq!(result.best_move, Some(Move::new("g1f3")));
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn test_winning_side_avoids_a_repetition() {
+        // White is a queen up and could repeat with Qd2; with contempt any other move
+        // should be preferred as well
+        let board = play(
+            "k7/8/8/8/8/8/8/3Q3K w - - 0 1",
+            &["d1d2", "a8b8", "d2d1", "b8a8"],
+        );
+        for contempt in [0, 50] {
+            let options = SearchOptions {
+                contempt,
+                ..options(4, true)
+            };
+            let result = search(&board, &options);
+
+            assert_ne!(result.best_move, Some(Move::new("d1d2")));
+            assert!(result.score > 500);
+        }
+    }
+
+    #[test]
+    fn test_fifty_move_rule_and_bare_kings_are_draws() {
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/8/Q3K3 w - - 99 80");
+        assert_eq!(search(&board, &options(3, true)).score, 0);
+
+        let board = Board::fen_to_board("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        assert_eq!(search(&board, &options(3, true)).score, 0);
+
+        // Contempt makes the draw unwelcome for the side to move
+        let options = SearchOptions {
+            contempt: 20,
+            ..options(3, true)
+        };
+        assert_eq!(search(&board, &options).score, -20);
+    }
+
+    /// Searches `board` for `millis` milliseconds and returns the node count.
+    /// Whether a search of `board` limited to `nodes` nodes plays `m`.
+    fn solves(board: &Board, m: Move, pruning: bool, nodes: u64) -> bool {
+        let options = options(MAX_DEPTH, pruning);
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let helper_nodes = AtomicU64::new(0);
+        let mut tables = MoveTables::new();
+        let mut searcher = Searcher::new(
+            &options,
+            &stop,
+            &tt,
+            &mut tables,
+            &helper_nodes,
+            Instant::now(),
+        );
+        searcher.node_limit = nodes;
+        searcher.iterative_deepening(board).best_move == Some(m)
+    }
+
+    fn nodes_in(board: &Board, options: &SearchOptions, millis: u64) -> u64 {
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(std::time::Duration::from_millis(millis));
+                stop.store(true, Ordering::Relaxed);
+            });
+            search_with_stop(board, options, &stop).stats.nodes
+        })
+    }
+
+    #[test]
+    #[ignore = "slow; run with --release -- --ignored"]
+    fn bench_four_threads_search_more_nodes() {
+        let board = Board::new();
+        let single = nodes_in(&board, &options(MAX_DEPTH, true), 1000);
+        let smp = SearchOptions {
+            threads: 4,
+            ..options(MAX_DEPTH, true)
+        };
+        let four = nodes_in(&board, &smp, 1000);
+
+        println!(
+            "nodes in 1s with 1 thread {}, with 4 threads {}",
+            single, four
+        );
+        // Helpers cannot add throughput without cores to run on
+        if thread::available_parallelism().map_or(1, |n| n.get()) >= 4 {
+            assert!((four as f64) > single as f64 * 1.5);
+        }
+    }
+
+    #[test]
+    #[ignore = "slow; run with --release -- --ignored"]
+    fn bench_self_play_with_four_threads_stays_legal() {
+        let mut rng = crate::utils::XorShift64::new(1829);
+        let options = SearchOptions {
+            threads: 4,
+            ..options(2, true)
+        };
+
+        for _ in 0..200 {
+            let mut board = Board::new();
+            for ply in 0..120 {
+                let legal = board.legal_moves();
+                if legal.is_empty() || board.is_50_move_rule() || board.is_3_fold_repetition() {
+                    break;
+                }
+                // A few random opening moves so the games differ
+                let m = if ply < 4 {
+                    legal
+                        .get((rng.next_u64() % legal.len() as u64) as usize)
+                        .unwrap()
+                } else {
+                    search(&board, &options).best_move.unwrap()
+                };
+                assert!(legal.contains(&m), "{} in {}", m, board.board_to_fen());
+                board.make_move_unchecked(&m);
+            }
+        }
+    }
+
+    #[test]
+    fn test_only_move_is_played_without_searching() {
+        // The king in the corner can only take the rook beside it
+        let board = Board::fen_to_board("7k/8/8/8/8/8/1r6/K7 w - - 0 1");
+        assert_eq!(board.legal_moves().len(), 1);
+        let easy = SearchOptions {
+            depth: MAX_DEPTH,
+            easy_moves: true,
+            ..SearchOptions::default()
+        };
+        let result = search(&board, &easy);
+        assert_eq!(result.best_move, Move::from_uci("a1b2"));
+        assert_eq!(result.stats.early_stop, Some(EarlyStop::OnlyMove));
+        assert_eq!(result.stats.nodes, 0);
+        assert!(result.stats.elapsed < Duration::from_millis(10));
+
+        // Without easy moves it is searched like any other position
+        let result = search(&board, &options(3, true));
+        assert_eq!(result.stats.early_stop, None);
+        assert!(result.stats.nodes > 0);
+    }
+
+    #[test]
+    fn test_easy_move_stops_a_timed_search_early() {
+        use std::sync::Arc;
+
+        // Black's rook has just taken on d2, and only Rxd2 wins it back
+        let board = Board::fen_to_board("6k1/5ppp/8/8/8/8/3r1PPP/3R2K1 w - - 0 1");
+        let options = SearchOptions {
+            depth: MAX_DEPTH,
+            easy_moves: true,
+            ..SearchOptions::default()
+        };
+        let move_time = Duration::from_secs(60);
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                thread::sleep(move_time);
+                stop.store(true, Ordering::Relaxed);
+            });
+        }
+        let result = search_with_stop(&board, &options, &stop);
+        assert_eq!(result.best_move, Move::from_uci("d1d2"));
+        assert_eq!(result.stats.early_stop, Some(EarlyStop::EasyMove));
+        assert!(result.stats.elapsed < move_time);
+        assert!(result.depth >= EASY_MOVE_DEPTH + EASY_MOVE_ITERATIONS - 1);
+    }
+
+    #[test]
+    fn test_table_carries_over_to_the_predicted_reply() {
+        let tt = TranspositionTable::new(16);
+        let stop = AtomicBool::new(false);
+        let hit_rate = |stats: &SearchStats| stats.tt_hits as f64 / stats.nodes as f64;
+
+        let mut board = Board::new();
+        let first = search_with_table(&board, &options(6, true), &stop, &tt, &mut ());
+        for m in &first.pv[..2] {
+            board.make_move_unchecked(m);
+        }
+
+        let warm = search_with_table(&board, &options(4, true), &stop, &tt, &mut ()).stats;
+        let cold = search(&board, &options(4, true)).stats;
+        assert!(first.stats.hashfull > 0);
+        assert!(warm.nodes < cold.nodes, "{:?} {:?}", warm, cold);
+        assert!(
+            hit_rate(&warm) > 5.0 * hit_rate(&cold),
+            "{:?} {:?}",
+            warm,
+            cold
+        );
+    }
+
+    #[test]
+    fn test_quiet_cutoffs_update_countermoves_and_continuation_history() {
+        let options = options(4, true);
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let helper_nodes = AtomicU64::new(0);
+        let mut tables = MoveTables::new();
+        let mut searcher = Searcher::new(
+            &options,
+            &stop,
+            &tt,
+            &mut tables,
+            &helper_nodes,
+            Instant::now(),
+        );
+        let uci = |m: &str| Move::from_uci(m).unwrap();
+
+        // 1. e4 is searched at the root and 1... Nf6 refutes it three plies from the
+        // leaves, then 1. d4 and 1... d5 likewise
+        let mut board = Board::new();
+        let (e4, d4, nf6, d5) = (uci("e2e4"), uci("d2d4"), uci("g8f6"), uci("d7d5"));
+        let (e4_index, d4_index) = (piece_to(&board, &e4), piece_to(&board, &d4));
+        let after_e4 = {
+            searcher.stack[0].current_move = e4_index;
+            board.make_move_unchecked(&e4);
+            searcher.record_quiet_cutoff(&board, &nf6, 1, 3);
+            let position = board;
+            board = Board::new();
+            position
+        };
+        searcher.stack[0].current_move = d4_index;
+        board.make_move_unchecked(&d4);
+        searcher.record_quiet_cutoff(&board, &d5, 1, 2);
+
+        assert_eq!(
+            searcher.tables.countermoves[e4_index],
+            Some(PackedMove::new(nf6))
+        );
+        assert_eq!(
+            searcher.tables.countermoves[d4_index],
+            Some(PackedMove::new(d5))
+        );
+        let continuation = |previous: usize, board: &Board, m: &Move| {
+            searcher.tables.continuation_history[previous * PIECE_TO_COUNT + piece_to(board, m)]
+        };
+        assert_eq!(continuation(e4_index, &after_e4, &nf6), 9);
+        assert_eq!(continuation(d4_index, &board, &d5), 4);
+        assert_eq!(continuation(e4_index, &after_e4, &d5), 0);
+
+        // Without killers or plain history, what answered e4 is tried first after it
+        searcher.stack = SearchStack::new();
+        searcher.history = [0; 64 * 64];
+        searcher.stack[0].current_move = e4_index;
+        searcher.stack[1].moves = after_e4.legal_moves();
+        searcher.order_moves(&after_e4, 1, None, false);
+        assert_eq!(searcher.stack[1].moves.first(), Some(nf6));
+
+        // A cutoff at the root has no previous move to answer
+        let before = searcher.tables.countermoves;
+        searcher.record_quiet_cutoff(&Board::new(), &uci("g1f3"), 0, 3);
+        assert_eq!(searcher.tables.countermoves, before);
+    }
+
+    #[test]
+    fn test_bench_node_count_is_unchanged() {
+        // Update this when a change is meant to alter the search
+        assert_eq!(bench(3).nodes, BENCH_NODES_AT_DEPTH_3);
+    }
+
+    thread_local! {
+        /// Search events logged on this thread, as JSON, while a test captures them.
+        static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    }
+
+    /// Hands each event to the test thread that logged it, so tests running at the
+    /// same time do not see each other's events.
+    struct CaptureLogger;
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            CAPTURED.with(|captured| captured.borrow().is_some())
+        }
+
+        fn log(&self, record: &log::Record) {
+            CAPTURED.with(|captured| {
+                if let Some(events) = captured.borrow_mut().as_mut() {
+                    events.push(crate::trace::to_json(record));
+                }
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn capture_events(f: impl FnOnce()) -> Vec<serde_json::Value> {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&CaptureLogger).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+        f();
+        let events = CAPTURED.with(|captured| captured.borrow_mut().take().unwrap());
+        events
+            .iter()
+            .map(|event| serde_json::from_str(event).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_logs_iterations_and_node_events() {
+        let board = Board::new();
+        let options = SearchOptions {
+            depth: 3,
+            hash_mb: 1,
+            ..SearchOptions::default()
+        };
+        let mut result = None;
+        let events = capture_events(|| result = Some(search(&board, &options)));
+        let result = result.unwrap();
+
+        let iterations: Vec<_> = events
+            .iter()
+            .filter(|event| event["event"] == "iteration")
+            .collect();
+        assert_eq!(iterations.len(), 3);
+        for (depth, iteration) in (1..=3).zip(&iterations) {
+            assert_eq!(iteration["depth"], depth);
+            assert!(iteration["score"].is_i64(), "{}", iteration);
+            assert!(iteration["pv"].is_string(), "{}", iteration);
+        }
+        let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+        assert_eq!(iterations[2]["pv"], pv.join(" "));
+
+        for kind in ["tt_probe", "tt_store", "beta_cutoff"] {
+            assert!(
+                events.iter().any(|event| event["event"] == kind),
+                "no {} events",
+                kind
+            );
+        }
+    }
+}
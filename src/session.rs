@@ -0,0 +1,417 @@
+//! A long-lived engine to embed in a GUI: the game, the engine with its table and
+//! settings, and searches run on a worker thread that report over a channel.
+//!
+//! The session is driven from one thread while its searches run on another, so
+//! [`EngineSession::stop`] and the rest can be called while the engine thinks. The
+//! UCI front-end ([`crate::uci`]) is a text adapter over it.
+
+use crate::board::{Board, Strictness};
+use crate::book::Book;
+use crate::chess_move::{Move, MAX_MOVES};
+use crate::engine::{AlphaBetaEngine, Engine, SearchLimits};
+use crate::game::{Game, GameError};
+use crate::search::{
+    SearchOptions, SearchReporter, SearchResult, SearchStats, MAX_DEPTH, MAX_SKILL,
+};
+use crate::utils::XorShift64;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Most search threads the `Threads` option accepts.
+pub const MAX_THREADS: usize = 64;
+/// Largest transposition table the `Hash` option accepts, in megabytes.
+pub const MAX_HASH_MB: usize = 65_536;
+/// Largest `Move Overhead` accepted, in milliseconds.
+pub const MAX_MOVE_OVERHEAD_MS: u64 = 5_000;
+/// Largest `Contempt` accepted either way, in centipawns.
+pub const MAX_CONTEMPT: i32 = 1_000;
+
+/// Engine settings, changed by name with [`EngineSession::set_option`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Transposition table size in megabytes.
+    pub hash_mb: usize,
+    pub threads: usize,
+    /// Number of best moves to report, each with its own line.
+    pub multi_pv: usize,
+    /// Milliseconds kept back from every move's time for GUI and network lag.
+    pub move_overhead_ms: u64,
+    /// Play moves from the opening book when it has one.
+    pub own_book: bool,
+    /// See [`SearchOptions::contempt`].
+    pub contempt: i32,
+    /// See [`SearchOptions::skill`].
+    pub skill: u32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            hash_mb: SearchOptions::default().hash_mb,
+            threads: 1,
+            multi_pv: 1,
+            move_overhead_ms: 10,
+            own_book: false,
+            contempt: 0,
+            skill: MAX_SKILL,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// The search settings these options stand for.
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            depth: MAX_DEPTH,
+            hash_mb: self.hash_mb,
+            threads: self.threads,
+            multi_pv: self.multi_pv,
+            contempt: self.contempt,
+            skill: self.skill,
+            ..SearchOptions::default()
+        }
+    }
+
+    /// Sets option `name` (matched case-insensitively, by its UCI name) to `value`,
+    /// clamping numbers into range. Returns false for unknown options and
+    /// unparsable values.
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        let number = value.trim().parse::<u64>().ok();
+        match (name.to_ascii_lowercase().as_str(), number) {
+            ("hash", Some(mb)) => self.hash_mb = (mb as usize).clamp(1, MAX_HASH_MB),
+            ("threads", Some(n)) => self.threads = (n as usize).clamp(1, MAX_THREADS),
+            ("multipv", Some(n)) => self.multi_pv = (n as usize).clamp(1, MAX_MOVES),
+            ("move overhead", Some(ms)) => self.move_overhead_ms = ms.min(MAX_MOVE_OVERHEAD_MS),
+            ("skill level", Some(level)) => self.skill = level.min(MAX_SKILL as u64) as u32,
+            ("contempt", _) => match value.trim().parse::<i32>() {
+                Ok(cp) => self.contempt = cp.clamp(-MAX_CONTEMPT, MAX_CONTEMPT),
+                Err(_) => return false,
+            },
+            ("ownbook", _) => match value.trim().to_ascii_lowercase().as_str() {
+                "true" => self.own_book = true,
+                "false" => self.own_book = false,
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// What a search started with [`EngineSession::go`] has to say, in the order it
+/// happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchEvent {
+    /// An iteration finished; this is the search so far.
+    Iteration(SearchResult),
+    /// The search is still going, about once a second.
+    Progress(SearchStats),
+    /// The search moved on to root move `m`, number `number` in its order,
+    /// counting from 1. Only sent once the search has run for a few seconds.
+    CurrentMove { m: Move, number: usize },
+    /// The move chosen, or `None` when the side to move has no legal move. Always
+    /// the last event.
+    BestMove(Option<Move>),
+}
+
+/// A search started with [`EngineSession::go`]: its events, and a way to stop it.
+pub struct SearchHandle {
+    events: Receiver<SearchEvent>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SearchHandle {
+    /// Waits for the next event. `None` after [`SearchEvent::BestMove`], or if the
+    /// search panicked.
+    pub fn recv(&self) -> Option<SearchEvent> {
+        self.events.recv().ok()
+    }
+
+    /// The next event if there is one yet, without waiting.
+    pub fn try_recv(&self) -> Option<SearchEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Every event as it arrives, up to and including the best move.
+    pub fn events(&self) -> mpsc::Iter<'_, SearchEvent> {
+        self.events.iter()
+    }
+
+    /// Asks the search to stop; it still ends with [`SearchEvent::BestMove`].
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Waits for the search to finish and returns the move it chose.
+    pub fn best_move(self) -> Option<Move> {
+        self.events().find_map(|event| match event {
+            SearchEvent::BestMove(m) => Some(m),
+            _ => None,
+        })?
+    }
+}
+
+/// Where the alpha-beta engine's reports go: the channel of the search in
+/// progress, if any.
+type EventSlot = Arc<Mutex<Option<Sender<SearchEvent>>>>;
+
+/// Sends search progress to the channel in its slot.
+struct EventReporter {
+    slot: EventSlot,
+}
+
+impl EventReporter {
+    fn send(&self, event: SearchEvent) {
+        if let Some(sender) = self.slot.lock().unwrap().as_ref() {
+            // A handle that was dropped is not listening any more
+            let _ = sender.send(event);
+        }
+    }
+}
+
+impl SearchReporter for EventReporter {
+    fn iteration(&mut self, result: &SearchResult) {
+        self.send(SearchEvent::Iteration(result.clone()));
+    }
+
+    fn progress(&mut self, stats: &SearchStats) {
+        self.send(SearchEvent::Progress(*stats));
+    }
+
+    fn current_move(&mut self, m: Move, number: usize) {
+        self.send(SearchEvent::CurrentMove { m, number });
+    }
+}
+
+/// An engine kept across searches, with its game, settings and opening book.
+///
+/// ```
+/// use chessbot::board::Board;
+/// use chessbot::chess_move::Move;
+/// use chessbot::engine::SearchLimits;
+/// use chessbot::session::EngineSession;
+///
+/// let mut session = EngineSession::new(None);
+/// let e4 = Move::from_uci("e2e4").unwrap();
+/// session.set_position(Board::START_FEN, &[e4]).unwrap();
+/// let search = session.go(SearchLimits {
+///     depth: Some(2),
+///     ..SearchLimits::default()
+/// });
+/// let reply = search.best_move().unwrap();
+/// assert!(session.game().board().legal_moves().contains(&reply));
+/// ```
+pub struct EngineSession {
+    game: Game,
+    config: EngineConfig,
+    /// Lent to the worker thread while it searches.
+    engine: Option<Box<dyn Engine + Send>>,
+    book: Option<Book>,
+    rng: XorShift64,
+    /// Stop flag of the search in progress, if any.
+    stop: Arc<AtomicBool>,
+    /// Hands the engine back once its search is over.
+    worker: Option<JoinHandle<Box<dyn Engine + Send>>>,
+    events: EventSlot,
+}
+
+impl EngineSession {
+    /// Plays with the alpha-beta searcher, which reports its progress as events.
+    ///
+    /// Moves from `book` are played while the `OwnBook` option is on, which it is
+    /// by default when a book is given.
+    pub fn new(book: Option<Book>) -> EngineSession {
+        let events = EventSlot::default();
+        let engine = reporting_engine(&events, &EngineConfig::default().search_options());
+        EngineSession::with_events(book, engine, events)
+    }
+
+    /// Plays with `engine`. Only the alpha-beta searcher of [`EngineSession::new`]
+    /// reports anything before its best move.
+    pub fn with_engine(book: Option<Book>, engine: Box<dyn Engine + Send>) -> EngineSession {
+        EngineSession::with_events(book, engine, EventSlot::default())
+    }
+
+    fn with_events(
+        book: Option<Book>,
+        mut engine: Box<dyn Engine + Send>,
+        events: EventSlot,
+    ) -> EngineSession {
+        let config = EngineConfig {
+            own_book: book.is_some(),
+            ..EngineConfig::default()
+        };
+        engine.configure(&config.search_options());
+        EngineSession {
+            game: Game::new(),
+            config,
+            engine: Some(engine),
+            book,
+            rng: XorShift64::new(0x2545_f491_4f6c_dd1d),
+            stop: Arc::new(AtomicBool::new(false)),
+            worker: None,
+            events,
+        }
+    }
+
+    /// The game being played, as of the last position set.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Stops any search and starts a new game from the usual position, with the
+    /// engine forgetting what it learned in the last one.
+    pub fn new_game(&mut self) {
+        self.stop();
+        self.game = Game::new();
+        self.engine_mut().new_game();
+    }
+
+    /// Stops any search and sets up the position after `moves` from `fen`. FENs are
+    /// read as GUIs write them, see [`Strictness::Lax`]. On error the position is
+    /// left as it was.
+    pub fn set_position(&mut self, fen: &str, moves: &[Move]) -> Result<(), GameError> {
+        let board = Board::try_from_fen(fen, Strictness::Lax).map_err(GameError::Fen)?;
+        let mut game = Game::from_board(board);
+        for &m in moves {
+            game.play(m)?;
+        }
+        self.set_game(game);
+        Ok(())
+    }
+
+    /// Stops any search and continues from `game`.
+    pub fn set_game(&mut self, game: Game) {
+        self.stop();
+        self.game = game;
+    }
+
+    /// Stops any search and sets option `name` to `value`, as
+    /// [`EngineConfig::set`] does. Returns false for unknown options and unparsable
+    /// values.
+    pub fn set_option(&mut self, name: &str, value: &str) -> bool {
+        self.stop();
+        if !self.config.set(name, value) {
+            return false;
+        }
+        let options = self.config.search_options();
+        self.engine_mut().configure(&options);
+        true
+    }
+
+    /// Stops any search and starts thinking about the current position on a worker
+    /// thread, within `limits`; their stop flag is replaced by the session's. A
+    /// book move is answered at once.
+    pub fn go(&mut self, limits: SearchLimits) -> SearchHandle {
+        self.stop();
+        self.stop = Arc::new(AtomicBool::new(false));
+        let (sender, events) = mpsc::channel();
+        let handle = SearchHandle {
+            events,
+            stop: Arc::clone(&self.stop),
+        };
+
+        if self.config.own_book {
+            if let Some(m) = self
+                .book
+                .as_ref()
+                .and_then(|book| book.pick_move(self.game.board(), &mut self.rng))
+            {
+                let _ = sender.send(SearchEvent::BestMove(Some(m)));
+                return handle;
+            }
+        }
+
+        let limits = SearchLimits {
+            stop: Some(Arc::clone(&self.stop)),
+            ..limits
+        };
+        let mut engine = self.engine.take().expect("no search is running");
+        let game = self.game.clone();
+        let slot = Arc::clone(&self.events);
+        *slot.lock().unwrap() = Some(sender.clone());
+        self.worker = Some(thread::spawn(move || {
+            let best_move = engine.choose_move(&game, &limits);
+            // Nothing is reported after the best move
+            *slot.lock().unwrap() = None;
+            let _ = sender.send(SearchEvent::BestMove(best_move));
+            engine
+        }));
+        handle
+    }
+
+    /// Stops the search in progress, if any, and waits for it to send its best move.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    /// Waits for the search in progress, if any, to finish by itself.
+    pub fn wait(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            // A search that panicked took its engine with it; start again afresh
+            let engine = worker.join().unwrap_or_else(|_| {
+                *self.events.lock().unwrap() = None;
+                reporting_engine(&self.events, &self.config.search_options())
+            });
+            self.engine = Some(engine);
+        }
+    }
+
+    /// The engine, which is only away while a search runs.
+    fn engine_mut(&mut self) -> &mut Box<dyn Engine + Send> {
+        self.engine.as_mut().expect("no search is running")
+    }
+}
+
+/// A session going away takes its search with it.
+impl Drop for EngineSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The alpha-beta searcher, reporting its progress to the channel in `slot`.
+fn reporting_engine(slot: &EventSlot, options: &SearchOptions) -> Box<dyn Engine + Send> {
+    let reporter = EventReporter {
+        slot: Arc::clone(slot),
+    };
+    Box::new(AlphaBetaEngine::new(options.clone()).with_reporter(Box::new(reporter)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_can_move_to_another_thread() {
+        fn assert_send<T: Send>() {}
+        assert_send::<EngineSession>();
+        assert_send::<SearchHandle>();
+    }
+
+    #[test]
+    fn test_set_option_reconfigures_the_engine() {
+        let mut session = EngineSession::new(None);
+        assert!(session.set_option("MultiPV", "2"));
+        assert!(!session.set_option("Ponder", "true"));
+        assert_eq!(session.config().multi_pv, 2);
+
+        let search = session.go(SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        });
+        let lines = search.events().find_map(|event| match event {
+            SearchEvent::Iteration(result) => Some(result.lines.len()),
+            _ => None,
+        });
+        assert_eq!(lines, Some(2));
+    }
+}
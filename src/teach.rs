@@ -0,0 +1,252 @@
+//! Teaching mode: turns the reason a move was rejected into a sentence a beginner can
+//! follow, naming the pieces and squares involved.
+
+use crate::board::{Board, PieceType};
+use crate::chess_move::{find_peice_for_color, square_name, Move, MoveError};
+
+/// Explains in plain language why the side to move cannot play `m` on `board`,
+/// where `err` is what [`Board::check_move`] said about it.
+pub fn explain(board: &Board, m: &Move, err: &MoveError) -> String {
+    let color = board.active_color;
+    let (from, to) = (square_name(m.from), square_name(m.to));
+    let moving = find_peice_for_color(board, color, m.from).map_or("piece".to_string(), name);
+
+    match *err {
+        MoveError::Malformed => {
+            "That is not a move: give the square to move from and the square to move to, like e2e4."
+                .to_string()
+        }
+        MoveError::EmptySquare { at } => {
+            format!("There is no piece on {} to move.", square_name(at))
+        }
+        MoveError::NotYourPiece { at } => format!(
+            "{} belongs to {}, and it is {}'s turn to move.",
+            capitalise(&describe(board, at)),
+            !color,
+            color
+        ),
+        MoveError::WrongPattern { peice } => format!(
+            "Your {} on {} cannot reach {}: {}.",
+            name(peice),
+            from,
+            to,
+            pattern_rule(peice)
+        ),
+        MoveError::Blocked { by } if is_castling(board, m) => format!(
+            "You cannot castle because {} stands between your king and rook.",
+            describe(board, by)
+        ),
+        MoveError::Blocked { by } if by == m.to => format!(
+            "Your pawn on {} is blocked by {}: pawns cannot capture straight ahead.",
+            from,
+            describe(board, by)
+        ),
+        MoveError::Blocked { by } => format!(
+            "Your {} on {} is blocked by {}.",
+            moving,
+            from,
+            describe(board, by)
+        ),
+        MoveError::OwnPieceOnTarget { at } => format!(
+            "You cannot move onto {}: {} is already there.",
+            square_name(at),
+            describe(board, at)
+        ),
+        MoveError::NothingToCapture { at } => format!(
+            "Your pawn on {} can only move diagonally to capture, and there is nothing to capture on {}.",
+            from,
+            square_name(at)
+        ),
+        MoveError::BadPromotion => match m.promotion {
+            None => format!(
+                "Your pawn reaching {} must promote: add the piece to promote to, like {}q.",
+                to, m
+            ),
+            Some(PieceType::Pawn | PieceType::King) => {
+                "A pawn can only promote to a queen, rook, bishop or knight.".to_string()
+            }
+            Some(_) => "Only a pawn reaching the last rank can promote.".to_string(),
+        },
+        MoveError::LeavesKingInCheck { by } => {
+            let attacker = describe(board, by);
+            let king = board.king_square(color).map_or(String::new(), square_name);
+            if find_peice_for_color(board, color, m.from) == Some(PieceType::King) {
+                format!(
+                    "Your king cannot go to {}: {} attacks that square.",
+                    to, attacker
+                )
+            } else if board.is_in_check(color) {
+                format!(
+                    "Your king on {} is in check, and after that move {} would still attack it.",
+                    king, attacker
+                )
+            } else {
+                format!(
+                    "Your {} on {} is pinned: moving it would expose your king on {} to {}.",
+                    moving, from, king, attacker
+                )
+            }
+        }
+        MoveError::NoCastlingRight => {
+            "You cannot castle on that side: your king or that rook has already moved.".to_string()
+        }
+        MoveError::CastlingOutOfCheck { by } => format!(
+            "You cannot castle out of check, and your king is attacked by {}.",
+            describe(board, by)
+        ),
+        MoveError::CastlingThroughCheck { square, by } => {
+            let passes = if square == m.to {
+                "land on"
+            } else {
+                "pass through"
+            };
+            format!(
+                "You cannot castle because your king would {} {}, a square attacked by {}.",
+                passes,
+                square_name(square),
+                describe(board, by)
+            )
+        }
+        MoveError::Illegal => format!("{} is not a legal move here.", m),
+    }
+}
+
+/// A piece type as a sentence names it, e.g. `knight`.
+fn name(peice: PieceType) -> String {
+    peice.to_string().to_lowercase()
+}
+
+/// The piece on `square` from the side to move's point of view: `your own pawn on
+/// d2` or `the rook on f8`.
+fn describe(board: &Board, square: u8) -> String {
+    let color = board.active_color;
+    match (
+        find_peice_for_color(board, color, square),
+        find_peice_for_color(board, !color, square),
+    ) {
+        (Some(peice), _) => format!("your own {} on {}", name(peice), square_name(square)),
+        (None, Some(peice)) => format!("the {} on {}", name(peice), square_name(square)),
+        (None, None) => square_name(square),
+    }
+}
+
+fn capitalise(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+fn is_castling(board: &Board, m: &Move) -> bool {
+    board.classify(m).is_castle()
+}
+
+/// How `peice` moves, for a move that does not fit it.
+fn pattern_rule(peice: PieceType) -> &'static str {
+    match peice {
+        PieceType::Pawn => {
+            "pawns move one square straight ahead, two from their starting square, and capture one square diagonally forward"
+        }
+        PieceType::Knight => "knights move in an L, two squares one way and one to the side",
+        PieceType::Bishop => "bishops move only along diagonals",
+        PieceType::Rook => "rooks move only along ranks and files",
+        PieceType::Queen => "queens move along ranks, files and diagonals",
+        PieceType::King => "kings move one square in any direction",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain_uci(fen: &str, uci: &str) -> String {
+        let board = Board::fen_to_board(fen);
+        let m = Move::from_uci(uci).unwrap();
+        let err = board.check_move(&m).unwrap_err();
+        explain(&board, &m, &err)
+    }
+
+    #[test]
+    fn test_explanations_name_the_rule_and_squares() {
+        let cases = [
+            (Board::START_FEN, "c1e3", "Your bishop on c1 is blocked by your own pawn on d2"),
+            (Board::START_FEN, "e2e5", "Your pawn on e2 cannot reach e5: pawns move"),
+            (Board::START_FEN, "g1g3", "knights move in an L"),
+            (Board::START_FEN, "a1a2", "your own pawn on a2 is already there"),
+            (Board::START_FEN, "e5e6", "There is no piece on e5"),
+            (Board::START_FEN, "e7e5", "The pawn on e7 belongs to Black"),
+            (
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+                "e4e5",
+                "blocked by the pawn on e5: pawns cannot capture straight ahead",
+            ),
+            (Board::START_FEN, "e2d3", "nothing to capture on d3"),
+            (
+                "r4rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+                "e1g1",
+                "You cannot castle because your king would pass through f1, a square attacked by the rook on f8",
+            ),
+            (
+                "r4r1k/8/8/8/8/8/8/R3K1rR w KQ - 0 1",
+                "e1g1",
+                "You cannot castle because the rook on g1 stands between your king and rook",
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/4R1K1 b kq - 0 1",
+                "e8c8",
+                "You cannot castle out of check, and your king is attacked by the rook on e1",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/R3K2R w Q - 0 1",
+                "e1g1",
+                "your king or that rook has already moved",
+            ),
+            (
+                "4k3/8/8/b7/8/8/3N4/4K3 w - - 0 1",
+                "d2f3",
+                "Your knight on d2 is pinned: moving it would expose your king on e1 to the bishop on a5",
+            ),
+            (
+                "4k3/8/8/8/8/8/3N4/4K2r w - - 0 1",
+                "d2b3",
+                "Your king on e1 is in check, and after that move the rook on h1 would still attack it",
+            ),
+            (
+                "3rk3/8/8/8/8/8/8/4K3 w - - 0 1",
+                "e1d2",
+                "Your king cannot go to d2: the rook on d8 attacks that square",
+            ),
+            (
+                "4k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+                "a7a8",
+                "Your pawn reaching a8 must promote",
+            ),
+        ];
+
+        for (fen, uci, expected) in cases {
+            let explanation = explain_uci(fen, uci);
+            assert!(
+                explanation.contains(expected),
+                "{} {}: {}",
+                fen,
+                uci,
+                explanation
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors_carry_the_squares_involved() {
+        let board = Board::new();
+        let check = |uci: &str| board.check_move(&Move::from_uci(uci).unwrap());
+        assert_eq!(check("c1e3"), Err(MoveError::Blocked { by: 11 }));
+        assert_eq!(check("d1d2"), Err(MoveError::OwnPieceOnTarget { at: 11 }));
+        assert_eq!(
+            check("b1b3"),
+            Err(MoveError::WrongPattern {
+                peice: PieceType::Knight
+            })
+        );
+        assert_eq!(check("e2d3"), Err(MoveError::NothingToCapture { at: 19 }));
+    }
+}
@@ -0,0 +1,299 @@
+//! Search traces: a [`log`] logger that writes each event from the search as one
+//! line of JSON, for `ChessBot --trace-search <file>`.
+//!
+//! ```text
+//! {"level":"DEBUG","event":"iteration","depth":3,"score":35,"nodes":1620,"pv":"e2e4 e7e5 g1f3"}
+//! ```
+//!
+//! Every key-value pair of the record becomes a field, and the message becomes
+//! `event`. Integers and booleans stay JSON numbers and booleans; anything else is
+//! written as a string.
+//!
+//! A search run with [`SearchOptions::trace_root`](crate::search::SearchOptions)
+//! also returns a [`SearchReport`] on its root moves, which can be written as JSON
+//! or as a Graphviz DOT graph of their principal variations.
+
+use crate::chess_move::Move;
+use crate::tt::Bound;
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Events come from this module and everything under it.
+const SEARCH_TARGET: &str = "chessbot::search";
+
+/// Writes the search's debug events to `output`, one JSON object per line.
+pub struct JsonLogger<W: Write + Send> {
+    output: Mutex<W>,
+}
+
+impl<W: Write + Send + 'static> JsonLogger<W> {
+    pub fn new(output: W) -> JsonLogger<W> {
+        JsonLogger {
+            output: Mutex::new(output),
+        }
+    }
+
+    /// Makes this the global logger. Fails if one is already installed.
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(LevelFilter::Debug);
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Log for JsonLogger<W> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug && metadata.target().starts_with(SEARCH_TARGET)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = to_json(record);
+        let mut output = self.output.lock().unwrap();
+        let _ = writeln!(output, "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = self.output.lock().unwrap().flush();
+    }
+}
+
+/// The record as a single-line JSON object.
+pub fn to_json(record: &Record) -> String {
+    let mut json = String::from("{");
+    push_string(&mut json, "level");
+    json.push(':');
+    push_string(&mut json, record.level().as_str());
+    json.push_str(",\"event\":");
+    push_string(&mut json, &record.args().to_string());
+
+    let mut fields = JsonFields(&mut json);
+    let _ = record.key_values().visit(&mut fields);
+    json.push('}');
+    json
+}
+
+struct JsonFields<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let json = &mut *self.0;
+        json.push(',');
+        push_string(json, key.as_str());
+        json.push(':');
+        if let Some(b) = value.to_bool() {
+            let _ = write!(json, "{}", b);
+        } else if let Some(n) = value.to_i64() {
+            let _ = write!(json, "{}", n);
+        } else if let Some(n) = value.to_u64() {
+            let _ = write!(json, "{}", n);
+        } else {
+            push_string(json, &value.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What the search made of one root move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootMoveReport {
+    pub root_move: Move,
+    /// Score in centipawns from the side to move's point of view.
+    pub score: i32,
+    /// Depth of the last iteration that searched the move.
+    pub depth: u32,
+    /// `Exact`, or `Upper` if the move failed low and `Lower` if it failed high.
+    pub bound: Bound,
+    /// Principal variation, starting with `root_move`.
+    pub pv: Vec<Move>,
+}
+
+/// Every legal root move of a search, as of the last iteration it completed, best
+/// first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchReport {
+    /// The position searched.
+    pub fen: String,
+    pub moves: Vec<RootMoveReport>,
+}
+
+impl SearchReport {
+    /// The report as a single-line JSON object.
+    ///
+    /// ```text
+    /// {"fen":"...","moves":[{"move":"e2e4","score":35,"depth":4,"bound":"exact","pv":["e2e4","e7e5"]},...]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"fen\":");
+        push_string(&mut json, &self.fen);
+        json.push_str(",\"moves\":[");
+        for (i, report) in self.moves.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"move\":");
+            push_string(&mut json, &report.root_move.to_string());
+            let _ = write!(
+                json,
+                ",\"score\":{},\"depth\":{},\"bound\":\"{}\",\"pv\":[",
+                report.score,
+                report.depth,
+                bound_name(report.bound)
+            );
+            for (j, m) in report.pv.iter().enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                push_string(&mut json, &m.to_string());
+            }
+            json.push_str("]}");
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// The report as a Graphviz DOT graph: the root position with a chain of moves
+    /// for each root move's principal variation, the best one drawn bold.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph search {\n");
+        let _ = writeln!(dot, "  root [shape=box, label={:?}];", self.fen);
+        for (i, report) in self.moves.iter().enumerate() {
+            let style = if i == 0 { ", style=bold" } else { "" };
+            let mut parent = String::from("root");
+            for (j, m) in report.pv.iter().enumerate() {
+                let node = format!("m{}_{}", i, j);
+                let _ = writeln!(dot, "  {} [label=\"{}\"];", node, m);
+                if j == 0 {
+                    let _ = writeln!(
+                        dot,
+                        "  {} -> {} [label=\"{} {} d{}\"{}];",
+                        parent,
+                        node,
+                        report.score,
+                        bound_name(report.bound),
+                        report.depth,
+                        style
+                    );
+                } else {
+                    let _ = writeln!(dot, "  {} -> {};", parent, node);
+                }
+                parent = node;
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn bound_name(bound: Bound) -> &'static str {
+    match bound {
+        Bound::Exact => "exact",
+        Bound::Lower => "lower",
+        Bound::Upper => "upper",
+    }
+}
+
+/// Appends `s` as a quoted JSON string.
+fn push_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_to_json() {
+        let pairs: [(&str, Value); 4] = [
+            ("depth", Value::from(3u32)),
+            ("score", Value::from(-25i32)),
+            ("exact", Value::from(true)),
+            ("pv", Value::from("e2e4 \"e7e5\"")),
+        ];
+        let record = Record::builder()
+            .level(Level::Debug)
+            .target("chessbot::search")
+            .args(format_args!("iteration"))
+            .key_values(&pairs)
+            .build();
+
+        assert_eq!(
+            to_json(&record),
+            r#"{"level":"DEBUG","event":"iteration","depth":3,"score":-25,"exact":true,"pv":"e2e4 \"e7e5\""}"#
+        );
+    }
+
+    #[test]
+    fn test_search_report_as_json_and_dot() {
+        let line = |uci: &str| -> Vec<Move> {
+            uci.split_whitespace()
+                .map(|m| Move::from_uci(m).unwrap())
+                .collect()
+        };
+        let report = SearchReport {
+            fen: "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string(),
+            moves: vec![
+                RootMoveReport {
+                    root_move: Move::from_uci("e2e4").unwrap(),
+                    score: 120,
+                    depth: 3,
+                    bound: Bound::Exact,
+                    pv: line("e2e4 e8d7"),
+                },
+                RootMoveReport {
+                    root_move: Move::from_uci("e1d1").unwrap(),
+                    score: 80,
+                    depth: 3,
+                    bound: Bound::Upper,
+                    pv: line("e1d1"),
+                },
+            ],
+        };
+
+        assert_eq!(
+            report.to_json(),
+            r#"{"fen":"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1","moves":[{"move":"e2e4","score":120,"depth":3,"bound":"exact","pv":["e2e4","e8d7"]},{"move":"e1d1","score":80,"depth":3,"bound":"upper","pv":["e1d1"]}]}"#
+        );
+        assert_eq!(
+            report.to_dot(),
+            "digraph search {\n\
+             \x20 root [shape=box, label=\"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"];\n\
+             \x20 m0_0 [label=\"e2e4\"];\n\
+             \x20 root -> m0_0 [label=\"120 exact d3\", style=bold];\n\
+             \x20 m0_1 [label=\"e8d7\"];\n\
+             \x20 m0_0 -> m0_1;\n\
+             \x20 m1_0 [label=\"e1d1\"];\n\
+             \x20 root -> m1_0 [label=\"80 upper d3\"];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_only_search_events_are_enabled() {
+        let logger = JsonLogger::new(Vec::new());
+        let metadata = |level, target| Metadata::builder().level(level).target(target).build();
+
+        assert!(logger.enabled(&metadata(Level::Debug, "chessbot::search")));
+        assert!(!logger.enabled(&metadata(Level::Trace, "chessbot::search")));
+        assert!(!logger.enabled(&metadata(Level::Debug, "chessbot::uci")));
+    }
+}
@@ -0,0 +1,345 @@
+//! Opening trees: how often each move was played from each position of a set of
+//! games, and how those games ended.
+//!
+//! The tree is keyed by [`PositionKey`], so lines that transpose share their
+//! positions, and a position's continuations count every game that reached it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::board::{Board, PositionKey};
+use crate::chess_move::Move;
+use crate::game::Game;
+use crate::pgn::ResultTag;
+use crate::san::to_san;
+
+/// How many games played a move, or reached a position, and how they ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub games: u32,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+}
+
+impl Stats {
+    /// `count` as a percentage of the games, 0 when there are none.
+    pub fn percent(&self, count: u32) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        100.0 * count as f64 / self.games as f64
+    }
+
+    /// Counts one more game. Unknown results only add to [`Stats::games`].
+    fn add(&mut self, result: ResultTag) {
+        self.games += 1;
+        match result {
+            ResultTag::WhiteWins => self.white_wins += 1,
+            ResultTag::Draw => self.draws += 1,
+            ResultTag::BlackWins => self.black_wins += 1,
+            ResultTag::Unknown => {}
+        }
+    }
+
+    fn merge(&mut self, other: &Stats) {
+        self.games += other.games;
+        self.white_wins += other.white_wins;
+        self.draws += other.draws;
+        self.black_wins += other.black_wins;
+    }
+}
+
+/// The moves played from each position in the first plies of a set of games.
+#[derive(Debug, Clone, Default)]
+pub struct OpeningTree {
+    /// The moves from each position in the order first played.
+    positions: HashMap<PositionKey, Vec<(Move, Stats)>>,
+    max_depth: usize,
+}
+
+impl OpeningTree {
+    /// An empty tree that keeps the first `max_depth` plies of each game.
+    pub fn new(max_depth: usize) -> OpeningTree {
+        OpeningTree {
+            positions: HashMap::new(),
+            max_depth,
+        }
+    }
+
+    /// The tree of the first `max_depth` plies of `games`, each counted with the
+    /// result it has, which for a game read with [`Game::from_pgn`] is the one its
+    /// PGN gives.
+    pub fn from_games(games: &[Game], max_depth: usize) -> OpeningTree {
+        let mut tree = OpeningTree::new(max_depth);
+        for game in games {
+            let result = game
+                .result()
+                .map_or(ResultTag::Unknown, |result| result.tag());
+            tree.add_game(game, result);
+        }
+        tree
+    }
+
+    /// Adds the first plies of `game` as ending in `result`. A game that comes back
+    /// to a position is counted there once, for the move it played the first time.
+    pub fn add_game(&mut self, game: &Game, result: ResultTag) {
+        let mut seen = HashSet::new();
+        for (board, m) in game.mainline().take(self.max_depth) {
+            let key = board.position_key();
+            if !seen.insert(key) {
+                continue;
+            }
+            let moves = self.positions.entry(key).or_default();
+            match moves.iter_mut().find(|(played, _)| *played == m) {
+                Some((_, stats)) => stats.add(result),
+                None => {
+                    let mut stats = Stats::default();
+                    stats.add(result);
+                    moves.push((m, stats));
+                }
+            }
+        }
+    }
+
+    /// How many plies of each game the tree keeps.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// The moves played from `board`'s position, most played first.
+    pub fn continuations(&self, board: &Board) -> Vec<(Move, Stats)> {
+        let mut moves = self
+            .positions
+            .get(&board.position_key())
+            .cloned()
+            .unwrap_or_default();
+        moves.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.games));
+        moves
+    }
+
+    /// The games that played a move from `board`'s position.
+    pub fn stats(&self, board: &Board) -> Stats {
+        let mut total = Stats::default();
+        for (_, stats) in self.continuations(board) {
+            total.merge(&stats);
+        }
+        total
+    }
+
+    /// The tree from `root` as nested JSON for visualization, each position with its
+    /// games and the moves played from it, most played first, up to the depth the
+    /// tree keeps.
+    ///
+    /// ```text
+    /// {"games":3,"white_wins":1,"draws":1,"black_wins":1,"moves":[{"uci":"e2e4","san":"e4","games":3,...,"moves":[...]}]}
+    /// ```
+    pub fn to_json(&self, root: &Board) -> String {
+        let mut json = String::new();
+        self.push_json(root, "", &self.stats(root), self.max_depth, &mut json);
+        json
+    }
+
+    /// Writes the position as an object, starting with the fields in `head`.
+    fn push_json(&self, board: &Board, head: &str, stats: &Stats, depth: usize, json: &mut String) {
+        json.push_str(&format!(
+            "{{{}\"games\":{},\"white_wins\":{},\"draws\":{},\"black_wins\":{},\"moves\":[",
+            head, stats.games, stats.white_wins, stats.draws, stats.black_wins
+        ));
+        // The depth bounds the walk, as lines that repeat a position loop
+        if depth > 0 {
+            for (idx, (m, move_stats)) in self.continuations(board).iter().enumerate() {
+                if idx > 0 {
+                    json.push(',');
+                }
+                let head = format!("\"uci\":\"{}\",\"san\":\"{}\",", m, to_san(board, m));
+                let mut after = *board;
+                after.make_move_unchecked(m);
+                self.push_json(&after, &head, move_stats, depth - 1, json);
+            }
+        }
+        json.push_str("]}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
+    use crate::game::{GameResult, Termination};
+
+    fn game(moves: &str, result: Option<GameResult>) -> Game {
+        let moves: Vec<Move> = moves
+            .split_whitespace()
+            .map(|uci| Move::from_uci(uci).unwrap())
+            .collect();
+        let mut game = Game::replay(Board::START_FEN, &moves).unwrap();
+        if let Some(result) = result {
+            game.set_result(result);
+        }
+        game
+    }
+
+    fn three_games() -> Vec<Game> {
+        vec![
+            game(
+                "e2e4 e7e5 g1f3 b8c6",
+                Some(GameResult::win(Color::White, Termination::Resignation)),
+            ),
+            game(
+                "e2e4 e7e5 g1f3 g8f6",
+                Some(GameResult::draw(Termination::Agreement)),
+            ),
+            game(
+                "e2e4 e7e5 f1c4 g8f6",
+                Some(GameResult::win(Color::Black, Termination::Resignation)),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_counts_at_the_divergence_point() {
+        let tree = OpeningTree::from_games(&three_games(), 10);
+
+        let mut board = Board::new();
+        let first = tree.continuations(&board);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].0.to_string(), "e2e4");
+        assert_eq!(
+            first[0].1,
+            Stats {
+                games: 3,
+                white_wins: 1,
+                draws: 1,
+                black_wins: 1
+            }
+        );
+
+        board.apply_uci_moves("e2e4 e7e5").unwrap();
+        let second: Vec<(String, Stats)> = tree
+            .continuations(&board)
+            .into_iter()
+            .map(|(m, stats)| (m.to_string(), stats))
+            .collect();
+        assert_eq!(
+            second,
+            [
+                (
+                    "g1f3".to_string(),
+                    Stats {
+                        games: 2,
+                        white_wins: 1,
+                        draws: 1,
+                        black_wins: 0
+                    }
+                ),
+                (
+                    "f1c4".to_string(),
+                    Stats {
+                        games: 1,
+                        white_wins: 0,
+                        draws: 0,
+                        black_wins: 1
+                    }
+                ),
+            ]
+        );
+        assert!((tree.stats(&board).percent(2) - 66.67).abs() < 0.01);
+
+        board.apply_uci_moves("g1f3").unwrap();
+        assert_eq!(tree.continuations(&board).len(), 2);
+        assert!(tree
+            .continuations(&Board::fen_to_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_games_read_from_pgn_count_their_results() {
+        let text = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n\
+                    [Result \"0-1\"]\n[Termination \"time forfeit\"]\n\n1. e4 c5 0-1\n\n\
+                    1. d4 d5 1/2-1/2\n\n\
+                    1. e4 e5 *\n";
+        let games: Vec<Game> = crate::pgn::parse_games(text)
+            .iter()
+            .map(|pgn| Game::from_pgn(pgn).unwrap())
+            .collect();
+        let tree = OpeningTree::from_games(&games, 4);
+
+        assert_eq!(
+            tree.stats(&Board::new()),
+            Stats {
+                games: 4,
+                white_wins: 1,
+                draws: 1,
+                black_wins: 1
+            }
+        );
+        let mut board = Board::new();
+        board.apply_uci_moves("e2e4").unwrap();
+        let replies: Vec<(String, Stats)> = tree
+            .continuations(&board)
+            .into_iter()
+            .map(|(m, stats)| (m.to_string(), stats))
+            .collect();
+        assert_eq!(
+            replies,
+            [
+                (
+                    "e7e5".to_string(),
+                    Stats {
+                        games: 2,
+                        white_wins: 1,
+                        draws: 0,
+                        black_wins: 0
+                    }
+                ),
+                (
+                    "c7c5".to_string(),
+                    Stats {
+                        games: 1,
+                        white_wins: 0,
+                        draws: 0,
+                        black_wins: 1
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_depth_and_json() {
+        let tree = OpeningTree::from_games(&three_games(), 2);
+        let mut board = Board::new();
+        board.apply_uci_moves("e2e4 e7e5").unwrap();
+        assert!(tree.continuations(&board).is_empty());
+
+        assert_eq!(
+            tree.to_json(&Board::new()),
+            concat!(
+                r#"{"games":3,"white_wins":1,"draws":1,"black_wins":1,"moves":["#,
+                r#"{"uci":"e2e4","san":"e4","games":3,"white_wins":1,"draws":1,"black_wins":1,"moves":["#,
+                r#"{"uci":"e7e5","san":"e5","games":3,"white_wins":1,"draws":1,"black_wins":1,"moves":[]}"#,
+                r#"]}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_transpositions_share_a_position_and_repeats_count_once() {
+        let games = [
+            game("g1f3 g8f6 e2e4", None),
+            game("e2e4 g8f6 g1f3", None),
+            game("g1f3 g8f6 f3g1 f6g8 g1f3", None),
+        ];
+        let tree = OpeningTree::from_games(&games, 10);
+
+        let mut board = Board::new();
+        board.apply_uci_moves("e2e4 g8f6 g1f3").unwrap();
+        // No game went on from there, but both move orders reached it
+        assert!(tree.continuations(&board).is_empty());
+
+        let start = tree.continuations(&Board::new());
+        assert_eq!(start[0].0.to_string(), "g1f3");
+        assert_eq!(start[0].1.games, 2);
+        assert_eq!(tree.stats(&Board::new()).games, 3);
+    }
+}
@@ -0,0 +1,240 @@
+//! Transposition table: remembers search results by position key so positions
+//! reached through different move orders are only searched once.
+
+use crate::chess_move::PackedMove;
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Slots [`TranspositionTable::hashfull`] looks at.
+const HASHFULL_SAMPLE: usize = 1000;
+
+/// How the stored score relates to the true score of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high: the true score is at least this.
+    Lower,
+    /// The search failed low: the true score is at most this.
+    Upper,
+}
+
+/// A search result. Scores must fit in an `i16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<PackedMove>,
+}
+
+/// A fixed-size hash table of [`TtEntry`] that several search threads can share.
+///
+/// Each slot is a pair of atomics holding the packed entry and the key xor-ed with
+/// it. A slot torn by two threads writing at once no longer matches its key, so it
+/// reads as empty instead of as a wrong entry.
+///
+/// Entries are stamped with the generation of the search that stored them. The
+/// table lives as long as the game, so an entry from an earlier move gives way to
+/// any new one, however deep it was.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table using roughly `size_mb` megabytes.
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let slot_size = std::mem::size_of::<[AtomicU64; 2]>();
+        let len = (size_mb * 1024 * 1024 / slot_size).max(1);
+
+        TranspositionTable {
+            slots: (0..len)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Starts a new generation, making every entry stored so far stale.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    fn slot(&self, key: u64) -> &[AtomicU64; 2] {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+
+    /// The entry stored for `key`, if the slot holds that position.
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        let [checked_key, data] = self.slot(key);
+        let data = data.load(Ordering::Relaxed);
+        if checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        unpack(key, data)
+    }
+
+    /// Stores `entry`, replacing whatever was in its slot unless that is a deeper
+    /// result for the same position from the current search.
+    pub fn store(&self, entry: TtEntry) {
+        let [checked_key, data] = self.slot(entry.key);
+        let existing = data.load(Ordering::Relaxed);
+        if checked_key.load(Ordering::Relaxed) ^ existing == entry.key
+            && generation_of(existing) == self.generation()
+            && unpack(entry.key, existing).is_some_and(|e| e.depth > entry.depth)
+        {
+            return;
+        }
+        let packed = pack(&entry) | (self.generation() as u64) << GENERATION_SHIFT;
+        checked_key.store(entry.key ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+
+    /// Roughly how full the table is with entries from the current search, in
+    /// thousandths, as UCI's `hashfull` reports it. Looks at the first thousand slots.
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(HASHFULL_SAMPLE)];
+        let generation = self.generation();
+        let full = sample
+            .iter()
+            .filter(|[_, data]| {
+                let data = data.load(Ordering::Relaxed);
+                data >> 40 & 3 != 0 && generation_of(data) == generation
+            })
+            .count();
+        (full * 1000 / sample.len()) as u32
+    }
+
+    pub fn clear(&self) {
+        for [checked_key, data] in &self.slots {
+            checked_key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Bits 42-49 of a packed entry hold the generation that stored it.
+const GENERATION_SHIFT: u32 = 42;
+
+fn generation_of(data: u64) -> u8 {
+    (data >> GENERATION_SHIFT) as u8
+}
+
+/// Packs an entry into 64 bits: the move in bits 0-15, 0 for none, the score in
+/// 16-31, the depth in 32-39 and the bound in 40-41. The bound is never zero, so an
+/// empty slot never unpacks. [`TranspositionTable::store`] adds the generation above
+/// them.
+fn pack(entry: &TtEntry) -> u64 {
+    let m = entry.best_move.map_or(0, |m| m.bits() as u64);
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+
+    m | (entry.score as i16 as u16 as u64) << 16 | (entry.depth as u64) << 32 | bound << 40
+}
+
+fn unpack(key: u64, data: u64) -> Option<TtEntry> {
+    let bound = match (data >> 40) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    Some(TtEntry {
+        key,
+        depth: (data >> 32) as u8,
+        score: (data >> 16) as u16 as i16 as i32,
+        bound,
+        best_move: PackedMove::from_bits(data as u16),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::Move;
+
+    fn entry(key: u64, depth: u8, score: i32) -> TtEntry {
+        TtEntry {
+            key,
+            depth,
+            score,
+            bound: Bound::Exact,
+            best_move: None,
+        }
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        tt.store(entry(12345, 3, 50));
+
+        assert_eq!(tt.probe(12345), Some(entry(12345, 3, 50)));
+        assert_eq!(tt.probe(54321), None);
+    }
+
+    #[test]
+    fn test_shallower_result_does_not_replace_deeper_one() {
+        let tt = TranspositionTable::new(1);
+        tt.store(entry(7, 5, 10));
+        tt.store(entry(7, 2, -10));
+
+        assert_eq!(tt.probe(7).unwrap().depth, 5);
+
+        // Unless it is left over from an earlier search
+        tt.new_search();
+        tt.store(entry(7, 2, -10));
+        assert_eq!(tt.probe(7), Some(entry(7, 2, -10)));
+
+        tt.clear();
+        assert_eq!(tt.probe(7), None);
+    }
+
+    #[test]
+    fn test_hashfull_counts_entries_of_the_current_search() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        // Keys 0 to 499 land in the first half of the sampled slots
+        for key in 1..500 {
+            tt.store(entry(key, 1, 0));
+        }
+        assert_eq!(tt.hashfull(), 499);
+
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+        tt.store(entry(1, 1, 0));
+        assert_eq!(tt.hashfull(), 1);
+    }
+
+    #[test]
+    fn test_entries_round_trip_through_packing() {
+        let tt = TranspositionTable::new(1);
+        let entry = TtEntry {
+            key: 0xdead_beef,
+            depth: 12,
+            score: -29_990,
+            bound: Bound::Upper,
+            best_move: Some(PackedMove::new(Move::new("b7a8n"))),
+        };
+        tt.store(entry);
+
+        assert_eq!(tt.probe(entry.key), Some(entry));
+        // An empty slot must not pass for key 0
+        assert_eq!(tt.probe(0), None);
+    }
+
+    #[test]
+    fn test_entry_size() {
+        // The key, score, depth and bound, and a 16-bit move with no room lost to padding
+        assert_eq!(std::mem::size_of::<TtEntry>(), 16);
+    }
+}
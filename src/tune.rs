@@ -0,0 +1,214 @@
+//! Texel tuning of the evaluation weights.
+//!
+//! Given positions labelled with the result of the game they came from, the tuner
+//! nudges a chosen set of weights so that a sigmoid of the static evaluation
+//! predicts those results as well as possible.
+
+use crate::board::{Board, Color};
+use crate::eval::{evaluate, EvalParams, Weight};
+use crate::utils::XorShift64;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Settings for [`texel`].
+#[derive(Debug, Clone)]
+pub struct TexelConfig {
+    /// The weights to tune; all others are left alone.
+    pub weights: Vec<Weight>,
+    /// Scales centipawns before the sigmoid. 1.0 is a reasonable start.
+    pub k: f64,
+    /// Seeds the order in which weights are tried, so a run can be repeated exactly.
+    pub seed: u64,
+}
+
+/// Expected score for White given a White-relative evaluation in centipawns.
+fn win_probability(score: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+/// Mean squared error between the predicted and actual results. Results are from
+/// White's point of view: 1.0 for a win, 0.5 for a draw and 0.0 for a loss.
+pub fn loss(params: &EvalParams, positions: &[(Board, f64)], k: f64) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = positions
+        .iter()
+        .map(|(board, result)| {
+            let score = match board.active_color {
+                Color::White => evaluate(board, params),
+                Color::Black => -evaluate(board, params),
+            };
+            (result - win_probability(score, k)).powi(2)
+        })
+        .sum();
+
+    total / positions.len() as f64
+}
+
+/// Tunes `config.weights` by local search for up to `iterations` passes and returns
+/// the improved weights.
+///
+/// Each pass tries moving every weight up and then down by the current step and
+/// keeps any change that lowers the loss. The step halves after a pass with no
+/// improvement, and the search stops once a pass with a step of 1 finds nothing.
+pub fn texel(
+    params: &EvalParams,
+    positions: &[(Board, f64)],
+    iterations: usize,
+    config: &TexelConfig,
+) -> EvalParams {
+    let mut best = *params;
+    let mut best_loss = loss(&best, positions, config.k);
+    let mut rng = XorShift64::new(config.seed);
+    let mut weights = config.weights.clone();
+    let mut step = 16;
+
+    for _ in 0..iterations {
+        // Shuffle so no weight always gets the first chance to absorb the error
+        for i in (1..weights.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            weights.swap(i, j);
+        }
+
+        let mut improved = false;
+        for &weight in &weights {
+            for delta in [step, -step] {
+                let mut candidate = best;
+                *candidate.weight_mut(weight) += delta;
+
+                let candidate_loss = loss(&candidate, positions, config.k);
+                if candidate_loss < best_loss {
+                    best = candidate;
+                    best_loss = candidate_loss;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        if !improved {
+            if step == 1 {
+                break;
+            }
+            step /= 2;
+        }
+    }
+
+    best
+}
+
+/// Parses labelled positions, one per line, in either of two formats:
+///
+/// * EPD with a result opcode: `<fen> c9 "1-0";`
+/// * CSV: `<fen>,<result>` where the result is `1-0`, `0-1`, `1/2-1/2` or a number
+///   between 0 and 1.
+///
+/// FENs may omit the move counters. Lines that cannot be read are skipped.
+pub fn load_positions(text: &str) -> Vec<(Board, f64)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (fen, result) = match line.split_once(',') {
+                Some((fen, result)) => (fen, result.trim()),
+                None => {
+                    let (fen, opcode) = line.split_once(" c9 ")?;
+                    (fen, opcode.trim_end_matches(';').trim().trim_matches('"'))
+                }
+            };
+
+            let result = match result {
+                "1-0" => 1.0,
+                "0-1" => 0.0,
+                "1/2-1/2" => 0.5,
+                number => number
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| (0.0..=1.0).contains(r))?,
+            };
+
+            let fields: Vec<&str> = fen.split_whitespace().collect();
+            let fen = match fields.len() {
+                4 => format!("{} 0 1", fields.join(" ")),
+                6 => fields.join(" "),
+                _ => return None,
+            };
+            Some((Board::fen_to_board(&fen), result))
+        })
+        .collect()
+}
+
+/// Reads labelled positions from an EPD or CSV file; see [`load_positions`].
+pub fn load_positions_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<(Board, f64)>> {
+    Ok(load_positions(&fs::read_to_string(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PieceType;
+
+    /// White is a knight or a rook up in every position and always wins.
+    const DATASET: &str = "\
+4k3/8/8/8/8/8/8/1N2K3 w - -,1-0
+4k3/pp6/8/8/8/8/PP6/1N2K3 b - -,1-0
+4k3/8/8/8/8/8/8/R3K3 w - - 0 1,1.0
+2k5/8/8/8/8/8/8/R3K3 b - - c9 \"1-0\";
+4k3/p7/8/8/8/8/P7/R3K3 w - - c9 \"1-0\";
+not a position
+";
+
+    #[test]
+    fn test_load_positions_reads_csv_and_epd() {
+        let positions = load_positions(DATASET);
+
+        assert_eq!(positions.len(), 5);
+        assert!(positions.iter().all(|&(_, result)| result == 1.0));
+        assert_eq!(positions[1].0.active_color, Color::Black);
+    }
+
+    #[test]
+    fn test_texel_lowers_the_loss() {
+        let positions = load_positions(DATASET);
+        // Start from badly undervalued knights and rooks
+        let params = EvalParams {
+            mg_piece_values: [82, 40, 365, 60, 1025, 0],
+            eg_piece_values: [94, 40, 297, 60, 936, 0],
+            ..EvalParams::DEFAULT
+        };
+        let config = TexelConfig {
+            weights: vec![
+                Weight::EgPieceValue(PieceType::Knight),
+                Weight::EgPieceValue(PieceType::Rook),
+            ],
+            k: 1.0,
+            seed: 1,
+        };
+
+        let tuned = texel(&params, &positions, 20, &config);
+
+        assert!(loss(&tuned, &positions, 1.0) < loss(&params, &positions, 1.0));
+        assert!(tuned.eg_piece_values[PieceType::Knight as usize] > 40);
+        assert!(tuned.eg_piece_values[PieceType::Rook as usize] > 60);
+        // Weights that were not selected are untouched
+        assert_eq!(tuned.mg_piece_values, params.mg_piece_values);
+    }
+
+    #[test]
+    fn test_texel_is_deterministic_for_a_seed() {
+        let positions = load_positions(DATASET);
+        let config = TexelConfig {
+            weights: vec![Weight::PstScale, Weight::EgPieceValue(PieceType::Rook)],
+            k: 1.0,
+            seed: 7,
+        };
+
+        assert_eq!(
+            texel(&EvalParams::DEFAULT, &positions, 5, &config),
+            texel(&EvalParams::DEFAULT, &positions, 5, &config)
+        );
+    }
+}
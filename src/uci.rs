@@ -0,0 +1,644 @@
+//! Universal Chess Interface (UCI) front-end, a text adapter over [`EngineSession`].
+//!
+//! Commands are read on the calling thread while searches run on a worker thread,
+//! so `stop`, `isready` and `quit` are answered while the engine is thinking.
+
+use crate::board::{Board, Color, Strictness};
+use crate::book::Book;
+use crate::chess_move::{Move, MoveError, MAX_MOVES};
+use crate::engine::{parse_root_moves, Engine, SearchLimits};
+use crate::game::{Game, GameError};
+use crate::search::{
+    bench, mate_in, move_time_budget, SearchResult, BENCH_DEPTH, MAX_DEPTH, MAX_SKILL,
+};
+use crate::session::{
+    EngineConfig, EngineSession, SearchEvent, SearchHandle, MAX_CONTEMPT, MAX_HASH_MB,
+    MAX_MOVE_OVERHEAD_MS, MAX_THREADS,
+};
+
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Reads UCI commands from `input` until `quit` or end of input, writing replies to
+/// `output`. At the end of input a running search is allowed to finish.
+///
+/// Moves from `book` are played while the `OwnBook` option is on, which it is by
+/// default when a book is given.
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W, book: Option<Book>) {
+    let mut engine = UciEngine::new(output, book);
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !engine.handle(&line) {
+            return;
+        }
+    }
+    engine.wait_for_search();
+}
+
+/// The `option` lines sent in reply to `uci`, advertising `config` as the defaults.
+fn option_lines(config: &EngineConfig) -> Vec<String> {
+    vec![
+        format!(
+            "option name Hash type spin default {} min 1 max {}",
+            config.hash_mb, MAX_HASH_MB
+        ),
+        format!(
+            "option name Threads type spin default {} min 1 max {}",
+            config.threads, MAX_THREADS
+        ),
+        format!(
+            "option name MultiPV type spin default {} min 1 max {}",
+            config.multi_pv, MAX_MOVES
+        ),
+        format!(
+            "option name Move Overhead type spin default {} min 0 max {}",
+            config.move_overhead_ms, MAX_MOVE_OVERHEAD_MS
+        ),
+        format!("option name OwnBook type check default {}", config.own_book),
+        format!(
+            "option name Contempt type spin default {} min {} max {}",
+            config.contempt, -MAX_CONTEMPT, MAX_CONTEMPT
+        ),
+        format!(
+            "option name Skill Level type spin default {} min 0 max {}",
+            config.skill, MAX_SKILL
+        ),
+    ]
+}
+
+/// Engine state between UCI commands.
+pub struct UciEngine<W: Write + Send + 'static> {
+    session: EngineSession,
+    output: Arc<Mutex<W>>,
+    /// Writes the events of the search in progress, if any, up to its `bestmove`.
+    forwarder: Option<JoinHandle<()>>,
+}
+
+impl<W: Write + Send + 'static> UciEngine<W> {
+    /// Plays with the alpha-beta searcher, reporting its progress in `info` lines.
+    pub fn new(output: W, book: Option<Book>) -> UciEngine<W> {
+        UciEngine::with_session(output, EngineSession::new(book))
+    }
+
+    /// Plays with `engine`.
+    pub fn with_engine(
+        output: W,
+        book: Option<Book>,
+        engine: Box<dyn Engine + Send>,
+    ) -> UciEngine<W> {
+        UciEngine::with_session(output, EngineSession::with_engine(book, engine))
+    }
+
+    fn with_session(output: W, session: EngineSession) -> UciEngine<W> {
+        UciEngine {
+            session,
+            output: Arc::new(Mutex::new(output)),
+            forwarder: None,
+        }
+    }
+
+    /// Handles one command line. Returns false once the engine should exit.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                self.send("id name ChessBot");
+                self.send("id author JusticeJJackson");
+                for line in option_lines(self.session.config()) {
+                    self.send(&line);
+                }
+                self.send("uciok");
+            }
+            Some("isready") => self.send("readyok"),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.session.new_game();
+            }
+            Some("position") => {
+                self.stop_search();
+                match parse_position(&tokens[1..]) {
+                    Ok(game) => self.session.set_game(game),
+                    Err(err) => self.send(&format!("info string {}", err)),
+                }
+            }
+            Some("setoption") => {
+                self.stop_search();
+                // Unknown options and bad values are ignored
+                if let Some((name, value)) = parse_setoption(&tokens[1..]) {
+                    self.session.set_option(&name, &value);
+                }
+            }
+            Some("go") => self.go(&tokens[1..]),
+            Some("stop") => self.stop_search(),
+            // Not UCI, but the usual engine convention: `bench [depth]`
+            Some("bench") => {
+                self.stop_search();
+                let depth = tokens
+                    .get(1)
+                    .and_then(|d| d.parse().ok())
+                    .unwrap_or(BENCH_DEPTH);
+                let stats = bench(depth);
+                self.send(&format!("Nodes searched: {}", stats.nodes));
+                self.send(&format!("Nodes/second: {}", stats.nps()));
+            }
+            Some("quit") => {
+                self.stop_search();
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn send(&self, line: &str) {
+        send_lines(&self.output, &[line.to_string()]);
+    }
+
+    /// Starts a search on the session's worker thread, and a thread of its own
+    /// writing what the search reports, `bestmove` last.
+    fn go(&mut self, args: &[&str]) {
+        self.stop_search();
+
+        let game = self.session.game();
+        let mut limits = SearchLimits {
+            depth: Some(MAX_DEPTH),
+            ..SearchLimits::default()
+        };
+        let mut move_time = None;
+        let (mut time_left, mut increment) = (None, 0);
+
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            let value = || args.clone().next().and_then(|v| v.parse::<u64>().ok());
+            match (arg, game.board().active_color) {
+                ("depth", _) => limits.depth = Some(value().map_or(MAX_DEPTH, |d| d as u32)),
+                ("mate", _) => limits.mate = value().map(|moves| moves as u32),
+                ("movetime", _) => move_time = value(),
+                ("wtime", Color::White) | ("btime", Color::Black) => time_left = value(),
+                ("winc", Color::White) | ("binc", Color::Black) => increment = value().unwrap_or(0),
+                // The moves run up to the next keyword, which is never a UCI move
+                ("searchmoves", _) => {
+                    let mut tokens = Vec::new();
+                    while let Some(&token) =
+                        args.clone().next().filter(|t| Move::from_uci(t).is_some())
+                    {
+                        tokens.push(token);
+                        args.next();
+                    }
+                    match parse_root_moves(game.board(), &tokens) {
+                        Ok(moves) => limits.root_moves = Some(moves),
+                        Err(err) => self.send(&format!("info string searchmoves: {}", err)),
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Only time shared out from a clock is worth saving for later moves
+        limits.easy_moves = move_time.is_none() && time_left.is_some();
+        let overhead = self.session.config().move_overhead_ms;
+        let move_time = match move_time {
+            Some(ms) => Some(ms.saturating_sub(overhead).max(1)),
+            None => time_left.map(|t| move_time_budget(t, increment, overhead)),
+        };
+        limits.move_time = move_time.map(Duration::from_millis);
+
+        let search = self.session.go(limits);
+        let output = Arc::clone(&self.output);
+        self.forwarder = Some(thread::spawn(move || forward_events(&search, &output)));
+    }
+
+    /// Stops the search in progress, if any, and waits for its `bestmove`.
+    pub fn stop_search(&mut self) {
+        self.session.stop();
+        self.wait_for_search();
+    }
+
+    fn wait_for_search(&mut self) {
+        self.session.wait();
+        if let Some(forwarder) = self.forwarder.take() {
+            // A forwarder only panics if writing did; there is nothing left to tell
+            let _ = forwarder.join();
+        }
+    }
+}
+
+/// Writes `lines` to `output` in one go, so that they are not interleaved with
+/// another thread's.
+fn send_lines<W: Write>(output: &Mutex<W>, lines: &[String]) {
+    let mut output = output.lock().unwrap();
+    // A closed output means the GUI has gone away; there is nobody to tell
+    let _ = lines
+        .iter()
+        .try_for_each(|line| writeln!(output, "{}", line))
+        .and_then(|_| output.flush());
+}
+
+/// Writes the events of `search` as they arrive, until its `bestmove`.
+fn forward_events<W: Write>(search: &SearchHandle, output: &Mutex<W>) {
+    for event in search.events() {
+        send_lines(output, &event_lines(&event));
+        if let SearchEvent::BestMove(_) = event {
+            break;
+        }
+    }
+}
+
+/// The UCI lines for a search event: `info` lines while searching, then `bestmove`.
+fn event_lines(event: &SearchEvent) -> Vec<String> {
+    match event {
+        SearchEvent::Iteration(result) => info_lines(result),
+        SearchEvent::Progress(stats) => vec![format!(
+            "info nodes {} nps {} hashfull {} time {}",
+            stats.nodes,
+            stats.nps(),
+            stats.hashfull,
+            stats.elapsed.as_millis()
+        )],
+        SearchEvent::CurrentMove { m, number } => {
+            vec![format!("info currmove {} currmovenumber {}", m, number)]
+        }
+        // With no legal move, mated or stalemated, answer as other engines do
+        SearchEvent::BestMove(m) => vec![format!(
+            "bestmove {}",
+            m.map_or("(none)".to_string(), |m| m.to_string())
+        )],
+    }
+}
+
+/// One `info` line per line of the search, e.g. `info depth 5 seldepth 9 multipv 1
+/// score cp 30 nodes 7740 nps 33831 hashfull 12 time 228 pv e2e4 e7e5`.
+fn info_lines(result: &SearchResult) -> Vec<String> {
+    let stats = &result.stats;
+
+    result
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let pv: Vec<String> = line.pv.iter().map(|m| m.to_string()).collect();
+            format!(
+                "info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+                result.depth,
+                stats.seldepth,
+                idx + 1,
+                uci_score(line.score),
+                stats.nodes,
+                stats.nps(),
+                stats.hashfull,
+                stats.elapsed.as_millis(),
+                pv.join(" ")
+            )
+        })
+        .collect()
+}
+
+/// Splits the arguments of `setoption` into name and value. Both may contain spaces,
+/// as in `setoption name Move Overhead value 30`.
+fn parse_setoption(args: &[&str]) -> Option<(String, String)> {
+    let rest = args.strip_prefix(&["name"])?;
+    let value_idx = rest.iter().position(|&arg| arg == "value");
+    let (name, value) = match value_idx {
+        Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+        None => (rest, &[][..]),
+    };
+    Some((name.join(" "), value.join(" ")))
+}
+
+/// Formats a score as `cp <centipawns>` or `mate <moves>`, negative when the engine
+/// is getting mated.
+fn uci_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+/// Parses the arguments of `position`: `startpos` or `fen <four or six fields>`, optionally
+/// followed by `moves` and a list of UCI moves. Says what is wrong, and for a bad move
+/// at which ply, if anything is malformed or illegal.
+fn parse_position(args: &[&str]) -> Result<Game, String> {
+    let moves_idx = args.iter().position(|&arg| arg == "moves");
+    let (setup, moves) = match moves_idx {
+        Some(idx) => (&args[..idx], &args[idx + 1..]),
+        None => (args, &[][..]),
+    };
+
+    let board = match setup {
+        ["startpos"] => Board::new(),
+        ["fen", fields @ ..] => Board::try_from_fen(&fields.join(" "), Strictness::Lax)
+            .map_err(|err| format!("invalid fen: {}", err))?,
+        _ => return Err(format!("unknown position: {}", setup.join(" "))),
+    };
+
+    let mut game = Game::from_board(board);
+    for (ply, uci) in moves.iter().enumerate() {
+        let played = match Move::from_uci(uci) {
+            Some(m) => game.play(m),
+            None => Err(GameError::IllegalMove {
+                ply,
+                uci: uci.to_string(),
+                reason: MoveError::Malformed,
+            }),
+        };
+        played.map_err(|err| err.to_string())?;
+    }
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// Output that tests can read back while the engine still holds it.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_parse_position_with_moves() {
+        let game = parse_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"]).unwrap();
+        assert_eq!(
+            game.board().board_to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        assert_eq!(
+            parse_position(&["startpos", "moves", "e2e4", "e2e3"]).unwrap_err(),
+            "illegal move e2e3 at ply 2: no piece on e2"
+        );
+        assert_eq!(
+            parse_position(&["startpos", "moves", "e2e4", "d2d4"]).unwrap_err(),
+            "illegal move d2d4 at ply 2: the piece on d2 belongs to the other side"
+        );
+        assert_eq!(
+            parse_position(&["startpos", "moves", "e2e5"]).unwrap_err(),
+            "illegal move e2e5 at ply 1: a pawn does not move like that"
+        );
+        assert!(parse_position(&["fen", "8/8/8/8"]).is_err());
+        // Six fields, but not a position; a GUI sending this must not crash the engine
+        let bad_fen = ["fen", "8/8/8/9/8/8/8/8", "w", "-", "-", "0", "1"];
+        assert!(parse_position(&bad_fen).is_err());
+
+        let short_fen = [
+            "fen",
+            "4k3/8/8/8/8/8/8/4K2R",
+            "w",
+            "KQ",
+            "-",
+            "moves",
+            "e1g1",
+        ];
+        assert_eq!(
+            parse_position(&short_fen).unwrap().board().board_to_fen(),
+            "4k3/8/8/8/8/8/8/5RK1 b - - 1 1"
+        );
+    }
+
+    #[test]
+    fn test_go_depth_replies_with_bestmove() {
+        let output = SharedOutput::default();
+        let input = "uci\nposition fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n";
+        run(input.as_bytes(), output.clone(), None);
+
+        let text = output.text();
+        assert!(text.contains("uciok"));
+        assert!(text.contains("score mate 1 "), "{}", text);
+        assert!(text.contains(" hashfull "), "{}", text);
+        assert!(text.ends_with("bestmove a1a8\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_root_moves_are_reported_as_currmove() {
+        let event = SearchEvent::CurrentMove {
+            m: Move::from_uci("e2e4").unwrap(),
+            number: 5,
+        };
+        assert_eq!(event_lines(&event), ["info currmove e2e4 currmovenumber 5"]);
+    }
+
+    #[test]
+    fn test_go_searchmoves_restricts_the_root() {
+        // The mate is not among the moves to search
+        let output = SharedOutput::default();
+        let input = "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\n\
+                     go searchmoves g1f2 g1g2 depth 3\n";
+        run(input.as_bytes(), output.clone(), None);
+        let text = output.text();
+        assert!(!text.contains("a1a8"), "{}", text);
+        assert!(
+            text.ends_with("bestmove g1f2\n") || text.ends_with("bestmove g1g2\n"),
+            "{}",
+            text
+        );
+
+        let output = SharedOutput::default();
+        let input = "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\n\
+                     go searchmoves g1f2 e2e4 depth 3\n";
+        run(input.as_bytes(), output.clone(), None);
+        let text = output.text();
+        assert!(
+            text.starts_with("info string searchmoves: illegal move: e2e4\n"),
+            "{}",
+            text
+        );
+        assert!(text.ends_with("bestmove a1a8\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_go_mate_reports_the_mate() {
+        let output = SharedOutput::default();
+        let input = "position fen k7/8/2K5/8/8/8/8/1R6 w - - 0 1\ngo mate 2\n";
+        run(input.as_bytes(), output.clone(), None);
+
+        let text = output.text();
+        assert!(text.contains("score mate 2 "), "{}", text);
+        assert!(text.contains("pv c6c7 a8a7 b1a1\n"), "{}", text);
+        assert!(text.ends_with("bestmove c6c7\n"));
+    }
+
+    #[test]
+    fn test_setoption_updates_config() {
+        assert_eq!(
+            parse_setoption(&["name", "Move", "Overhead", "value", "30"]),
+            Some(("Move Overhead".to_string(), "30".to_string()))
+        );
+
+        let mut engine = UciEngine::new(io::sink(), None);
+        for line in [
+            "setoption name Hash value 128",
+            "setoption  name threads  value 4",
+            "setoption name MultiPV value 3",
+            "setoption name Move Overhead value 30",
+            "setoption name OwnBook value true",
+            "setoption name Contempt value -25",
+            "setoption name Skill Level value 3",
+        ] {
+            engine.handle(line);
+        }
+        assert_eq!(
+            *engine.session.config(),
+            EngineConfig {
+                hash_mb: 128,
+                threads: 4,
+                multi_pv: 3,
+                move_overhead_ms: 30,
+                own_book: true,
+                contempt: -25,
+                skill: 3,
+            }
+        );
+
+        // Out of range values are clamped, nonsense and unknown options ignored
+        for line in [
+            "setoption name Threads value 1000",
+            "setoption name Hash value lots",
+            "setoption name OwnBook value maybe",
+            "setoption name Ponder value true",
+            "setoption Hash 64",
+        ] {
+            engine.handle(line);
+        }
+        assert_eq!(engine.session.config().threads, MAX_THREADS);
+        assert_eq!(engine.session.config().hash_mb, 128);
+        assert!(engine.session.config().own_book);
+    }
+
+    #[test]
+    fn test_multipv_reports_distinct_lines_best_first() {
+        let output = SharedOutput::default();
+        let input = "setoption name MultiPV value 3\n\
+                     position fen 4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1\n\
+                     go depth 3\n";
+        run(input.as_bytes(), output.clone(), None);
+
+        let text = output.text();
+        // Each depth reports all three lines; look at the last depth
+        let lines: Vec<&str> = text
+            .lines()
+            .filter(|l| l.starts_with("info depth 3 "))
+            .collect();
+        assert_eq!(lines.len(), 3, "{}", text);
+
+        let field = |line: &str, name: &str| -> String {
+            let mut words = line.split(' ').skip_while(|&w| w != name).skip(1);
+            match name {
+                "score" => words.nth(1).unwrap().to_string(),
+                _ => words.next().unwrap().to_string(),
+            }
+        };
+        let scores: Vec<i32> = lines
+            .iter()
+            .map(|l| field(l, "score").parse().unwrap())
+            .collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]), "{:?}", scores);
+
+        let first_moves: Vec<String> = lines.iter().map(|l| field(l, "pv")).collect();
+        assert_eq!(first_moves[0], "d1d5");
+        assert!(first_moves[1] != first_moves[0] && first_moves[2] != first_moves[0]);
+        assert!(first_moves[1] != first_moves[2]);
+        assert!(text.ends_with("bestmove d1d5\n"));
+    }
+
+    #[test]
+    fn test_own_book_answers_without_searching() {
+        let book = Book::open("tests/fixtures/tiny_book.bin").unwrap();
+
+        let output = SharedOutput::default();
+        run(
+            "go depth 5\n".as_bytes(),
+            output.clone(),
+            Some(book.clone()),
+        );
+        assert!(!output.text().contains("info"));
+        assert!(["bestmove e2e4\n", "bestmove d2d4\n"].contains(&output.text().as_str()));
+
+        let output = SharedOutput::default();
+        let input = "setoption name OwnBook value false\ngo depth 1\n";
+        run(input.as_bytes(), output.clone(), Some(book));
+        assert!(output.text().contains("info depth 1"));
+    }
+
+    #[test]
+    fn test_plays_with_any_engine() {
+        let output = SharedOutput::default();
+        let engine = Box::new(crate::engine::GreedyEngine);
+        let mut engine = UciEngine::with_engine(output.clone(), None, engine);
+
+        engine.handle("position fen 4k3/8/8/8/8/1r1q4/2P5/4K3 w - - 0 1");
+        engine.handle("go movetime 10");
+        engine.handle("quit");
+
+        assert_eq!(output.text(), "bestmove c2d3\n");
+    }
+
+    #[test]
+    fn test_no_legal_move_replies_bestmove_none() {
+        for fen in [
+            "R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1",
+            "k7/8/1QK5/8/8/8/8/8 b - - 0 1",
+        ] {
+            let output = SharedOutput::default();
+            let mut engine = UciEngine::new(output.clone(), None);
+            engine.handle(&format!("position fen {}", fen));
+            engine.handle("go depth 3");
+            engine.handle("quit");
+            assert!(
+                output.text().ends_with("bestmove (none)\n"),
+                "{}",
+                output.text()
+            );
+        }
+    }
+
+    #[test]
+    fn test_stop_interrupts_infinite_search() {
+        let output = SharedOutput::default();
+        let mut engine = UciEngine::new(output.clone(), None);
+
+        engine.handle("position startpos");
+        engine.handle("go infinite");
+        thread::sleep(Duration::from_millis(20));
+        engine.handle("isready");
+        engine.handle("stop");
+
+        let text = output.text();
+        assert!(text.lines().any(|line| line == "readyok"));
+        let best_move = text.trim_end().rsplit(' ').next().unwrap();
+        let mut board = Board::new();
+        assert!(board.apply_uci_moves(best_move).is_ok(), "{}", text);
+    }
+
+    #[test]
+    fn test_bench_prints_node_count() {
+        let output = SharedOutput::default();
+        let mut engine = UciEngine::new(output.clone(), None);
+
+        engine.handle("bench 1");
+
+        let text = output.text();
+        let nodes = format!("Nodes searched: {}", crate::search::bench(1).nodes);
+        assert!(text.lines().any(|line| line == nodes), "{}", text);
+        assert!(text.lines().any(|line| line.starts_with("Nodes/second: ")));
+    }
+}
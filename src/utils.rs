@@ -1,79 +1,136 @@
-use once_cell::sync::Lazy;
 use crate::board::{Board, Color};
-use crate::chess_move::{validate_move, generate_all_moves_for_color};
-
-pub fn convert_board_coordinate_to_idx(board_coordinate: String) -> u8 {
-    let mut board_coordinate = board_coordinate.chars();
-    let file = board_coordinate.next().unwrap();
-    let rank = board_coordinate.next().unwrap();
-
-    let file = match file {
-        'a' => 0,
-        'b' => 1,
-        'c' => 2,
-        'd' => 3,
-        'e' => 4,
-        'f' => 5,
-        'g' => 6,
-        'h' => 7,
-        _ => panic!("Invalid file"),
-    };
 
-    let rank = match rank {
-        '1' => 0,
-        '2' => 1,
-        '3' => 2,
-        '4' => 3,
-        '5' => 4,
-        '6' => 5,
-        '7' => 6,
-        '8' => 7,
-        _ => panic!("Invalid rank"),
+/// Index of the square named at the start of `board_coordinate`, e.g. 28 for `e4`.
+/// Panics if it does not start with a square name.
+pub fn convert_board_coordinate_to_idx(board_coordinate: &str) -> u8 {
+    let (file, rank) = match board_coordinate.as_bytes() {
+        [file, rank, ..] => (*file, *rank),
+        _ => panic!("Invalid square"),
     };
+    if !(b'a'..=b'h').contains(&file) {
+        panic!("Invalid file");
+    }
+    if !(b'1'..=b'8').contains(&rank) {
+        panic!("Invalid rank");
+    }
+
+    (rank - b'1') * 8 + (file - b'a') // Return the index of the square
+}
 
-    (rank * 8 + file) as u8 // Return the index of the square
+/// The name of `square` as ASCII bytes, e.g. `*b"e4"` for 28.
+pub const fn square_to_coordinate(square: u8) -> [u8; 2] {
+    [b'a' + square % 8, b'1' + square / 8]
 }
 
-// Directions: [North, South, East, West, North-East, North-West, South-East, South-West]
-pub static EDGE_DISTANCES: Lazy<[Vec<u8>; 8]> = Lazy::new(|| {
-    let mut north: Vec<u8> = vec![0; 64];
-    let mut south: Vec<u8> = vec![0; 64];
-    let mut east: Vec<u8> = vec![0; 64];
-    let mut west: Vec<u8> = vec![0; 64];
-    let mut north_east: Vec<u8> = vec![0; 64];
-    let mut north_west: Vec<u8> = vec![0; 64];
-    let mut south_east: Vec<u8> = vec![0; 64];
-    let mut south_west: Vec<u8> = vec![0; 64];
-
-    for square in 0..64 {
-        let row = square / 8;
-        let col = square % 8;
-
-        north[square] = (7 - row) as u8;
-        south[square] = (row) as u8;
-        east[square] = (7 - col) as u8;
-        west[square] = (col) as u8;
-        north_east[square] = (north[square].min(east[square])) as u8;
-        north_west[square] = (north[square].min(west[square])) as u8;
-        south_east[square] = (south[square].min(east[square])) as u8;
-        south_west[square] = (south[square].min(west[square])) as u8;
+/// Names of the squares, by index.
+pub const SQUARE_NAMES: [&str; 64] = [
+    "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1", "a2", "b2", "c2", "d2", "e2", "f2", "g2", "h2",
+    "a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3", "a4", "b4", "c4", "d4", "e4", "f4", "g4", "h4",
+    "a5", "b5", "c5", "d5", "e5", "f5", "g5", "h5", "a6", "b6", "c6", "d6", "e6", "f6", "g6", "h6",
+    "a7", "b7", "c7", "d7", "e7", "f7", "g7", "h7", "a8", "b8", "c8", "d8", "e8", "f8", "g8", "h8",
+];
+
+/// The name of `square`, e.g. `"e4"` for 28.
+pub fn square_to_str(square: u8) -> &'static str {
+    SQUARE_NAMES[square as usize]
+}
+
+/// Directions a slider moves in, as indices of [`EDGE_DISTANCES`].
+pub const NORTH: usize = 0;
+pub const SOUTH: usize = 1;
+pub const EAST: usize = 2;
+pub const WEST: usize = 3;
+pub const NORTH_EAST: usize = 4;
+pub const NORTH_WEST: usize = 5;
+pub const SOUTH_EAST: usize = 6;
+pub const SOUTH_WEST: usize = 7;
+
+/// How many squares a slider on each square can move in each direction before it
+/// reaches the edge of the board, by direction ([`NORTH`] and so on) and then
+/// square.
+pub const EDGE_DISTANCES: [[u8; 64]; 8] = edge_distances();
+
+const fn edge_distances() -> [[u8; 64]; 8] {
+    let mut distances = [[0; 64]; 8];
+    let mut square = 0;
+    while square < 64 {
+        let (rank, file) = ((square / 8) as u8, (square % 8) as u8);
+        let (north, south, east, west) = (7 - rank, rank, 7 - file, file);
+        distances[NORTH][square] = north;
+        distances[SOUTH][square] = south;
+        distances[EAST][square] = east;
+        distances[WEST][square] = west;
+        distances[NORTH_EAST][square] = if north < east { north } else { east };
+        distances[NORTH_WEST][square] = if north < west { north } else { west };
+        distances[SOUTH_EAST][square] = if south < east { south } else { east };
+        distances[SOUTH_WEST][square] = if south < west { south } else { west };
+        square += 1;
+    }
+    distances
+}
+
+/// Squares strictly between `a` and `b` when they share a rank, file or diagonal.
+/// Returns an empty bitboard for unaligned or adjacent squares.
+pub fn squares_between(a: u8, b: u8) -> u64 {
+    let (a_rank, a_file) = ((a / 8) as i8, (a % 8) as i8);
+    let (b_rank, b_file) = ((b / 8) as i8, (b % 8) as i8);
+    let (rank_diff, file_diff) = (b_rank - a_rank, b_file - a_file);
+
+    let aligned = rank_diff == 0 || file_diff == 0 || rank_diff.abs() == file_diff.abs();
+    if a == b || !aligned {
+        return 0;
     }
 
-    [
-        north, south, east, west, north_east, north_west, south_east, south_west,
-    ]
-});
+    let step = rank_diff.signum() * 8 + file_diff.signum();
+    let mut between = 0;
+    let mut square = a as i8 + step;
+    while square != b as i8 {
+        between |= 1u64 << square;
+        square += step;
+    }
+    between
+}
 
+/// A small xorshift random number generator, so picking book moves and similar
+/// choices can be reproduced from a seed without pulling in a dependency.
+#[derive(Debug, Clone)]
+pub struct XorShift64 {
+    state: u64,
+}
+
+impl XorShift64 {
+    /// Creates a generator from `seed`. A zero seed is replaced, since xorshift would
+    /// only ever return zero.
+    pub fn new(seed: u64) -> XorShift64 {
+        XorShift64 {
+            state: if seed == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                seed
+            },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+/// True if the game is drawn with `side_to_move` to play: it is stalemated, or the
+/// position is drawn by insufficient material, the fifty-move rule or repetition.
 pub fn is_stalemate(board: &Board, side_to_move: Color) -> bool {
     // If the king is in check, it's not stalemate
     if board.is_in_check(side_to_move) {
         return false;
     }
 
-    // Check if there are any legal moves
-    // Generate all possible moves for the side to move
-    let moves = generate_all_moves_for_color(board);
-    if moves.is_empty() {
+    // Check if there are any legal moves for the side to move
+    let mut board = *board;
+    board.active_color = side_to_move;
+    if !board.has_legal_moves() {
         return true;
     }
 
@@ -92,6 +149,62 @@ pub fn is_stalemate(board: &Board, side_to_move: Color) -> bool {
         return true;
     }
 
-    // If we get here, there are no legal moves
-    true
+    // Legal moves remain and nothing else ends the game
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edge_distances_match_the_table_built_at_run_time() {
+        // Each square's distances as the table used to compute them on first use
+        for square in 0..64 {
+            let (row, col) = (square / 8, square % 8);
+            let (north, south, east, west) = (7 - row, row, 7 - col, col);
+            let expected = [
+                north,
+                south,
+                east,
+                west,
+                north.min(east),
+                north.min(west),
+                south.min(east),
+                south.min(west),
+            ];
+            let actual = EDGE_DISTANCES.map(|distances| distances[square] as usize);
+            assert_eq!(actual, expected, "square {}", square);
+        }
+
+        // From d4 a rook reaches d8 and a bishop h8
+        assert_eq!(EDGE_DISTANCES[NORTH][27], 4);
+        assert_eq!(EDGE_DISTANCES[NORTH_EAST][27], 4);
+        assert_eq!(EDGE_DISTANCES[SOUTH_WEST][27], 3);
+    }
+
+    #[test]
+    fn test_square_names_round_trip() {
+        assert_eq!(square_to_coordinate(0), *b"a1");
+        assert_eq!(square_to_coordinate(28), *b"e4");
+        assert_eq!(square_to_str(63), "h8");
+        assert_eq!(convert_board_coordinate_to_idx("e4"), 28);
+        // Only the first two bytes are read, as when slicing the squares out of a move
+        assert_eq!(convert_board_coordinate_to_idx("e7e8q"), 52);
+
+        for square in 0..64 {
+            let coordinate = square_to_coordinate(square);
+            assert_eq!(square_to_str(square).as_bytes(), coordinate);
+            assert_eq!(
+                convert_board_coordinate_to_idx(square_to_str(square)),
+                square
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid rank")]
+    fn test_board_coordinate_off_the_board_panics() {
+        convert_board_coordinate_to_idx("e9");
+    }
 }
@@ -0,0 +1,122 @@
+//! JavaScript bindings, built with `wasm-pack build --features wasm`.
+//!
+//! ```js
+//! const position = new Position();            // or new Position(fen)
+//! position.legalMoves("e2");                  // ["e2e3", "e2e4"]
+//! position.legalDestinations("g1");           // ["f3", "h3"]
+//! position.playMove("e2e4");
+//! position.status();                          // "ongoing"
+//! position.bestMove();                        // e.g. "e7e5"
+//! ```
+
+use crate::board::Board;
+use crate::chess_move::{square_name, Move, MoveError};
+use crate::search::{search, SearchOptions};
+
+use wasm_bindgen::prelude::*;
+
+/// Depth [`Position::best_move`] searches to; shallow enough to answer at once.
+const BEST_MOVE_DEPTH: u32 = 4;
+
+/// A position that moves can be played in, keeping the history needed to spot
+/// repetitions.
+#[wasm_bindgen]
+pub struct Position {
+    board: Board,
+}
+
+#[wasm_bindgen]
+impl Position {
+    /// The position described by `fen`, or the starting position.
+    #[wasm_bindgen(constructor)]
+    pub fn new(fen: Option<String>) -> Result<Position, JsError> {
+        let board = Board::from_fen(fen.as_deref().unwrap_or(Board::START_FEN))?;
+        Ok(Position { board })
+    }
+
+    pub fn fen(&self) -> String {
+        self.board.board_to_fen()
+    }
+
+    /// Legal moves in UCI notation, only those of the piece on `from` (e.g. `"e2"`)
+    /// if given.
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self, from: Option<String>) -> Vec<String> {
+        let moves = match from.as_deref().map(parse_square) {
+            Some(Some(from)) => self.board.legal_moves_from(from),
+            Some(None) => return Vec::new(),
+            None => self.board.legal_moves().to_vec(),
+        };
+        moves.iter().map(|m| m.to_string()).collect()
+    }
+
+    /// The squares the piece on `from` (e.g. `"g1"`) can move to, each once, for
+    /// highlighting them when the user picks it up.
+    #[wasm_bindgen(js_name = legalDestinations)]
+    pub fn legal_destinations(&self, from: &str) -> Vec<String> {
+        let mut destinations = match parse_square(from) {
+            Some(from) => self.board.legal_destinations(from),
+            None => return Vec::new(),
+        };
+        let mut squares = Vec::new();
+        while destinations != 0 {
+            squares.push(square_name(destinations.trailing_zeros() as u8));
+            destinations &= destinations - 1;
+        }
+        squares
+    }
+
+    /// Plays `uci` (e.g. `"e7e8q"`). Throws if it is not a legal move.
+    #[wasm_bindgen(js_name = playMove)]
+    pub fn play_move(&mut self, uci: &str) -> Result<(), JsError> {
+        Move::from_uci(uci)
+            .ok_or(MoveError::Malformed)
+            .and_then(|m| self.board.make_move(&m))
+            .map_err(|err| JsError::new(&format!("illegal move {}: {}", uci, err)))?;
+        Ok(())
+    }
+
+    /// `"checkmate"`, `"stalemate"`, `"draw"` (insufficient material, the fifty-move
+    /// rule or threefold repetition), `"check"` or `"ongoing"`.
+    pub fn status(&self) -> String {
+        let in_check = self.board.is_in_check(self.board.active_color);
+        let status = if !self.board.has_legal_moves() {
+            if in_check {
+                "checkmate"
+            } else {
+                "stalemate"
+            }
+        } else if self.board.is_insufficient_material()
+            || self.board.halfmove_clock >= 100
+            || self.board.is_3_fold_repetition()
+        {
+            "draw"
+        } else if in_check {
+            "check"
+        } else {
+            "ongoing"
+        };
+        status.to_string()
+    }
+
+    /// The engine's move in UCI notation, or nothing if the game is over.
+    #[wasm_bindgen(js_name = bestMove)]
+    pub fn best_move(&self) -> Option<String> {
+        let options = SearchOptions {
+            depth: BEST_MOVE_DEPTH,
+            hash_mb: 1,
+            ..SearchOptions::default()
+        };
+        search(&self.board, &options)
+            .best_move
+            .map(|m| m.to_string())
+    }
+}
+
+/// Index of a square named like `"e4"`.
+fn parse_square(name: &str) -> Option<u8> {
+    match *name.as_bytes() {
+        [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Some((rank - b'1') * 8 + (file - b'a')),
+        _ => None,
+    }
+}
@@ -0,0 +1,67 @@
+//! Heap allocations made by the search, counted by a global allocator. In a file of
+//! its own, since the allocator counts everything the test binary does.
+
+use chessbot::board::Board;
+use chessbot::search::{search_with_table, SearchOptions, SearchReporter, SearchResult};
+use chessbot::tt::TranspositionTable;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Allocations counted so far at the end of each iteration.
+struct Counts(Vec<usize>);
+
+impl SearchReporter for Counts {
+    fn iteration(&mut self, _result: &SearchResult) {
+        self.0.push(ALLOCATIONS.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn test_search_allocates_per_iteration_not_per_node() {
+    let board = Board::fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let options = SearchOptions {
+        depth: 8,
+        ..SearchOptions::default()
+    };
+    let tt = TranspositionTable::new(options.hash_mb);
+    let mut counts = Counts(Vec::with_capacity(options.depth as usize));
+
+    let result = search_with_table(&board, &options, &AtomicBool::new(false), &tt, &mut counts);
+
+    assert_eq!(result.depth, 8);
+    // Each iteration builds its result, lines and principal variation, whatever the
+    // hundreds of thousands of nodes it searches
+    for (depth, pair) in (2..).zip(counts.0.windows(2)) {
+        let allocations = pair[1] - pair[0];
+        assert!(
+            allocations < 50,
+            "{} allocations at depth {}",
+            allocations,
+            depth
+        );
+    }
+}
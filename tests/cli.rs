@@ -0,0 +1,224 @@
+//! Drives the `ChessBot` binary through its interactive loop, as a player would.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs the binary with `input` typed on stdin and returns what it printed.
+fn play(input: &str) -> String {
+    play_with(&[], input)
+}
+
+/// [`play`] with `args` on the command line.
+fn play_with(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ChessBot"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the binary starts");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_fools_mate_is_announced_for_black() {
+    let output = play("f3\ne5\ng4\nQh4\n");
+
+    assert!(output.contains("Checkmate — Black wins\n"), "{}", output);
+    assert!(!output.contains("White wins"), "{}", output);
+    assert!(output.contains("1. f3 e5 2. g4 Qh4# 0-1"), "{}", output);
+}
+
+#[test]
+fn test_engine_move_is_reported_without_terminal_codes() {
+    let output = play("f3\ne5\ng4\ngo\nexit\n");
+
+    assert!(output.contains("played Qh4# (depth "), "{}", output);
+    assert!(output.contains(", mate 1, "), "{}", output);
+    // Piped output gets no spinner redrawn over itself
+    assert!(!output.contains('\r'), "{:?}", output);
+    assert!(!output.contains("\x1b[K"), "{:?}", output);
+}
+
+#[test]
+fn test_closing_stdin_ends_the_game() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ChessBot"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the binary starts");
+    // No `exit`: stdin is closed after the move
+    child.stdin.take().unwrap().write_all(b"e4\n").unwrap();
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if started.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("the game went on after stdin was closed");
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    assert!(status.success());
+    let mut output = String::new();
+    child.stdout.unwrap().read_to_string(&mut output).unwrap();
+    assert!(output.contains("Black to move (move 1)"), "{}", output);
+}
+
+#[test]
+fn test_threefold_repetition_is_announced_and_claimed() {
+    let knights = "Nf3\nNf6\nNg1\nNg8\n";
+    let output = play(&format!("claim\n{}{}claim\n", knights, knights));
+
+    assert!(output.contains("No draw to claim"), "{}", output);
+    assert_eq!(
+        output
+            .matches("You may claim a draw by threefold repetition — type 'claim'")
+            .count(),
+        1,
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("Draw by threefold repetition\n"),
+        "{}",
+        output
+    );
+    assert!(output.contains("1/2-1/2"), "{}", output);
+}
+
+#[test]
+fn test_finished_games_are_saved_and_listed() {
+    let dir = format!("{}/saved-games", env!("CARGO_TARGET_TMPDIR"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let output = play_with(&["--save-dir", &dir], "f3\ne5\ng4\nQh4\n");
+    assert!(output.contains("Saved as game 1 in "), "{}", output);
+    let output = play_with(&["--save-dir", &dir], "e4\nresign\n");
+    assert!(output.contains("Saved as game 2 in "), "{}", output);
+    // Unfinished games are not kept
+    let output = play_with(&["--save-dir", &dir], "e4\nexit\n");
+    assert!(!output.contains("Saved"), "{}", output);
+
+    let list = play_with(&["games", "list", "--save-dir", &dir], "");
+    let lines: Vec<&str> = list.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", list);
+    assert!(lines[0].starts_with("  1. "), "{}", list);
+    assert!(
+        lines[0].ends_with("  ? - ?  0-1  (Black wins by checkmate, 4 plies)"),
+        "{}",
+        list
+    );
+    assert!(
+        lines[1].ends_with("  1-0  (White wins by resignation, 1 ply)"),
+        "{}",
+        list
+    );
+
+    let pgn = play_with(&["games", "show", "1", "--save-dir", &dir], "");
+    assert!(pgn.starts_with("[Event \"ChessBot game\"]\n"), "{}", pgn);
+    assert!(pgn.ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"), "{}", pgn);
+}
+
+/// Up to White's pawn on b7 taking the rook on a8.
+const BEFORE_PROMOTION: &str = "e4\nd5\nexd5\nc6\ndxc6\nNf6\ncxb7\nNbd7\n";
+
+#[test]
+fn test_promotion_without_a_piece_is_completed() {
+    let output = play_with(
+        &["--auto-queen"],
+        &format!("{}b7a8\nexit\n", BEFORE_PROMOTION),
+    );
+    assert!(!output.contains("Promote to"), "{}", output);
+    assert!(output.contains("5. bxa8=Q"), "{}", output);
+
+    // Otherwise the player is asked, until the answer is a piece
+    let output = play(&format!("{}b7a8\nk\nn\nexit\n", BEFORE_PROMOTION));
+    assert!(output.contains("Promote to (q/r/b/n)? "), "{}", output);
+    assert!(output.contains("Answer q, r, b or n"), "{}", output);
+    assert!(output.contains("5. bxa8=N"), "{}", output);
+}
+
+#[test]
+fn test_features_prints_a_json_line_per_fen() {
+    let fens = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/differential.fens"
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_ChessBot"))
+        .args(["features", fens])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let expected = std::fs::read_to_string(fens)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .count();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), expected);
+    assert_eq!(
+        lines[0]["fen"],
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    assert_eq!(lines[0]["white"]["moves"], 20);
+    assert_eq!(lines[1]["white"]["captures"], 8);
+    assert_eq!(lines[1]["in_check"], false);
+}
+
+#[test]
+fn test_evalfile_scores_every_fen_in_order() {
+    let fens = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/evalfile.fens");
+    let evalfile = |output: &str, args: &[&str]| {
+        let path = format!("{}/{}", env!("CARGO_TARGET_TMPDIR"), output);
+        let status = Command::new(env!("CARGO_BIN_EXE_ChessBot"))
+            .args(["evalfile", fens, &path])
+            .args(args)
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        std::fs::read_to_string(path).unwrap()
+    };
+
+    // 18 positions, the line that is not a FEN skipped, in the same order however
+    // many threads share the work
+    let csv = evalfile("evalfile.csv", &["--depth", "2", "--threads", "3"]);
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 1 + 18);
+    assert_eq!(rows[0], "fen,score_cp,best_move");
+    assert_eq!(rows[2], "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1,29999,a1a8");
+    assert_eq!(rows[10], "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1,0,");
+    assert_eq!(evalfile("evalfile-1.csv", &["--depth", "2"]), csv);
+
+    let jsonl = evalfile("evalfile.jsonl", &["--static"]);
+    let rows: Vec<serde_json::Value> = jsonl
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 18);
+    assert_eq!(
+        rows[0],
+        serde_json::json!({
+            "fen": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "score_cp": 0,
+            "best_move": null,
+        })
+    );
+}
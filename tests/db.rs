@@ -0,0 +1,198 @@
+//! Writes games to a store on disk and reads them back through a fresh `GameDb`.
+
+use chessbot::board::Color;
+use chessbot::chess_move::Move;
+use chessbot::db::{DbError, GameDb, RecordError, DB_FILE};
+use chessbot::game::{Game, GameResult, Termination};
+
+use std::fs;
+use std::path::PathBuf;
+
+/// An empty directory for the store of test `name`.
+fn store_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn game(fen: &str, moves: &str) -> Game {
+    let moves: Vec<Move> = moves
+        .split_whitespace()
+        .map(|m| Move::from_uci(m).unwrap())
+        .collect();
+    Game::replay(fen, &moves).unwrap()
+}
+
+fn tags(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|&(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Fool's mate, a resignation from a set-up position and an unfinished game.
+fn three_games() -> Vec<(Game, Vec<(String, String)>)> {
+    let fools_mate = game(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "f2f3 e7e5 g2g4 d8h4",
+    );
+    let mut resigned = game("4k3/8/8/8/8/8/4P3/4K3 b - - 3 30", "e8d7 e2e4 d7e6");
+    resigned.set_result(GameResult::win(Color::Black, Termination::Resignation));
+    let unfinished = game(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "e2e4 c7c5",
+    );
+    vec![
+        (
+            fools_mate,
+            tags(&[("Event", "Casual game"), ("Date", "2026.10.14")]),
+        ),
+        (resigned, tags(&[("White", "Anna"), ("Black", "Ben")])),
+        (unfinished, Vec::new()),
+    ]
+}
+
+#[test]
+fn test_games_round_trip_through_a_reopened_store() {
+    let dir = store_dir("db-round-trip");
+    let games = three_games();
+    {
+        let db = GameDb::new(&dir);
+        assert!(db.list().unwrap().games.is_empty());
+        for (index, (game, tags)) in games.iter().enumerate() {
+            assert_eq!(db.append(game, tags).unwrap(), index);
+        }
+    }
+
+    let db = GameDb::new(&dir);
+    let listing = db.list().unwrap();
+    assert!(listing.skipped.is_empty());
+    assert_eq!(listing.games.len(), 3);
+    for (index, (stored, (game, tags))) in listing.games.iter().zip(&games).enumerate() {
+        assert_eq!(stored.index, index);
+        assert_eq!(&stored.tags, tags);
+        assert_eq!(stored.game.moves(), game.moves());
+        assert_eq!(stored.game.result(), game.result());
+    }
+    assert_eq!(listing.games[0].tag("Date"), Some("2026.10.14"));
+    assert_eq!(
+        listing.games[1].game.result(),
+        Some(GameResult::win(Color::Black, Termination::Resignation))
+    );
+    assert_eq!(listing.games[2].game.result(), None);
+
+    for (index, (game, _)) in games.iter().enumerate() {
+        let loaded = db.load(index).unwrap();
+        assert_eq!(
+            loaded.game.start().board_to_fen(),
+            game.start().board_to_fen()
+        );
+        assert_eq!(
+            loaded.game.board().board_to_fen(),
+            game.board().board_to_fen()
+        );
+        assert_eq!(loaded.to_pgn(), game.to_pgn_with_tags(&loaded.tags));
+    }
+    assert!(listing.games[1]
+        .to_pgn()
+        .contains("[White \"Anna\"]\n[Black \"Ben\"]\n"));
+    assert!(db.load(3).is_err());
+}
+
+#[test]
+fn test_corrupted_games_are_skipped_not_fatal() {
+    let dir = store_dir("db-corrupted");
+    let db = GameDb::new(&dir);
+    for (game, tags) in three_games() {
+        db.append(&game, &tags).unwrap();
+    }
+
+    // Damage a byte of the second game's moves, then cut the file inside a fourth
+    let path = dir.join(DB_FILE);
+    let mut bytes = fs::read(&path).unwrap();
+    let first_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+    let second_contents = 8 + 16 + first_len + 16;
+    bytes[second_contents + 40] ^= 0x55;
+    bytes.extend_from_slice(&[7, 0, 0]);
+    fs::write(&path, &bytes).unwrap();
+
+    let listing = db.list().unwrap();
+    let indices: Vec<usize> = listing.games.iter().map(|stored| stored.index).collect();
+    assert_eq!(indices, [0, 2]);
+    assert_eq!(listing.skipped.len(), 2);
+    assert_eq!(listing.skipped[0].index, 1);
+    assert_eq!(listing.skipped[0].reason, RecordError::Checksum);
+    assert_eq!(
+        listing.skipped[0].to_string(),
+        "skipped game 2: the game is damaged (checksum mismatch)"
+    );
+    assert_eq!(listing.skipped[1].reason, RecordError::Truncated);
+    assert!(db.load(1).is_err());
+
+    // Appending drops the partial game and takes its place
+    let (game, tags) = three_games().remove(0);
+    assert_eq!(db.append(&game, &tags).unwrap(), 3);
+    let listing = db.list().unwrap();
+    assert_eq!(listing.games.len(), 3);
+    assert_eq!(listing.skipped.len(), 1);
+    assert_eq!(db.load(3).unwrap().game.moves(), game.moves());
+}
+
+#[test]
+fn test_a_damaged_length_loses_only_its_game() {
+    let dir = store_dir("db-damaged-length");
+    let db = GameDb::new(&dir);
+    for (game, tags) in three_games() {
+        db.append(&game, &tags).unwrap();
+    }
+
+    // Flip a bit of the second game's length
+    let path = dir.join(DB_FILE);
+    let mut bytes = fs::read(&path).unwrap();
+    let first_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+    let second_length = 8 + 16 + first_len + 4;
+    bytes[second_length] ^= 1;
+    fs::write(&path, &bytes).unwrap();
+
+    let listing = db.list().unwrap();
+    let indices: Vec<usize> = listing.games.iter().map(|stored| stored.index).collect();
+    assert_eq!(indices, [0, 2]);
+    assert_eq!(listing.skipped.len(), 1);
+    assert_eq!(listing.skipped[0].reason, RecordError::Header);
+
+    // Appending keeps every byte there was
+    let (game, tags) = three_games().remove(1);
+    assert_eq!(db.append(&game, &tags).unwrap(), 3);
+    assert!(fs::read(&path).unwrap().starts_with(&bytes));
+    let listing = db.list().unwrap();
+    let indices: Vec<usize> = listing.games.iter().map(|stored| stored.index).collect();
+    assert_eq!(indices, [0, 2, 3]);
+    assert_eq!(db.load(2).unwrap().game.moves(), three_games()[2].0.moves());
+    assert_eq!(db.load(3).unwrap().game.moves(), game.moves());
+}
+
+#[test]
+fn test_oversize_tags_are_rejected() {
+    let dir = store_dir("db-oversize");
+    let db = GameDb::new(&dir);
+    let (game, _) = three_games().remove(0);
+    let long = "x".repeat(u16::MAX as usize + 1);
+
+    let err = db.append(&game, &tags(&[("Event", &long)])).unwrap_err();
+    assert!(
+        matches!(&err, DbError::TooLong { part, len: 65_536, max: 65_535 } if part == "tag Event"),
+        "{:?}",
+        err
+    );
+    assert_eq!(
+        err.to_string(),
+        "tag Event is too long to store (65536, at most 65535)"
+    );
+    assert!(db.append(&game, &tags(&[(&long, "value")])).is_err());
+
+    // Nothing was written, and the largest value that fits still does
+    assert!(db.list().unwrap().games.is_empty());
+    let longest = "x".repeat(u16::MAX as usize);
+    assert_eq!(db.append(&game, &tags(&[("Event", &longest)])).unwrap(), 0);
+    assert_eq!(db.load(0).unwrap().tag("Event"), Some(longest.as_str()));
+}
@@ -0,0 +1,106 @@
+//! Compares `Board::legal_moves` with the `shakmaty` crate, move for move, on the
+//! positions in `fixtures/differential.fens` and on positions reached from them by
+//! random playouts.
+//!
+//! Both sides are written as sorted UCI strings, so castling is the king's two-square
+//! move (`e1g1`) and en passant is the capturing pawn's diagonal step.
+
+use chessbot::board::Board;
+use chessbot::utils::XorShift64;
+use shakmaty::fen::Fen;
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, Chess, Position};
+
+const CORPUS: &str = include_str!("fixtures/differential.fens");
+
+/// Random plies played from every corpus position, comparing after each one.
+const PLAYOUT_PLIES: usize = 6;
+
+fn corpus() -> impl Iterator<Item = &'static str> {
+    CORPUS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn our_moves(board: &Board) -> Vec<String> {
+    let mut moves: Vec<String> = board.legal_moves().iter().map(|m| m.to_string()).collect();
+    moves.sort();
+    moves
+}
+
+fn reference_moves(position: &Chess) -> Vec<String> {
+    let mut moves: Vec<String> = position
+        .legal_moves()
+        .iter()
+        .map(|m| UciMove::from_standard(*m).to_string())
+        .collect();
+    moves.sort();
+    moves
+}
+
+fn reference_position(fen: &str) -> Chess {
+    Fen::from_ascii(fen.as_bytes())
+        .unwrap_or_else(|err| panic!("reference cannot parse {}: {}", fen, err))
+        .into_position(CastlingMode::Standard)
+        .unwrap_or_else(|err| panic!("reference rejects {}: {}", fen, err))
+}
+
+/// Describes how our moves differ from the reference's, or `None` if they agree.
+fn mismatch(fen: &str, ours: &[String], expected: &[String]) -> Option<String> {
+    let missing: Vec<&String> = expected.iter().filter(|m| !ours.contains(m)).collect();
+    let extra: Vec<&String> = ours.iter().filter(|m| !expected.contains(m)).collect();
+    if missing.is_empty() && extra.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}\n  missing: {:?}\n  extra:   {:?}",
+        fen, missing, extra
+    ))
+}
+
+#[test]
+fn test_legal_moves_match_reference() {
+    let mut rng = XorShift64::new(0x5eed);
+    let mut failures = Vec::new();
+    let mut compared = 0;
+
+    for fen in corpus() {
+        let mut board = Board::fen_to_board(fen);
+        let mut position = reference_position(fen);
+
+        for _ in 0..=PLAYOUT_PLIES {
+            let fen = board.board_to_fen();
+            let ours = our_moves(&board);
+            let expected = reference_moves(&position);
+            compared += 1;
+
+            if let Some(failure) = mismatch(&fen, &ours, &expected) {
+                failures.push(failure);
+                break;
+            }
+            if expected.is_empty() {
+                break;
+            }
+
+            let uci = &expected[(rng.next_u64() % expected.len() as u64) as usize];
+            let m = board
+                .legal_moves()
+                .into_iter()
+                .find(|m| m.to_string() == *uci)
+                .unwrap();
+            board.make_move_unchecked(&m);
+            let reference_move = uci.parse::<UciMove>().unwrap().to_move(&position).unwrap();
+            position.play_unchecked(reference_move);
+        }
+    }
+
+    assert!(compared >= 200, "only {} positions compared", compared);
+    assert!(
+        failures.is_empty(),
+        "{} of {} positions differ:\n{}",
+        failures.len(),
+        compared,
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,73 @@
+//! Plays a scripted game against the JSON server over a real loopback socket:
+//! `cargo test --features net`.
+
+#![cfg(feature = "net")]
+
+use chessbot::net::Server;
+use chessbot::search::SearchOptions;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn send(&mut self, request: &str) -> Value {
+        writeln!(self.writer, "{}", request).unwrap();
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+}
+
+#[test]
+fn test_scripted_game_over_loopback() {
+    let options = SearchOptions {
+        depth: 3,
+        hash_mb: 1,
+        ..SearchOptions::default()
+    };
+    let server = Server::bind("127.0.0.1:0", options).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || server.serve_one());
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut client = Client {
+        reader: BufReader::new(stream.try_clone().unwrap()),
+        writer: stream,
+    };
+
+    // Every black reply is the king's only legal move, so the game is fixed
+    let ready = client.send(r#"{"type":"newgame","fen":"7k/8/5K2/8/8/8/8/R7 w - - 0 1"}"#);
+    assert_eq!(ready["type"], "ready");
+    assert_eq!(ready["status"], "ongoing");
+
+    for (ours, theirs) in [
+        ("a1a7", "h8g8"),
+        ("a7a8", "g8h7"),
+        ("f6f7", "h7h6"),
+        ("a8h8", "h6g5"),
+    ] {
+        let reply = client.send(&format!(r#"{{"type":"move","uci":"{}"}}"#, ours));
+        assert_eq!(reply["type"], "move", "{}", reply);
+        assert_eq!(reply["uci"], theirs);
+    }
+
+    let illegal = client.send(r#"{"type":"move","uci":"f7f5"}"#);
+    assert_eq!(illegal["type"], "error");
+    let garbage = client.send("{");
+    assert_eq!(garbage["type"], "error");
+
+    let status = client.send(r#"{"type":"status"}"#);
+    assert_eq!(status["fen"], "7R/5K2/8/6k1/8/8/8/8 w - - 8 5");
+    assert_eq!(status["status"], "ongoing");
+    assert_eq!(status["moves"].as_array().unwrap().len(), 8);
+
+    // Hanging up ends the game cleanly
+    drop(client);
+    handle.join().unwrap().unwrap();
+}
@@ -0,0 +1,263 @@
+//! Property tests: invariants that must hold in every position reachable by legal
+//! play, and for every structurally valid FEN.
+//!
+//! Positions come from random playouts driven by a seed that proptest picks, so a
+//! failure reports (and shrinks to) the seed and ply count that reproduce it.
+
+use chessbot::board::{Board, Color};
+use chessbot::chess_move::Move;
+use chessbot::eval::{evaluate, explain, EvalParams, IncrementalTerms, Term};
+use chessbot::utils::XorShift64;
+use proptest::prelude::*;
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// A game of random legal moves from the starting position.
+#[derive(Debug, Clone)]
+struct Playout {
+    seed: u64,
+    /// Moves played, in UCI notation. Fewer than asked for if the game ended.
+    moves: Vec<String>,
+}
+
+impl Playout {
+    fn new(seed: u64, plies: usize) -> Playout {
+        let mut moves = Vec::new();
+        play(seed, plies, |_, m| moves.push(m));
+        Playout { seed, moves }
+    }
+
+    /// Every position of the game, the starting one included.
+    fn positions(&self) -> Vec<Board> {
+        let mut positions = vec![Board::fen_to_board(STARTING_FEN)];
+        play(self.seed, self.moves.len(), |board, _| {
+            positions.push(*board)
+        });
+        positions
+    }
+}
+
+/// Plays up to `plies` random legal moves from the starting position, calling `visit`
+/// with the position after each one and the move that led there.
+fn play(seed: u64, plies: usize, mut visit: impl FnMut(&Board, String)) {
+    let mut rng = XorShift64::new(seed);
+    let mut board = Board::fen_to_board(STARTING_FEN);
+
+    for _ in 0..plies {
+        let legal = board.legal_moves();
+        if legal.is_empty() {
+            break;
+        }
+        let m = legal
+            .get((rng.next_u64() % legal.len() as u64) as usize)
+            .unwrap();
+        board.make_move_unchecked(&m);
+        visit(&board, m.to_string());
+    }
+}
+
+/// Random legal positions, as the games that reach them.
+fn playouts() -> impl Strategy<Value = Playout> {
+    (any::<u64>(), 0..60usize).prop_map(|(seed, plies)| Playout::new(seed, plies))
+}
+
+/// FENs that are well formed, though not necessarily legal positions.
+fn valid_fens() -> impl Strategy<Value = String> {
+    let square = prop_oneof![
+        3 => Just(None),
+        1 => prop::sample::select("PNBRQKpnbrqk".chars().collect::<Vec<_>>()).prop_map(Some),
+    ];
+    let placement = prop::collection::vec(square, 64).prop_map(|squares| {
+        let ranks: Vec<String> = squares
+            .chunks(8)
+            .map(|rank| {
+                let mut text = String::new();
+                let mut empty = 0;
+                for square in rank {
+                    match square {
+                        Some(piece) => {
+                            if empty > 0 {
+                                text.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            text.push(*piece);
+                        }
+                        None => empty += 1,
+                    }
+                }
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                }
+                text
+            })
+            .collect();
+        ranks.join("/")
+    });
+    let castling = any::<[bool; 4]>().prop_map(|rights| {
+        let text: String = "KQkq"
+            .chars()
+            .zip(rights)
+            .filter(|(_, allowed)| *allowed)
+            .map(|(c, _)| c)
+            .collect();
+        if text.is_empty() {
+            "-".to_string()
+        } else {
+            text
+        }
+    });
+    let en_passant = prop::option::of((0..8u8, prop::bool::ANY)).prop_map(|square| match square {
+        Some((file, white_moved)) => {
+            format!(
+                "{}{}",
+                (b'a' + file) as char,
+                if white_moved { 3 } else { 6 }
+            )
+        }
+        None => "-".to_string(),
+    });
+
+    (
+        placement,
+        prop::bool::ANY,
+        castling,
+        en_passant,
+        0..150u32,
+        1..300u32,
+    )
+        .prop_map(
+            |(placement, white, castling, en_passant, halfmove, fullmove)| {
+                let side = if white { "w" } else { "b" };
+                format!(
+                    "{} {} {} {} {} {}",
+                    placement, side, castling, en_passant, halfmove, fullmove
+                )
+            },
+        )
+}
+
+/// True if every field of the two boards is the same, position history included.
+fn same_state(a: &Board, b: &Board) -> bool {
+    a.bitboards == b.bitboards
+        && a.all_white_bitboard == b.all_white_bitboard
+        && a.all_black_bitboard == b.all_black_bitboard
+        && a.active_color == b.active_color
+        && a.castling_rights == b.castling_rights
+        && a.en_passant == b.en_passant
+        && a.halfmove_clock == b.halfmove_clock
+        && a.fullmove_number == b.fullmove_number
+        && a.king_moves == b.king_moves
+        && a.position_count == b.position_count
+        && a.hashed_board_states == b.hashed_board_states
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn prop_played_positions_are_consistent(playout in playouts()) {
+        for board in playout.positions() {
+            board.assert_consistent();
+        }
+    }
+
+    #[test]
+    fn prop_fen_round_trips_in_played_positions(playout in playouts()) {
+        for board in playout.positions() {
+            let fen = board.board_to_fen();
+            prop_assert_eq!(Board::from_fen(&fen).unwrap().board_to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn prop_gives_check_matches_playing_the_move(playout in playouts()) {
+        for board in playout.positions() {
+            for m in board.legal_moves().iter() {
+                let mut after = board;
+                after.make_move_unchecked(&m);
+                prop_assert_eq!(
+                    board.gives_check(&m),
+                    after.is_in_check(after.active_color),
+                    "{} in {}",
+                    m,
+                    board.board_to_fen()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn prop_moves_from_a_square_match_the_full_generator(playout in playouts()) {
+        for board in playout.positions() {
+            let legal = board.legal_moves();
+            for from in 0..64u8 {
+                let mut expected: Vec<_> = legal.iter().filter(|m| m.from == from).collect();
+                let mut moves = board.legal_moves_from(from);
+                let key = |m: &Move| (m.to, m.promotion.map(|p| p as u8));
+                expected.sort_by_key(key);
+                moves.sort_by_key(key);
+                prop_assert_eq!(&moves, &expected, "from {} in {}", from, board.board_to_fen());
+
+                let destinations = expected.iter().fold(0u64, |bb, m| bb | (1u64 << m.to));
+                prop_assert_eq!(board.legal_destinations(from), destinations);
+            }
+        }
+    }
+
+    #[test]
+    fn prop_unmake_restores_every_move(playout in playouts()) {
+        let board = *playout.positions().last().unwrap();
+        let mut scratch = board;
+
+        for m in board.legal_moves().iter() {
+            let undo = scratch.make_move_unchecked(&m);
+            scratch.assert_consistent();
+            scratch.unmake_move(&m, undo);
+            prop_assert!(same_state(&scratch, &board), "{} was not undone", m);
+        }
+    }
+
+    #[test]
+    fn prop_incremental_eval_terms_match_a_recount(playout in playouts()) {
+        let board = *playout.positions().last().unwrap();
+        let recount = |board: &Board| IncrementalTerms::from_bitboards(&board.bitboards);
+        prop_assert_eq!(*board.eval_terms(), recount(&board));
+
+        // Every kind of move, and taking it back, keeps them in step
+        let mut scratch = board;
+        for m in board.legal_moves().iter() {
+            let undo = scratch.make_move_unchecked(&m);
+            prop_assert_eq!(*scratch.eval_terms(), recount(&scratch), "after {}", m);
+            scratch.unmake_move(&m, undo);
+            prop_assert_eq!(*scratch.eval_terms(), recount(&board), "after undoing {}", m);
+        }
+    }
+
+    #[test]
+    fn prop_eval_breakdown_sums_to_evaluate(playout in playouts()) {
+        for board in playout.positions() {
+            let breakdown = explain(&board, &EvalParams::DEFAULT);
+            let white: i32 = Term::ALL.iter().map(|&term| breakdown.net(term)).sum();
+            let total = match board.active_color {
+                Color::White => white,
+                Color::Black => -white,
+            };
+            prop_assert_eq!(total, evaluate(&board, &EvalParams::DEFAULT), "{}", board.board_to_fen());
+        }
+    }
+
+    #[test]
+    fn prop_valid_fens_parse_and_round_trip(fen in valid_fens()) {
+        let board = Board::from_fen(&fen);
+        prop_assert!(board.is_ok(), "{}: {:?}", fen, board.err());
+
+        let board = board.unwrap();
+        board.assert_well_formed();
+        // An en passant square nobody can capture on is written as "-"
+        let mut fields: Vec<&str> = fen.split(' ').collect();
+        if board.en_passant_square().is_none() {
+            fields[3] = "-";
+        }
+        prop_assert_eq!(board.board_to_fen(), fields.join(" "));
+    }
+}
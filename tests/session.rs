@@ -0,0 +1,102 @@
+//! Drives searches through `EngineSession` alone, as a GUI embedding the engine would.
+
+use chessbot::board::Board;
+use chessbot::chess_move::Move;
+use chessbot::engine::SearchLimits;
+use chessbot::game::GameError;
+use chessbot::session::{EngineSession, SearchEvent};
+
+use std::thread;
+
+fn uci_moves(moves: &[&str]) -> Vec<Move> {
+    moves.iter().map(|m| Move::from_uci(m).unwrap()).collect()
+}
+
+#[test]
+fn test_infinite_search_reports_until_stopped() {
+    let mut session = EngineSession::new(None);
+    let moves = uci_moves(&["e2e4", "e7e5", "g1f3"]);
+    session.set_position(Board::START_FEN, &moves).unwrap();
+
+    let search = session.go(SearchLimits::default());
+    match search.recv() {
+        Some(SearchEvent::Iteration(result)) => assert_eq!(result.depth, 1),
+        event => panic!("expected the first iteration, got {:?}", event),
+    }
+    // Stopped from another thread than the one reading the events
+    let stopper = thread::spawn(move || {
+        session.stop();
+        session
+    });
+
+    let mut events: Vec<SearchEvent> = search.events().collect();
+    let session = stopper.join().unwrap();
+    let best_move = match events.pop() {
+        Some(SearchEvent::BestMove(Some(m))) => m,
+        event => panic!("expected a best move last, got {:?}", event),
+    };
+    assert!(session.game().board().legal_moves().contains(&best_move));
+    assert!(events
+        .iter()
+        .all(|event| !matches!(event, SearchEvent::BestMove(_))));
+}
+
+#[test]
+fn test_stop_through_the_handle() {
+    let mut session = EngineSession::new(None);
+    let search = session.go(SearchLimits::default());
+    assert!(matches!(search.recv(), Some(SearchEvent::Iteration(_))));
+
+    search.stop();
+    let best_move = search.best_move().unwrap();
+    assert!(Board::new().legal_moves().contains(&best_move));
+}
+
+#[test]
+fn test_new_search_replaces_the_old_one() {
+    let mut session = EngineSession::new(None);
+    let first = session.go(SearchLimits::default());
+    assert!(matches!(first.recv(), Some(SearchEvent::Iteration(_))));
+
+    // A mate in one, found however the first search ended
+    session
+        .set_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[])
+        .unwrap();
+    assert!(matches!(
+        first.events().last(),
+        Some(SearchEvent::BestMove(Some(_)))
+    ));
+    let second = session.go(SearchLimits {
+        depth: Some(3),
+        ..SearchLimits::default()
+    });
+    assert_eq!(second.best_move(), Move::from_uci("a1a8"));
+}
+
+#[test]
+fn test_position_errors_keep_the_last_position() {
+    let mut session = EngineSession::new(None);
+    let moves = uci_moves(&["e2e4"]);
+    session.set_position(Board::START_FEN, &moves).unwrap();
+
+    assert!(matches!(
+        session.set_position("8/8/8/8", &[]),
+        Err(GameError::Fen(_))
+    ));
+    let illegal = uci_moves(&["e2e4", "e2e4"]);
+    assert!(matches!(
+        session.set_position(Board::START_FEN, &illegal),
+        Err(GameError::IllegalMove { ply: 1, .. })
+    ));
+    assert_eq!(session.game().moves(), &moves[..]);
+}
+
+#[test]
+fn test_no_legal_move_is_reported_as_none() {
+    let mut session = EngineSession::new(None);
+    session
+        .set_position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", &[])
+        .unwrap();
+    let search = session.go(SearchLimits::default());
+    assert_eq!(search.best_move(), None);
+}
@@ -0,0 +1,70 @@
+//! Tests for the JavaScript bindings: `wasm-pack test --node --features wasm`.
+
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use chessbot::wasm::Position;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn new_position_round_trips_fen() {
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    assert_eq!(Position::new(Some(fen.to_string())).unwrap().fen(), fen);
+    assert_eq!(
+        Position::new(None).unwrap().fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    assert!(Position::new(Some("not a fen".to_string())).is_err());
+}
+
+#[wasm_bindgen_test]
+fn legal_moves_can_be_filtered_by_square() {
+    let position = Position::new(None).unwrap();
+
+    assert_eq!(position.legal_moves(None).len(), 20);
+    let mut from_e2 = position.legal_moves(Some("e2".to_string()));
+    from_e2.sort();
+    assert_eq!(from_e2, ["e2e3", "e2e4"]);
+    assert!(position.legal_moves(Some("e4".to_string())).is_empty());
+    assert!(position.legal_moves(Some("z9".to_string())).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn play_move_rejects_illegal_moves() {
+    let mut position = Position::new(None).unwrap();
+
+    position.play_move("e2e4").unwrap();
+    assert!(position.play_move("e2e4").is_err());
+    assert!(position.play_move("nonsense").is_err());
+    assert_eq!(
+        position.fen(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    );
+}
+
+#[wasm_bindgen_test]
+fn status_reports_check_mate_and_draws() {
+    let mut position = Position::new(None).unwrap();
+    assert_eq!(position.status(), "ongoing");
+    for m in ["f2f3", "e7e5", "g2g4"] {
+        position.play_move(m).unwrap();
+    }
+    assert_eq!(position.status(), "ongoing");
+    position.play_move("d8h4").unwrap();
+    assert_eq!(position.status(), "checkmate");
+
+    let stalemate = Position::new(Some("k7/8/1Q6/8/8/8/8/7K b - - 0 1".to_string())).unwrap();
+    assert_eq!(stalemate.status(), "stalemate");
+    let bare_kings = Position::new(Some("k7/8/8/8/8/8/8/7K w - - 0 1".to_string())).unwrap();
+    assert_eq!(bare_kings.status(), "draw");
+    let check = Position::new(Some("k7/8/8/8/8/8/8/R6K b - - 0 1".to_string())).unwrap();
+    assert_eq!(check.status(), "check");
+}
+
+#[wasm_bindgen_test]
+fn best_move_finds_mate_in_one() {
+    let position = Position::new(Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string())).unwrap();
+    assert_eq!(position.best_move().as_deref(), Some("a1a8"));
+
+    let mated = Position::new(Some("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".to_string())).unwrap();
+    assert_eq!(mated.best_move(), None);
+}